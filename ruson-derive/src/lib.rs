@@ -0,0 +1,159 @@
+//! Derive macros for the `ToJson` and `FromJson` traits of `ruson` (enabled
+//! by the `derive` feature of `ruson`).
+//! Token streams are processed by hand, to keep `ruson` free of third party
+//! dependencies. Only structs with named fields (without generics) are
+//! supported, fields are mapped to object keys of the same name.
+use proc_macro::{Delimiter, Spacing, TokenStream, TokenTree};
+
+/// struct being derived.
+struct Struct {
+    name: String,
+    /// field identifiers, as written in the source (example: `r#type`).
+    fields: Vec<String>,
+}
+
+/// field identifier, without the raw identifier prefix (object key).
+fn key(field: &str) -> &str {
+    field.trim_start_matches("r#")
+}
+
+fn parse_struct(input: TokenStream) -> Result<Struct, &'static str> {
+    let mut tokens = input.into_iter();
+    // skip attributes and visibility, up to the 'struct' keyword.
+    loop {
+        match tokens.next() {
+            Some(TokenTree::Ident(ident)) => match ident.to_string().as_str() {
+                "struct" => break,
+                "enum" | "union" => return Err("only structs are supported."),
+                _ => continue,
+            },
+            Some(_) => continue,
+            None => return Err("expected struct."),
+        }
+    }
+    let name = match tokens.next() {
+        Some(TokenTree::Ident(ident)) => ident.to_string(),
+        _ => return Err("expected struct name."),
+    };
+    match tokens.next() {
+        Some(TokenTree::Group(group))
+            if group.delimiter() == Delimiter::Brace =>
+        {
+            Ok(Struct {
+                name,
+                fields: parse_fields(group.stream()),
+            })
+        }
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => {
+            Err("generic structs are not supported.")
+        }
+        _ => Err("only structs with named fields are supported."),
+    }
+}
+
+/// field names of `struct { [attributes] [visibility] name: Type, ... }`.
+fn parse_fields(stream: TokenStream) -> Vec<String> {
+    let mut fields = vec![];
+    // nesting depth of angle brackets (commas inside belong to the type).
+    let mut depth = 0usize;
+    let (mut previous, mut in_type) = (None, false);
+    for token in stream {
+        if let TokenTree::Punct(punct) = &token {
+            match punct.as_char() {
+                '<' => depth += 1,
+                // not a closing bracket, if part of `->`.
+                '>' if previous.as_deref() != Some("-") => {
+                    depth = depth.saturating_sub(1)
+                }
+                ',' if depth == 0 => in_type = false,
+                // first colon (not a path separator) is followed by the type.
+                ':' if !in_type && punct.spacing() == Spacing::Alone => {
+                    in_type = true;
+                    fields.extend(previous.take());
+                }
+                _ => {}
+            }
+        }
+        previous = match token {
+            TokenTree::Ident(ident) => Some(ident.to_string()),
+            TokenTree::Punct(punct) => Some(punct.as_char().to_string()),
+            _ => None,
+        };
+    }
+    fields
+}
+
+fn compile_error(message: &str) -> TokenStream {
+    format!("compile_error!({:?});", message).parse().unwrap()
+}
+
+/// `#[derive(ToJson)]`: struct is converted into a json object.
+#[proc_macro_derive(ToJson)]
+pub fn derive_to_json(input: TokenStream) -> TokenStream {
+    let Struct { name, fields } = match parse_struct(input) {
+        Ok(parsed) => parsed,
+        Err(message) => return compile_error(message),
+    };
+    let inserts: String = fields
+        .iter()
+        .map(|field| {
+            format!(
+                "object.insert({:?}.into(), \
+                 ::ruson::json::convert::ToJson::to_json(&self.{}));",
+                key(field),
+                field
+            )
+        })
+        .collect();
+    format!(
+        "impl ::ruson::json::convert::ToJson for {name} {{
+            fn to_json(&self) -> ::ruson::json::token::Json {{
+                let mut object = ::std::collections::HashMap::new();
+                {inserts}
+                ::ruson::json::token::Json::Object(::std::boxed::Box::new(object))
+            }}
+        }}",
+        name = name,
+        inserts = inserts
+    )
+    .parse()
+    .unwrap()
+}
+
+/// `#[derive(FromJson)]`: struct is constructed from a json object.
+/// missing keys are treated as `null` (valid for `Option` fields only).
+#[proc_macro_derive(FromJson)]
+pub fn derive_from_json(input: TokenStream) -> TokenStream {
+    let Struct { name, fields } = match parse_struct(input) {
+        Ok(parsed) => parsed,
+        Err(message) => return compile_error(message),
+    };
+    let initializers: String = fields
+        .iter()
+        .map(|field| {
+            format!(
+                "{}: ::ruson::json::convert::FromJson::from_json(\
+                 object.get({:?}).unwrap_or(&::ruson::json::token::Json::Null)\
+                 )?,",
+                field,
+                key(field)
+            )
+        })
+        .collect();
+    format!(
+        "impl ::ruson::json::convert::FromJson for {name} {{
+            fn from_json(
+                json: &::ruson::json::token::Json,
+            ) -> ::std::result::Result<Self, ::ruson::json::error::JsonConvertError> {{
+                let object = json.as_object().ok_or_else(|| {{
+                    ::ruson::json::error::JsonConvertError::new({name:?}, json)
+                }})?;
+                ::std::result::Result::Ok(Self {{ {initializers} }})
+            }}
+        }}",
+        name = name,
+        initializers = initializers
+    )
+    .parse()
+    .unwrap()
+}
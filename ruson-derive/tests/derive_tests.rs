@@ -0,0 +1,63 @@
+use ruson::json::{
+    convert::{FromJson, ToJson},
+    token::Json,
+};
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Author {
+    name: String,
+    email: Option<String>,
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+pub struct Package {
+    /// doc comments (and other attributes) are skipped.
+    pub name: String,
+    pub(crate) version: Vec<u32>,
+    r#type: String,
+    authors: Vec<Author>,
+    metadata: HashMap<String, Option<bool>>,
+    size: f64,
+}
+
+#[test]
+fn success_derive_roundtrip() {
+    let package = Package {
+        name: "ruson \"json\"".into(),
+        version: vec![0, 2, 2],
+        r#type: "bin".into(),
+        authors: vec![Author {
+            name: "lycuid".into(),
+            email: None,
+        }],
+        metadata: HashMap::from([("stable".into(), Some(true))]),
+        size: 1.5,
+    };
+    let json = package.to_json();
+    assert_eq!(json.get("type"), Some(&Json::from("bin")));
+    assert_eq!(json.get("name"), Some(&Json::from("ruson \"json\"")));
+    assert_eq!(Package::from_json(&json), Ok(package));
+}
+
+#[test]
+fn success_derive_parsed() {
+    let json: Json = r#"{ "name": "lycuid" }"#.parse().unwrap();
+    let author = Author::from_json(&json).unwrap();
+    assert_eq!(author.name, "lycuid");
+    assert_eq!(author.email, None);
+}
+
+#[test]
+fn error_derive() {
+    let json: Json = r#"{ "email": "a@b.c" }"#.parse().unwrap();
+    assert_eq!(
+        Author::from_json(&json).unwrap_err().to_string(),
+        " cannot convert 'Null' (null) into 'String'."
+    );
+    let json: Json = "[]".parse().unwrap();
+    assert_eq!(
+        Author::from_json(&json).unwrap_err().to_string(),
+        " cannot convert 'Array' into 'Author'."
+    );
+}
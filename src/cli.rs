@@ -2,6 +2,8 @@
 use super::lexer::Lexer;
 
 pub type Lines = Vec<String>;
+/// `Err` describes the expected value (example: "positive integer").
+pub type CliValidator = fn(&str) -> Result<(), &'static str>;
 
 /// Command line Flag (doesn't accept argument).
 #[derive(Debug, Clone)]
@@ -19,6 +21,53 @@ impl CliFlag {
     }
 }
 
+/// Kind of value accepted by a `CliOption` (checked while parsing).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CliValue {
+    /// any string.
+    Text,
+    /// non-negative integer.
+    Integer,
+    /// one of the allowed strings.
+    Choice(&'static [&'static str]),
+    /// file system path (cannot be empty).
+    Path,
+    /// name followed by a string (two arguments, example: `--arg name
+    /// value`), stored as consecutive values.
+    Pair,
+}
+
+impl CliValue {
+    /// description of the expected value, used in error messages.
+    pub fn expected(&self) -> String {
+        match self {
+            Self::Text => "string".into(),
+            Self::Integer => "non-negative integer".into(),
+            Self::Choice(choices) => choices.join("|"),
+            Self::Path => "path".into(),
+            Self::Pair => "name and value".into(),
+        }
+    }
+
+    /// number of arguments taken by every occurrence of the option.
+    pub fn arity(&self) -> usize {
+        match self {
+            Self::Pair => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn accepts(&self, value: &str) -> bool {
+        match self {
+            Self::Text => true,
+            Self::Integer => value.parse::<usize>().is_ok(),
+            Self::Choice(choices) => choices.contains(&value),
+            Self::Path => !value.is_empty(),
+            Self::Pair => true,
+        }
+    }
+}
+
 /// Command line Argument Option (always accept argument).
 #[derive(Debug, Clone)]
 pub struct CliOption {
@@ -28,14 +77,48 @@ pub struct CliOption {
     /// default value for the current option.
     pub default: Option<String>,
     pub flag: CliFlag,
+    /// accumulate every occurrence, instead of keeping only the last one.
+    pub repeatable: bool,
+    pub value: CliValue,
+    /// additional check, run after `value` is accepted.
+    pub validate: Option<CliValidator>,
 }
 
 impl CliOption {
+    /// check `value` against the option's value kind and `validate` callback.
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        let expected = if !self.value.accepts(value) {
+            Some(self.value.expected())
+        } else {
+            self.validate
+                .and_then(|validate| validate(value).err())
+                .map(String::from)
+        };
+        match expected {
+            Some(expected) => Err(format!(
+                " Invalid value for '{}': '{}' (expected {}).",
+                self.name, value, expected
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// usage notation of the value(s), example: `<name>`, `<name> <value>`.
+    pub fn usage(&self) -> String {
+        match self.value {
+            CliValue::Pair => "<name> <value>".into(),
+            _ => format!("<{}>", self.name),
+        }
+    }
+
     /// parse long option with syntax `--option=value` and return `value`.
     pub fn assoc_value(&self, arg: &str) -> Option<String> {
         let mut argparser = Lexer::new(&arg);
+        // long only options are declared as `short`.
+        let short = Some(self.flag.short).filter(|s| s.starts_with("--"));
         self.flag
             .long
+            .or(short)
             .and_then(|long| argparser.consume_string(long))
             .and_then(|_| argparser.consume_byte('='))
             .and_then(|_| {
@@ -44,6 +127,65 @@ impl CliOption {
     }
 }
 
+/// Number of values accepted by a positional argument.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CliArity {
+    One,
+    /// all the remaining arguments (only sensible for the last positional).
+    Many,
+}
+
+/// Positional argument (any argument that isn't a flag or option).
+#[derive(Debug, Clone)]
+pub struct CliArg {
+    /// Display name in the Program Usage string (example: FILE).
+    pub name: &'static str,
+    pub arity: CliArity,
+    pub required: bool,
+    pub description: Lines,
+}
+
+impl CliArg {
+    /// usage notation, example: `FILE`, `[FILE]`, `[FILE]...`.
+    pub fn usage(&self) -> String {
+        let name = if self.required {
+            self.name.to_string()
+        } else {
+            format!("[{}]", self.name)
+        };
+        match self.arity {
+            CliArity::One => name,
+            CliArity::Many => format!("{}...", name),
+        }
+    }
+}
+
+/// Usage example, rendered in the manpage.
+#[derive(Debug, Clone)]
+pub struct CliExample {
+    pub description: &'static str,
+    pub command: &'static str,
+    pub output: &'static str,
+}
+
+/// Additional manpage section (not displayed in the usage string).
+#[derive(Debug, Clone)]
+pub struct CliSection {
+    pub title: &'static str,
+    /// paragraphs, rendered before the examples.
+    pub lines: Lines,
+    pub examples: Vec<CliExample>,
+}
+
+/// Subcommand (example: `ruson validate FILE`), with its own flags, options
+/// and positional arguments.
+#[derive(Debug, Clone)]
+pub struct CliCommand {
+    pub name: &'static str,
+    pub description: Lines,
+    pub cli: Cli,
+}
+
 #[derive(Debug, Clone)]
 pub struct Cli {
     name: &'static str,
@@ -51,8 +193,15 @@ pub struct Cli {
     footer: Lines,
     /// using `Vec` instead of `HashMap` to preserve order.
     flags: Vec<CliFlag>,
+    /// flags that are parsed, but never displayed.
+    hidden_flags: Vec<CliFlag>,
     /// using `Vec` instead of `HashMap` to preserve order.
     options: Vec<CliOption>,
+    /// matched in order, against the arguments following flags and options.
+    positionals: Vec<CliArg>,
+    /// using `Vec` instead of `HashMap` to preserve order.
+    commands: Vec<CliCommand>,
+    sections: Vec<CliSection>,
 }
 
 impl Cli {
@@ -73,7 +222,11 @@ impl Cli {
                     description: vec!["Display version and exit.".into()],
                 },
             ],
+            hidden_flags: vec![],
             options: vec![],
+            positionals: vec![],
+            commands: vec![],
+            sections: vec![],
         }
     }
 
@@ -92,16 +245,64 @@ impl Cli {
         self
     }
 
+    pub fn add_hidden_flag(&mut self, flag: CliFlag) -> &mut Self {
+        self.hidden_flags.push(flag);
+        self
+    }
+
     pub fn add_option(&mut self, option: CliOption) -> &mut Self {
         self.options.push(option);
         self
     }
 
+    pub fn add_positional(&mut self, arg: CliArg) -> &mut Self {
+        self.positionals.push(arg);
+        self
+    }
+
+    pub fn add_command(&mut self, command: CliCommand) -> &mut Self {
+        self.commands.push(command);
+        self
+    }
+
+    /// subcommand matching `arg` exactly (only valid as the first argument).
+    pub fn command(&self, arg: &str) -> Option<&CliCommand> {
+        self.commands.iter().find(|command| command.name == arg)
+    }
+
+    pub fn add_section(&mut self, section: CliSection) -> &mut Self {
+        self.sections.push(section);
+        self
+    }
+
+    /// roff formatted manpage, generated from the current `Cli`.
+    pub fn manpage<'a>(
+        &'a self,
+        version: &'a str,
+        author: &'a str,
+    ) -> ManPage<'a> {
+        ManPage {
+            cli: self,
+            version,
+            author,
+        }
+    }
+
+    /// usage notation of all the positional arguments (space separated).
+    pub fn synopsis(&self) -> String {
+        let usages: Vec<String> =
+            self.positionals.iter().map(CliArg::usage).collect();
+        usages.join(" ")
+    }
+
     fn empty_err(key: &str) -> String {
         format!("'{}' cannot be empty.", key)
     }
 
-    /// parses and populates `Vec<flag.short>` and `HashMap<option.name, value>`.
+    /// parses and populates `Vec<flag.short>` and `HashMap<option.name, values>`.
+    /// every occurrence of a repeatable option is appended to its values (in
+    /// order), other options only keep the last occurrence.
+    /// default value is only kept, if the option is never provided.
     ///
     /// Returns:
     /// - `Err(String)`: argument parse error (malformed arguments etc).
@@ -111,14 +312,64 @@ impl Cli {
         &self,
         args: &mut I,
         flags: &mut Vec<String>,
-        options: &mut std::collections::HashMap<&'static str, String>,
+        options: &mut std::collections::HashMap<&'static str, Vec<String>>,
     ) -> Result<Option<String>, String> {
+        self.populate(args, flags, options, false)
+            .map(|positionals| positionals.into_iter().next())
+    }
+
+    /// same as [`parse_and_populate`](Self::parse_and_populate), flags and
+    /// options are also parsed after the positional arguments (only the
+    /// arguments following `--` are positionals, whatever they look like).
+    /// a single hyphen (stdin) is skipped.
+    ///
+    /// Returns the positional arguments (in order), see
+    /// [`populate_positionals`](Self::populate_positionals).
+    pub fn parse_all<I: Iterator<Item = String>>(
+        &self,
+        args: &mut I,
+        flags: &mut Vec<String>,
+        options: &mut std::collections::HashMap<&'static str, Vec<String>>,
+    ) -> Result<Vec<String>, String> {
+        self.populate(args, flags, options, true)
+    }
+
+    /// positional arguments, up to the first one (unless `interleaved`).
+    fn populate<I: Iterator<Item = String>>(
+        &self,
+        args: &mut I,
+        flags: &mut Vec<String>,
+        options: &mut std::collections::HashMap<&'static str, Vec<String>>,
+        interleaved: bool,
+    ) -> Result<Vec<String>, String> {
         // populating with options that have default value.
         for option in self.options.iter() {
             if let Some(value) = &option.default {
-                options.insert(option.name, value.clone());
+                options.insert(option.name, vec![value.clone()]);
             }
         }
+        let mut provided = std::collections::HashSet::new();
+        // `value` followed by the rest of the arguments of an occurrence
+        // (see `CliValue::arity`).
+        let mut insert = |opt: &CliOption, value: String, rest: &mut I| {
+            let mut occurrence = vec![value];
+            occurrence.extend(rest.take(opt.value.arity() - 1));
+            if occurrence.len() < opt.value.arity() {
+                return Err(Self::empty_err(opt.name));
+            }
+            // only the value is checked (example: not the name of a pair).
+            if let Some(value) = occurrence.last() {
+                opt.check(value)?;
+            }
+            let values = options.entry(opt.name).or_default();
+            // first occurrence overrides the default value.
+            if provided.insert(opt.name) || !opt.repeatable {
+                values.clear();
+            }
+            values.extend(occurrence);
+            Ok::<_, String>(())
+        };
+        let mut positionals = vec![];
 
         'mainloop: while let Some(arg) = args.next() {
             let mut chars = arg.chars();
@@ -126,37 +377,44 @@ impl Cli {
             match chars.next() {
                 Some('-') => match chars.next() {
                     // read from stdin (single hyphen).
+                    None if interleaved => continue,
                     None => break,
                     Some('-') => {
+                        // double hyphen, end of command.
+                        // return the next argument(s) as is.
+                        if chars.next().is_none() {
+                            match interleaved {
+                                true => positionals.extend(args),
+                                false => positionals.extend(args.next()),
+                            }
+                            break;
+                        }
                         // handle long options only (starts with double hyphen).
-                        if chars.next().is_some() {
-                            // try matching flags, continue mainloop if found.
-                            for flag in self.flags.iter() {
-                                if flag.matches(&arg) {
-                                    flags.push(String::from(flag.short));
-                                    continue 'mainloop;
-                                }
+                        // try matching flags, continue mainloop if found.
+                        for flag in self.flags.iter().chain(&self.hidden_flags)
+                        {
+                            if flag.matches(&arg) {
+                                flags.push(String::from(flag.short));
+                                continue 'mainloop;
                             }
-                            // try matching options, continue mainloop if found.
-                            for opt in self.options.iter() {
-                                if opt.flag.matches(&arg) {
-                                    args.next()
-                                        .and_then(|next| {
-                                            options.insert(opt.name, next);
-                                            Some(())
-                                        })
-                                        .ok_or(Self::empty_err(opt.name))?;
-                                    continue 'mainloop;
-                                }
-                                if let Some(value) = opt.assoc_value(&arg) {
-                                    options.insert(opt.name, value);
-                                    continue 'mainloop;
-                                }
+                        }
+                        // try matching options, continue mainloop if found.
+                        // the next argument is always the value, even if it
+                        // starts with a hyphen (example: `--query -1`).
+                        for opt in self.options.iter() {
+                            if opt.flag.matches(&arg) {
+                                let next = args
+                                    .next()
+                                    .ok_or(Self::empty_err(opt.name))?;
+                                insert(opt, next, args)?;
+                                continue 'mainloop;
+                            }
+                            if let Some(value) = opt.assoc_value(&arg) {
+                                insert(opt, value, args)?;
+                                continue 'mainloop;
                             }
                         }
-                        // double hyphen, end of command.
-                        // return the next argument as is.
-                        return Ok(args.next());
+                        return Err(format!(" Invalid flag: '{}'.", arg));
                     }
 
                     // single hyphen followed by non hyphen character[s]:
@@ -198,7 +456,7 @@ impl Cli {
                                     } else {
                                         rest
                                     };
-                                    options.insert(option.name, value);
+                                    insert(option, value, args)?;
                                     continue 'mainloop;
                                 }
                             }
@@ -210,22 +468,91 @@ impl Cli {
                 },
                 // return arg as the 'default' argument.
                 // if it doesn't start with a hyphen (`-`).
-                _ => return Ok(Some(arg)),
+                _ => {
+                    positionals.push(arg);
+                    if !interleaved {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(positionals)
+    }
+
+    /// assigns `args` to the declared positional arguments (in order) and
+    /// populates `HashMap<arg.name, values>`.
+    ///
+    /// Returns `Err(String)`, if a required argument is missing or there are
+    /// more arguments than declared.
+    pub fn populate_positionals<I: Iterator<Item = String>>(
+        &self,
+        mut args: I,
+        positionals: &mut std::collections::HashMap<&'static str, Vec<String>>,
+    ) -> Result<(), String> {
+        for positional in self.positionals.iter() {
+            let values: Vec<String> = match positional.arity {
+                CliArity::One => args.next().into_iter().collect(),
+                CliArity::Many => args.by_ref().collect(),
+            };
+            if values.is_empty() && positional.required {
+                return Err(format!(
+                    " Missing argument: '{}'.",
+                    positional.name
+                ));
             }
+            positionals.insert(positional.name, values);
+        }
+        match args.next() {
+            Some(arg) => Err(format!(" Unexpected argument: '{}'.", arg)),
+            None => Ok(()),
         }
-        Ok(None)
     }
 }
 
 impl std::fmt::Display for Cli {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "USAGE: {} [FLAGS|OPTIONS]... FILE", self.name)?;
+        writeln!(
+            f,
+            "USAGE: {} [FLAGS|OPTIONS]... {}",
+            self.name,
+            self.synopsis()
+        )?;
 
         if !self.description.is_empty() {
             writeln!(f, "{}", self.description.join("\n"))?;
             writeln!(f, "")?; // padding.
         }
 
+        if !self.positionals.is_empty() {
+            writeln!(f, "ARGUMENTS:")?;
+            for arg in self.positionals.iter() {
+                writeln!(f, "  {}", arg.usage())?;
+
+                let printable_arg_description: String = arg
+                    .description
+                    .iter()
+                    .map(|s| format!("\t\t{}\n", s))
+                    .collect();
+                write!(f, "{}", printable_arg_description)?;
+            }
+            writeln!(f, "")?; // padding.
+        }
+
+        if !self.commands.is_empty() {
+            writeln!(f, "COMMANDS:")?;
+            for command in self.commands.iter() {
+                writeln!(f, "  {}", command.name)?;
+
+                let printable_command_description: String = command
+                    .description
+                    .iter()
+                    .map(|s| format!("\t\t{}\n", s))
+                    .collect();
+                write!(f, "{}", printable_command_description)?;
+            }
+            writeln!(f, "")?; // padding.
+        }
+
         if !self.flags.is_empty() {
             writeln!(f, "FLAGS:")?;
             for flag in self.flags.iter() {
@@ -252,7 +579,8 @@ impl std::fmt::Display for Cli {
                 if let Some(long_opt) = opt.flag.long {
                     write!(f, ", {}", long_opt)?;
                 }
-                writeln!(f, " <{}>", opt.name)?;
+                write!(f, " {}", opt.usage())?;
+                writeln!(f, "{}", if opt.repeatable { "..." } else { "" })?;
 
                 let printable_option_description: String = opt
                     .flag
@@ -268,3 +596,133 @@ impl std::fmt::Display for Cli {
         write!(f, "{}", self.footer.join("\n"))
     }
 }
+
+pub struct ManPage<'a> {
+    cli: &'a Cli,
+    version: &'a str,
+    author: &'a str,
+}
+
+impl ManPage<'_> {
+    /// escape roff special characters, so that text is displayed as is.
+    fn escape(s: &str) -> String {
+        let escaped = s.replace('\\', "\\e").replace('-', "\\-");
+        if escaped.starts_with(['.', '\'']) {
+            format!("\\&{}", escaped)
+        } else {
+            escaped
+        }
+    }
+
+    fn write_flag(
+        f: &mut std::fmt::Formatter<'_>,
+        flag: &CliFlag,
+        usage: Option<&str>,
+    ) -> std::fmt::Result {
+        writeln!(f, ".PP")?;
+        write!(f, "\\fB{}", Self::escape(flag.short))?;
+        if let Some(long) = flag.long {
+            write!(f, ", {}", Self::escape(long))?;
+        }
+        write!(f, "\\fR")?;
+        if let Some(usage) = usage {
+            write!(f, " \\fI{}\\fR", Self::escape(usage))?;
+        }
+        writeln!(f)?;
+        writeln!(f, ".RS")?;
+        for line in flag.description.iter() {
+            writeln!(f, "{}", Self::escape(line))?;
+        }
+        writeln!(f, ".RE")
+    }
+}
+
+impl std::fmt::Display for ManPage<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = Self::escape(self.cli.name);
+        let summary = self.cli.description.first().map(String::as_str);
+
+        writeln!(f, ".TH {} 1 {}-{}", name, name, Self::escape(self.version))?;
+
+        writeln!(f, ".SH NAME")?;
+        writeln!(f, "{} \\- {}", name, Self::escape(summary.unwrap_or("")))?;
+
+        writeln!(f, ".SH SYNOPSIS")?;
+        writeln!(
+            f,
+            "\\fB{}\\fR [\\fIFLAGS|OPTIONS\\fR]... \\fI{}\\fR",
+            name,
+            Self::escape(&self.cli.synopsis())
+        )?;
+
+        if !self.cli.description.is_empty() {
+            writeln!(f, ".SH DESCRIPTION")?;
+            for line in self.cli.description.iter() {
+                writeln!(f, "{}", Self::escape(line))?;
+            }
+        }
+
+        if !self.cli.positionals.is_empty() {
+            writeln!(f, ".SH ARGUMENTS")?;
+            for arg in self.cli.positionals.iter() {
+                writeln!(f, ".PP")?;
+                writeln!(f, "\\fI{}\\fR", Self::escape(&arg.usage()))?;
+                writeln!(f, ".RS")?;
+                for line in arg.description.iter() {
+                    writeln!(f, "{}", Self::escape(line))?;
+                }
+                writeln!(f, ".RE")?;
+            }
+        }
+
+        if !self.cli.commands.is_empty() {
+            writeln!(f, ".SH COMMANDS")?;
+            for command in self.cli.commands.iter() {
+                writeln!(f, ".PP")?;
+                writeln!(f, "\\fB{}\\fR", Self::escape(command.name))?;
+                writeln!(f, ".RS")?;
+                for line in command.description.iter() {
+                    writeln!(f, "{}", Self::escape(line))?;
+                }
+                writeln!(f, ".RE")?;
+            }
+        }
+
+        if !self.cli.flags.is_empty() || !self.cli.options.is_empty() {
+            writeln!(f, ".SH OPTIONS")?;
+            for flag in self.cli.flags.iter() {
+                Self::write_flag(f, flag, None)?;
+            }
+            for opt in self.cli.options.iter() {
+                Self::write_flag(f, &opt.flag, Some(&opt.usage()))?;
+            }
+        }
+
+        for section in self.cli.sections.iter() {
+            writeln!(f, ".SH {}", Self::escape(section.title).to_uppercase())?;
+            if !section.lines.is_empty() {
+                writeln!(f, ".PP")?;
+                for line in section.lines.iter() {
+                    writeln!(f, "{}", Self::escape(line))?;
+                }
+            }
+            for example in section.examples.iter() {
+                writeln!(f, ".IP \\[bu] 2")?;
+                writeln!(f, "{}", Self::escape(example.description))?;
+                writeln!(f, ".RS 4")?;
+                writeln!(f, ".nf")?;
+                writeln!(f, "{}", Self::escape(example.command))?;
+                writeln!(f, ".fi")?;
+                writeln!(f, ".RE")?;
+                if !example.output.is_empty() {
+                    writeln!(f, ".RS 4")?;
+                    writeln!(f, "output: {}", Self::escape(example.output))?;
+                    writeln!(f, ".RE")?;
+                }
+            }
+        }
+
+        writeln!(f, ".SH AUTHOR")?;
+        write!(f, "Written by {}", Self::escape(self.author))
+    }
+}
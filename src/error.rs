@@ -1,12 +1,51 @@
-//! Error formatting utilities.
+//! Error formatting utilities (exit helpers require the `cli` feature).
+use crate::prelude::*;
+#[cfg(feature = "cli")]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Exit codes, distinct for every class of failure.
+#[cfg(feature = "cli")]
+pub mod exit_code {
+    pub const SUCCESS: i32 = 0;
+    /// generic failure (internal errors).
+    pub const FAILURE: i32 = 1;
+    /// invalid command line arguments (flags, options or query syntax).
+    pub const USAGE: i32 = 2;
+    /// input is not valid json text (or exceeds the parser limits).
+    pub const PARSE: i32 = 3;
+    /// query cannot be applied on the input.
+    pub const QUERY: i32 = 4;
+    /// input cannot be read (file, url or stdin).
+    pub const IO: i32 = 5;
+}
+
+/// suppress error messages (exit codes are kept), see [`set_quiet`].
+#[cfg(feature = "cli")]
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "cli")]
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// print error message to stderr (unless quiet).
+#[cfg(feature = "cli")]
+pub fn report(message: &str) {
+    if !QUIET.load(Ordering::Relaxed) {
+        eprintln!("{}", message);
+    }
+}
+
+#[cfg(feature = "cli")]
 pub trait RusonResult<T> {
     fn unwrap_or_exit(self) -> T;
     fn unwrap_or_exit_with(self, exit_code: i32) -> T;
 }
 
+#[cfg(feature = "cli")]
 impl<T, E: std::fmt::Display> RusonResult<T> for Result<T, E> {
     fn unwrap_or_exit(self) -> T {
-        self.unwrap_or_exit_with(1)
+        self.unwrap_or_exit_with(exit_code::FAILURE)
     }
 
     fn unwrap_or_exit_with(self, exit_code: i32) -> T {
@@ -16,16 +55,22 @@ impl<T, E: std::fmt::Display> RusonResult<T> for Result<T, E> {
                 let exit_string = format!("{}", displayable).errorfmt();
 
                 match exit_code {
-                    0 => {
-                        println!("{}", exit_string);
+                    // exiting successfully, even if stdout is closed (example:
+                    // `ruson -v | true`).
+                    exit_code::SUCCESS => {
+                        use std::io::Write;
+                        let _ = writeln!(std::io::stdout(), "{}", exit_string);
                     }
-                    2 => {
+                    exit_code::USAGE => {
                         let bin = std::env::args().next().unwrap();
-                        eprintln!("{}", exit_string);
-                        eprintln!("Try '{} --help' for more information.", bin);
+                        report(&exit_string);
+                        report(&format!(
+                            "Try '{} --help' for more information.",
+                            bin
+                        ));
                     }
                     _ => {
-                        eprintln!("{}", exit_string);
+                        report(&exit_string);
                     }
                 };
 
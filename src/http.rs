@@ -0,0 +1,341 @@
+//! Minimal HTTP/1.1 client (`GET` only), for reading json text from urls.
+//! `https` urls are fetched using the external `curl` command, if available.
+use std::{
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    process::Command,
+    time::Duration,
+};
+
+/// maximum number of redirects followed before erroring out.
+pub const MAX_REDIRECTS: usize = 5;
+/// connect/read/write timeout (each), of the `GET` requests.
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+/// bytes of the response head (status line and headers, chunk sizes of the
+/// body) read on top of the body limit, see [`get_within`].
+pub const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Url {
+    pub scheme: String,
+    /// ipv6 addresses without the brackets.
+    pub host: String,
+    pub port: u16,
+    /// path including the query string (always starts with '/').
+    pub path: String,
+}
+
+impl Url {
+    /// parse `scheme://host[:port][/path]`, only `http` and `https` schemes
+    /// (ipv6 hosts in brackets, example: `http://[::1]:8000/`).
+    pub fn parse(s: &str) -> Option<Self> {
+        let (scheme, rest) = s.split_once("://")?;
+        let default_port = default_port(scheme)?;
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(index) => (&rest[..index], rest[index..].to_string()),
+            None => (rest, "/".into()),
+        };
+        let path = if path.starts_with('?') {
+            format!("/{}", path)
+        } else {
+            path
+        };
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(ipv6) => match ipv6.split_once(']')? {
+                (host, "") => (host, default_port),
+                (host, port) => (host, port.strip_prefix(':')?.parse().ok()?),
+            },
+            None => match authority.rsplit_once(':') {
+                Some((host, port)) => (host, port.parse().ok()?),
+                None => (authority, default_port),
+            },
+        };
+        if host.is_empty() {
+            return None;
+        }
+        Some(Self {
+            scheme: scheme.into(),
+            host: host.into(),
+            port,
+            path,
+        })
+    }
+
+    /// `host[:port]` as in the url (ipv6 hosts in brackets), without the
+    /// default port of the scheme.
+    pub fn authority(&self) -> String {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        match default_port(&self.scheme) {
+            Some(port) if port == self.port => host,
+            _ => format!("{}:{}", host, self.port),
+        }
+    }
+
+    /// resolve `location` (absolute or relative) against the current url.
+    pub fn join(&self, location: &str) -> Option<Self> {
+        if location.contains("://") {
+            Self::parse(location)
+        } else if location.starts_with('/') {
+            Some(Self {
+                path: location.into(),
+                ..self.clone()
+            })
+        } else {
+            let base = &self.path[..=self.path.rfind('/').unwrap_or(0)];
+            Some(Self {
+                path: format!("{}{}", base, location),
+                ..self.clone()
+            })
+        }
+    }
+}
+
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" => Some(80),
+        "https" => Some(443),
+        _ => None,
+    }
+}
+
+/// true, if the argument should be treated as a url rather than a file.
+pub fn is_url(s: &str) -> bool {
+    s.starts_with("http://") || s.starts_with("https://")
+}
+
+/// fetch response body of the `GET` request (following redirects).
+#[inline]
+pub fn get(url: &str) -> Result<String, String> {
+    get_with_timeout(url, TIMEOUT)
+}
+
+/// same as [`get`], with `timeout` for connecting, reading and writing.
+#[inline]
+pub fn get_with_timeout(
+    url: &str,
+    timeout: Duration,
+) -> Result<String, String> {
+    fetch(url, timeout, None)
+}
+
+/// same as [`get`], errors if the response body exceeds `max_size` bytes
+/// (the response is not read any further).
+#[inline]
+pub fn get_within(url: &str, max_size: usize) -> Result<String, String> {
+    fetch(url, TIMEOUT, Some(max_size))
+}
+
+fn too_large(max_size: usize) -> String {
+    format!(" Response body exceeds {} bytes.", max_size)
+}
+
+fn fetch(
+    url: &str,
+    timeout: Duration,
+    max_size: Option<usize>,
+) -> Result<String, String> {
+    let mut url = Url::parse(url).ok_or(format!(" Invalid url: '{}'.", url))?;
+    let within = |body: String| match max_size {
+        Some(max_size) if body.len() > max_size => Err(too_large(max_size)),
+        _ => Ok(body),
+    };
+    for _ in 0..=MAX_REDIRECTS {
+        if url.scheme == "https" {
+            return curl(&url, timeout, max_size).and_then(within);
+        }
+        let response = request(&url, timeout, max_size)?;
+        match response.status {
+            200..=299 => return response.body().and_then(within),
+            300..=399 => {
+                url = response
+                    .header("location")
+                    .and_then(|location| url.join(location))
+                    .ok_or(format!(
+                        " Invalid redirect ({}).",
+                        response.status
+                    ))?
+            }
+            status => {
+                return Err(format!(" HTTP {} {}.", status, response.reason))
+            }
+        }
+    }
+    Err(format!(" Too many redirects (max {}).", MAX_REDIRECTS))
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub reason: String,
+    /// header names are lowercased.
+    pub headers: Vec<(String, String)>,
+    pub raw_body: Vec<u8>,
+}
+
+impl Response {
+    /// parse raw HTTP/1.1 response bytes.
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let malformed = || String::from(" Malformed HTTP response.");
+        let separator = bytes
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .ok_or_else(malformed)?;
+        let head = String::from_utf8_lossy(&bytes[..separator]);
+        let mut lines = head.split("\r\n");
+
+        let mut status_line =
+            lines.next().ok_or_else(malformed)?.splitn(3, ' ');
+        let status = status_line
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(malformed)?;
+        let reason = status_line.next().unwrap_or("").into();
+
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().into()))
+            .collect();
+
+        Ok(Self {
+            status,
+            reason,
+            headers,
+            raw_body: bytes[separator + 4..].to_vec(),
+        })
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// decoded (de-chunked, truncated to 'content-length') response body.
+    pub fn body(&self) -> Result<String, String> {
+        let chunked = self
+            .header("transfer-encoding")
+            .is_some_and(|te| te.eq_ignore_ascii_case("chunked"));
+        let bytes = if chunked {
+            dechunk(&self.raw_body)?
+        } else if let Some(len) =
+            self.header("content-length").and_then(|l| l.parse().ok())
+        {
+            self.raw_body[..std::cmp::min(len, self.raw_body.len())].to_vec()
+        } else {
+            self.raw_body.clone()
+        };
+        String::from_utf8(bytes)
+            .map_err(|_| " Response body is not valid utf-8.".into())
+    }
+}
+
+/// decode `Transfer-Encoding: chunked` body.
+pub fn dechunk(mut bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let malformed = || String::from(" Malformed chunked response body.");
+    let mut body = Vec::new();
+    loop {
+        let line_end = bytes
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .ok_or_else(malformed)?;
+        let size_line = String::from_utf8_lossy(&bytes[..line_end]);
+        // ignore chunk extensions (`size;name=value`).
+        let size_hex = size_line.split(';').next().unwrap_or("").trim();
+        let size =
+            usize::from_str_radix(size_hex, 16).map_err(|_| malformed())?;
+        bytes = &bytes[line_end + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        if bytes.len() < size {
+            return Err(malformed());
+        }
+        body.extend_from_slice(&bytes[..size]);
+        bytes = bytes.get(size + 2..).unwrap_or(&[]);
+    }
+}
+
+/// connect to the first reachable address of `url`.
+fn connect(url: &Url, timeout: Duration) -> std::io::Result<TcpStream> {
+    let mut last_err = None;
+    for addr in (url.host.as_str(), url.port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no addresses to connect to",
+        )
+    }))
+}
+
+fn request(
+    url: &Url,
+    timeout: Duration,
+    max_size: Option<usize>,
+) -> Result<Response, String> {
+    let io_err = |err: std::io::Error| format!(" '{}' {}", url.host, err);
+    let mut stream = connect(url, timeout).map_err(io_err)?;
+    stream.set_read_timeout(Some(timeout)).map_err(io_err)?;
+    stream.set_write_timeout(Some(timeout)).map_err(io_err)?;
+    let request = format!(
+        "GET {} HTTP/1.1\r\n\
+         Host: {}\r\n\
+         User-Agent: {}/{}\r\n\
+         Accept: application/json, */*\r\n\
+         Connection: close\r\n\r\n",
+        url.path,
+        url.authority(),
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+    );
+    stream.write_all(request.as_bytes()).map_err(io_err)?;
+    // one byte over the limit, to be able to detect oversized responses.
+    let limit = max_size.map_or(u64::MAX, |max_size| {
+        max_size.saturating_add(MAX_HEAD_SIZE) as u64 + 1
+    });
+    let mut bytes = Vec::new();
+    stream.take(limit).read_to_end(&mut bytes).map_err(io_err)?;
+    match max_size {
+        Some(max_size) if bytes.len() as u64 == limit => {
+            Err(too_large(max_size))
+        }
+        _ => Response::parse(&bytes),
+    }
+}
+
+/// fallback for `https` (tls not implemented).
+fn curl(
+    url: &Url,
+    timeout: Duration,
+    max_size: Option<usize>,
+) -> Result<String, String> {
+    let url = format!("{}://{}{}", url.scheme, url.authority(), url.path);
+    let timeout = timeout.as_secs().max(1).to_string();
+    let max_size = max_size.map(|max_size| max_size.to_string());
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location"])
+        .args(["--connect-timeout", &timeout, "--speed-time", &timeout])
+        .args(
+            max_size
+                .iter()
+                .flat_map(|max_size| ["--max-filesize", max_size]),
+        )
+        .arg(&url)
+        .output()
+        .map_err(|err| format!(" 'curl' is required for https: {}", err))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(" {}", stderr.trim()));
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|_| " Response body is not valid utf-8.".into())
+}
@@ -0,0 +1,352 @@
+//! Arena allocated json values: every node of a document is stored in a few
+//! flat buffers (owned by [`JsonArena`]), nodes refer to each other by index.
+//! Parsing performs no per-node allocation (strings borrow from the input,
+//! same as [`JsonRef`](super::borrowed::JsonRef)) and the whole document is
+//! freed at once, without a recursive `Drop`.
+use super::{
+    borrowed::{ParseResult, Parser, RawStr},
+    error::{JsonErrorType, JsonEvalError, JsonParseError},
+    query::JsonQuery,
+    token::{array_index, unescape, Json, Property},
+    HashSet,
+};
+use crate::prelude::*;
+use alloc::borrow::Cow;
+
+/// index of a node in [`JsonArena`].
+pub type NodeId = usize;
+
+/// objects with more keys use a hash set for detecting duplicate keys.
+const LINEAR_KEY_SCAN: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node<'a> {
+    Null,
+    Boolean(bool),
+    Number(f32),
+    /// unescaped on demand (same as [`JsonRef`](super::borrowed::JsonRef)).
+    QString(RawStr<'a>),
+    /// `len` elements in `children`, starting at `start`.
+    Array {
+        start: usize,
+        len: usize,
+    },
+    /// `len` entries in `entries`, starting at `start`.
+    Object {
+        start: usize,
+        len: usize,
+    },
+}
+
+/// Parsed json document, borrowing strings from the input text.
+#[derive(Debug, Clone, Default)]
+pub struct JsonArena<'a> {
+    nodes: Vec<Node<'a>>,
+    children: Vec<NodeId>,
+    entries: Vec<(Cow<'a, str>, NodeId)>,
+}
+
+/// reference to a node of a [`JsonArena`].
+#[derive(Debug, Clone, Copy)]
+pub struct NodeRef<'r, 'a> {
+    arena: &'r JsonArena<'a>,
+    id: NodeId,
+}
+
+/// parser state, children of the arrays/objects being parsed are collected
+/// on the scratch stacks (and moved to the arena once complete).
+struct Builder<'a> {
+    parser: Parser<'a>,
+    arena: JsonArena<'a>,
+    children: Vec<NodeId>,
+    entries: Vec<(Cow<'a, str>, NodeId)>,
+}
+
+impl<'a> Builder<'a> {
+    #[inline]
+    fn push(&mut self, node: Node<'a>) -> NodeId {
+        self.arena.nodes.push(node);
+        self.arena.nodes.len() - 1
+    }
+
+    fn parse_any(&mut self) -> ParseResult<NodeId> {
+        let parser = &mut self.parser;
+        let node = match parser.peek() {
+            Some(b'-' | b'0'..=b'9') => Node::Number(parser.parse_number()?),
+            Some(b't') => {
+                parser.parse_literal("true")?;
+                Node::Boolean(true)
+            }
+            Some(b'f') => {
+                parser.parse_literal("false")?;
+                Node::Boolean(false)
+            }
+            Some(b'n') => {
+                parser.parse_literal("null")?;
+                Node::Null
+            }
+            Some(b'"') => Node::QString(parser.parse_raw_qstring()?),
+            Some(b'[') => return self.parse_array(),
+            Some(b'{') => return self.parse_object(),
+            _ => return parser.error(JsonErrorType::SyntaxError),
+        };
+        Ok(self.push(node))
+    }
+
+    fn parse_array(&mut self) -> ParseResult<NodeId> {
+        self.parser.parse_byte(b'[')?;
+        let mark = self.children.len();
+        if self.parser.trim_front().peek() != Some(b']') {
+            loop {
+                self.parser.trim_front();
+                let id = self.parse_any()?;
+                self.children.push(id);
+                if self.parser.trim_front().peek() != Some(b',') {
+                    break;
+                }
+                let comma = self.parser.cursor;
+                if self.parser.trim_front_after(1).peek() == Some(b']') {
+                    return Err((JsonErrorType::TrailingCommaError, comma));
+                }
+            }
+        }
+        self.parser.trim_front().parse_byte(b']')?;
+        let start = self.arena.children.len();
+        self.arena.children.extend(self.children.drain(mark..));
+        let len = self.arena.children.len() - start;
+        Ok(self.push(Node::Array { start, len }))
+    }
+
+    fn parse_object(&mut self) -> ParseResult<NodeId> {
+        self.parser.parse_byte(b'{')?;
+        let mark = self.entries.len();
+        let mut keys = HashSet::new();
+        if self.parser.trim_front().peek() != Some(b'}') {
+            loop {
+                let key_start = self.parser.trim_front().cursor;
+                let key = self.parser.parse_qstring()?;
+                let entries = &self.entries[mark..];
+                let duplicate = if entries.len() < LINEAR_KEY_SCAN {
+                    entries.iter().any(|(found, _)| *found == key)
+                } else {
+                    if keys.is_empty() {
+                        keys.extend(entries.iter().map(|(key, _)| key.clone()));
+                    }
+                    !keys.insert(key.clone())
+                };
+                if duplicate {
+                    return Err((JsonErrorType::DuplicateKeyError, key_start));
+                }
+                self.parser.trim_front().parse_byte(b':')?;
+                self.parser.trim_front();
+                let id = self.parse_any()?;
+                self.entries.push((key, id));
+                if self.parser.trim_front().peek() != Some(b',') {
+                    break;
+                }
+                let comma = self.parser.cursor;
+                if self.parser.trim_front_after(1).peek() == Some(b'}') {
+                    return Err((JsonErrorType::TrailingCommaError, comma));
+                }
+            }
+        }
+        self.parser.trim_front().parse_byte(b'}')?;
+        let start = self.arena.entries.len();
+        self.arena.entries.extend(self.entries.drain(mark..));
+        let len = self.arena.entries.len() - start;
+        Ok(self.push(Node::Object { start, len }))
+    }
+}
+
+impl<'a> JsonArena<'a> {
+    /// parse json text, borrowing strings from `input`.
+    pub fn parse(input: &'a str) -> Result<Self, JsonParseError> {
+        let mut builder = Builder {
+            parser: Parser { input, cursor: 0 },
+            arena: Self::default(),
+            children: Vec::new(),
+            entries: Vec::new(),
+        };
+        builder.parser.trim_front();
+        match builder.parse_any() {
+            Ok(_) => Ok(builder.arena),
+            Err(err) => Err(builder.parser.parse_error(err)),
+        }
+    }
+
+    /// total number of nodes in the document.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// top level value (children are always parsed before their parent, so
+    /// the root is the last node).
+    pub fn root(&self) -> NodeRef<'_, 'a> {
+        NodeRef {
+            arena: self,
+            id: self.nodes.len() - 1,
+        }
+    }
+}
+
+impl<'r, 'a> NodeRef<'r, 'a> {
+    #[inline]
+    fn node(&self) -> &'r Node<'a> {
+        &self.arena.nodes[self.id]
+    }
+
+    #[inline]
+    fn at(&self, id: NodeId) -> Self {
+        Self {
+            arena: self.arena,
+            id,
+        }
+    }
+
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self.node(), Node::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.node() {
+            Node::Boolean(boolean) => Some(*boolean),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<f32> {
+        match self.node() {
+            Node::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    /// unescaped string (allocating only if it contains escapes).
+    pub fn as_str(&self) -> Option<Cow<'a, str>> {
+        self.as_raw_str().map(|string| string.unescaped())
+    }
+
+    pub fn as_raw_str(&self) -> Option<RawStr<'a>> {
+        match self.node() {
+            Node::QString(string) => Some(*string),
+            _ => None,
+        }
+    }
+
+    /// elements, if `self` is an array.
+    pub fn elements(&self) -> Option<impl Iterator<Item = Self> + 'r> {
+        let this = *self;
+        match self.node() {
+            Node::Array { start, len } => Some(
+                self.arena.children[*start..start + len]
+                    .iter()
+                    .map(move |&id| this.at(id)),
+            ),
+            _ => None,
+        }
+    }
+
+    /// (unescaped key, value) pairs in input order, if `self` is an object.
+    pub fn entries(
+        &self,
+    ) -> Option<impl Iterator<Item = (&'r str, Self)> + 'r> {
+        let this = *self;
+        match self.node() {
+            Node::Object { start, len } => Some(
+                self.arena.entries[*start..start + len]
+                    .iter()
+                    .map(move |(key, id)| (key.as_ref(), this.at(*id))),
+            ),
+            _ => None,
+        }
+    }
+
+    /// value of `key`, if `self` is an object containing `key`.
+    pub fn get(&self, key: &str) -> Option<Self> {
+        self.entries()?
+            .find(|(found, _)| *found == key)
+            .map(|(_, value)| value)
+    }
+
+    /// element at `index`, if `self` is an array with more than `index` elements.
+    pub fn get_index(&self, index: usize) -> Option<Self> {
+        self.elements()?.nth(index)
+    }
+
+    /// number of elements (array) or entries (object).
+    pub fn size(&self) -> Option<usize> {
+        match self.node() {
+            Node::Array { len, .. } | Node::Object { len, .. } => Some(*len),
+            _ => None,
+        }
+    }
+
+    /// owned [`Json`] (strings are escaped back).
+    pub fn to_json(&self) -> Json {
+        match self.node() {
+            Node::Null => Json::Null,
+            Node::Boolean(boolean) => Json::Boolean(*boolean),
+            Node::Number(number) => Json::Number(*number),
+            Node::QString(string) => Json::QString(string.as_raw().into()),
+            Node::Array { .. } => self
+                .elements()
+                .into_iter()
+                .flatten()
+                .map(|json| json.to_json())
+                .collect(),
+            Node::Object { .. } => self
+                .entries()
+                .into_iter()
+                .flatten()
+                .map(|(key, value)| (key.to_string(), value.to_json()))
+                .collect(),
+        }
+    }
+
+    /// apply `query`, same as [`JsonRef::apply`](super::borrowed::JsonRef::apply).
+    pub fn apply(&self, query: &JsonQuery) -> Result<Json, JsonEvalError> {
+        let mut node = *self;
+        let mut properties = query.properties().peekable();
+        while let Some(property) = properties.peek() {
+            let selected = match (property, node.node()) {
+                (
+                    Property::Dot(key, _) | Property::Bracket(key, _),
+                    Node::Object { .. },
+                ) => node.get(&unescape(key)).ok_or_else(|| {
+                    let keys = node.entries().into_iter().flatten();
+                    JsonEvalError::missing_key(key, keys.map(|(key, _)| key))
+                }),
+                (Property::Index(index, _), Node::Array { len, .. }) => {
+                    array_index(*index, *len)
+                        .and_then(|index| node.get_index(index))
+                        .ok_or(JsonEvalError::InvalidIndex(*index, *len))
+                }
+                (Property::Pipe, _) => Ok(node),
+                // rest of the query (including errors) is handled by `Json`.
+                _ => break,
+            };
+            node = match selected {
+                Ok(node) => node,
+                // rest of the stage is short-circuited.
+                Err(_) if property.is_optional() => {
+                    properties
+                        .find(|property| matches!(property, Property::Pipe));
+                    return Json::Null
+                        .apply(&JsonQuery(properties.cloned().collect()));
+                }
+                Err(err) => return Err(err),
+            };
+            properties.next();
+        }
+        node.to_json()
+            .apply(&JsonQuery(properties.cloned().collect()))
+    }
+}
@@ -0,0 +1,375 @@
+//! Zero-copy json values: strings (and keys) borrow from the input text.
+//! String values are kept escaped (as in the input) and unescaped on demand,
+//! so values passed through verbatim are never unescaped (or escaped back),
+//! keys are unescaped while parsing (allocating only if they contain
+//! escapes). Useful for read-only workloads, where most of the document is
+//! never converted to [`Json`].
+use super::{
+    error::{JsonErrorType, JsonEvalError, JsonParseError},
+    query::JsonQuery,
+    token::{array_index, unescape, Json, Property},
+    HashMap,
+};
+use crate::{lexer::Position, prelude::*};
+use alloc::borrow::Cow;
+use core::fmt;
+
+/// String contents, borrowed (escaped) from the json text. compares, formats
+/// and hashes by the unescaped contents.
+#[derive(Clone, Copy)]
+pub struct RawStr<'a>(&'a str);
+
+impl<'a> RawStr<'a> {
+    /// escaped contents (without the quotes), as in the json text.
+    #[inline]
+    pub fn as_raw(&self) -> &'a str {
+        self.0
+    }
+
+    #[inline]
+    pub fn is_escaped(&self) -> bool {
+        self.0.contains('\\')
+    }
+
+    /// unescaped contents (allocating only if `self` contains escapes).
+    pub fn unescaped(&self) -> Cow<'a, str> {
+        if self.is_escaped() {
+            Cow::Owned(unescape(self.0))
+        } else {
+            Cow::Borrowed(self.0)
+        }
+    }
+}
+
+impl PartialEq for RawStr<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+            || (self.is_escaped() || other.is_escaped())
+                && self.unescaped() == other.unescaped()
+    }
+}
+
+impl Eq for RawStr<'_> {}
+
+impl core::hash::Hash for RawStr<'_> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.unescaped().hash(state)
+    }
+}
+
+impl fmt::Display for RawStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.unescaped(), f)
+    }
+}
+
+impl fmt::Debug for RawStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.unescaped(), f)
+    }
+}
+
+/// Borrowed counterpart of [`Json`], string values are unescaped on demand
+/// (see [`RawStr`]), keys are stored unescaped.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonRef<'a> {
+    Null,
+    Boolean(bool),
+    Number(f32),
+    QString(RawStr<'a>),
+    Array(Vec<JsonRef<'a>>),
+    Object(HashMap<Cow<'a, str>, JsonRef<'a>>),
+}
+
+pub(super) type ParseResult<T> = Result<T, (JsonErrorType, usize)>;
+
+/// byte oriented parser, over the input text (also used by
+/// [`JsonArena`](super::arena::JsonArena)).
+pub(super) struct Parser<'a> {
+    pub(super) input: &'a str,
+    pub(super) cursor: usize,
+}
+
+impl<'a> Parser<'a> {
+    #[inline]
+    pub(super) fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.cursor).copied()
+    }
+
+    #[inline]
+    pub(super) fn error<T>(&self, error_type: JsonErrorType) -> ParseResult<T> {
+        Err((error_type, self.cursor))
+    }
+
+    #[inline]
+    pub(super) fn trim_front(&mut self) -> &mut Self {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.cursor += 1;
+        }
+        self
+    }
+
+    pub(super) fn parse_byte(&mut self, byte: u8) -> ParseResult<()> {
+        match self.peek() {
+            Some(found) if found == byte => {
+                self.cursor += 1;
+                Ok(())
+            }
+            _ => self.error(JsonErrorType::SyntaxError),
+        }
+    }
+
+    pub(super) fn parse_literal(&mut self, literal: &str) -> ParseResult<()> {
+        if self.input[self.cursor..].starts_with(literal) {
+            self.cursor += literal.len();
+            Ok(())
+        } else {
+            self.error(JsonErrorType::SyntaxError)
+        }
+    }
+
+    fn parse_any(&mut self) -> ParseResult<JsonRef<'a>> {
+        match self.peek() {
+            Some(b'-' | b'0'..=b'9') => {
+                self.parse_number().map(JsonRef::Number)
+            }
+            Some(b't') => {
+                self.parse_literal("true").map(|_| JsonRef::Boolean(true))
+            }
+            Some(b'f') => {
+                self.parse_literal("false").map(|_| JsonRef::Boolean(false))
+            }
+            Some(b'n') => self.parse_literal("null").map(|_| JsonRef::Null),
+            Some(b'"') => self.parse_raw_qstring().map(JsonRef::QString),
+            Some(b'[') => self.parse_array(),
+            Some(b'{') => self.parse_object(),
+            _ => self.error(JsonErrorType::SyntaxError),
+        }
+    }
+
+    pub(super) fn parse_number(&mut self) -> ParseResult<f32> {
+        let start = self.cursor;
+        let digits = |parser: &mut Self| {
+            let start = parser.cursor;
+            while let Some(b'0'..=b'9') = parser.peek() {
+                parser.cursor += 1;
+            }
+            parser.cursor > start
+        };
+        if self.peek() == Some(b'-') {
+            self.cursor += 1;
+        }
+        if !digits(self) {
+            return self.error(JsonErrorType::SyntaxError);
+        }
+        if self.peek() == Some(b'.') {
+            self.cursor += 1;
+            if !digits(self) {
+                return self.error(JsonErrorType::SyntaxError);
+            }
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.cursor += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.cursor += 1;
+            }
+            if !digits(self) {
+                return self.error(JsonErrorType::SyntaxError);
+            }
+        }
+        self.input[start..self.cursor]
+            .parse()
+            .or_else(|_| self.error(JsonErrorType::SyntaxError))
+    }
+
+    /// string (escaped contents, without unescaping).
+    pub(super) fn parse_raw_qstring(&mut self) -> ParseResult<RawStr<'a>> {
+        self.parse_byte(b'"')?;
+        let start = self.cursor;
+        loop {
+            match self.peek() {
+                Some(b'"') => break,
+                Some(b'\\') => self.cursor += 2,
+                Some(_) => self.cursor += 1,
+                None => return self.error(JsonErrorType::SyntaxError),
+            }
+        }
+        let raw = &self.input[start..self.cursor];
+        self.cursor += 1;
+        Ok(RawStr(raw))
+    }
+
+    /// unescaped string (keys).
+    pub(super) fn parse_qstring(&mut self) -> ParseResult<Cow<'a, str>> {
+        self.parse_raw_qstring().map(|raw| raw.unescaped())
+    }
+
+    fn parse_array(&mut self) -> ParseResult<JsonRef<'a>> {
+        self.parse_byte(b'[')?;
+        let mut array = Vec::new();
+        if self.trim_front().peek() != Some(b']') {
+            loop {
+                array.push(self.trim_front().parse_any()?);
+                if self.trim_front().peek() != Some(b',') {
+                    break;
+                }
+                let comma = self.cursor;
+                if self.trim_front_after(1).peek() == Some(b']') {
+                    return Err((JsonErrorType::TrailingCommaError, comma));
+                }
+            }
+        }
+        self.trim_front().parse_byte(b']')?;
+        Ok(JsonRef::Array(array))
+    }
+
+    fn parse_object(&mut self) -> ParseResult<JsonRef<'a>> {
+        self.parse_byte(b'{')?;
+        let mut hashmap = HashMap::new();
+        if self.trim_front().peek() != Some(b'}') {
+            loop {
+                let key_start = self.trim_front().cursor;
+                let key = self.parse_qstring()?;
+                if hashmap.contains_key(&key) {
+                    return Err((JsonErrorType::DuplicateKeyError, key_start));
+                }
+                self.trim_front().parse_byte(b':')?;
+                let value = self.trim_front().parse_any()?;
+                hashmap.insert(key, value);
+                if self.trim_front().peek() != Some(b',') {
+                    break;
+                }
+                let comma = self.cursor;
+                if self.trim_front_after(1).peek() == Some(b'}') {
+                    return Err((JsonErrorType::TrailingCommaError, comma));
+                }
+            }
+        }
+        self.trim_front().parse_byte(b'}')?;
+        Ok(JsonRef::Object(hashmap))
+    }
+
+    #[inline]
+    pub(super) fn trim_front_after(&mut self, bytes: usize) -> &mut Self {
+        self.cursor += bytes;
+        self.trim_front()
+    }
+
+    /// row (starting at 1) and column (in chars) of the byte offset `cursor`.
+    fn position(&self, cursor: usize) -> Position {
+        let before = &self.input[..cursor];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        Position {
+            row: Position::MINROW + before.matches('\n').count(),
+            col: before[line_start..].chars().count(),
+        }
+    }
+
+    /// error (at byte offset `cursor`) with the position and line.
+    pub(super) fn parse_error(
+        &self,
+        (error_type, cursor): (JsonErrorType, usize),
+    ) -> JsonParseError {
+        // cursor may point in the middle of a char (after escape).
+        let cursor = (0..=cursor)
+            .rev()
+            .find(|&cursor| self.input.is_char_boundary(cursor))
+            .unwrap_or(0);
+        let position = self.position(cursor);
+        JsonParseError {
+            line: self
+                .input
+                .lines()
+                .nth(position.row - 1)
+                .unwrap_or("")
+                .to_string(),
+            position,
+            error_type,
+        }
+    }
+}
+
+impl<'a> JsonRef<'a> {
+    /// parse json text, borrowing strings from `input`.
+    pub fn parse(input: &'a str) -> Result<Self, JsonParseError> {
+        let mut parser = Parser { input, cursor: 0 };
+        parser
+            .trim_front()
+            .parse_any()
+            .map_err(|err| parser.parse_error(err))
+    }
+
+    /// value of `key`, if `self` is an object containing `key`.
+    pub fn get(&self, key: &str) -> Option<&JsonRef<'a>> {
+        match self {
+            Self::Object(hashmap) => hashmap.get(key),
+            _ => None,
+        }
+    }
+
+    /// element at `index`, if `self` is an array with more than `index` elements.
+    pub fn get_index(&self, index: usize) -> Option<&JsonRef<'a>> {
+        match self {
+            Self::Array(array) => array.get(index),
+            _ => None,
+        }
+    }
+
+    /// owned [`Json`] (string values are copied raw, keys are escaped back).
+    pub fn to_json(&self) -> Json {
+        match self {
+            Self::Null => Json::Null,
+            Self::Boolean(boolean) => Json::Boolean(*boolean),
+            Self::Number(number) => Json::Number(*number),
+            Self::QString(string) => Json::QString(string.as_raw().into()),
+            Self::Array(array) => array.iter().map(Self::to_json).collect(),
+            Self::Object(hashmap) => hashmap
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_json()))
+                .collect(),
+        }
+    }
+
+    /// apply `query`, walking by reference for the leading path properties
+    /// (dot, bracket, index), only the selected subtree is converted to
+    /// [`Json`] (for the rest of the query).
+    pub fn apply(&self, query: &JsonQuery) -> Result<Json, JsonEvalError> {
+        let mut node = self;
+        let mut properties = query.properties().peekable();
+        while let Some(property) = properties.peek() {
+            let selected = match (property, node) {
+                (
+                    Property::Dot(key, _) | Property::Bracket(key, _),
+                    Self::Object(hashmap),
+                ) => hashmap.get(unescape(key).as_str()).ok_or_else(|| {
+                    JsonEvalError::missing_key(
+                        key,
+                        hashmap.keys().map(|key| &**key),
+                    )
+                }),
+                (Property::Index(index, _), Self::Array(array)) => {
+                    array_index(*index, array.len())
+                        .map(|index| &array[index])
+                        .ok_or(JsonEvalError::InvalidIndex(*index, array.len()))
+                }
+                (Property::Pipe, _) => Ok(node),
+                // rest of the query (including errors) is handled by `Json`.
+                _ => break,
+            };
+            node = match selected {
+                Ok(node) => node,
+                // rest of the stage is short-circuited.
+                Err(_) if property.is_optional() => {
+                    properties
+                        .find(|property| matches!(property, Property::Pipe));
+                    return Json::Null
+                        .apply(&JsonQuery(properties.cloned().collect()));
+                }
+                Err(err) => return Err(err),
+            };
+            properties.next();
+        }
+        node.to_json()
+            .apply(&JsonQuery(properties.cloned().collect()))
+    }
+}
@@ -0,0 +1,179 @@
+//! Conversion traits between rust types and [`Json`](Json) values, derivable
+//! for structs with the `derive` feature (`#[derive(ToJson, FromJson)]`).
+use super::{error::JsonConvertError, token::Json, HashMap};
+use crate::prelude::*;
+use core::convert::{TryFrom, TryInto};
+
+#[cfg(feature = "derive")]
+pub use ruson_derive::{FromJson, ToJson};
+
+pub trait ToJson {
+    fn to_json(&self) -> Json;
+}
+
+pub trait FromJson: Sized {
+    fn from_json(json: &Json) -> Result<Self, JsonConvertError>;
+}
+
+impl ToJson for Json {
+    fn to_json(&self) -> Json {
+        self.clone()
+    }
+}
+
+impl FromJson for Json {
+    fn from_json(json: &Json) -> Result<Self, JsonConvertError> {
+        Ok(json.clone())
+    }
+}
+
+impl ToJson for str {
+    fn to_json(&self) -> Json {
+        Json::from(self)
+    }
+}
+
+macro_rules! convert_via {
+    ($($t:ty => $via:ty),*) => {$(
+        /// nearest number, for integers an `f32` can't represent exactly.
+        impl ToJson for $t {
+            fn to_json(&self) -> Json {
+                Json::from_lossy(*self as f64)
+            }
+        }
+
+        impl FromJson for $t {
+            fn from_json(json: &Json) -> Result<Self, JsonConvertError> {
+                <$via>::try_from(json).and_then(|value| {
+                    value.try_into().map_err(|_| JsonConvertError {
+                        expected: stringify!($t),
+                        found: format!("'Number' ({})", value),
+                    })
+                })
+            }
+        }
+    )*};
+}
+
+convert_via!(i64 => i64, i32 => i64, u32 => i64, usize => i64);
+
+/// nearest number (see [`Json::from_lossy`]).
+impl ToJson for f64 {
+    fn to_json(&self) -> Json {
+        Json::from_lossy(*self)
+    }
+}
+
+impl FromJson for f64 {
+    fn from_json(json: &Json) -> Result<Self, JsonConvertError> {
+        json.try_into()
+    }
+}
+
+impl ToJson for f32 {
+    fn to_json(&self) -> Json {
+        Json::Number(*self)
+    }
+}
+
+impl FromJson for f32 {
+    fn from_json(json: &Json) -> Result<Self, JsonConvertError> {
+        f64::from_json(json).map(|float| float as f32)
+    }
+}
+
+impl ToJson for bool {
+    fn to_json(&self) -> Json {
+        Json::from(*self)
+    }
+}
+
+impl FromJson for bool {
+    fn from_json(json: &Json) -> Result<Self, JsonConvertError> {
+        json.try_into()
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> Json {
+        Json::from(self.as_str())
+    }
+}
+
+impl FromJson for String {
+    fn from_json(json: &Json) -> Result<Self, JsonConvertError> {
+        json.try_into()
+    }
+}
+
+/// `None` is converted to `null` (and vice versa).
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> Json {
+        self.as_ref().map_or(Json::Null, ToJson::to_json)
+    }
+}
+
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(json: &Json) -> Result<Self, JsonConvertError> {
+        match json {
+            Json::Null => Ok(None),
+            _ => T::from_json(json).map(Some),
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> Json {
+        self.iter().map(ToJson::to_json).collect()
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(json: &Json) -> Result<Self, JsonConvertError> {
+        json.as_array()
+            .ok_or_else(|| JsonConvertError::new("Vec", json))?
+            .iter()
+            .map(T::from_json)
+            .collect()
+    }
+}
+
+/// keys are escaped (same as [`Json::from`](Json::from) for strings).
+impl<T: ToJson> ToJson for HashMap<String, T> {
+    fn to_json(&self) -> Json {
+        self.iter()
+            .map(|(key, value)| (key.clone(), value.to_json()))
+            .collect()
+    }
+}
+
+impl<T: FromJson> FromJson for HashMap<String, T> {
+    fn from_json(json: &Json) -> Result<Self, JsonConvertError> {
+        json.as_object()
+            .ok_or_else(|| JsonConvertError::new("HashMap", json))?
+            .iter()
+            .map(|(key, value)| {
+                T::from_json(value)
+                    .map(|value| (super::token::unescape(key), value))
+            })
+            .collect()
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn to_json(&self) -> Json {
+        (**self).to_json()
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for Box<T> {
+    fn to_json(&self) -> Json {
+        (**self).to_json()
+    }
+}
+
+impl<T: FromJson> FromJson for Box<T> {
+    fn from_json(json: &Json) -> Result<Self, JsonConvertError> {
+        T::from_json(json).map(Box::new)
+    }
+}
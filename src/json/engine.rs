@@ -0,0 +1,150 @@
+//! Query evaluation with user defined functions: applications embedding
+//! ruson can register functions (callable as `.name()` in queries), in
+//! addition to the builtins (`.keys()`, `.values()`, `.length()`,
+//! `.bytelength()`, `.omit()`, `.find()`, `.findpaths()`, `.map()`).
+use super::{
+    error::JsonEvalError,
+    query::JsonQuery,
+    token::{self, Json, Property},
+    HashMap,
+};
+use crate::prelude::*;
+use alloc::borrow::Cow;
+
+/// user defined function, the error message is reported as
+/// [`JsonEvalError::Function`](JsonEvalError::Function).
+pub type QueryFunction =
+    Box<dyn Fn(&Json) -> Result<Json, String> + Send + Sync>;
+
+/// Registry of user defined functions.
+///
+/// ```
+/// use ruson::json::{engine::QueryEngine, token::Json};
+///
+/// let mut engine = QueryEngine::new();
+/// engine.register("double", |json| match json {
+///     Json::Number(number) => Ok(Json::Number(number * 2.0)),
+///     _ => Err("expected 'Number'".into()),
+/// });
+/// let json: Json = "[1, 2]".parse().unwrap();
+/// let query = ".map(.double())".parse().unwrap();
+/// assert_eq!(engine.apply(&json, &query).unwrap().to_string(), "[2, 4]");
+/// ```
+#[derive(Default)]
+pub struct QueryEngine {
+    functions: HashMap<String, QueryFunction>,
+}
+
+impl QueryEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// register `function` as `.name()` (replacing any function registered
+    /// with the same name). builtin names (`keys`, `values`, `length`) are
+    /// always resolved to the builtins.
+    pub fn register<F>(&mut self, name: &str, function: F) -> &mut Self
+    where
+        F: Fn(&Json) -> Result<Json, String> + Send + Sync + 'static,
+    {
+        self.functions.insert(name.into(), Box::new(function));
+        self
+    }
+
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    /// same as [`Json::apply`](Json::apply), with the registered functions
+    /// available (including inside `.map()`).
+    pub fn apply(
+        &self,
+        json: &Json,
+        query: &JsonQuery,
+    ) -> Result<Json, JsonEvalError> {
+        // walked by reference, same as `Json::apply`.
+        let mut json = Cow::Borrowed(json);
+        let mut skipping = false;
+        for property in query.properties() {
+            // up to the next stage (see `Property::is_optional`).
+            if skipping {
+                skipping = !matches!(property, Property::Pipe);
+                continue;
+            }
+            let selected = match (property, json) {
+                (Property::Call(name), json) => {
+                    self.call(name, &json).map(Cow::Owned)
+                }
+                (Property::Map(query), json) => match json.as_array() {
+                    Some(array) => array
+                        .iter()
+                        .map(|json| self.apply(json, query))
+                        .collect::<Result<_, _>>()
+                        .map(Cow::Owned),
+                    // not an array (error).
+                    None => json
+                        .select(property)
+                        .map(|json| Cow::Owned(json.into_owned())),
+                },
+                (Property::GroupBy(query), json) => match json.as_array() {
+                    Some(array) => array
+                        .iter()
+                        .map(|json| self.apply(json, query))
+                        .collect::<Result<_, _>>()
+                        .map(|keys| Cow::Owned(token::group_by(array, keys))),
+                    // not an array (error).
+                    None => json
+                        .select(property)
+                        .map(|json| Cow::Owned(json.into_owned())),
+                },
+                (Property::Any(predicate) | Property::All(predicate), json) => {
+                    match json.as_array() {
+                        Some(array) => {
+                            let all = matches!(property, Property::All(_));
+                            token::quantify(array, predicate, all, |json| {
+                                self.apply(json, &predicate.query)
+                            })
+                            .map(Cow::Owned)
+                        }
+                        // not an array (error).
+                        None => json
+                            .select(property)
+                            .map(|json| Cow::Owned(json.into_owned())),
+                    }
+                }
+                (Property::Each(query), json) => match json.iterate() {
+                    Some(elements) => elements
+                        .iter()
+                        .map(|json| self.apply(json, query))
+                        .collect::<Result<_, _>>()
+                        .map(|results| Cow::Owned(token::each(query, results))),
+                    // neither an array nor an object (error).
+                    None => json
+                        .select(property)
+                        .map(|json| Cow::Owned(json.into_owned())),
+                },
+                (_, Cow::Borrowed(json)) => json.select(property),
+                (_, Cow::Owned(json)) => json.take(property).map(Cow::Owned),
+            };
+            json = match selected {
+                Ok(json) => json,
+                // rest of the stage is short-circuited.
+                Err(err) if property.is_optional() && err.is_missing() => {
+                    skipping = true;
+                    Cow::Owned(Json::Null)
+                }
+                Err(err) => return Err(err),
+            };
+        }
+        Ok(json.into_owned())
+    }
+
+    fn call(&self, name: &str, json: &Json) -> Result<Json, JsonEvalError> {
+        let function = self
+            .functions
+            .get(name)
+            .ok_or_else(|| JsonEvalError::UnknownFunction(name.into()))?;
+        function(json)
+            .map_err(|message| JsonEvalError::Function(name.into(), message))
+    }
+}
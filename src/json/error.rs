@@ -1,8 +1,9 @@
-//! Error types (mainly parsing related), implements [`Display`](std::fmt::Display)
+//! Error types (mainly parsing related), implements [`Display`](core::fmt::Display)
 //! for well formatted error messages.
 use crate::{
     error::ErrorString,
     lexer::{Cursor, Position},
+    prelude::*,
 };
 
 #[derive(Debug, PartialEq)]
@@ -10,6 +11,9 @@ pub enum JsonErrorType {
     SyntaxError,
     DuplicateKeyError,
     TrailingCommaError,
+    DepthLimitError,
+    SizeLimitError,
+    MemoryLimitError,
 }
 
 pub struct JsonParseError {
@@ -18,8 +22,8 @@ pub struct JsonParseError {
     pub error_type: JsonErrorType,
 }
 
-impl std::fmt::Display for JsonParseError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl core::fmt::Display for JsonParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         let printable_error = format!("{:?}", self.error_type).uncamelize();
         writeln!(
             f,
@@ -27,12 +31,12 @@ impl std::fmt::Display for JsonParseError {
             self.position.row, self.position.col, printable_error
         )?;
 
-        let start = std::cmp::max(0, self.position.col as i32 - 26);
+        let start = core::cmp::max(0, self.position.col as i32 - 26);
         let printable_string = &self.line.shorten(start as usize);
         writeln!(f, "{}.\t| {}", self.position.row, printable_string)?;
 
         let error_position = if self.line.len() > 50 {
-            std::cmp::min(self.position.col, 25)
+            core::cmp::min(self.position.col, 25)
         } else {
             self.position.col
         };
@@ -44,15 +48,30 @@ impl std::fmt::Display for JsonParseError {
     }
 }
 
-impl std::fmt::Debug for JsonParseError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        std::fmt::Display::fmt(self, f)
+impl core::fmt::Debug for JsonParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        core::fmt::Display::fmt(self, f)
     }
 }
 
+impl core::error::Error for JsonParseError {}
+
 #[derive(Debug, PartialEq)]
 pub enum JsonQueryErrorType {
     SyntaxError,
+    /// key not allowed in dot notation (see
+    /// [`Property::is_dot_key`](super::token::Property::is_dot_key)).
+    InvalidKeyError,
+    /// `$name` without a value (see
+    /// [`JsonQuery::with_variables`](super::query::JsonQuery::with_variables)).
+    UndefinedVariableError,
+    /// `[$name]` of a variable that is neither a string nor an integer.
+    InvalidVariableError,
+    /// regex of `.test()` or `.match()` with invalid syntax (see
+    /// [`Regex`](crate::regex::Regex)).
+    InvalidRegexError,
+    /// integer (index, or argument of a function) out of range.
+    OutOfRangeError,
 }
 
 pub struct JsonQueryError {
@@ -61,17 +80,17 @@ pub struct JsonQueryError {
     pub error_type: JsonQueryErrorType,
 }
 
-impl std::fmt::Display for JsonQueryError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl core::fmt::Display for JsonQueryError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         let printable_error = format!("{:?}", self.error_type).uncamelize();
         writeln!(f, "{} JsonQuery {}", self.cursor, printable_error)?;
 
-        let start = std::cmp::max(0, self.cursor as i32 - 26);
+        let start = core::cmp::max(0, self.cursor as i32 - 26);
         let printable_string = self.line.shorten(start as usize);
         writeln!(f, "near: '{}'", printable_string)?;
 
         let error_position = if self.line.len() > 50 {
-            std::cmp::min(self.cursor, 25)
+            core::cmp::min(self.cursor, 25)
         } else {
             self.cursor
         };
@@ -79,12 +98,382 @@ impl std::fmt::Display for JsonQueryError {
             f,
             "       {}^",
             (1..error_position).map(|_| ' ').collect::<String>()
+        )?;
+        if self.error_type == JsonQueryErrorType::InvalidKeyError {
+            write!(
+                f,
+                "\nkeys with other characters need bracket notation \
+                 (example: '[\"my key\"]')"
+            )?;
+        }
+        if self.error_type == JsonQueryErrorType::UndefinedVariableError {
+            write!(
+                f,
+                "\nvariables need a value (example: '--arg name value')"
+            )?;
+        }
+        if self.error_type == JsonQueryErrorType::InvalidVariableError {
+            write!(f, "\nvariables in brackets need a string or an integer")?;
+        }
+        if self.error_type == JsonQueryErrorType::InvalidRegexError {
+            write!(
+                f,
+                "\nsupported regex syntax: '.', '[a-z]', '\\d', '\\w', '\\s', \
+                 '^', '$', '(a|b)', '*', '+', '?', '{{n,m}}'"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl core::fmt::Debug for JsonQueryError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        core::fmt::Display::fmt(self, f)
+    }
+}
+
+impl core::error::Error for JsonQueryError {}
+
+/// Error while applying a [`JsonQuery`](super::query::JsonQuery) on a
+/// [`Json`](super::token::Json) value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonEvalError {
+    /// key doesn't exist in the object (key, similar keys of the object,
+    /// see [`missing_key`](Self::missing_key)).
+    MissingKey(String, Vec<String>),
+    /// index out of range (index, array length), negative indices count
+    /// from the end.
+    InvalidIndex(i32, usize),
+    /// element of an empty array (example: `.first()`, the property).
+    EmptyArray(String),
+    /// property not applicable (expectation, found variant).
+    InvalidType(String, &'static str),
+    /// function is not registered (see
+    /// [`QueryEngine`](super::engine::QueryEngine)).
+    UnknownFunction(String),
+    /// user defined function failed (function name, message).
+    Function(String, String),
+    /// intermediate value exceeds the memory budget (in bytes), see
+    /// [`Json::apply_within`](super::token::Json::apply_within).
+    MemoryLimit(usize),
+    /// error of the property following a query prefix (prefix path, shape
+    /// of the value found there, error), see [`cause`](Self::cause).
+    At(String, String, Box<JsonEvalError>),
+}
+
+impl JsonEvalError {
+    /// `key` doesn't exist in an object of `keys`, along with the keys
+    /// closest to `key` (edit distance, at most a third of the key and less
+    /// than the whole key).
+    pub fn missing_key<'k, I: IntoIterator<Item = &'k str>>(
+        key: &str,
+        keys: I,
+    ) -> Self {
+        const MAX_SUGGESTIONS: usize = 3;
+        let len = key.chars().count();
+        let max_distance = (len / 3).max(1).min(len.saturating_sub(1));
+        let mut similar: Vec<_> = keys
+            .into_iter()
+            .map(|found| (edit_distance(key, found), found))
+            .filter(|&(distance, _)| distance <= max_distance)
+            .collect();
+        similar.sort_unstable();
+        let similar = similar
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, found)| found.to_string())
+            .collect();
+        Self::MissingKey(key.into(), similar)
+    }
+
+    /// underlying error (without the query path).
+    pub fn cause(&self) -> &Self {
+        match self {
+            Self::At(_, _, err) => err.cause(),
+            err => err,
+        }
+    }
+
+    /// value doesn't exist (missing key, out of range index, empty array or
+    /// type mismatch), see [`Missing`](super::query::Missing).
+    pub fn is_missing(&self) -> bool {
+        matches!(
+            self,
+            Self::MissingKey(..)
+                | Self::InvalidIndex(..)
+                | Self::EmptyArray(..)
+                | Self::InvalidType(..)
         )
     }
+
+    /// `self`, located after `path` on a value of `shape` (an already
+    /// located error is prefixed with `path`).
+    pub(crate) fn after<F: FnOnce() -> String>(
+        self,
+        path: &str,
+        shape: F,
+    ) -> Self {
+        match self {
+            Self::MemoryLimit(_) => self,
+            Self::At(rest, shape, err) => {
+                Self::At(format!("{}{}", path, rest), shape, err)
+            }
+            err => Self::At(path.into(), shape(), Box::new(err)),
+        }
+    }
 }
 
-impl std::fmt::Debug for JsonQueryError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        std::fmt::Display::fmt(self, f)
+impl core::fmt::Display for JsonEvalError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::MissingKey(key, similar) => {
+                write!(f, " key doesn't exist: '{}'", key)?;
+                let similar: Vec<_> = similar
+                    .iter()
+                    .map(|found| format!("'{}'", found))
+                    .collect();
+                match similar.split_last() {
+                    Some((last, [])) => write!(f, " (did you mean {}?)", last),
+                    Some((last, rest)) => write!(
+                        f,
+                        " (did you mean {} or {}?)",
+                        rest.join(", "),
+                        last
+                    ),
+                    None => Ok(()),
+                }
+            }
+            Self::InvalidIndex(index, 0) => {
+                write!(f, " Index {} is out of range (array is empty).", index)
+            }
+            Self::InvalidIndex(index, len) => write!(
+                f,
+                " Index {} is out of range (valid indices: 0 to {}, or -{} \
+                 to -1).",
+                index,
+                len - 1,
+                len
+            ),
+            Self::EmptyArray(property) => {
+                write!(f, " '{}' can't be applied on an empty array.", property)
+            }
+            Self::InvalidType(expected, found) => {
+                write!(f, " {}, found '{}' instead.", expected, found)
+            }
+            Self::UnknownFunction(name) => {
+                write!(f, " Unknown function: '.{}()'.", name)
+            }
+            Self::Function(name, message) => {
+                write!(f, " '.{}()' failed: {}.", name, message)
+            }
+            Self::MemoryLimit(max_memory) => {
+                write!(f, " Query exceeds memory limit ({} bytes).", max_memory)
+            }
+            Self::At(path, shape, err) => {
+                write!(f, " after '{}' ({}):{}", path, shape, err)
+            }
+        }
+    }
+}
+
+impl core::error::Error for JsonEvalError {}
+
+/// number of single char edits (insertion, deletion, substitution or
+/// transposition of adjacent chars) turning `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) =
+        (a.chars().collect(), b.chars().collect());
+    // last two rows of the distance matrix.
+    let mut previous: Vec<usize> = vec![0; b.len() + 1];
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let before = core::mem::replace(&mut previous, row.clone());
+        row[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (previous[j] + 1)
+                .min(row[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before[j - 2] + 1);
+            }
+        }
+    }
+    row[b.len()]
+}
+
+/// Error while converting a [`Json`](super::token::Json) value into a rust
+/// type (example: `String::try_from(json)`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonConvertError {
+    /// name of the rust type.
+    pub expected: &'static str,
+    /// variant of the json value (with the value, for scalars).
+    pub found: String,
+}
+
+impl core::fmt::Display for JsonConvertError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            " cannot convert {} into '{}'.",
+            self.found, self.expected
+        )
+    }
+}
+
+impl core::error::Error for JsonConvertError {}
+
+/// Error while parsing or applying a [`Patch`](super::patch::Patch).
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonPatchError {
+    /// malformed patch document (or operation).
+    InvalidOperation(String),
+    /// malformed json pointer (not starting with '/').
+    InvalidPointer(String),
+    /// json pointer doesn't refer to an existing location.
+    PathNotFound(String),
+    /// value at json pointer doesn't match the value of 'test' operation.
+    TestFailed(String),
+}
+
+impl core::fmt::Display for JsonPatchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::InvalidOperation(message) => {
+                write!(f, " Invalid patch operation: {}.", message)
+            }
+            Self::InvalidPointer(pointer) => {
+                write!(f, " Invalid json pointer: '{}'.", pointer)
+            }
+            Self::PathNotFound(path) => {
+                write!(f, " path doesn't exist: '{}'.", path)
+            }
+            Self::TestFailed(path) => write!(f, " test failed at: '{}'.", path),
+        }
+    }
+}
+
+impl core::error::Error for JsonPatchError {}
+
+/// Error while reading json from an [`io::Read`](std::io::Read) source (see
+/// [`Json::from_reader`](super::token::Json::from_reader)).
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum JsonReadError {
+    /// reading from the source failed (or input is not valid utf-8).
+    Io(std::io::Error),
+    /// input is not valid json.
+    Parse(JsonParseError),
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Display for JsonReadError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, " cannot read input: {}.", err),
+            Self::Parse(err) => core::fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::error::Error for JsonReadError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Parse(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for JsonReadError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<JsonParseError> for JsonReadError {
+    fn from(err: JsonParseError) -> Self {
+        Self::Parse(err)
+    }
+}
+
+/// Any error of the library, every error type converts into it (with `?`).
+#[derive(Debug)]
+pub enum RusonError {
+    /// input is not valid json.
+    Parse(JsonParseError),
+    /// invalid query syntax.
+    Query(JsonQueryError),
+    /// query cannot be applied on the value.
+    Eval(JsonEvalError),
+    /// value cannot be converted into a rust type.
+    Convert(JsonConvertError),
+    /// patch cannot be parsed or applied.
+    Patch(JsonPatchError),
+    /// reading from the source failed (or input is not valid utf-8).
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+}
+
+impl core::fmt::Display for RusonError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Parse(err) => core::fmt::Display::fmt(err, f),
+            Self::Query(err) => core::fmt::Display::fmt(err, f),
+            Self::Eval(err) => core::fmt::Display::fmt(err, f),
+            Self::Convert(err) => core::fmt::Display::fmt(err, f),
+            Self::Patch(err) => core::fmt::Display::fmt(err, f),
+            #[cfg(feature = "std")]
+            Self::Io(err) => write!(f, " cannot read input: {}.", err),
+        }
+    }
+}
+
+impl core::error::Error for RusonError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Parse(err) => Some(err),
+            Self::Query(err) => Some(err),
+            Self::Eval(err) => Some(err),
+            Self::Convert(err) => Some(err),
+            Self::Patch(err) => Some(err),
+            #[cfg(feature = "std")]
+            Self::Io(err) => Some(err),
+        }
+    }
+}
+
+macro_rules! from_error {
+    ($($variant:ident($error:ty)),*) => {
+        $(impl From<$error> for RusonError {
+            fn from(err: $error) -> Self {
+                Self::$variant(err)
+            }
+        })*
+    };
+}
+
+from_error!(
+    Parse(JsonParseError),
+    Query(JsonQueryError),
+    Eval(JsonEvalError),
+    Convert(JsonConvertError),
+    Patch(JsonPatchError)
+);
+
+#[cfg(feature = "std")]
+from_error!(Io(std::io::Error));
+
+#[cfg(feature = "std")]
+impl From<JsonReadError> for RusonError {
+    fn from(err: JsonReadError) -> Self {
+        match err {
+            JsonReadError::Io(err) => Self::Io(err),
+            JsonReadError::Parse(err) => Self::Parse(err),
+        }
     }
 }
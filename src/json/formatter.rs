@@ -1,113 +1,400 @@
 //! Json Formatter: can call `dump()`, returns string of formatted json token.
-use super::token::Json;
+use super::{
+    hash_map,
+    string::JsonString,
+    token::{escape, unescape, Json},
+};
+use crate::prelude::*;
+#[cfg(feature = "std")]
+use std::io;
 
 pub trait Formatter {
     type Token;
     fn dump(&self, token: &Self::Token) -> String;
+
+    /// write formatted token to `writer`.
+    #[cfg(feature = "std")]
+    fn write(
+        &self,
+        token: &Self::Token,
+        writer: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        writer.write_all(self.dump(token).as_bytes())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Json {
+    /// write `self` to `writer`, formatted with `style` (example:
+    /// `json.write(io::stdout(), &RawJson { color: false })`).
+    pub fn write<W: io::Write>(
+        &self,
+        mut writer: W,
+        style: &dyn Formatter<Token = Json>,
+    ) -> io::Result<()> {
+        style.write(self, &mut writer)
+    }
 }
 
-pub struct RawJson {}
+/// ANSI color codes for each token type (same defaults as `jq`).
+pub mod color {
+    use crate::prelude::*;
+
+    pub const NULL: &str = "1;30";
+    pub const BOOLEAN: &str = "0;39";
+    pub const NUMBER: &str = "0;39";
+    pub const STRING: &str = "0;32";
+    pub const KEY: &str = "34;1";
+    pub const PUNCTUATION: &str = "1;39";
+
+    /// wraps `s` with ANSI escape sequence, if `enabled`.
+    #[inline]
+    pub fn paint(
+        enabled: bool,
+        code: &str,
+        s: &dyn core::fmt::Display,
+    ) -> String {
+        if enabled {
+            format!("\x1b[{}m{}\x1b[0m", code, s)
+        } else {
+            format!("{}", s)
+        }
+    }
+
+    /// painted token (array/object painted as a whole).
+    #[inline]
+    pub fn paint_token(enabled: bool, token: &super::Json) -> String {
+        use super::Json;
+        let code = match token {
+            Json::Null => NULL,
+            Json::Boolean(_) => BOOLEAN,
+            Json::Number(_) => NUMBER,
+            Json::QString(_) => STRING,
+            Json::Array(_) | Json::Object(_) => PUNCTUATION,
+        };
+        paint(enabled, code, token)
+    }
+}
+
+pub struct RawJson {
+    pub color: bool,
+}
+
+impl RawJson {
+    fn colored(&self, s: &mut String, token: &Json) {
+        let punct = |p: &str| color::paint(true, color::PUNCTUATION, &p);
+        match token {
+            Json::Array(tokens) => {
+                s.push_str(&punct("["));
+                for (index, token) in tokens.iter().enumerate() {
+                    if index > 0 {
+                        s.push_str(&(punct(",") + " "));
+                    }
+                    self.colored(s, token);
+                }
+                s.push_str(&punct("]"));
+            }
+            Json::Object(pairs) => {
+                s.push_str(&punct("{"));
+                for (index, (key, token)) in pairs.iter().enumerate() {
+                    if index > 0 {
+                        s.push_str(&(punct(",") + " "));
+                    }
+                    let key = Json::QString(key.clone());
+                    s.push_str(&color::paint(true, color::KEY, &key));
+                    s.push_str(&(punct(":") + " "));
+                    self.colored(s, token);
+                }
+                s.push_str(&punct("}"));
+            }
+            _ => s.push_str(&color::paint_token(true, token)),
+        }
+    }
+}
 
 impl Formatter for RawJson {
     type Token = Json;
     fn dump(&self, token: &Self::Token) -> String {
-        format!("{}", token)
+        if self.color {
+            let mut string = String::new();
+            self.colored(&mut string, token);
+            string
+        } else {
+            format!("{}", token)
+        }
     }
 }
 
 pub struct PrettyJson<'a> {
     pub indent: &'a str,
+    pub color: bool,
 }
 
-impl<'a> PrettyJson<'a> {
-    fn prettified(&self, s: &mut String, token: &Json, depth: usize) {
-        match token {
-            Json::Array(tokens) => {
-                let mut tokens = tokens.iter();
-
-                s.push_str("[\n");
-                if let Some(token) = tokens.next() {
-                    s.push_str(&format!("{}", self.indented(depth + 1, &"")));
-                    self.prettified(s, token, depth + 1);
-                }
+/// array/object being formatted (with an explicit stack of frames, so
+/// nesting depth isn't bound by the call stack).
+enum Frame<'j> {
+    Array(core::slice::Iter<'j, Json>),
+    Object(hash_map::Iter<'j, JsonString, Json>),
+}
 
-                for token in tokens {
-                    s.push_str(&format!(
-                        ",\n{}",
-                        self.indented(depth + 1, &"")
-                    ));
-                    self.prettified(s, token, depth + 1);
+impl<'a> PrettyJson<'a> {
+    fn prettified(&self, s: &mut String, token: &Json) {
+        let mut frames: Vec<(Frame, bool)> = vec![];
+        let mut next = Some(token);
+        loop {
+            match next.take() {
+                Some(Json::Array(tokens)) => {
+                    s.push_str(&format!("{}\n", self.punct("[")));
+                    frames.push((Frame::Array(tokens.iter()), true));
                 }
-                s.push_str(&format!("\n{}", self.indented(depth, &"]")));
-            }
-            Json::Object(pairs) => {
-                let mut pairs = pairs.iter();
-
-                s.push_str("{\n");
-                if let Some((key, token)) = pairs.next() {
-                    s.push_str(&format!(
-                        "{}: ",
-                        self.indented(depth + 1, &Json::QString(key.into()))
-                    ));
-                    self.prettified(s, token, depth + 1);
+                Some(Json::Object(pairs)) => {
+                    s.push_str(&format!("{}\n", self.punct("{")));
+                    frames.push((Frame::Object(pairs.iter()), true));
                 }
-
-                for (key, token) in pairs {
-                    s.push_str(&format!(
-                        ",\n{}: ",
-                        self.indented(depth + 1, &Json::QString(key.into()))
-                    ));
-                    self.prettified(s, token, depth + 1)
+                Some(token) => {
+                    s.push_str(&color::paint_token(self.color, token))
                 }
-                s.push_str(&format!("\n{}", self.indented(depth, &"}")));
+                None => {}
+            }
+            // nesting depth of the elements of the innermost frame.
+            let depth = frames.len();
+            let (frame, first) = match frames.last_mut() {
+                Some(frame) => frame,
+                None => break,
+            };
+            let separator = match first {
+                true => String::new(),
+                false => format!("{}\n", self.punct(",")),
+            };
+            match frame {
+                Frame::Array(tokens) => match tokens.next() {
+                    Some(token) => {
+                        s.push_str(&separator);
+                        s.push_str(&self.indented(depth, &""));
+                        next = Some(token);
+                    }
+                    None => {
+                        s.push_str(&format!(
+                            "\n{}",
+                            self.indented(depth - 1, &self.punct("]"))
+                        ));
+                        frames.pop();
+                        continue;
+                    }
+                },
+                Frame::Object(pairs) => match pairs.next() {
+                    Some((key, token)) => {
+                        s.push_str(&format!(
+                            "{}{}{}",
+                            separator,
+                            self.indented(depth, &self.key(key)),
+                            self.punct(":") + " "
+                        ));
+                        next = Some(token);
+                    }
+                    None => {
+                        s.push_str(&format!(
+                            "\n{}",
+                            self.indented(depth - 1, &self.punct("}"))
+                        ));
+                        frames.pop();
+                        continue;
+                    }
+                },
             }
-            _ => s.push_str(&format!("{}", token)),
+            *first = false;
         }
     }
 
-    fn indented(&self, depth: usize, s: &dyn std::fmt::Display) -> String {
+    fn indented(&self, depth: usize, s: &dyn core::fmt::Display) -> String {
         format!("{}{}", vec![self.indent; depth].join(""), s)
     }
+
+    #[inline]
+    fn punct(&self, s: &str) -> String {
+        color::paint(self.color, color::PUNCTUATION, &s)
+    }
+
+    #[inline]
+    fn key(&self, key: &str) -> String {
+        color::paint(self.color, color::KEY, &Json::QString(key.into()))
+    }
 }
 
 impl<'a> Formatter for PrettyJson<'a> {
     type Token = Json;
     fn dump(&self, token: &Self::Token) -> String {
         let mut string = String::new();
-        self.prettified(&mut string, token, 0);
+        self.prettified(&mut string, token);
         string
     }
 }
 
-pub struct TableJson {}
+pub struct TableJson {
+    pub color: bool,
+}
 
 impl Formatter for TableJson {
     type Token = Json;
     fn dump(&self, token: &Self::Token) -> String {
+        let painted = |token| RawJson { color: self.color }.dump(token);
         match token {
             Json::Array(array) => {
                 let mut string = String::new();
                 let mut iter = array.iter();
                 if let Some(value) = iter.next() {
-                    string.push_str(&format!("{}", value));
+                    string.push_str(&painted(value));
                 }
                 while let Some(value) = iter.next() {
-                    string.push_str(&format!("\n{}", value));
+                    string.push_str(&format!("\n{}", painted(value)));
                 }
                 string
             }
             Json::Object(map) => {
                 let mut string = String::new();
                 let mut iter = map.iter();
-                if let Some((key, value)) = iter.next() {
-                    string.push_str(&format!("{}\t{}", key, value));
+                let key = |key| color::paint(self.color, color::KEY, key);
+                if let Some((k, value)) = iter.next() {
+                    string.push_str(&format!("{}\t{}", key(k), painted(value)));
                 }
-                while let Some((key, value)) = iter.next() {
-                    string.push_str(&format!("\n{}\t{}", key, value));
+                while let Some((k, value)) = iter.next() {
+                    string.push_str(&format!(
+                        "\n{}\t{}",
+                        key(k),
+                        painted(value)
+                    ));
                 }
                 string
             }
-            _ => format!("{}", token),
+            _ => painted(token),
+        }
+    }
+}
+
+/// elements of an array of scalars joined by `separator`, strings are
+/// printed unescaped (without quotes) and `null` as empty (same as `jq`'s
+/// `join`). other values are printed same as [`RawJson`].
+pub struct JoinedJson {
+    pub separator: String,
+    pub color: bool,
+}
+
+impl Formatter for JoinedJson {
+    type Token = Json;
+    fn dump(&self, token: &Self::Token) -> String {
+        let raw = RawJson { color: self.color };
+        match token {
+            Json::Array(array)
+                if array.iter().all(|value| {
+                    !matches!(value, Json::Array(_) | Json::Object(_))
+                }) =>
+            {
+                array
+                    .iter()
+                    .map(|value| match value {
+                        Json::QString(string) => unescape(string),
+                        Json::Null => String::new(),
+                        value => raw.dump(value),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(&self.separator)
+            }
+            _ => raw.dump(token),
+        }
+    }
+}
+
+/// canonical json text (RFC 8785, JCS): no whitespace, object keys sorted by
+/// their utf-16 code units, strings with minimal escaping and numbers in
+/// ECMAScript notation (shortest digits of the 32-bit value). structurally
+/// identical values have the same canonical text.
+pub struct CanonicalJson;
+
+impl CanonicalJson {
+    fn canonical(s: &mut String, token: &Json) {
+        match token {
+            Json::Null => s.push_str("null"),
+            Json::Boolean(boolean) => s.push_str(&boolean.to_string()),
+            Json::Number(number) => s.push_str(&canonical_number(*number)),
+            Json::QString(string) => {
+                s.push('"');
+                s.push_str(&escape(&unescape(string)));
+                s.push('"');
+            }
+            Json::Array(tokens) => {
+                s.push('[');
+                for (index, token) in tokens.iter().enumerate() {
+                    if index > 0 {
+                        s.push(',');
+                    }
+                    Self::canonical(s, token);
+                }
+                s.push(']');
+            }
+            Json::Object(pairs) => {
+                let mut pairs: Vec<_> = pairs
+                    .iter()
+                    .map(|(key, token)| (unescape(key), token))
+                    .collect();
+                pairs.sort_unstable_by(|(a, _), (b, _)| {
+                    a.encode_utf16().cmp(b.encode_utf16())
+                });
+                s.push('{');
+                for (index, (key, token)) in pairs.into_iter().enumerate() {
+                    if index > 0 {
+                        s.push(',');
+                    }
+                    s.push('"');
+                    s.push_str(&escape(&key));
+                    s.push_str("\":");
+                    Self::canonical(s, token);
+                }
+                s.push('}');
+            }
         }
     }
 }
+
+impl Formatter for CanonicalJson {
+    type Token = Json;
+    fn dump(&self, token: &Self::Token) -> String {
+        let mut string = String::new();
+        Self::canonical(&mut string, token);
+        string
+    }
+}
+
+/// `number` as formatted by ECMAScript's `Number.prototype.toString`, with
+/// the shortest digits that round trip the 32-bit value.
+fn canonical_number(number: f32) -> String {
+    if number == 0. {
+        // including `-0`.
+        return "0".into();
+    }
+    // shortest digits `d.ddd` and exponent, from `d.ddde<exponent>`.
+    let formatted = format!("{:e}", number.abs());
+    let (mantissa, exponent) = formatted.split_once('e').unwrap_or(("0", "0"));
+    let digits: String =
+        mantissa.chars().filter(char::is_ascii_digit).collect();
+    // value is `0.digits * 10^n`.
+    let n = exponent.parse::<i32>().unwrap_or(0) + 1;
+    let k = digits.len() as i32;
+    let sign = if number < 0. { "-" } else { "" };
+    let body = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat(-n as usize), digits)
+    } else {
+        let exponent = match n - 1 {
+            e if e < 0 => format!("e-{}", -e),
+            e => format!("e+{}", e),
+        };
+        match digits.split_at(1) {
+            (first, "") => format!("{}{}", first, exponent),
+            (first, rest) => format!("{}.{}{}", first, rest, exponent),
+        }
+    };
+    format!("{}{}", sign, body)
+}
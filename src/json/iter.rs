@@ -0,0 +1,63 @@
+//! Iterators for traversing [`Json`](Json) values, without recursion.
+use super::{string::JsonString, token::Json};
+use crate::prelude::*;
+
+/// query syntax of the property accessing `key` (dot notation, if possible).
+pub fn key_path(key: &str) -> String {
+    let special = |ch: char| ".[]()\"\\".contains(ch) || ch.is_whitespace();
+    if key.is_empty() || key.contains(special) {
+        format!("[\"{}\"]", key)
+    } else {
+        format!(".{}", key)
+    }
+}
+
+/// Depth first (pre-order) iterator over every node, along with its path
+/// (query syntax, example: `.list[0]["key with spaces"]`).
+/// object keys are visited in sorted order.
+pub struct Paths<'a> {
+    stack: Vec<(String, &'a Json)>,
+}
+
+impl<'a> Iterator for Paths<'a> {
+    type Item = (String, &'a Json);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, json) = self.stack.pop()?;
+        match json {
+            Json::Array(array) => {
+                for (index, element) in array.iter().enumerate().rev() {
+                    self.stack.push((format!("{}[{}]", path, index), element));
+                }
+            }
+            Json::Object(hashmap) => {
+                let mut entries: Vec<_> = hashmap.iter().collect();
+                entries.sort_by(|a, b| b.0.cmp(a.0));
+                for (key, value) in entries {
+                    self.stack.push((path.clone() + &key_path(key), value));
+                }
+            }
+            _ => {}
+        }
+        Some((path, json))
+    }
+}
+
+impl Json {
+    /// key/value pairs of an object (empty for any other value).
+    pub fn entries(&self) -> impl Iterator<Item = (&JsonString, &Json)> {
+        self.as_object().into_iter().flatten()
+    }
+
+    /// elements of an array (empty for any other value).
+    pub fn elements(&self) -> impl Iterator<Item = &Json> {
+        self.as_array().into_iter().flatten()
+    }
+
+    /// every node of the tree with its path, starting with `self` (empty path).
+    pub fn iter_paths(&self) -> Paths<'_> {
+        Paths {
+            stack: vec![(String::new(), self)],
+        }
+    }
+}
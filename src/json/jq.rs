@@ -0,0 +1,430 @@
+//! `jq` compatible query dialect: translates the most common `jq` spellings
+//! into a [`JsonQuery`](JsonQuery).
+//!
+//! | jq                     | ruson                  |
+//! |------------------------|------------------------|
+//! | `.`                    | (empty query)          |
+//! | `.a.b`, `.[0]`, `.["a"]` | `.a.b`, `[0]`, `["a"]` |
+//! | `.[$name]`             | `[$name]`              |
+//! | `.[1:4]`, `.[:3]`       | `[1:4]`, `[:3]`        |
+//! | `.a?`, `.[0]?`         | `.a?`, `[0]?`          |
+//! | `.a \| .b`             | `.a \| .b`             |
+//! | `.a[] \| .b`, `.a[].b` | `.a[] \| .b`, `.a[].b` |
+//! | `map(.b)`              | `.map(.b)`             |
+//! | `group_by(.b)`         | `.group_by(.b)`        |
+//! | `length`, `keys`       | `.length()`, `.keys()` |
+//! | `flatten`, `flatten(2)` | `.flatten()`, `.flatten(2)` |
+//! | `min`, `max`           | `.min()`, `.max()`     |
+//! | `first`, `last`        | `.first()`, `.last()`  |
+//! | `has("a")`, `has(0)`   | `.has("a")`, `.has(0)` |
+//! | `test("a+")`, `match("a+")` | `.test("a+")`, `.match("a+")` |
+//! | `utf8bytelength`       | `.bytelength()`        |
+//! | `type`                 | `.type()`              |
+//! | `any`, `all`           | `.any()`, `.all()`     |
+//! | `{id, "my key"}`       | `.{id, "my key"}`      |
+//! | `del(.a, .["b"])`      | `.omit("a", "b")`      |
+//! | `del(.[3])`            | `.del(3)`              |
+//!
+//! `[]` iterates over array elements (or object values), results are
+//! collected in an array (ruson has no notion of multiple outputs).
+use super::{
+    error::{JsonQueryError, JsonQueryErrorType},
+    parser::{key_char, variable_property},
+    query::{JsonQuery, Predicate, Variables},
+    token::{unescape, Property},
+};
+use crate::{prelude::*, regex::Regex};
+
+enum Step {
+    Property(Property<'static>),
+    /// `[]`: rest of the query is applied on each element.
+    Iterate,
+}
+
+struct Translator<'v> {
+    chars: Vec<char>,
+    cursor: usize,
+    variables: &'v Variables,
+    /// kind of the error at the cursor (syntax error, unless set).
+    error_type: JsonQueryErrorType,
+}
+
+type TranslateResult<T> = Result<T, usize>;
+
+impl Translator<'_> {
+    #[inline]
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.cursor).copied()
+    }
+
+    #[inline]
+    fn trim_front(&mut self) -> &mut Self {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.cursor += 1;
+        }
+        self
+    }
+
+    /// consume `s`, if the input continues with `s`.
+    fn consume(&mut self, s: &str) -> bool {
+        let matches = s.chars().enumerate().all(|(index, ch)| {
+            self.chars.get(self.cursor + index) == Some(&ch)
+        });
+        if matches {
+            self.cursor += s.chars().count();
+        }
+        matches
+    }
+
+    fn consume_while<F: FnMut(char) -> bool>(&mut self, mut f: F) -> String {
+        let start = self.cursor;
+        while self.peek().is_some_and(&mut f) {
+            self.cursor += 1;
+        }
+        self.chars[start..self.cursor].iter().collect()
+    }
+
+    #[inline]
+    fn error<T>(&self) -> TranslateResult<T> {
+        Err(self.cursor + 1)
+    }
+
+    /// `term ('|' term)*`, up to the end of input or an unmatched `)`.
+    fn pipeline(&mut self) -> TranslateResult<Vec<Step>> {
+        let mut steps = self.trim_front().term()?;
+        while self.trim_front().consume("|") {
+            steps.push(Step::Property(Property::Pipe));
+            steps.extend(self.trim_front().term()?);
+        }
+        Ok(steps)
+    }
+
+    fn term(&mut self) -> TranslateResult<Vec<Step>> {
+        if self.consume("map(") {
+            let steps = self.pipeline()?;
+            if !self.trim_front().consume(")") {
+                return self.error();
+            }
+            return Ok(vec![Step::Property(Property::Map(build(steps)))]);
+        }
+        if self.consume("group_by(") {
+            let steps = self.pipeline()?;
+            if !self.trim_front().consume(")") {
+                return self.error();
+            }
+            return Ok(vec![Step::Property(Property::GroupBy(build(steps)))]);
+        }
+        if self.consume("del(") {
+            return self.del();
+        }
+        if self.peek() == Some('{') {
+            return self.pick();
+        }
+        if self.peek() != Some('.') {
+            return match self.consume_while(is_key).as_str() {
+                "length" => Ok(vec![Step::Property(Property::Length)]),
+                "utf8bytelength" => {
+                    Ok(vec![Step::Property(Property::ByteLength)])
+                }
+                "keys" | "keys_unsorted" => {
+                    Ok(vec![Step::Property(Property::Keys)])
+                }
+                "type" => Ok(vec![Step::Property(Property::Type)]),
+                "any" => Ok(vec![Step::Property(Property::Any(truthy()))]),
+                "all" => Ok(vec![Step::Property(Property::All(truthy()))]),
+                "has" if self.consume("(") => self.has(),
+                "test" if self.consume("(") => self.regex(Property::Test),
+                "match" if self.consume("(") => self.regex(Property::Match),
+                "first" => Ok(vec![Step::Property(Property::First)]),
+                "last" => Ok(vec![Step::Property(Property::Last)]),
+                "min" => Ok(vec![Step::Property(Property::Min)]),
+                "max" => Ok(vec![Step::Property(Property::Max)]),
+                "flatten" if !self.consume("(") => {
+                    Ok(vec![Step::Property(Property::Flatten(1))])
+                }
+                "flatten" => {
+                    let start = self.cursor;
+                    let depth = self.consume_while(|ch| ch.is_ascii_digit());
+                    match depth.parse() {
+                        Ok(depth) if self.trim_front().consume(")") => {
+                            Ok(vec![Step::Property(Property::Flatten(depth))])
+                        }
+                        Err(_) if !depth.is_empty() => {
+                            self.cursor = start;
+                            self.error_type =
+                                JsonQueryErrorType::OutOfRangeError;
+                            self.error()
+                        }
+                        _ => self.error(),
+                    }
+                }
+                _ => self.error(),
+            };
+        }
+        self.path()
+    }
+
+    /// `.`, or a sequence of `.key`, `[index]`, `["key"]` and `[]`
+    /// (starting with `.`).
+    fn path(&mut self) -> TranslateResult<Vec<Step>> {
+        self.consume(".");
+        let mut steps = vec![];
+        match self.peek() {
+            Some('[') => steps.push(self.bracket()?),
+            Some(ch) if is_key(ch) => steps.push(self.key()),
+            // identity.
+            _ => return Ok(steps),
+        }
+        loop {
+            let next = self.chars.get(self.cursor + 1).copied();
+            match (self.peek(), next) {
+                (Some('['), _) => steps.push(self.bracket()?),
+                (Some('.'), Some('[')) => {
+                    self.consume(".");
+                    steps.push(self.bracket()?);
+                }
+                (Some('.'), Some(ch)) if is_key(ch) => {
+                    self.consume(".");
+                    steps.push(self.key());
+                }
+                _ => return Ok(steps),
+            }
+        }
+    }
+
+    #[inline]
+    fn key(&mut self) -> Step {
+        let key = self.consume_while(is_key).into();
+        Step::Property(Property::Dot(key, self.consume("?")))
+    }
+
+    /// `{key, "key", ...}` (object construction, keys only).
+    fn pick(&mut self) -> TranslateResult<Vec<Step>> {
+        self.consume("{");
+        let mut keys = vec![];
+        while !self.trim_front().consume("}") {
+            if !keys.is_empty() && !self.consume(",") {
+                return self.error();
+            }
+            let key = if self.trim_front().consume("\"") {
+                let key = self.consume_while(key_char());
+                if !self.consume("\"") {
+                    return self.error();
+                }
+                key
+            } else {
+                match self.consume_while(is_key) {
+                    key if key.is_empty() => return self.error(),
+                    key => key,
+                }
+            };
+            keys.push(key.into());
+        }
+        Ok(vec![Step::Property(Property::Pick(keys))])
+    }
+
+    /// `del(.key, ...)` of keys, or `del(.[index])` of a single index
+    /// (without the `del(`).
+    fn del(&mut self) -> TranslateResult<Vec<Step>> {
+        let mut keys = vec![];
+        loop {
+            let mut steps = self.trim_front().path()?;
+            match (steps.pop(), steps.is_empty()) {
+                (
+                    Some(Step::Property(
+                        Property::Dot(key, _) | Property::Bracket(key, _),
+                    )),
+                    true,
+                ) => keys.push(key),
+                (Some(Step::Property(Property::Index(index, _))), true)
+                    if keys.is_empty() && self.trim_front().consume(")") =>
+                {
+                    return Ok(vec![Step::Property(Property::DelIndex(index))]);
+                }
+                _ => return self.error(),
+            }
+            if self.trim_front().consume(")") {
+                return Ok(vec![Step::Property(Property::Omit(keys))]);
+            }
+            if !self.consume(",") {
+                return self.error();
+            }
+        }
+    }
+
+    /// `has("key")` or `has(index)` (after `has(`).
+    fn has(&mut self) -> TranslateResult<Vec<Step>> {
+        let property = if self.trim_front().consume("\"") {
+            let key = self.consume_while(key_char());
+            if !self.consume("\"") {
+                return self.error();
+            }
+            Property::HasKey(key.into())
+        } else {
+            match self.int()? {
+                Some(index) => Property::HasIndex(index),
+                None => return self.error(),
+            }
+        };
+        if !self.trim_front().consume(")") {
+            return self.error();
+        }
+        Ok(vec![Step::Property(property)])
+    }
+
+    /// `test("regex")` or `match("regex")` (after the `(`).
+    fn regex(
+        &mut self,
+        property: fn(Regex) -> Property<'static>,
+    ) -> TranslateResult<Vec<Step>> {
+        if !self.trim_front().consume("\"") {
+            return self.error();
+        }
+        let start = self.cursor;
+        let pattern = self.consume_while(key_char());
+        if !self.consume("\"") {
+            return self.error();
+        }
+        let regex = match Regex::new(&unescape(&pattern)) {
+            Ok(regex) => regex,
+            Err(_) => {
+                self.cursor = start;
+                self.error_type = JsonQueryErrorType::InvalidRegexError;
+                return self.error();
+            }
+        };
+        if !self.trim_front().consume(")") {
+            return self.error();
+        }
+        Ok(vec![Step::Property(property(regex))])
+    }
+
+    /// optionally signed integer (`None`, if there are no digits).
+    fn int(&mut self) -> TranslateResult<Option<i32>> {
+        let sign = if self.consume("-") { "-" } else { "" };
+        let digits = self.consume_while(|ch| ch.is_ascii_digit());
+        match (sign, digits.as_str()) {
+            ("", "") => Ok(None),
+            _ => match format!("{}{}", sign, digits).parse() {
+                Ok(int) => Ok(Some(int)),
+                Err(_) => self.error(),
+            },
+        }
+    }
+
+    /// `[]`, `[index]`, `[start:end]` or `["key"]`.
+    fn bracket(&mut self) -> TranslateResult<Step> {
+        self.consume("[");
+        if self.consume("]") {
+            return Ok(Step::Iterate);
+        }
+        let start = self.cursor;
+        let step = if self.consume("\"") {
+            let key = self.consume_while(key_char());
+            if !self.consume("\"") {
+                return self.error();
+            }
+            Step::Property(Property::Bracket(key.into(), false))
+        } else if self.consume("$") {
+            let name = self.consume_while(is_key);
+            let value = self.variables.get(&name);
+            match value.map(variable_property) {
+                Some(Some(property)) => Step::Property(property),
+                undefined_or_invalid => {
+                    self.cursor = start;
+                    self.error_type = match undefined_or_invalid {
+                        None => JsonQueryErrorType::UndefinedVariableError,
+                        Some(_) => JsonQueryErrorType::InvalidVariableError,
+                    };
+                    return self.error();
+                }
+            }
+        } else {
+            let start = self.int()?;
+            match (start, self.consume(":")) {
+                (Some(index), false) => {
+                    Step::Property(Property::Index(index, false))
+                }
+                (start, true) => {
+                    Step::Property(Property::Slice(start, self.int()?))
+                }
+                (None, false) => return self.error(),
+            }
+        };
+        if !self.consume("]") {
+            return self.error();
+        }
+        match step {
+            Step::Property(
+                property @ (Property::Bracket(..) | Property::Index(..)),
+            ) if self.consume("?") => Ok(Step::Property(property.optional())),
+            step => Ok(step),
+        }
+    }
+}
+
+/// predicate of the elements themselves (`any`, `all`).
+fn truthy() -> Predicate<'static> {
+    Predicate {
+        query: JsonQuery::identity(),
+        comparison: None,
+    }
+}
+
+#[inline]
+fn is_key(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// properties up to the first `[]`, rest of the steps are applied on every
+/// element.
+fn build(steps: Vec<Step>) -> JsonQuery<'static> {
+    let mut properties = vec![];
+    let mut steps = steps.into_iter();
+    while let Some(step) = steps.next() {
+        match step {
+            Step::Property(property) => properties.push(property),
+            Step::Iterate => {
+                properties.push(Property::Each(build(steps.collect())));
+                break;
+            }
+        }
+    }
+    JsonQuery::from_stages(properties)
+}
+
+/// translate `jq` query into [`JsonQuery`](JsonQuery) (example:
+/// `.items[] | .id` becomes `.items.map(.id)`).
+pub fn translate(s: &str) -> Result<JsonQuery<'static>, JsonQueryError> {
+    translate_with_variables(s, &Variables::new())
+}
+
+/// same as [`translate`], `.[$name]` selects the key (or index) given by the
+/// value of the variable (see [`JsonQuery::with_variables`]).
+pub fn translate_with_variables(
+    s: &str,
+    variables: &Variables,
+) -> Result<JsonQuery<'static>, JsonQueryError> {
+    // empty query is the identity (same as ruson queries).
+    if s.trim().is_empty() {
+        return Ok(JsonQuery(vec![]));
+    }
+    let mut translator = Translator {
+        chars: s.chars().collect(),
+        cursor: 0,
+        variables,
+        error_type: JsonQueryErrorType::SyntaxError,
+    };
+    let result = translator.pipeline().and_then(|steps| {
+        // unmatched ')' (or any trailing characters).
+        match translator.trim_front().peek() {
+            Some(_) => translator.error(),
+            None => Ok(build(steps)),
+        }
+    });
+    result.map_err(|cursor| JsonQueryError {
+        line: s.into(),
+        cursor,
+        error_type: translator.error_type,
+    })
+}
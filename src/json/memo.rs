@@ -0,0 +1,96 @@
+//! Memoization of `.map()`: elements with identical subtrees (common with
+//! denormalized data) are evaluated once, following elements reuse the
+//! cached result. Enabled by default, see [`set_enabled`].
+//!
+//! Path queries (dot, bracket and index properties) are never cached, as
+//! selecting by reference is cheaper than hashing the element.
+use super::{
+    error::JsonEvalError,
+    query::{ApplyOptions, JsonQuery},
+    scan,
+    token::Json,
+    HashMap,
+};
+use crate::prelude::*;
+use core::{
+    hash::{Hash, Hasher},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// enable/disable caching of `.map()` results (for every query evaluated
+/// afterwards).
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// element compared by its raw contents (unlike [`Json`]'s `Eq`, `"\u0041"`
+/// and `"A"` differ, as queries like `.keys()` see the raw text).
+struct Exact<'j>(&'j Json);
+
+impl PartialEq for Exact<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        exact_eq(self.0, other.0)
+    }
+}
+
+impl Eq for Exact<'_> {}
+
+impl Hash for Exact<'_> {
+    // exactly equal values are equal as `Json` (same hash).
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+fn exact_eq(a: &Json, b: &Json) -> bool {
+    match (a, b) {
+        (Json::Null, Json::Null) => true,
+        (Json::Boolean(a), Json::Boolean(b)) => a == b,
+        (Json::Number(a), Json::Number(b)) => a.to_bits() == b.to_bits(),
+        (Json::QString(a), Json::QString(b)) => a == b,
+        (Json::Array(a), Json::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| exact_eq(a, b))
+        }
+        (Json::Object(a), Json::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| exact_eq(a, b)))
+        }
+        _ => false,
+    }
+}
+
+/// apply `query` on every element of `array`.
+pub(super) fn map(
+    array: &[Json],
+    query: &JsonQuery,
+    options: ApplyOptions,
+) -> Result<Vec<Json>, JsonEvalError> {
+    // errors are located at the index of the element.
+    let apply = |(index, json): (usize, &Json)| {
+        json.apply_with(query, options)
+            .map_err(|err| err.after(&format!("[{}]", index), || json.shape()))
+    };
+    if !is_enabled() || array.len() < 2 || scan::is_path(query) {
+        return array.iter().enumerate().map(apply).collect();
+    }
+    let mut cache = HashMap::new();
+    array
+        .iter()
+        .enumerate()
+        .map(|(index, json)| match cache.get(&Exact(json)) {
+            Some(result) => Ok(Json::clone(result)),
+            None => {
+                let result = apply((index, json))?;
+                cache.insert(Exact(json), result.clone());
+                Ok(result)
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,58 @@
+//! Deep merge of json values, for layering documents (example: defaults,
+//! overridden by user configuration).
+use super::token::Json;
+
+/// How arrays present in both values are merged.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ArrayMerge {
+    /// array of the other value replaces the array.
+    #[default]
+    Replace,
+    /// elements of the other array are appended.
+    Concat,
+    /// elements at the same index are merged (extra elements are appended).
+    ByIndex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MergeStrategy {
+    pub arrays: ArrayMerge,
+    /// `null` values of the other object remove the key, instead of setting
+    /// it to `null` (same as JSON Merge Patch, rfc7396).
+    pub null_removes: bool,
+}
+
+impl Json {
+    /// merge `other` into `self`, recursively for objects (and arrays,
+    /// depending on `strategy`). any other value of `other` replaces `self`.
+    pub fn deep_merge(&mut self, mut other: Json, strategy: MergeStrategy) {
+        match (self, &mut other) {
+            (Json::Object(hashmap), Json::Object(other)) => {
+                for (key, value) in core::mem::take(&mut **other) {
+                    if value.is_null() && strategy.null_removes {
+                        hashmap.remove(&key);
+                    } else if let Some(existing) = hashmap.get_mut(&key) {
+                        existing.deep_merge(value, strategy);
+                    } else {
+                        hashmap.insert(key, value);
+                    }
+                }
+            }
+            (Json::Array(array), Json::Array(other))
+                if strategy.arrays != ArrayMerge::Replace =>
+            {
+                let other = core::mem::take(other);
+                if strategy.arrays == ArrayMerge::Concat {
+                    array.extend(other);
+                    return;
+                }
+                let mut other = other.into_iter();
+                for (existing, value) in array.iter_mut().zip(other.by_ref()) {
+                    existing.deep_merge(value, strategy);
+                }
+                array.extend(other);
+            }
+            (this, _) => *this = other,
+        }
+    }
+}
@@ -1,6 +1,34 @@
 //! Json parsing and processing utilities.
+pub mod arena;
+pub mod borrowed;
+pub mod convert;
+pub mod engine;
 pub mod error;
 pub mod formatter;
+pub mod iter;
+pub mod jq;
+pub mod memo;
+pub mod merge;
+#[cfg(feature = "std")]
+pub mod parallel;
 pub mod parser;
+pub mod patch;
 pub mod query;
+pub mod scan;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod shared;
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod stream;
+pub mod string;
+pub mod template;
 pub mod token;
+pub mod visit;
+
+#[cfg(not(feature = "std"))]
+pub use hashbrown::{hash_map, HashMap, HashSet};
+/// maps of [`Json::Object`](token::Json::Object) (from `hashbrown` without
+/// the `std` feature).
+#[cfg(feature = "std")]
+pub use std::collections::{hash_map, HashMap, HashSet};
@@ -0,0 +1,98 @@
+//! Parallel parsing of (huge) top-level arrays: the json text is split at the
+//! top-level commas (a single byte scan, balancing brackets outside of
+//! strings), and the elements are parsed in chunks across threads.
+//!
+//! Invalid input is parsed again sequentially (for the error message), so
+//! errors are reported exactly as [`JsonParser::parse`] does.
+use super::{error::JsonParseError, parser::JsonParser, token::Json};
+use std::{ops::Range, thread};
+
+/// inputs smaller than this (in bytes) are parsed sequentially.
+pub const MIN_SIZE: usize = 256 * 1024;
+
+/// byte ranges of the elements of the top-level array in `input` (without
+/// surrounding whitespace). `None` if `input` is not an array, or if its
+/// brackets are not balanced.
+fn elements(input: &str) -> Option<Vec<Range<usize>>> {
+    let bytes = input.as_bytes();
+    let open = bytes.iter().position(|byte| !byte.is_ascii_whitespace())?;
+    if bytes[open] != b'[' {
+        return None;
+    }
+    let mut ranges = Vec::new();
+    let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+    let mut start = open + 1;
+    for (index, &byte) in bytes.iter().enumerate().skip(start) {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => depth += 1,
+            b']' if depth == 0 => {
+                // empty array (a missing last element is a syntax error).
+                if !ranges.is_empty() || !input[start..index].trim().is_empty()
+                {
+                    ranges.push(start..index);
+                }
+                return Some(ranges);
+            }
+            b']' | b'}' => depth = depth.checked_sub(1)?,
+            b',' if depth == 0 => {
+                ranges.push(start..index);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// parse json text, the elements of a top-level array across `jobs` threads
+/// (any other value is parsed sequentially). limits are not supported (see
+/// [`JsonParser::with_limits`]).
+pub fn parse(input: &str, jobs: usize) -> Result<Json, JsonParseError> {
+    let sequential = || JsonParser::new(input).parse();
+    if jobs < 2 || input.len() < MIN_SIZE {
+        return sequential();
+    }
+    let ranges = match elements(input) {
+        Some(ranges) => ranges,
+        None => return sequential(),
+    };
+    let chunk_size = std::cmp::max(ranges.len().div_ceil(jobs), 1);
+    let parse_chunk = |chunk: &[Range<usize>]| {
+        let mut parser = JsonParser::new("");
+        chunk
+            .iter()
+            .map(|range| match parser.reset(&input[range.clone()]).parse() {
+                Ok(json) if parser.is_done() => Some(json),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+    };
+    let chunks: Option<Vec<Vec<Json>>> = thread::scope(|scope| {
+        let handles: Vec<_> = ranges
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || parse_chunk(chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().ok().flatten())
+            .collect()
+    });
+    match chunks {
+        Some(chunks) => {
+            let mut array = Vec::with_capacity(ranges.len());
+            chunks.into_iter().for_each(|chunk| array.extend(chunk));
+            Ok(Json::Array(array))
+        }
+        None => sequential(),
+    }
+}
@@ -1,10 +1,14 @@
 //! Utilities for tokenizing raw json string.
 use super::{
-    error::{JsonErrorType, JsonParseError},
-    query::JsonQuery,
-    token::{Json, Property},
+    error::{JsonErrorType, JsonParseError, JsonQueryErrorType},
+    query::{Comparison, JsonQuery, Predicate, Variables},
+    string::JsonString,
+    token::{unescape, Json, Property},
+    HashMap,
 };
-use crate::lexer::*;
+use crate::{lexer::*, prelude::*, regex::Regex};
+use alloc::borrow::Cow;
+use core::mem::size_of;
 
 macro_rules! lexer {
     ($self:expr) => {
@@ -23,46 +27,102 @@ macro_rules! ndigits {
 }
 
 type JsonParseResult<T> = Result<T, (JsonErrorType, usize)>;
+/// parsed query property, or the cursor (in chars) and type of the error.
+pub type PropertyResult<'a> = Result<Property<'a>, (usize, JsonQueryErrorType)>;
+
+/// maximum number of chars scanned ahead, when estimating the number of
+/// elements in an array/object (keeps the pre-scan linear overall).
+const CAPACITY_SCAN_LIMIT: usize = 1024;
+
+/// `10^exp` for non-negative `exp`, computed the same way (and with the same
+/// rounding) as `f32::powi`, which isn't available without `std`.
+pub(crate) fn pow10(exp: i32) -> f32 {
+    let (mut base, mut exp, mut pow) = (10f32, exp.unsigned_abs(), 1f32);
+    loop {
+        if exp & 1 == 1 {
+            pow *= base;
+        }
+        exp >>= 1;
+        if exp == 0 {
+            return pow;
+        }
+        base *= base;
+    }
+}
+
+/// Resource limits for parsing untrusted input (`None` means unlimited).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct JsonLimits {
+    /// maximum nesting depth of arrays/objects.
+    pub max_depth: Option<usize>,
+    /// maximum size of the json text (in bytes).
+    pub max_size: Option<usize>,
+    /// approximate memory budget of the parsed value (in bytes, excluding
+    /// the json text), queries can be evaluated within the rest of the
+    /// budget (see [`Json::apply_within`](Json::apply_within)).
+    pub max_memory: Option<usize>,
+}
 
 #[derive(Debug)]
-pub struct JsonParser(Lexer);
+pub struct JsonParser(
+    Lexer,
+    JsonLimits,
+    /// current nesting depth.
+    usize,
+    /// approximate bytes allocated for the parsed values.
+    usize,
+    /// frames of the nested values (kept for the next document, see
+    /// [`reset`](JsonParser::reset)).
+    Vec<Frame>,
+);
 
 impl JsonParser /* Public */ {
     pub fn new(s: &str) -> Self {
-        Self(Lexer::new(s))
+        Self(Lexer::new(s), JsonLimits::default(), 0, 0, Vec::new())
+    }
+
+    /// parse `s` next, reusing the buffers allocated for the previous
+    /// documents (limits are kept), example: `parser.reset(s).parse()`.
+    pub fn reset(&mut self, s: &str) -> &mut Self {
+        lexer!(self).reset(s);
+        self.2 = 0;
+        self.3 = 0;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: JsonLimits) -> Self {
+        self.1 = limits;
+        self
+    }
+
+    /// approximate bytes allocated for the values parsed so far.
+    pub fn allocated(&self) -> usize {
+        self.3
     }
 
     #[inline(always)]
     pub fn parse(&mut self) -> Result<Json, JsonParseError> {
-        self.trim_front()
-            .parse_any()
+        self.check_size()
+            .and_then(|_| self.trim_front().parse_any())
             .or_else(|(error_type, cursor)| {
                 let position = lexer!(self).position(cursor);
                 Err(JsonParseError {
-                    line: lexer!(self)
-                        .get_string()
-                        .lines()
-                        .skip(position.row - 1)
-                        .take(1)
-                        .collect(),
+                    line: lexer!(self).line(position.row),
                     position,
                     error_type,
                 })
             })
     }
 
+    /// only whitespace is left after the parsed token(s).
+    pub fn is_done(&mut self) -> bool {
+        lexer!(self.trim_front()).peek().is_none()
+    }
+
     /// try parsing any token.
     #[inline(always)]
     pub fn parse_any(&mut self) -> JsonParseResult<Json> {
-        match lexer!(self).peek() {
-            Some('-' | '0'..='9') => self.parse_number(),
-            Some('t' | 'f') => self.parse_boolean(),
-            Some('"') => self.parse_qstring(),
-            Some('n') => self.parse_null(),
-            Some('[') => self.parse_array(),
-            Some('{') => self.parse_object(),
-            _ => return Err(self.error(JsonErrorType::SyntaxError)),
-        }
+        self.parse_nested(None)
     }
 
     /// try parsing [`Json::Null`](Json::Null).
@@ -90,15 +150,13 @@ impl JsonParser /* Public */ {
             lexer!(self)
                 .consume_byte('.')
                 // parse leading decimal zeroes.
-                .map(|_| {
-                    lexer!(self).consume_while(|&ch| ch == '0').len() as i32
-                })
+                .map(|_| lexer!(self).skip_while(|&ch| ch == '0') as i32)
                 // parse decimal number.
                 .and_then(|leading_zeroes| {
                     lexer!(self).consume_int().and_then(|number| {
                         if number >= 0 {
                             let digits = ndigits!(number) + leading_zeroes;
-                            let decimal = number as f32 / 10f32.powi(digits);
+                            let decimal = number as f32 / pow10(digits);
                             Some(f + if f >= 0. { decimal } else { -decimal })
                         } else {
                             None
@@ -135,93 +193,284 @@ impl JsonParser /* Public */ {
     /// try parsing [`Json::QString`](Json::QString).
     pub fn parse_qstring(&mut self) -> JsonParseResult<Json> {
         self.parse_byte('"')?;
-        let mut escaped = false;
-        let string = lexer!(self).consume_while(|&ch| {
-            if ch == '"' && !escaped {
-                return false;
-            }
-            escaped = ch == '\\';
-            true
-        });
-        self.parse_byte('"').and(Ok(Json::QString(string)))
+        let string = lexer!(self).consume_quoted('"');
+        self.parse_byte('"').and(Ok(Json::QString(string.into())))
     }
 
     /// try parsing [`Json::Array`](Json::Array).
     pub fn parse_array(&mut self) -> JsonParseResult<Json> {
-        self.parse_byte('[')?;
-        let mut array = Vec::new();
-        if self
-            .trim_front()
-            .parse_any()
-            .map(|token| array.push(token))
-            .is_ok()
-        {
-            // try parsing token, only if comma present.
-            while self.trim_front().parse_byte(',').is_ok() {
-                self.trim_front()
-                    .parse_any()
-                    .map(|token| array.push(token))
-                    .or_else(|_| {
-                        Err(self
-                            .untrim_front()
-                            .error(JsonErrorType::TrailingCommaError))
-                    })?;
-            }
-        }
-        self.trim_front()
-            .parse_byte(']')
-            .and(Ok(Json::Array(array)))
+        self.parse_nested(Some('['))
     }
 
     /// try parsing [`Json::Object`](Json::Object).
     pub fn parse_object(&mut self) -> JsonParseResult<Json> {
-        self.parse_byte('{')?;
-        let mut hashmap = std::collections::HashMap::new();
-        let mut string_key = String::new();
-        let mut json_key = self.trim_front().parse_qstring().ok();
-        while {
-            // unwrap Json key -> string key.
-            match json_key {
-                Some(Json::QString(key)) => {
-                    if hashmap.contains_key(&key) {
-                        lexer!(self).cursor -= key.len() - 1; // for better error message.
-                        return Err(
-                            self.error(JsonErrorType::DuplicateKeyError)
-                        );
-                    }
-                    string_key = key;
-                    true
+        self.parse_nested(Some('{'))
+    }
+}
+
+/// array/object waiting for the value being parsed (parsing uses an
+/// explicit stack of frames, so nesting depth isn't bound by the call stack).
+#[derive(Debug)]
+enum Frame {
+    /// first element (errors other than limit errors are ignored, and the
+    /// array is expected to be closed).
+    ArrayFirst(Vec<Json>),
+    /// element after a comma.
+    ArrayNext(Vec<Json>),
+    /// value of `key`.
+    ObjectValue(HashMap<JsonString, Json>, JsonString),
+}
+
+/// `None` if a frame has been pushed (waiting for the next value).
+type Step = Option<JsonParseResult<Json>>;
+
+impl JsonParser /* Nested */ {
+    /// parse value (or the array/object opened by `open`).
+    fn parse_nested(&mut self, open: Option<char>) -> JsonParseResult<Json> {
+        let mut frames = core::mem::take(&mut self.4);
+        let mut step = match open {
+            Some(bracket) => self.open(bracket, &mut frames),
+            None => self.begin(&mut frames),
+        };
+        let result = loop {
+            let result = match step {
+                Some(result) => result,
+                None => {
+                    step = self.begin(&mut frames);
+                    continue;
                 }
-                _ => false,
+            };
+            step = match frames.pop() {
+                Some(frame) => self.resume(frame, result, &mut frames),
+                None => break result,
+            };
+        };
+        self.4 = frames;
+        result
+    }
+
+    fn begin(&mut self, frames: &mut Vec<Frame>) -> Step {
+        let scalar = match lexer!(self).peek() {
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some('t' | 'f') => self.parse_boolean(),
+            Some('"') => self.parse_qstring(),
+            Some('n') => self.parse_null(),
+            Some(&bracket @ ('[' | '{')) => return self.open(bracket, frames),
+            _ => Err(self.error(JsonErrorType::SyntaxError)),
+        };
+        Some(scalar.and_then(|json| {
+            let heap = match &json {
+                Json::QString(string) => string.heap_size(),
+                _ => 0,
+            };
+            self.allocate(size_of::<Json>() + heap).map(|_| json)
+        }))
+    }
+
+    /// opening `bracket` of an array/object.
+    fn open(&mut self, bracket: char, frames: &mut Vec<Frame>) -> Step {
+        // objects are boxed (see `Json::Object`).
+        let boxed = match bracket {
+            '{' => size_of::<HashMap<JsonString, Json>>(),
+            _ => 0,
+        };
+        if let Err(err) = self
+            .allocate(size_of::<Json>() + boxed)
+            .and_then(|_| self.enter())
+            .and_then(|this| this.parse_byte(bracket))
+        {
+            return Some(Err(err));
+        }
+        if bracket == '[' {
+            let array = Vec::with_capacity(self.capacity_hint());
+            self.trim_front();
+            frames.push(Frame::ArrayFirst(array));
+            return None;
+        }
+        let hashmap = HashMap::with_capacity(self.capacity_hint());
+        let json_key = self.trim_front().parse_qstring().ok();
+        self.object_entry(hashmap, json_key, frames)
+    }
+
+    /// continue parsing `frame`, with the `result` of its value.
+    fn resume(
+        &mut self,
+        frame: Frame,
+        result: JsonParseResult<Json>,
+        frames: &mut Vec<Frame>,
+    ) -> Step {
+        match (frame, result) {
+            // limit errors are not recoverable (unlike the empty array case).
+            (
+                _,
+                Err(
+                    err @ (
+                        JsonErrorType::DepthLimitError
+                        | JsonErrorType::MemoryLimitError,
+                        _,
+                    ),
+                ),
+            ) => Some(Err(err)),
+            (Frame::ArrayFirst(array), Err(_)) => self.close_array(array),
+            (Frame::ArrayNext(_), Err(_)) => Some(Err(self
+                .untrim_front()
+                .error(JsonErrorType::TrailingCommaError))),
+            (
+                Frame::ArrayFirst(mut array) | Frame::ArrayNext(mut array),
+                Ok(token),
+            ) => {
+                array.push(token);
+                // try parsing token, only if comma present.
+                if self.trim_front().parse_byte(',').is_ok() {
+                    self.trim_front();
+                    frames.push(Frame::ArrayNext(array));
+                    return None;
+                }
+                self.close_array(array)
+            }
+            (Frame::ObjectValue(..), Err(err)) => Some(Err(err)),
+            (Frame::ObjectValue(mut hashmap, key), Ok(token)) => {
+                hashmap.insert(key, token);
+                // try parsing 'json_key' only if comma parsed.
+                let json_key = if self.trim_front().parse_byte(',').is_ok() {
+                    // comma needs to be followed by a string.
+                    match self.trim_front().parse_qstring() {
+                        Ok(json_key) => Some(json_key),
+                        Err(_) => {
+                            return Some(Err(self
+                                .untrim_front()
+                                .error(JsonErrorType::TrailingCommaError)))
+                        }
+                    }
+                } else {
+                    None
+                };
+                self.object_entry(hashmap, json_key, frames)
             }
-        } {
+        }
+    }
+
+    fn close_array(&mut self, array: Vec<Json>) -> Step {
+        Some(
             self.trim_front()
-                .parse_byte(':')?
-                .trim_front()
-                .parse_any()
-                .map(|token| hashmap.insert(string_key.clone(), token))?;
-            // try parsing 'json_key' only if comma parsed.
-            json_key = if self.trim_front().parse_byte(',').is_ok() {
-                // comma needs to be followed by a string.
-                self.trim_front().parse_qstring().map(Some).or_else(|_| {
-                    Err(self
-                        .untrim_front()
-                        .error(JsonErrorType::TrailingCommaError))
-                })?
-            } else {
+                .parse_byte(']')
+                .map(|this| this.leave())
+                .map(|_| Json::Array(array)),
+        )
+    }
+
+    /// entry starting with `json_key` (object is closed, if `None`).
+    fn object_entry(
+        &mut self,
+        hashmap: HashMap<JsonString, Json>,
+        mut json_key: Option<Json>,
+        frames: &mut Vec<Frame>,
+    ) -> Step {
+        // unwrap Json key -> string key.
+        match json_key.as_mut() {
+            Some(Json::QString(key)) => {
+                let key = core::mem::take(key);
+                if hashmap.contains_key(&key) {
+                    lexer!(self).cursor -= key.len() - 1; // for better error message.
+                    return Some(Err(
+                        self.error(JsonErrorType::DuplicateKeyError)
+                    ));
+                }
+                if let Err(err) = self
+                    .allocate(size_of::<JsonString>() + key.heap_size())
+                    .and_then(|_| self.trim_front().parse_byte(':'))
+                {
+                    return Some(Err(err));
+                }
+                self.trim_front();
+                frames.push(Frame::ObjectValue(hashmap, key));
                 None
-            };
+            }
+            _ => Some(
+                self.trim_front()
+                    .parse_byte('}')
+                    .map(|this| this.leave())
+                    .map(|_| Json::Object(hashmap.into())),
+            ),
         }
-        self.trim_front()
-            .parse_byte('}')
-            .and(Ok(Json::Object(hashmap)))
     }
 }
 
 impl JsonParser /* Private */ {
+    /// increment nesting depth (array/object), erroring out if over limit.
+    #[inline]
+    fn enter(&mut self) -> JsonParseResult<&mut Self> {
+        self.2 += 1;
+        match self.1.max_depth {
+            Some(max_depth) if self.2 > max_depth => {
+                Err(self.error(JsonErrorType::DepthLimitError))
+            }
+            _ => Ok(self),
+        }
+    }
+
+    #[inline]
+    fn leave(&mut self) -> &mut Self {
+        self.2 -= 1;
+        self
+    }
+
+    /// account for `bytes` allocated, erroring out if over budget.
+    #[inline]
+    fn allocate(&mut self, bytes: usize) -> JsonParseResult<()> {
+        self.3 += bytes;
+        match self.1.max_memory {
+            Some(max_memory) if self.3 > max_memory => {
+                Err(self.error(JsonErrorType::MemoryLimitError))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// number of elements in the array/object starting at the cursor (after
+    /// the opening bracket), counting the top level commas. only the next
+    /// [`CAPACITY_SCAN_LIMIT`] chars are scanned, so this is a lower bound
+    /// for large collections (and exact for small ones).
+    fn capacity_hint(&self) -> usize {
+        let stack = &lexer!(self).stack[lexer!(self).cursor..];
+        let (mut elements, mut depth, mut in_string, mut escaped) =
+            (0, 0usize, false, false);
+        for &ch in stack.iter().take(CAPACITY_SCAN_LIMIT) {
+            if in_string {
+                in_string = escaped || ch != '"';
+                escaped = !escaped && ch == '\\';
+                continue;
+            }
+            match ch {
+                '"' => in_string = true,
+                '[' | '{' => depth += 1,
+                ']' | '}' if depth == 0 => break,
+                ']' | '}' => depth -= 1,
+                ',' if depth == 0 => elements += 1,
+                _ => {}
+            }
+            if elements == 0 && !is_whitespace(ch) {
+                elements = 1;
+            }
+        }
+        elements
+    }
+
+    fn check_size(&self) -> JsonParseResult<()> {
+        if let Some(max_size) = self.1.max_size {
+            let (mut size, stack) = (0, &lexer!(self).stack);
+            for (cursor, ch) in stack.iter().enumerate() {
+                size += ch.len_utf8();
+                if size > max_size {
+                    return Err((JsonErrorType::SizeLimitError, cursor));
+                }
+            }
+        }
+        Ok(())
+    }
     #[inline]
     fn trim_front(&mut self) -> &mut Self {
-        lexer!(self).consume_while(|c| c.is_whitespace());
+        lexer!(self).skip_whitespace();
         self
     }
 
@@ -230,7 +479,7 @@ impl JsonParser /* Private */ {
     fn untrim_front(&mut self) -> &mut Self {
         lexer!(self).cursor -= 1;
         while let Some(ch) = lexer!(self).peek() {
-            if ch.is_whitespace() && lexer!(self).cursor > 0 {
+            if is_whitespace(*ch) && lexer!(self).cursor > 0 {
                 lexer!(self).cursor -= 1;
             } else {
                 break;
@@ -253,70 +502,590 @@ impl JsonParser /* Private */ {
     }
 }
 
-pub struct PropertyParser(Lexer);
+impl core::str::FromStr for Json {
+    type Err = JsonParseError;
 
-impl PropertyParser /* Public */ {
+    /// parse json text (example: `"[1, 2]".parse::<Json>()`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JsonParser::new(s).parse()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Json {
+    /// read the whole of `reader` and parse it as json text.
+    pub fn from_reader<R: std::io::Read>(
+        mut reader: R,
+    ) -> Result<Self, super::error::JsonReadError> {
+        let mut buffer = String::new();
+        reader.read_to_string(&mut buffer)?;
+        Ok(buffer.parse()?)
+    }
+}
+
+/// Query parser, over the query text (keys and names of the parsed
+/// properties borrow from it).
+pub struct PropertyParser<'a>(
+    &'a str,
+    /// byte offset in the query text.
+    usize,
+    /// values of the `$name` variables.
+    Option<&'a Variables>,
+);
+
+impl<'a> PropertyParser<'a> /* Public */ {
     #[rustfmt::skip]
-    pub fn new(s: &str) -> Self { Self(Lexer::new(s)) }
-
-    pub fn parse_any(&mut self) -> Option<Result<Property, usize>> {
-        let maybe_property = match lexer!(self).peek() {
-            Some('.') => self
-                .try_consume(".keys()", Property::Keys)
-                .or_else(|| self.try_consume(".values()", Property::Values))
-                .or_else(|| self.try_consume(".length()", Property::Length))
-                .or_else(|| self.parse_map_func())
-                .or_else(|| self.parse_dot_prop()),
-            Some('[') => match lexer!(self).peek_at(lexer!(self).cursor + 1) {
-                Some('"') => self.parse_bracket_prop(),
-                Some('-' | '0'..='9') => self.parse_array_index(),
-                _ => return Some(Err(lexer!(self).cursor + 2)),
+    pub fn new(s: &'a str) -> Self { Self(s, 0, None) }
+
+    pub fn with_variables(s: &'a str, variables: &'a Variables) -> Self {
+        Self(s, 0, Some(variables))
+    }
+
+    /// errors contain the cursor (in chars) of the invalid syntax.
+    pub fn parse_any(&mut self) -> Option<PropertyResult<'a>> {
+        let syntax_error =
+            |cursor| Err((cursor, JsonQueryErrorType::SyntaxError));
+        // stages separated by `|` (example: `.a | .b.length()`) are applied in
+        // sequence, see `Property::Pipe`.
+        match self.parse_pipe() {
+            Ok(true) => return Some(Ok(Property::Pipe)),
+            Ok(false) => {}
+            Err(cursor) => return Some(syntax_error(cursor)),
+        }
+        // identity (`.`), alone or followed by brackets (`.[0]`) or a
+        // comparison (`. > 0`), at the start of a query (`.map()` or stage),
+        // selects the value itself.
+        let before = &self.0[..self.1];
+        let at_start = before.is_empty()
+            || before.ends_with('(')
+            || before.trim_end_matches(is_whitespace).ends_with('|');
+        if at_start
+            && self.peek() == Some(b'.')
+            && matches!(
+                self.0.as_bytes().get(self.1 + 1),
+                None | Some(
+                    b')' | b'['
+                        | b'|'
+                        | b' '
+                        | b'\n'
+                        | b'\t'
+                        | b'\r'
+                        | b'='
+                        | b'!'
+                        | b'<'
+                        | b'>'
+                )
+            )
+        {
+            self.1 += 1;
+            // identity stage (`. | .a`).
+            if self.peek().is_some_and(|byte| byte != b'[' && byte != b')') {
+                return self.parse_any();
+            }
+        }
+        let maybe_property = match self.peek() {
+            Some(b'.') if self.0[self.1..].starts_with(".{") => {
+                self.parse_pick()
+            }
+            Some(b'.') if self.0[self.1..].starts_with(".omit(") => {
+                self.parse_omit()
+            }
+            Some(b'.') if self.0[self.1..].starts_with(".find(") => {
+                self.parse_find(".find(", Property::Find)
+            }
+            Some(b'.') if self.0[self.1..].starts_with(".findpaths(") => {
+                self.parse_find(".findpaths(", Property::FindPaths)
+            }
+            Some(b'.') if self.0[self.1..].starts_with(".has(") => {
+                match self.parse_has() {
+                    Some(Err(err)) => return Some(Err(err)),
+                    maybe_property => maybe_property.and_then(Result::ok),
+                }
+            }
+            Some(b'.') if self.0[self.1..].starts_with(".del(") => {
+                match self.parse_del() {
+                    Some(Err(err)) => return Some(Err(err)),
+                    maybe_property => maybe_property.and_then(Result::ok),
+                }
+            }
+            Some(b'.') if self.0[self.1..].starts_with(".flatten(") => {
+                match self.parse_flatten() {
+                    Some(Err(err)) => return Some(Err(err)),
+                    maybe_property => maybe_property.and_then(Result::ok),
+                }
+            }
+            Some(b'.') if self.0[self.1..].starts_with(".test(") => {
+                match self.parse_regex(".test(", Property::Test) {
+                    Some(Err(err)) => return Some(Err(err)),
+                    maybe_property => maybe_property.and_then(Result::ok),
+                }
+            }
+            Some(b'.') if self.0[self.1..].starts_with(".match(") => {
+                match self.parse_regex(".match(", Property::Match) {
+                    Some(Err(err)) => return Some(Err(err)),
+                    maybe_property => maybe_property.and_then(Result::ok),
+                }
+            }
+            Some(b'.') if self.0[self.1..].starts_with(".any(") => {
+                match self.parse_quantifier(".any(", Property::Any) {
+                    Some(Err(err)) => return Some(Err(err)),
+                    maybe_property => maybe_property.and_then(Result::ok),
+                }
+            }
+            Some(b'.') if self.0[self.1..].starts_with(".all(") => {
+                match self.parse_quantifier(".all(", Property::All) {
+                    Some(Err(err)) => return Some(Err(err)),
+                    maybe_property => maybe_property.and_then(Result::ok),
+                }
+            }
+            Some(b'.') => {
+                let maybe_property = self.parse_dot_any();
+                let rest = &self.0[self.1..];
+                // dot key followed by a char not allowed in keys (other than
+                // a pipe, or a comparison of a predicate).
+                let next = rest.trim_start_matches(is_whitespace);
+                let piped = next.starts_with('|')
+                    || Comparison::ALL.iter().any(|comparison| {
+                        next.starts_with(comparison.as_str())
+                    });
+                match (&maybe_property, rest.chars().next()) {
+                    (None | Some(Property::Dot(..)), Some(ch))
+                        if !".[)?".contains(ch) && !piped =>
+                    {
+                        return Some(Err((
+                            self.char_cursor() + 1,
+                            JsonQueryErrorType::InvalidKeyError,
+                        )))
+                    }
+                    _ => maybe_property,
+                }
+            }
+            Some(b'[') => match self.0.as_bytes().get(self.1 + 1) {
+                Some(b'"') => self.parse_bracket_prop(),
+                Some(b']') => return Some(self.parse_each()),
+                Some(b'-' | b'0'..=b'9' | b':') => {
+                    match self.parse_array_index() {
+                        Some(Err(err)) => return Some(Err(err)),
+                        maybe_property => maybe_property.and_then(Result::ok),
+                    }
+                }
+                Some(b'$') => match self.parse_variable() {
+                    Ok(property) => Some(property),
+                    Err(err) => return Some(Err(err)),
+                },
+                _ => return Some(syntax_error(self.char_cursor() + 2)),
             },
             None => return None,
-            _ => return Some(Err(lexer!(self).cursor + 1)),
+            _ => return Some(syntax_error(self.char_cursor() + 1)),
         };
-        Some(maybe_property.ok_or(lexer!(self).cursor))
+        Some(
+            maybe_property
+                .map(|property| self.parse_optional(property))
+                .map_or_else(|| syntax_error(self.char_cursor()), Ok),
+        )
+    }
+
+    /// `?` following a dot, bracket or index property (example: `.a?.b`),
+    /// see [`Property::is_optional`](Property::is_optional).
+    fn parse_optional(&mut self, property: Property<'a>) -> Property<'a> {
+        match property {
+            Property::Dot(..) | Property::Bracket(..) | Property::Index(..)
+                if self.consume("?").is_some() =>
+            {
+                property.optional()
+            }
+            property => property,
+        }
+    }
+
+    /// consume `|` between stages (with the surrounding whitespace), errors
+    /// (cursor) if a stage is empty.
+    fn parse_pipe(&mut self) -> Result<bool, usize> {
+        let start = self.1;
+        self.consume_while(is_whitespace);
+        if self.consume("|").is_none() {
+            self.1 = start;
+            return Ok(false);
+        }
+        let empty = |s: &str| s.is_empty() || s.ends_with('(');
+        if empty(&self.0[..start]) {
+            return Err(self.char_cursor());
+        }
+        self.consume_while(is_whitespace);
+        match self.peek() {
+            None | Some(b')' | b'|') => Err(self.char_cursor() + 1),
+            _ => Ok(true),
+        }
+    }
+
+    /// try parsing any property starting with `.` (functions, or a dot key).
+    #[inline(always)]
+    pub fn parse_dot_any(&mut self) -> Option<Property<'a>> {
+        self.try_consume(".keys()", Property::Keys)
+            .or_else(|| self.try_consume(".values()", Property::Values))
+            .or_else(|| self.try_consume(".length()", Property::Length))
+            .or_else(|| self.try_consume(".bytelength()", Property::ByteLength))
+            .or_else(|| self.try_consume(".type()", Property::Type))
+            .or_else(|| self.try_consume(".paths()", Property::Paths))
+            .or_else(|| self.try_consume(".first()", Property::First))
+            .or_else(|| self.try_consume(".last()", Property::Last))
+            .or_else(|| self.try_consume(".sum()", Property::Sum))
+            .or_else(|| self.try_consume(".min()", Property::Min))
+            .or_else(|| self.try_consume(".max()", Property::Max))
+            .or_else(|| self.try_consume(".avg()", Property::Avg))
+            .or_else(|| self.parse_map_func())
+            .or_else(|| self.parse_group_by())
+            .or_else(|| self.parse_call())
+            .or_else(|| self.parse_dot_prop())
     }
 
-    /// try parsing [`Property::Dot`](Property::Dot).
+    /// try parsing [`Property::Dot`](Property::Dot) (see
+    /// [`Property::is_dot_key`](Property::is_dot_key)).
     #[inline(always)]
-    pub fn parse_dot_prop(&mut self) -> Option<Property> {
-        lexer!(self).consume_byte('.')?;
-        let prop = lexer!(self).consume_while(|&ch| !".[)".contains(ch));
+    pub fn parse_dot_prop(&mut self) -> Option<Property<'a>> {
+        self.consume(".")?;
+        let prop = self.consume_while(dot_key_char);
         if prop.is_empty() {
             return None;
         }
-        Some(Property::Dot(prop))
+        Some(Property::Dot(prop.into(), false))
     }
 
-    /// try parsing [`Property::Bracket`](Property::Bracket).
+    /// try parsing [`Property::Bracket`](Property::Bracket), the key is the
+    /// contents of a json string (up to the first unescaped `"`).
     #[inline(always)]
-    pub fn parse_bracket_prop(&mut self) -> Option<Property> {
-        lexer!(self).consume_string("[\"")?;
-        let prop = lexer!(self).consume_while(|&ch| ch != '"');
-        if prop.is_empty() {
-            return None;
+    pub fn parse_bracket_prop(&mut self) -> Option<Property<'a>> {
+        self.consume("[\"")?;
+        let prop = self.consume_while(key_char());
+        self.consume("\"]")
+            .and(Some(Property::Bracket(prop.into(), false)))
+    }
+
+    /// try parsing `[$name]`, [`Property::Bracket`](Property::Bracket) or
+    /// [`Property::Index`](Property::Index) of the value of the variable
+    /// (errors if it has no value, or it is neither a string nor an integer).
+    pub fn parse_variable(&mut self) -> PropertyResult<'a> {
+        let cursor = self.char_cursor();
+        self.consume("[$");
+        let value = self.variable();
+        match (value.map(variable_property), self.consume("]")) {
+            (Some(Some(property)), Some(_)) => Ok(property),
+            (None, _) => {
+                Err((cursor + 2, JsonQueryErrorType::UndefinedVariableError))
+            }
+            (Some(None), _) => {
+                Err((cursor + 2, JsonQueryErrorType::InvalidVariableError))
+            }
+            (_, None) => {
+                Err((self.char_cursor() + 1, JsonQueryErrorType::SyntaxError))
+            }
         }
-        lexer!(self)
-            .consume_string("\"]")
-            .and(Some(Property::Bracket(prop)))
     }
 
-    /// try parsing [`Property::Index`](Property::Index).
+    /// value of the variable named by the following key chars (`None`, if it
+    /// has no value).
+    fn variable(&mut self) -> Option<&'a Json> {
+        let name = self.consume_while(dot_key_char);
+        self.2.and_then(|variables| variables.get(name))
+    }
+
+    /// try parsing [`Property::Index`](Property::Index) or
+    /// [`Property::Slice`](Property::Slice) (`[1:4]`, `[:3]`, `[2:]`).
     #[inline(always)]
-    pub fn parse_array_index(&mut self) -> Option<Property> {
-        lexer!(self).consume_byte('[')?;
-        lexer!(self).consume_int().and_then(|num| {
-            lexer!(self)
-                .consume_byte(']')
-                .and(Some(Property::Index(num)))
-        })
+    pub fn parse_array_index(&mut self) -> Option<PropertyResult<'a>> {
+        self.consume("[")?;
+        let start = match self.parse_int() {
+            Ok(start) => start,
+            Err(err) => return Some(Err(err)),
+        };
+        if self.consume(":").is_some() {
+            let end = match self.parse_int() {
+                Ok(end) => end,
+                Err(err) => return Some(Err(err)),
+            };
+            return self
+                .consume("]")
+                .and(Some(Ok(Property::Slice(start, end))));
+        }
+        let index = start?;
+        self.consume("]")
+            .and(Some(Ok(Property::Index(index, false))))
+    }
+
+    /// optionally signed integer (`None`, if there are no digits), errors
+    /// (cursor of the number) if it is out of range of `i32`.
+    #[inline(always)]
+    fn parse_int(
+        &mut self,
+    ) -> Result<Option<i32>, (usize, JsonQueryErrorType)> {
+        let (start, cursor) = (self.1, self.char_cursor() + 1);
+        let sign = self.consume("-").and(Some(-1)).unwrap_or(1);
+        let digits = self.consume_while(|ch| ch.is_ascii_digit());
+        if digits.is_empty() {
+            self.1 = start;
+            return Ok(None);
+        }
+        // (more digits than an `i64` holds, are out of range too).
+        digits
+            .parse::<i64>()
+            .ok()
+            .and_then(|number| {
+                core::convert::TryFrom::try_from(number * sign).ok()
+            })
+            .map(Some)
+            .ok_or((cursor, JsonQueryErrorType::OutOfRangeError))
+    }
+
+    /// parse [`Property::Each`](Property::Each), `[]` followed by the rest of
+    /// the query (up to the end of the enclosing `.map()`, if any).
+    pub fn parse_each(&mut self) -> PropertyResult<'a> {
+        self.1 += "[]".len();
+        let mut properties = vec![];
+        while self.peek().is_some_and(|byte| byte != b')') {
+            if let Some(property) = self.parse_any() {
+                properties.push(property?);
+            }
+        }
+        Ok(Property::Each(JsonQuery::from_stages(properties)))
+    }
+
+    /// try parsing [`Property::Flatten`](Property::Flatten), with an
+    /// optional depth argument (`.flatten()`, `.flatten(2)`), errors (cursor
+    /// of the depth) if the depth is out of range of `u32`.
+    #[inline(always)]
+    pub fn parse_flatten(&mut self) -> Option<PropertyResult<'a>> {
+        let start = self.1;
+        self.consume(".flatten(")?;
+        let cursor = self.char_cursor() + 1;
+        let depth = match self.consume_while(|ch| ch.is_ascii_digit()) {
+            "" => Ok(1),
+            depth => depth
+                .parse()
+                .map_err(|_| (cursor, JsonQueryErrorType::OutOfRangeError)),
+        };
+        match (depth, self.consume(")")) {
+            (Err(err), _) => Some(Err(err)),
+            (Ok(depth), Some(_)) => Some(Ok(Property::Flatten(depth))),
+            (Ok(_), None) => {
+                self.1 = start;
+                None
+            }
+        }
     }
 
     /// try parsing [`Property::Map(JsonQuery)`](Property::Map).
     #[inline(always)]
-    pub fn parse_map_func(&mut self) -> Option<Property> {
-        lexer!(self).consume_string(".map(")?;
+    pub fn parse_map_func(&mut self) -> Option<Property<'a>> {
+        self.parse_sub_query(".map(").map(Property::Map)
+    }
+
+    /// try parsing [`Property::GroupBy(JsonQuery)`](Property::GroupBy).
+    #[inline(always)]
+    pub fn parse_group_by(&mut self) -> Option<Property<'a>> {
+        self.parse_sub_query(".group_by(").map(Property::GroupBy)
+    }
+
+    /// try parsing [`Property::Pick`](Property::Pick), comma separated keys
+    /// in dot notation or quoted (`.{id, "my key"}`).
+    #[inline(always)]
+    pub fn parse_pick(&mut self) -> Option<Property<'a>> {
+        self.consume(".{")?;
+        let keys = self.parse_keys("}", |parser| {
+            parser.parse_quoted().or_else(|| {
+                Some(parser.consume_while(dot_key_char))
+                    .filter(|key| !key.is_empty())
+            })
+        })?;
+        Some(Property::Pick(keys))
+    }
+
+    /// try parsing [`Property::Omit`](Property::Omit), comma separated
+    /// quoted keys (`.omit("password", "token")`).
+    #[inline(always)]
+    pub fn parse_omit(&mut self) -> Option<Property<'a>> {
+        self.consume(".omit(")?;
+        let keys = self.parse_keys(")", Self::parse_quoted)?;
+        Some(Property::Omit(keys))
+    }
+
+    /// try parsing [`Property::Find`](Property::Find) or
+    /// [`Property::FindPaths`](Property::FindPaths) (`prefix` followed by a
+    /// quoted pattern).
+    #[inline(always)]
+    pub fn parse_find<F: FnOnce(Cow<'a, str>) -> Property<'a>>(
+        &mut self,
+        prefix: &str,
+        property: F,
+    ) -> Option<Property<'a>> {
+        self.consume(prefix)?;
+        self.consume_while(is_whitespace);
+        let pattern = self.parse_quoted()?;
+        self.consume_while(is_whitespace);
+        self.consume(")").and(Some(property(pattern.into())))
+    }
+
+    /// try parsing [`Property::Test`](Property::Test) or
+    /// [`Property::Match`](Property::Match) (`prefix` followed by a quoted
+    /// regex and `)`), errors if the regex is invalid.
+    pub fn parse_regex<F: FnOnce(Regex) -> Property<'a>>(
+        &mut self,
+        prefix: &str,
+        property: F,
+    ) -> Option<PropertyResult<'a>> {
+        self.consume(prefix)?;
+        self.consume_while(is_whitespace);
+        let cursor = self.char_cursor();
+        let pattern = self.parse_quoted()?;
+        let regex = match Regex::new(&unescape(pattern)) {
+            Ok(regex) => regex,
+            Err(offset) => {
+                // offset in the query is only known without escapes.
+                let offset = if pattern.contains('\\') { 0 } else { offset };
+                return Some(Err((
+                    cursor + 2 + offset,
+                    JsonQueryErrorType::InvalidRegexError,
+                )));
+            }
+        };
+        self.consume_while(is_whitespace);
+        self.consume(")").and(Some(Ok(property(regex))))
+    }
+
+    /// try parsing [`Property::HasKey`](Property::HasKey) (`.has("key")`) or
+    /// [`Property::HasIndex`](Property::HasIndex) (`.has(0)`).
+    #[inline(always)]
+    pub fn parse_has(&mut self) -> Option<PropertyResult<'a>> {
+        self.parse_member(".has(", Property::HasKey, Property::HasIndex)
+    }
+
+    /// try parsing [`Property::DelKey`](Property::DelKey) (`.del("key")`) or
+    /// [`Property::DelIndex`](Property::DelIndex) (`.del(0)`).
+    #[inline(always)]
+    pub fn parse_del(&mut self) -> Option<PropertyResult<'a>> {
+        self.parse_member(".del(", Property::DelKey, Property::DelIndex)
+    }
+
+    /// try parsing [`Property::Any`](Property::Any) or
+    /// [`Property::All`](Property::All) following `open`, the predicate is a
+    /// query optionally compared with a json literal (`.all(.age >= 18)`) or
+    /// a variable (`.all(.age >= $min)`, errors if it has no value).
+    pub fn parse_quantifier<F: FnOnce(Predicate<'a>) -> Property<'a>>(
+        &mut self,
+        open: &str,
+        f: F,
+    ) -> Option<PropertyResult<'a>> {
+        self.consume(open)?;
+        self.consume_while(is_whitespace);
+        let mut properties = vec![];
+        while let Some(Ok(property)) = self.parse_any() {
+            properties.push(property);
+        }
+        self.consume_while(is_whitespace);
+        let comparison = Comparison::ALL
+            .iter()
+            .copied()
+            .find(|comparison| self.consume(comparison.as_str()).is_some());
+        let comparison = match comparison {
+            Some(comparison) => {
+                self.consume_while(is_whitespace);
+                let cursor = self.char_cursor();
+                let variable = self.0[self.1..].starts_with('$');
+                match self.parse_literal() {
+                    Some(literal) => Some((comparison, literal)),
+                    None if variable => {
+                        return Some(Err((
+                            cursor + 1,
+                            JsonQueryErrorType::UndefinedVariableError,
+                        )))
+                    }
+                    None => return None,
+                }
+            }
+            None => None,
+        };
+        self.consume_while(is_whitespace);
+        self.consume(")").and(Some(Ok(f(Predicate {
+            query: JsonQuery::from_stages(properties),
+            comparison,
+        }))))
+    }
+
+    /// try parsing [`Property::Call`](Property::Call) (`.name()`).
+    #[inline(always)]
+    pub fn parse_call(&mut self) -> Option<Property<'a>> {
+        let cursor = self.1;
+        let call = self.consume(".").and_then(|_| {
+            let name =
+                self.consume_while(|ch| ch.is_alphanumeric() || ch == '_');
+            match self.consume("()") {
+                Some(_) if !name.is_empty() => {
+                    Some(Property::Call(name.into()))
+                }
+                _ => None,
+            }
+        });
+        if call.is_none() {
+            self.1 = cursor;
+        }
+        call
+    }
+}
+
+impl<'a> PropertyParser<'a> /* Private */ {
+    /// quoted key (`key`) or index (`index`) following `open`, up to the
+    /// closing `)`.
+    fn parse_member<K, I>(
+        &mut self,
+        open: &str,
+        key: K,
+        index: I,
+    ) -> Option<PropertyResult<'a>>
+    where
+        K: FnOnce(Cow<'a, str>) -> Property<'a>,
+        I: FnOnce(i32) -> Property<'a>,
+    {
+        self.consume(open)?;
+        self.consume_while(is_whitespace);
+        let property = match self.parse_quoted() {
+            Some(quoted) => key(quoted.into()),
+            None => match self.parse_int() {
+                Ok(int) => index(int?),
+                Err(err) => return Some(Err(err)),
+            },
+        };
+        self.consume_while(is_whitespace);
+        self.consume(")").and(Some(Ok(property)))
+    }
+
+    /// json literal up to the closing `)` of a predicate (trailing whitespace
+    /// is consumed, the `)` isn't).
+    fn parse_literal(&mut self) -> Option<Json> {
+        if self.consume("$").is_some() {
+            return self.variable().cloned();
+        }
+        let rest = &self.0[self.1..];
+        let (mut quoted, mut key) = (false, key_char());
+        let end = rest.find(|ch| {
+            // inside a string, up to the closing (unescaped) quote.
+            if quoted {
+                quoted = key(ch);
+                return false;
+            }
+            quoted = ch == '"';
+            ch == ')'
+        })?;
+        let mut parser = JsonParser::new(&rest[..end]);
+        let literal = parser.parse().ok()?;
+        if !parser.is_done() {
+            return None;
+        }
+        self.1 += end;
+        Some(literal)
+    }
+
+    /// query following `open`, up to the closing `)`.
+    fn parse_sub_query(&mut self, open: &str) -> Option<JsonQuery<'a>> {
+        self.consume(open)?;
         let mut properties = vec![];
         while let Some(maybe_property) = self.parse_any() {
             if let Ok(property) = maybe_property {
@@ -325,21 +1094,114 @@ impl PropertyParser /* Public */ {
                 break;
             }
         }
-        lexer!(self)
-            .consume_byte(')')
-            .and(Some(Property::Map(JsonQuery(properties))))
+        self.consume(")")
+            .and(Some(JsonQuery::from_stages(properties)))
     }
-}
 
-impl PropertyParser /* Private */ {
     #[inline(always)]
-    fn try_consume(&mut self, s: &str, t: Property) -> Option<Property> {
-        lexer!(self).consume_string(s).and(Some(t))
+    fn peek(&self) -> Option<u8> {
+        self.0.as_bytes().get(self.1).copied()
+    }
+
+    /// consume `s`, if the rest of the query starts with it.
+    #[inline(always)]
+    fn consume(&mut self, s: &str) -> Option<()> {
+        if self.0[self.1..].starts_with(s) {
+            self.1 += s.len();
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    fn consume_while<F: FnMut(char) -> bool>(&mut self, mut f: F) -> &'a str {
+        let rest = &self.0[self.1..];
+        let len = rest.find(|ch| !f(ch)).unwrap_or(rest.len());
+        self.1 += len;
+        &rest[..len]
+    }
+
+    /// comma separated keys (parsed with `key`) up to `close`, whitespace
+    /// is allowed around the keys.
+    fn parse_keys<F: FnMut(&mut Self) -> Option<&'a str>>(
+        &mut self,
+        close: &str,
+        mut key: F,
+    ) -> Option<Vec<Cow<'a, str>>> {
+        let mut keys = vec![];
+        self.consume_while(is_whitespace);
+        if self.consume(close).is_some() {
+            return Some(keys);
+        }
+        loop {
+            self.consume_while(is_whitespace);
+            keys.push(key(self)?.into());
+            self.consume_while(is_whitespace);
+            if self.consume(close).is_some() {
+                return Some(keys);
+            }
+            self.consume(",")?;
+        }
+    }
+
+    /// contents of a json string (`"key"`, escapes are kept as is).
+    fn parse_quoted(&mut self) -> Option<&'a str> {
+        self.consume("\"")?;
+        let key = self.consume_while(key_char());
+        self.consume("\"").and(Some(key))
+    }
+
+    #[inline(always)]
+    fn try_consume(
+        &mut self,
+        s: &str,
+        t: Property<'a>,
+    ) -> Option<Property<'a>> {
+        self.consume(s).and(Some(t))
+    }
+
+    /// cursor in chars (same as the query error cursor).
+    fn char_cursor(&self) -> usize {
+        self.0[..self.1].chars().count()
+    }
+}
+
+/// chars allowed in dot notation keys: unicode letters and digits, `_`, `-`
+/// and `$`.
+#[inline]
+pub(crate) fn dot_key_char(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '_' | '-' | '$')
+}
+
+/// predicate for the chars of a json string, up to the first unescaped `"`
+/// (escape sequences are kept as is, same as object keys).
+pub(crate) fn key_char() -> impl FnMut(char) -> bool {
+    let mut escaped = false;
+    move |ch| {
+        let end = !escaped && ch == '"';
+        escaped = !escaped && ch == '\\';
+        !end
+    }
+}
+
+/// property selecting the value of a `[$name]` variable: key of a string,
+/// index of an integer (`None`, for other values).
+pub(crate) fn variable_property(value: &Json) -> Option<Property<'static>> {
+    match value {
+        Json::QString(key) => {
+            Some(Property::Bracket(key.as_str().to_string().into(), false))
+        }
+        // integral and in range of an index (`f32::fract` needs `std`).
+        Json::Number(number) if (*number as i32) as f32 == *number => {
+            Some(Property::Index(*number as i32, false))
+        }
+        _ => None,
     }
 }
 
-impl Iterator for PropertyParser {
-    type Item = Result<Property, usize>;
+impl<'a> Iterator for PropertyParser<'a> {
+    type Item = PropertyResult<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.parse_any()
@@ -0,0 +1,336 @@
+//! JSON Patch ([`rfc6902`](https://datatracker.ietf.org/doc/html/rfc6902)),
+//! with JSON Pointer ([`rfc6901`](https://datatracker.ietf.org/doc/html/rfc6901))
+//! paths: applying patches and generating a patch from the difference of two
+//! json values.
+use super::{
+    convert::ToJson,
+    error::JsonPatchError,
+    string::JsonString,
+    token::{escape, unescape, Json},
+    HashMap,
+};
+use crate::prelude::*;
+use core::convert::TryFrom;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    Add { path: String, value: Json },
+    Remove { path: String },
+    Replace { path: String, value: Json },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Json },
+}
+
+/// list of operations, applied in order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Patch(pub Vec<Operation>);
+
+/// split json pointer into (unescaped) reference tokens.
+fn tokens(pointer: &str) -> Result<Vec<String>, JsonPatchError> {
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+    if !pointer.starts_with('/') {
+        return Err(JsonPatchError::InvalidPointer(pointer.into()));
+    }
+    Ok(pointer[1..]
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
+
+/// json pointer escaped reference token (from a raw object key).
+fn pointer_token(key: &str) -> String {
+    unescape(key).replace('~', "~0").replace('/', "~1")
+}
+
+/// array index (`-` refers to the index past the last element).
+fn index(token: &str, len: usize) -> Option<usize> {
+    match token {
+        "-" => Some(len),
+        "0" => Some(0),
+        _ if token.starts_with('0') => None,
+        _ => token.parse().ok(),
+    }
+}
+
+fn get<'a>(json: &'a Json, tokens: &[String]) -> Option<&'a Json> {
+    tokens.iter().try_fold(json, |json, token| match json {
+        Json::Object(hashmap) => hashmap.get(escape(token).as_str()),
+        Json::Array(array) => array.get(index(token, array.len())?),
+        _ => None,
+    })
+}
+
+fn get_mut<'a>(json: &'a mut Json, tokens: &[String]) -> Option<&'a mut Json> {
+    tokens.iter().try_fold(json, |json, token| match json {
+        Json::Object(hashmap) => hashmap.get_mut(escape(token).as_str()),
+        Json::Array(array) => {
+            let index = index(token, array.len())?;
+            array.get_mut(index)
+        }
+        _ => None,
+    })
+}
+
+fn add(json: &mut Json, path: &str, value: Json) -> Result<(), JsonPatchError> {
+    let not_found = || JsonPatchError::PathNotFound(path.into());
+    let tokens = tokens(path)?;
+    let (last, parent) = match tokens.split_last() {
+        Some(split) => split,
+        None => {
+            *json = value;
+            return Ok(());
+        }
+    };
+    match get_mut(json, parent).ok_or_else(not_found)? {
+        Json::Object(hashmap) => {
+            hashmap.insert(escape(last).into(), value);
+        }
+        Json::Array(array) => {
+            let index = index(last, array.len())
+                .filter(|&index| index <= array.len())
+                .ok_or_else(not_found)?;
+            array.insert(index, value);
+        }
+        _ => return Err(not_found()),
+    }
+    Ok(())
+}
+
+fn remove(json: &mut Json, path: &str) -> Result<Json, JsonPatchError> {
+    let not_found = || JsonPatchError::PathNotFound(path.into());
+    let tokens = tokens(path)?;
+    let (last, parent) = tokens.split_last().ok_or_else(not_found)?;
+    match get_mut(json, parent).ok_or_else(not_found)? {
+        Json::Object(hashmap) => {
+            hashmap.remove(escape(last).as_str()).ok_or_else(not_found)
+        }
+        Json::Array(array) => index(last, array.len())
+            .filter(|&index| index < array.len())
+            .map(|index| array.remove(index))
+            .ok_or_else(not_found),
+        _ => Err(not_found()),
+    }
+}
+
+impl Operation {
+    /// apply operation on `json` (`json` may be left partially modified on
+    /// error, see [`Patch::apply`] for atomic application).
+    pub fn apply(&self, json: &mut Json) -> Result<(), JsonPatchError> {
+        let not_found = |path: &str| JsonPatchError::PathNotFound(path.into());
+        match self {
+            Self::Add { path, value } => add(json, path, value.clone()),
+            Self::Remove { path } => remove(json, path).map(|_| ()),
+            Self::Replace { path, value } => {
+                *get_mut(json, &tokens(path)?)
+                    .ok_or_else(|| not_found(path))? = value.clone();
+                Ok(())
+            }
+            Self::Move { from, path } => {
+                // a location cannot be moved into one of its children.
+                if path.starts_with(&format!("{}/", from)) {
+                    return Err(JsonPatchError::InvalidOperation(format!(
+                        "cannot move '{}' into '{}'",
+                        from, path
+                    )));
+                }
+                let value = remove(json, from)?;
+                add(json, path, value)
+            }
+            Self::Copy { from, path } => {
+                let value = get(json, &tokens(from)?)
+                    .cloned()
+                    .ok_or_else(|| not_found(from))?;
+                add(json, path, value)
+            }
+            Self::Test { path, value } => match get(json, &tokens(path)?) {
+                Some(found) if found == value => Ok(()),
+                Some(_) => Err(JsonPatchError::TestFailed(path.clone())),
+                None => Err(not_found(path)),
+            },
+        }
+    }
+
+    /// parse a single operation object (example: `{"op": "remove", "path": "/a"}`).
+    pub fn from_json(json: &Json) -> Result<Self, JsonPatchError> {
+        let invalid = |message: &str| {
+            JsonPatchError::InvalidOperation(format!(
+                "{} in '{}'",
+                message, json
+            ))
+        };
+        let string = |key: &str| {
+            json.get(key)
+                .and_then(|value| String::try_from(value).ok())
+                .ok_or_else(|| invalid(&format!("missing string '{}'", key)))
+        };
+        let value = || {
+            json.get("value")
+                .cloned()
+                .ok_or_else(|| invalid("missing 'value'"))
+        };
+        let path = string("path")?;
+        Ok(match string("op")?.as_str() {
+            "add" => Self::Add {
+                path,
+                value: value()?,
+            },
+            "remove" => Self::Remove { path },
+            "replace" => Self::Replace {
+                path,
+                value: value()?,
+            },
+            "move" => Self::Move {
+                from: string("from")?,
+                path,
+            },
+            "copy" => Self::Copy {
+                from: string("from")?,
+                path,
+            },
+            "test" => Self::Test {
+                path,
+                value: value()?,
+            },
+            op => return Err(invalid(&format!("unknown op '{}'", op))),
+        })
+    }
+}
+
+impl ToJson for Operation {
+    fn to_json(&self) -> Json {
+        let mut hashmap = HashMap::new();
+        let mut insert = |key: &str, value: Json| {
+            hashmap.insert(key.into(), value);
+        };
+        let (op, path) = match self {
+            Self::Add { path, value } => {
+                insert("value", value.clone());
+                ("add", path)
+            }
+            Self::Remove { path } => ("remove", path),
+            Self::Replace { path, value } => {
+                insert("value", value.clone());
+                ("replace", path)
+            }
+            Self::Move { from, path } => {
+                insert("from", Json::from(from.as_str()));
+                ("move", path)
+            }
+            Self::Copy { from, path } => {
+                insert("from", Json::from(from.as_str()));
+                ("copy", path)
+            }
+            Self::Test { path, value } => {
+                insert("value", value.clone());
+                ("test", path)
+            }
+        };
+        insert("op", Json::from(op));
+        insert("path", Json::from(path.as_str()));
+        Json::Object(hashmap.into())
+    }
+}
+
+impl Patch {
+    /// parse patch document (array of operation objects).
+    pub fn from_json(json: &Json) -> Result<Self, JsonPatchError> {
+        json.as_array()
+            .ok_or_else(|| {
+                JsonPatchError::InvalidOperation(
+                    "patch must be an 'Array' of operations".into(),
+                )
+            })?
+            .iter()
+            .map(Operation::from_json)
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    /// apply all the operations in order, `json` is left unmodified if any
+    /// of the operations fail.
+    pub fn apply(&self, json: &mut Json) -> Result<(), JsonPatchError> {
+        let mut patched = json.clone();
+        for operation in self.0.iter() {
+            operation.apply(&mut patched)?;
+        }
+        *json = patched;
+        Ok(())
+    }
+
+    /// patch that transforms `from` into `to`.
+    /// object keys are compared individually, array elements are compared
+    /// index by index (elements are added/removed at the end of the array).
+    pub fn diff(from: &Json, to: &Json) -> Self {
+        let mut operations = vec![];
+        Self::diff_at(&mut operations, String::new(), from, to);
+        Self(operations)
+    }
+
+    fn diff_at(
+        operations: &mut Vec<Operation>,
+        path: String,
+        from: &Json,
+        to: &Json,
+    ) {
+        match (from, to) {
+            _ if from == to => {}
+            (Json::Object(from), Json::Object(to)) => {
+                let mut keys: Vec<&JsonString> =
+                    from.keys().chain(to.keys()).collect();
+                keys.sort();
+                keys.dedup();
+                for key in keys {
+                    let path = format!("{}/{}", path, pointer_token(key));
+                    match (from.get(key), to.get(key)) {
+                        (Some(from), Some(to)) => {
+                            Self::diff_at(operations, path, from, to)
+                        }
+                        (Some(_), None) => {
+                            operations.push(Operation::Remove { path })
+                        }
+                        (None, Some(to)) => operations.push(Operation::Add {
+                            path,
+                            value: to.clone(),
+                        }),
+                        (None, None) => {}
+                    }
+                }
+            }
+            (Json::Array(from), Json::Array(to)) => {
+                for (index, (from, to)) in from.iter().zip(to).enumerate() {
+                    Self::diff_at(
+                        operations,
+                        format!("{}/{}", path, index),
+                        from,
+                        to,
+                    );
+                }
+                // removing from the end, so that the indices stay valid.
+                for index in (to.len()..from.len()).rev() {
+                    operations.push(Operation::Remove {
+                        path: format!("{}/{}", path, index),
+                    });
+                }
+                for (index, value) in to.iter().enumerate().skip(from.len()) {
+                    operations.push(Operation::Add {
+                        path: format!("{}/{}", path, index),
+                        value: value.clone(),
+                    });
+                }
+            }
+            _ => operations.push(Operation::Replace {
+                path,
+                value: to.clone(),
+            }),
+        }
+    }
+}
+
+impl ToJson for Patch {
+    fn to_json(&self) -> Json {
+        self.0.iter().map(ToJson::to_json).collect()
+    }
+}
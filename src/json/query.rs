@@ -1,30 +1,248 @@
 //! list of properties (chronological) needed to extract sub tree from `json`.
 use super::{
-    error::{JsonQueryError, JsonQueryErrorType},
+    error::JsonQueryError,
     parser::PropertyParser,
-    token::Property,
+    token::{Json, Property},
+    HashMap,
 };
+use crate::prelude::*;
+use core::cmp::Ordering;
 
+/// empty query (written as `.`) is the identity, selects the whole value.
 #[derive(Debug, Clone, PartialEq)]
-pub struct JsonQuery(pub Vec<Property>);
+pub struct JsonQuery<'a>(pub Vec<Property<'a>>);
 
-impl JsonQuery {
-    pub fn new(s: &str) -> Result<Self, JsonQueryError> {
+impl<'a> JsonQuery<'a> {
+    /// query selecting the whole value (same as `.` or an empty query).
+    pub fn identity() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// parse query, keys and names borrow from `s`. stages are separated by
+    /// [`Property::Pipe`] (`.a | .b`), same as [`then`](Self::then).
+    pub fn new(s: &'a str) -> Result<Self, JsonQueryError> {
+        Self::parse(s, PropertyParser::new(s))
+    }
+
+    /// same as [`new`](Self::new), `[$name]` selects the key (string) or
+    /// index (integer) given by the value of the variable (example:
+    /// `.users[$user]`), `$name` is also a literal of a predicate (example:
+    /// `.any(.age > $min)`).
+    pub fn with_variables(
+        s: &'a str,
+        variables: &'a Variables,
+    ) -> Result<Self, JsonQueryError> {
+        Self::parse(s, PropertyParser::with_variables(s, variables))
+    }
+
+    fn parse(
+        s: &str,
+        parser: PropertyParser<'a>,
+    ) -> Result<Self, JsonQueryError> {
         let mut properties = Vec::new();
-        for maybe_property in PropertyParser::new(s) {
-            let property = maybe_property.or_else(|cursor| {
+        for maybe_property in parser {
+            let property = maybe_property.or_else(|(cursor, error_type)| {
                 Err(JsonQueryError {
                     line: s.into(),
                     cursor,
-                    error_type: JsonQueryErrorType::SyntaxError,
+                    error_type,
                 })
             })?;
             properties.push(property)
         }
-        Ok(Self(properties))
+        Ok(Self::from_stages(properties))
     }
 
-    pub fn properties<'a>(&'a self) -> std::slice::Iter<'a, Property> {
+    /// properties of stages separated by [`Property::Pipe`], without the
+    /// empty (identity) stages (example: `. | .a | .` is `.a`).
+    pub(crate) fn from_stages(properties: Vec<Property<'a>>) -> Self {
+        let mut stages = Vec::with_capacity(properties.len());
+        for property in properties {
+            match property {
+                Property::Pipe
+                    if matches!(stages.last(), None | Some(Property::Pipe)) => {
+                }
+                property => stages.push(property),
+            }
+        }
+        if let Some(Property::Pipe) = stages.last() {
+            stages.pop();
+        }
+        Self(stages)
+    }
+
+    /// query that applies `self` followed by `other` (on the result of
+    /// `self`), as separate stages (same as `self | other`).
+    pub fn then(mut self, other: Self) -> Self {
+        self.0.push(Property::Pipe);
+        self.0.extend(other.0);
+        Self::from_stages(self.0)
+    }
+
+    pub fn properties(&self) -> core::slice::Iter<'_, Property<'a>> {
         self.0.iter()
     }
+
+    /// same query, not borrowing from the query text.
+    pub fn into_owned(self) -> JsonQuery<'static> {
+        JsonQuery(self.0.into_iter().map(Property::into_owned).collect())
+    }
+}
+
+/// Values of the query variables (`$name`), by name (example: `user` for
+/// `$user`).
+pub type Variables = HashMap<String, Json>;
+
+/// Operator of a [`Predicate`] (example: `>=`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    /// operators, longest first (for parsing).
+    pub const ALL: [Self; 6] =
+        [Self::Eq, Self::Ne, Self::Le, Self::Ge, Self::Lt, Self::Gt];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+        }
+    }
+
+    /// `ordering` of the compared values satisfies the operator.
+    pub fn test(&self, ordering: Ordering) -> bool {
+        match self {
+            Self::Eq => ordering.is_eq(),
+            Self::Ne => ordering.is_ne(),
+            Self::Lt => ordering.is_lt(),
+            Self::Le => ordering.is_le(),
+            Self::Gt => ordering.is_gt(),
+            Self::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+/// Condition on a value (example: `.age > 18`), the result of `query` is
+/// compared with a json literal (values are ordered same as
+/// [`Json`](Json#impl-Ord-for-Json)), or is truthy (neither `null` nor
+/// `false`) without one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicate<'a> {
+    pub query: JsonQuery<'a>,
+    pub comparison: Option<(Comparison, Json)>,
+}
+
+impl Predicate<'_> {
+    /// `value` (result of the query) satisfies the predicate.
+    pub fn matches(&self, value: &Json) -> bool {
+        match &self.comparison {
+            Some((comparison, literal)) => comparison.test(value.cmp(literal)),
+            None => !matches!(value, Json::Null | Json::Boolean(false)),
+        }
+    }
+
+    /// same predicate, not borrowing from the query text.
+    pub fn into_owned(self) -> Predicate<'static> {
+        Predicate {
+            query: self.query.into_owned(),
+            comparison: self.comparison,
+        }
+    }
+}
+
+/// valid predicate syntax (identity is written as `.`).
+impl core::fmt::Display for Predicate<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.query)?;
+        match &self.comparison {
+            Some((comparison, literal)) => {
+                write!(f, " {} {}", comparison.as_str(), literal)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// How [`Json::apply_with`](super::token::Json::apply_with) handles values
+/// that don't exist.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Missing {
+    /// missing keys, out of range indices and type mismatches are errors.
+    #[default]
+    Error,
+    /// missing keys, out of range indices and type mismatches yield `null`
+    /// (example: `.a.b` on `{}` is `null`).
+    Null,
+}
+
+/// Query evaluation options (defaults are same as
+/// [`Json::apply`](super::token::Json::apply)).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ApplyOptions {
+    /// memory budget of computed values (in bytes), see
+    /// [`Json::apply_within`](super::token::Json::apply_within).
+    pub max_memory: Option<usize>,
+    pub missing: Missing,
+}
+
+/// Window of the elements of an array result (example: `--offset 20
+/// --limit 10`), see [`Json::page`](super::token::Json::page).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Page {
+    /// number of elements skipped.
+    pub offset: usize,
+    /// maximum number of elements (all of them, if `None`).
+    pub limit: Option<usize>,
+}
+
+impl Page {
+    /// every element is selected.
+    pub fn is_all(&self) -> bool {
+        self.offset == 0 && self.limit.is_none()
+    }
+
+    /// indices of the selected elements, in an array of `len` elements.
+    pub fn range(&self, len: usize) -> core::ops::Range<usize> {
+        let start = self.offset.min(len);
+        let end = self
+            .limit
+            .map_or(len, |limit| start.saturating_add(limit).min(len));
+        start..end
+    }
+}
+
+impl core::str::FromStr for JsonQuery<'static> {
+    type Err = JsonQueryError;
+
+    /// parse query (example: `".a.b[0]".parse::<JsonQuery>()`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JsonQuery::new(s).map(JsonQuery::into_owned)
+    }
+}
+
+/// valid query syntax, `JsonQuery::new(&query.to_string())` gives back
+/// `query` (identity is written as `.`).
+impl core::fmt::Display for JsonQuery<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.is_identity() {
+            return write!(f, ".");
+        }
+        self.properties()
+            .try_for_each(|property| write!(f, "{}", property))
+    }
 }
@@ -0,0 +1,223 @@
+//! Streaming query evaluation: path queries (dot, bracket and index
+//! properties only) are evaluated while scanning the json text, values that
+//! don't match are skipped without being parsed, and scanning stops as soon
+//! as the target value has been parsed.
+//!
+//! The text outside of the target value is only scanned (not validated), so
+//! [`select`] only reports success, callers fall back to the full parser
+//! (for the error messages) when it returns `None`. Duplicate keys are not
+//! detected (the first occurrence is selected).
+use super::{
+    parser::JsonParser,
+    query::{JsonQuery, Page},
+    token::{Json, Property},
+};
+use crate::prelude::*;
+use core::convert::TryFrom;
+
+struct Scanner<'a> {
+    input: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> Scanner<'a> {
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.cursor).copied()
+    }
+
+    #[inline]
+    fn trim_front(&mut self) -> &mut Self {
+        while self.peek().is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.cursor += 1;
+        }
+        self
+    }
+
+    fn consume(&mut self, byte: u8) -> Option<()> {
+        match self.trim_front().peek() {
+            Some(found) if found == byte => {
+                self.cursor += 1;
+                Some(())
+            }
+            _ => None,
+        }
+    }
+
+    /// skip string (starting at the opening quote), returns its raw contents.
+    fn skip_string(&mut self) -> Option<&'a [u8]> {
+        self.consume(b'"')?;
+        let start = self.cursor;
+        loop {
+            match self.peek()? {
+                b'"' => break,
+                b'\\' => self.cursor += 2,
+                _ => self.cursor += 1,
+            }
+        }
+        self.cursor += 1;
+        self.input.get(start..self.cursor - 1)
+    }
+
+    /// skip any value (nested arrays/objects are only balanced).
+    fn skip_value(&mut self) -> Option<()> {
+        let mut depth = 0usize;
+        loop {
+            match self.trim_front().peek()? {
+                b'"' => {
+                    self.skip_string()?;
+                }
+                b'[' | b'{' => {
+                    depth += 1;
+                    self.cursor += 1;
+                }
+                b']' | b'}' => {
+                    depth = depth.checked_sub(1)?;
+                    self.cursor += 1;
+                }
+                b',' | b':' if depth > 0 => self.cursor += 1,
+                _ => {
+                    let rest = &self.input[self.cursor..];
+                    let len = rest
+                        .iter()
+                        .position(|byte| b",:]} \t\n\r".contains(byte))
+                        .unwrap_or(rest.len());
+                    if len == 0 {
+                        return None;
+                    }
+                    self.cursor += len;
+                }
+            }
+            if depth == 0 {
+                return Some(());
+            }
+        }
+    }
+
+    /// move to the value of `key` (first occurrence) in the current object.
+    fn descend_key(&mut self, key: &str) -> Option<()> {
+        self.consume(b'{')?;
+        loop {
+            self.trim_front();
+            let found = self.skip_string()? == key.as_bytes();
+            self.consume(b':')?;
+            if found {
+                return Some(());
+            }
+            self.skip_value()?;
+            self.consume(b',')?;
+        }
+    }
+
+    /// move to the element at `index` in the current array.
+    fn descend_index(&mut self, index: i32) -> Option<()> {
+        self.consume(b'[')?;
+        // negative indices need the array length (left to the parser).
+        for _ in 0..usize::try_from(index).ok()? {
+            self.skip_value()?;
+            self.consume(b',')?;
+        }
+        match self.trim_front().peek()? {
+            b']' => None,
+            _ => Some(()),
+        }
+    }
+}
+
+/// `query` contains only dot, bracket and index properties (not optional,
+/// see [`Property::is_optional`]), in one or more stages.
+pub fn is_path(query: &JsonQuery) -> bool {
+    query.properties().all(|property| {
+        matches!(
+            property,
+            Property::Dot(_, false)
+                | Property::Bracket(_, false)
+                | Property::Index(_, false)
+                | Property::Pipe
+        )
+    })
+}
+
+/// value selected by path `query` from json text `input`, parsing only the
+/// selected value. `None` if `query` is not a path query, or if the value
+/// cannot be selected (missing key, invalid json etc.).
+pub fn select(input: &str, query: &JsonQuery) -> Option<Json> {
+    JsonParser::new(locate(input, query)?).parse().ok()
+}
+
+/// same as [`select`], returns the text of the selected value (as in
+/// `input`, byte for byte) instead. the value is still parsed (validated).
+pub fn select_raw<'a>(input: &'a str, query: &JsonQuery) -> Option<&'a str> {
+    let raw = locate(input, query)?;
+    JsonParser::new(raw).parse().ok().map(|_| raw)
+}
+
+/// same as [`select`], only the elements in `page` of a selected array are
+/// parsed (the skipped ones are only scanned, the ones following the page
+/// are not even scanned), see [`Json::page`].
+pub fn select_page(input: &str, query: &JsonQuery, page: Page) -> Option<Json> {
+    let mut scanner = descend(input, query)?;
+    if page.is_all() || scanner.peek() != Some(b'[') {
+        let start = scanner.cursor;
+        scanner.skip_value()?;
+        return JsonParser::new(input.get(start..scanner.cursor)?)
+            .parse()
+            .ok();
+    }
+    scanner.cursor += 1;
+    let mut elements = vec![];
+    for index in 0.. {
+        if page.limit.is_some_and(|limit| elements.len() >= limit)
+            || scanner.trim_front().peek()? == b']'
+        {
+            break;
+        }
+        let start = scanner.cursor;
+        scanner.skip_value()?;
+        if index >= page.offset {
+            let raw = input.get(start..scanner.cursor)?;
+            elements.push(JsonParser::new(raw).parse().ok()?);
+        }
+        if scanner.consume(b',').is_none() {
+            scanner.consume(b']')?;
+            break;
+        }
+        // trailing comma.
+        if scanner.trim_front().peek()? == b']' {
+            return None;
+        }
+    }
+    Some(Json::Array(elements))
+}
+
+/// scanner at the value selected by path `query`.
+fn descend<'a>(input: &'a str, query: &JsonQuery) -> Option<Scanner<'a>> {
+    if !is_path(query) {
+        return None;
+    }
+    let mut scanner = Scanner {
+        input: input.as_bytes(),
+        cursor: 0,
+    };
+    for property in query.properties() {
+        match property {
+            Property::Dot(key, _) | Property::Bracket(key, _) => {
+                scanner.descend_key(key)?
+            }
+            Property::Index(index, _) => scanner.descend_index(*index)?,
+            Property::Pipe => {}
+            _ => return None,
+        }
+    }
+    scanner.trim_front();
+    Some(scanner)
+}
+
+/// text of the value selected by path `query` (not validated).
+fn locate<'a>(input: &'a str, query: &JsonQuery) -> Option<&'a str> {
+    let mut scanner = descend(input, query)?;
+    let start = scanner.cursor;
+    scanner.skip_value()?;
+    // boundaries are ascii, so the slice is valid utf-8.
+    input.get(start..scanner.cursor)
+}
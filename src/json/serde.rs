@@ -0,0 +1,183 @@
+//! [`serde`](::serde) interop (enabled by the `serde` feature): `Serialize`
+//! and `Deserialize` for [`Json`], and conversions to/from
+//! [`serde_json::Value`](::serde_json::Value).
+use super::token::{escape, unescape, Json};
+use ::serde::{
+    de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor},
+    ser::{Serialize, SerializeMap, SerializeSeq, Serializer},
+};
+use ::serde_json::{Map, Number, Value};
+use std::{collections::HashMap, fmt};
+
+/// shortest decimal representation of the `f32` (as printed by ruson), so
+/// that `0.1` doesn't become `0.10000000149011612`.
+fn widen(float: f32) -> f64 {
+    float.to_string().parse().unwrap_or(float as f64)
+}
+
+/// integral numbers are serialized as integers (`1` instead of `1.0`).
+fn as_integer(float: f32) -> Option<i64> {
+    if float.fract() == 0.0 && float.abs() < i64::MAX as f32 {
+        Some(float as i64)
+    } else {
+        None
+    }
+}
+
+/// strings (and object keys) are serialized unescaped.
+impl Serialize for Json {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Null => serializer.serialize_unit(),
+            Self::Boolean(boolean) => serializer.serialize_bool(*boolean),
+            Self::Number(float) => match as_integer(*float) {
+                Some(int) => serializer.serialize_i64(int),
+                None => serializer.serialize_f64(widen(*float)),
+            },
+            Self::QString(string) => {
+                serializer.serialize_str(&unescape(string))
+            }
+            Self::Array(array) => {
+                let mut seq = serializer.serialize_seq(Some(array.len()))?;
+                for value in array {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            Self::Object(hashmap) => {
+                let mut map = serializer.serialize_map(Some(hashmap.len()))?;
+                for (key, value) in hashmap.iter() {
+                    map.serialize_entry(&unescape(key), value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+struct JsonVisitor;
+
+impl<'de> Visitor<'de> for JsonVisitor {
+    type Value = Json;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "any valid json value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Json, E> {
+        Ok(Json::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Json, E> {
+        Ok(Json::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Json, D::Error> {
+        Json::deserialize(deserializer)
+    }
+
+    fn visit_bool<E: de::Error>(self, boolean: bool) -> Result<Json, E> {
+        Ok(Json::Boolean(boolean))
+    }
+
+    fn visit_i64<E: de::Error>(self, int: i64) -> Result<Json, E> {
+        Ok(Json::Number(int as f32))
+    }
+
+    fn visit_u64<E: de::Error>(self, int: u64) -> Result<Json, E> {
+        Ok(Json::Number(int as f32))
+    }
+
+    fn visit_f64<E: de::Error>(self, float: f64) -> Result<Json, E> {
+        Ok(Json::from_lossy(float))
+    }
+
+    fn visit_str<E: de::Error>(self, string: &str) -> Result<Json, E> {
+        Ok(Json::from(string))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> Result<Json, A::Error> {
+        let mut array = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            array.push(value);
+        }
+        Ok(Json::Array(array))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> Result<Json, A::Error> {
+        let mut hashmap = HashMap::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, value)) = map.next_entry::<String, Json>()? {
+            hashmap.insert(escape(&key).into(), value);
+        }
+        Ok(Json::Object(hashmap.into()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Json {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(JsonVisitor)
+    }
+}
+
+impl From<Value> for Json {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => Self::Null,
+            Value::Bool(boolean) => Self::Boolean(boolean),
+            Value::Number(number) => {
+                Self::from_lossy(number.as_f64().unwrap_or(f64::NAN))
+            }
+            Value::String(string) => Self::from(string),
+            Value::Array(array) => array.into_iter().map(Self::from).collect(),
+            Value::Object(map) => map
+                .into_iter()
+                .map(|(key, value)| (key, Self::from(value)))
+                .collect(),
+        }
+    }
+}
+
+/// `NaN` and infinite numbers (not representable in json) become `null`.
+impl From<&Json> for Value {
+    fn from(json: &Json) -> Self {
+        match json {
+            Json::Null => Self::Null,
+            Json::Boolean(boolean) => Self::Bool(*boolean),
+            Json::Number(float) => match as_integer(*float) {
+                Some(int) => Self::from(int),
+                None => Number::from_f64(widen(*float))
+                    .map_or(Self::Null, Self::Number),
+            },
+            Json::QString(string) => Self::String(unescape(string)),
+            Json::Array(array) => {
+                Self::Array(array.iter().map(Self::from).collect())
+            }
+            Json::Object(hashmap) => Self::Object(
+                hashmap
+                    .iter()
+                    .map(|(key, value)| (unescape(key), Self::from(value)))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+}
+
+impl From<Json> for Value {
+    fn from(json: Json) -> Self {
+        Self::from(&json)
+    }
+}
@@ -0,0 +1,236 @@
+//! Json values with shared ownership of arrays and objects: cloning a
+//! document (or a subtree) only bumps a reference count, nodes are copied on
+//! write. Nodes are [`Arc`](Arc) backed, so values are `Send + Sync` and can
+//! be passed between threads.
+//!
+//! Object keys are interned (per conversion/parse), so documents with many
+//! similar objects store every distinct key only once.
+use super::{
+    borrowed::JsonRef,
+    error::{JsonEvalError, JsonParseError},
+    query::JsonQuery,
+    string::JsonString,
+    token::{array_index, escape, Json, Property},
+    HashMap, HashSet,
+};
+use crate::prelude::*;
+use alloc::sync::Arc;
+use core::fmt;
+
+/// interned object key.
+pub type Key = Arc<str>;
+
+/// table of distinct keys.
+#[derive(Default)]
+struct Interner(HashSet<Key>);
+
+impl Interner {
+    fn intern(&mut self, key: &str) -> Key {
+        match self.0.get(key) {
+            Some(interned) => interned.clone(),
+            None => {
+                let interned: Key = key.into();
+                self.0.insert(interned.clone());
+                interned
+            }
+        }
+    }
+
+    fn share_json(&mut self, mut json: Json) -> SharedJson {
+        match &mut json {
+            Json::Null => SharedJson::Null,
+            Json::Boolean(boolean) => SharedJson::Boolean(*boolean),
+            Json::Number(number) => SharedJson::Number(*number),
+            Json::QString(string) => {
+                SharedJson::QString(core::mem::take(string).into())
+            }
+            Json::Array(array) => SharedJson::Array(Arc::new(
+                core::mem::take(array)
+                    .into_iter()
+                    .map(|json| self.share_json(json))
+                    .collect(),
+            )),
+            Json::Object(hashmap) => SharedJson::Object(Arc::new(
+                core::mem::take(&mut **hashmap)
+                    .into_iter()
+                    .map(|(key, value)| {
+                        (self.intern(&key), self.share_json(value))
+                    })
+                    .collect(),
+            )),
+        }
+    }
+
+    /// keys are escaped back (same as [`JsonRef::to_json`]).
+    fn share_json_ref(&mut self, json: &JsonRef) -> SharedJson {
+        match json {
+            JsonRef::Null => SharedJson::Null,
+            JsonRef::Boolean(boolean) => SharedJson::Boolean(*boolean),
+            JsonRef::Number(number) => SharedJson::Number(*number),
+            JsonRef::QString(string) => {
+                SharedJson::QString(string.as_raw().into())
+            }
+            JsonRef::Array(array) => SharedJson::Array(Arc::new(
+                array.iter().map(|json| self.share_json_ref(json)).collect(),
+            )),
+            JsonRef::Object(hashmap) => SharedJson::Object(Arc::new(
+                hashmap
+                    .iter()
+                    .map(|(key, value)| {
+                        (self.intern(&escape(key)), self.share_json_ref(value))
+                    })
+                    .collect(),
+            )),
+        }
+    }
+}
+
+/// Shared counterpart of [`Json`] (strings are stored raw, same as [`Json`]).
+#[derive(Clone, PartialEq)]
+pub enum SharedJson {
+    Null,
+    Boolean(bool),
+    Number(f32),
+    QString(String),
+    Array(Arc<Vec<SharedJson>>),
+    Object(Arc<HashMap<Key, SharedJson>>),
+}
+
+impl SharedJson {
+    /// parse json text (strings borrowed while parsing, see [`JsonRef`]),
+    /// interning the object keys.
+    pub fn parse(input: &str) -> Result<Self, JsonParseError> {
+        JsonRef::parse(input)
+            .map(|json| Interner::default().share_json_ref(&json))
+    }
+
+    #[inline]
+    pub fn as_array(&self) -> Option<&[SharedJson]> {
+        match self {
+            Self::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_object(&self) -> Option<&HashMap<Key, SharedJson>> {
+        match self {
+            Self::Object(hashmap) => Some(hashmap),
+            _ => None,
+        }
+    }
+
+    /// value of `key`, if `self` is an object containing `key`.
+    pub fn get(&self, key: &str) -> Option<&SharedJson> {
+        self.as_object().and_then(|hashmap| hashmap.get(key))
+    }
+
+    /// element at `index`, if `self` is an array with more than `index` elements.
+    pub fn get_index(&self, index: usize) -> Option<&SharedJson> {
+        self.as_array().and_then(|array| array.get(index))
+    }
+
+    /// mutable value of `key` (object is copied, if shared).
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut SharedJson> {
+        match self {
+            Self::Object(hashmap) => Arc::make_mut(hashmap).get_mut(key),
+            _ => None,
+        }
+    }
+
+    /// mutable element at `index` (array is copied, if shared).
+    pub fn get_index_mut(&mut self, index: usize) -> Option<&mut SharedJson> {
+        match self {
+            Self::Array(array) => Arc::make_mut(array).get_mut(index),
+            _ => None,
+        }
+    }
+
+    /// deep copy into an owned [`Json`].
+    pub fn to_json(&self) -> Json {
+        match self {
+            Self::Null => Json::Null,
+            Self::Boolean(boolean) => Json::Boolean(*boolean),
+            Self::Number(number) => Json::Number(*number),
+            Self::QString(string) => Json::QString(string.into()),
+            Self::Array(array) => array.iter().map(Self::to_json).collect(),
+            Self::Object(hashmap) => hashmap
+                .iter()
+                .map(|(key, value)| (JsonString::from(&**key), value.to_json()))
+                .collect(),
+        }
+    }
+
+    /// apply `query`, the leading path properties (dot, bracket, index)
+    /// select a shared subtree (without copying), rest of the query is
+    /// applied on a [`Json`] copy of the subtree.
+    pub fn apply(&self, query: &JsonQuery) -> Result<Self, JsonEvalError> {
+        let mut node = self;
+        let mut properties = query.properties().peekable();
+        while let Some(property) = properties.peek() {
+            let selected = match (property, node) {
+                (
+                    Property::Dot(key, _) | Property::Bracket(key, _),
+                    Self::Object(hashmap),
+                ) => hashmap.get(&**key).ok_or_else(|| {
+                    JsonEvalError::missing_key(
+                        key,
+                        hashmap.keys().map(|key| &**key),
+                    )
+                }),
+                (Property::Index(index, _), Self::Array(array)) => {
+                    array_index(*index, array.len())
+                        .map(|index| &array[index])
+                        .ok_or(JsonEvalError::InvalidIndex(*index, array.len()))
+                }
+                (Property::Pipe, _) => Ok(node),
+                // rest of the query (including errors) is handled by `Json`.
+                _ => break,
+            };
+            node = match selected {
+                Ok(node) => node,
+                // rest of the stage is short-circuited.
+                Err(_) if property.is_optional() => {
+                    properties
+                        .find(|property| matches!(property, Property::Pipe));
+                    return Json::Null
+                        .apply(&JsonQuery(properties.cloned().collect()))
+                        .map(Self::from);
+                }
+                Err(err) => return Err(err),
+            };
+            properties.next();
+        }
+        match properties.len() {
+            0 => Ok(node.clone()),
+            _ => node
+                .to_json()
+                .apply(&JsonQuery(properties.cloned().collect()))
+                .map(Self::from),
+        }
+    }
+}
+
+impl From<Json> for SharedJson {
+    fn from(json: Json) -> Self {
+        Interner::default().share_json(json)
+    }
+}
+
+impl From<&SharedJson> for Json {
+    fn from(shared: &SharedJson) -> Self {
+        shared.to_json()
+    }
+}
+
+impl fmt::Display for SharedJson {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.to_json(), f)
+    }
+}
+
+impl fmt::Debug for SharedJson {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
@@ -0,0 +1,97 @@
+//! Aggregation of the values selected from a stream of records (example:
+//! the status codes of access log entries): counts, distinct values and
+//! min/max/mean of the numbers.
+use super::{token::Json, HashMap};
+use crate::prelude::*;
+use core::iter::FromIterator;
+
+/// Accumulated statistics, see [`add`](Self::add).
+#[derive(Debug, Default, Clone)]
+pub struct Stats {
+    /// number of records.
+    pub count: usize,
+    /// number of records without a value.
+    pub missing: usize,
+    /// occurrences of every distinct value.
+    pub values: HashMap<Json, usize>,
+    /// number of numeric values.
+    pub numbers: usize,
+    pub sum: f64,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// record `value` of the next record (`None`, if the record doesn't
+    /// have one).
+    pub fn add(&mut self, value: Option<Json>) {
+        self.count += 1;
+        let value = match value {
+            Some(value) => value,
+            None => {
+                self.missing += 1;
+                return;
+            }
+        };
+        if let Json::Number(number) = value {
+            self.numbers += 1;
+            self.sum += number as f64;
+            self.min = Some(self.min.map_or(number, |min| min.min(number)));
+            self.max = Some(self.max.map_or(number, |max| max.max(number)));
+        }
+        *self.values.entry(value).or_insert(0) += 1;
+    }
+
+    /// mean of the numeric values.
+    pub fn mean(&self) -> Option<f64> {
+        (self.numbers > 0).then(|| self.sum / self.numbers as f64)
+    }
+
+    /// at most `n` of the most frequent values with their number of
+    /// occurrences (ties ordered by value).
+    pub fn top(&self, n: usize) -> Vec<(&Json, usize)> {
+        let mut values: Vec<_> = self
+            .values
+            .iter()
+            .map(|(value, &count)| (value, count))
+            .collect();
+        values.sort_unstable_by(|(a, a_count), (b, b_count)| {
+            b_count.cmp(a_count).then_with(|| a.cmp(b))
+        });
+        values.truncate(n);
+        values
+    }
+
+    /// statistics as a json object (`null` for the min/max/mean, without
+    /// numeric values), with the `top` most frequent values.
+    pub fn to_json(&self, top: usize) -> Json {
+        let number =
+            |number: Option<f64>| number.map_or(Json::Null, Json::from_lossy);
+        let top = self
+            .top(top)
+            .into_iter()
+            .map(|(value, count)| {
+                Json::from_iter([
+                    ("value".to_string(), value.clone()),
+                    ("count".to_string(), Json::from_lossy(count as f64)),
+                ])
+            })
+            .collect();
+        Json::from_iter([
+            ("count".to_string(), Json::from_lossy(self.count as f64)),
+            ("missing".to_string(), Json::from_lossy(self.missing as f64)),
+            (
+                "distinct".to_string(),
+                Json::from_lossy(self.values.len() as f64),
+            ),
+            ("min".to_string(), number(self.min.map(f64::from))),
+            ("max".to_string(), number(self.max.map(f64::from))),
+            ("mean".to_string(), number(self.mean())),
+            ("top".to_string(), Json::Array(top)),
+        ])
+    }
+}
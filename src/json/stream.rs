@@ -0,0 +1,152 @@
+//! Streaming utilities: split a (huge) top-level json array into the raw text
+//! of its elements, reading the input incrementally so memory usage is
+//! bounded by the largest element rather than the whole document.
+use std::io::BufRead;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Start,
+    Elements,
+    /// closing bracket consumed, only whitespace may follow.
+    End,
+    Done,
+}
+
+/// Iterator over the raw (unparsed) text of top-level array elements.
+pub struct ArrayStream<R> {
+    reader: R,
+    state: State,
+}
+
+impl<R: BufRead> ArrayStream<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            state: State::Start,
+        }
+    }
+
+    fn io_err(err: std::io::Error) -> String {
+        format!(" cannot read input: {}", err)
+    }
+
+    /// skip whitespace and return the next byte (without consuming it).
+    fn peek_non_whitespace(&mut self) -> Result<Option<u8>, String> {
+        loop {
+            let buffer = self.reader.fill_buf().map_err(Self::io_err)?;
+            if buffer.is_empty() {
+                return Ok(None);
+            }
+            match buffer.iter().position(|b| !b.is_ascii_whitespace()) {
+                Some(index) => {
+                    let byte = buffer[index];
+                    self.reader.consume(index);
+                    return Ok(Some(byte));
+                }
+                None => {
+                    let len = buffer.len();
+                    self.reader.consume(len);
+                }
+            }
+        }
+    }
+
+    /// consume the opening bracket of the top-level array.
+    fn start(&mut self) -> Result<(), String> {
+        match self.peek_non_whitespace()? {
+            Some(b'[') => self.reader.consume(1),
+            _ => return Err(" '--stream' requires a top-level array.".into()),
+        }
+        self.state = match self.peek_non_whitespace()? {
+            Some(b']') => {
+                self.reader.consume(1);
+                State::End
+            }
+            _ => State::Elements,
+        };
+        Ok(())
+    }
+
+    /// read the next element, up to (and consuming) the separating ',' or the
+    /// closing ']' of the top-level array.
+    fn element(&mut self) -> Result<String, String> {
+        let mut element = Vec::new();
+        let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+        loop {
+            let buffer = self.reader.fill_buf().map_err(Self::io_err)?;
+            if buffer.is_empty() {
+                return Err(" unexpected end of input (unclosed array).".into());
+            }
+            let mut boundary = None;
+            for (index, &byte) in buffer.iter().enumerate() {
+                if in_string {
+                    match byte {
+                        _ if escaped => escaped = false,
+                        b'\\' => escaped = true,
+                        b'"' => in_string = false,
+                        _ => {}
+                    }
+                    continue;
+                }
+                match byte {
+                    b'"' => in_string = true,
+                    b'[' | b'{' => depth += 1,
+                    b']' | b'}' if depth > 0 => depth -= 1,
+                    b',' | b']' if depth == 0 => {
+                        boundary = Some((index, byte));
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            match boundary {
+                Some((index, byte)) => {
+                    element.extend_from_slice(&buffer[..index]);
+                    self.reader.consume(index + 1);
+                    if byte == b']' {
+                        self.state = State::End;
+                    }
+                    break;
+                }
+                None => {
+                    let len = buffer.len();
+                    element.extend_from_slice(buffer);
+                    self.reader.consume(len);
+                }
+            }
+        }
+        String::from_utf8(element)
+            .map_err(|_| " input is not valid utf-8.".into())
+    }
+
+    /// error, if anything but whitespace follows the top-level array.
+    fn end(&mut self) -> Result<(), String> {
+        match self.peek_non_whitespace()? {
+            Some(_) => Err(" unexpected characters after the array.".into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ArrayStream<R> {
+    type Item = Result<String, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.state == State::Start {
+            if let Err(err) = self.start() {
+                self.state = State::Done;
+                return Some(Err(err));
+            }
+        }
+        match self.state {
+            State::Elements => Some(self.element().inspect_err(|_| {
+                self.state = State::Done;
+            })),
+            State::End => {
+                self.state = State::Done;
+                self.end().err().map(Err)
+            }
+            _ => None,
+        }
+    }
+}
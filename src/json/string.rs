@@ -0,0 +1,181 @@
+//! Compact string storage: strings of up to [`INLINE_CAPACITY`] bytes are
+//! stored inline (without allocating), longer strings spill to the heap.
+//! Used by [`Json::QString`](super::token::Json::QString) and object keys,
+//! most of which are short.
+use crate::prelude::*;
+use core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    str,
+};
+
+/// maximum length (in bytes) of inline strings (same size as [`String`]).
+pub const INLINE_CAPACITY: usize = 22;
+
+#[derive(Clone)]
+enum Repr {
+    Inline(u8, [u8; INLINE_CAPACITY]),
+    Heap(Box<str>),
+}
+
+/// Immutable string, dereferences to [`str`] (compares, hashes and formats
+/// same as [`str`]).
+#[derive(Clone)]
+pub struct JsonString(Repr);
+
+impl JsonString {
+    pub fn new(s: &str) -> Self {
+        if s.len() <= INLINE_CAPACITY {
+            let mut buffer = [0; INLINE_CAPACITY];
+            buffer[..s.len()].copy_from_slice(s.as_bytes());
+            Self(Repr::Inline(s.len() as u8, buffer))
+        } else {
+            Self(Repr::Heap(s.into()))
+        }
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            // only ever initialized from a `&str`.
+            Repr::Inline(len, buffer) => unsafe {
+                str::from_utf8_unchecked(&buffer[..*len as usize])
+            },
+            Repr::Heap(string) => string,
+        }
+    }
+
+    /// stored without a heap allocation.
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline(..))
+    }
+
+    /// bytes allocated on the heap.
+    #[inline]
+    pub fn heap_size(&self) -> usize {
+        match self.0 {
+            Repr::Inline(..) => 0,
+            Repr::Heap(ref string) => string.len(),
+        }
+    }
+}
+
+impl Default for JsonString {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl Deref for JsonString {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for JsonString {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl Borrow<str> for JsonString {
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+impl From<&str> for JsonString {
+    fn from(s: &str) -> Self {
+        Self::new(s)
+    }
+}
+
+impl From<&String> for JsonString {
+    fn from(s: &String) -> Self {
+        Self::new(s)
+    }
+}
+
+impl From<String> for JsonString {
+    /// long strings reuse the allocation of `s`.
+    fn from(s: String) -> Self {
+        if s.len() <= INLINE_CAPACITY {
+            Self::new(&s)
+        } else {
+            Self(Repr::Heap(s.into_boxed_str()))
+        }
+    }
+}
+
+impl From<JsonString> for String {
+    fn from(s: JsonString) -> Self {
+        match s.0 {
+            Repr::Heap(string) => string.into(),
+            Repr::Inline(..) => s.as_str().into(),
+        }
+    }
+}
+
+impl PartialEq for JsonString {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for JsonString {}
+
+impl PartialEq<str> for JsonString {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for JsonString {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for JsonString {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialOrd for JsonString {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for JsonString {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for JsonString {
+    // same as `str` (required by `Borrow<str>`).
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Display for JsonString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Debug for JsonString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
@@ -0,0 +1,137 @@
+//! Text templates with query placeholders (example:
+//! `Name: {.name}, Stars: {.stars}`), for rendering json values as arbitrary
+//! text. `{{` and `}}` are literal braces.
+use super::{
+    error::{JsonEvalError, JsonQueryError, JsonQueryErrorType},
+    formatter::{Formatter, RawJson},
+    parser::key_char,
+    query::{ApplyOptions, JsonQuery},
+    token::{unescape, Json},
+};
+use crate::prelude::*;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Query(JsonQuery<'static>),
+}
+
+/// Parsed template, placeholders are evaluated on every rendered value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template(Vec<Segment>);
+
+impl Template {
+    /// parse template, errors of the placeholder queries are located in the
+    /// template text.
+    pub fn new(s: &str) -> Result<Self, JsonQueryError> {
+        let error = |cursor, error_type| JsonQueryError {
+            line: s.into(),
+            cursor,
+            error_type,
+        };
+        let (mut segments, mut text) = (vec![], String::new());
+        let mut chars = s.char_indices().enumerate().peekable();
+        while let Some((cursor, (start, ch))) = chars.next() {
+            match ch {
+                '{' | '}'
+                    if chars.peek().map(|(_, (_, ch))| ch) == Some(&ch) =>
+                {
+                    chars.next();
+                    text.push(ch);
+                }
+                '{' => {
+                    let query =
+                        placeholder(&s[start + 1..]).ok_or_else(|| {
+                            error(cursor + 1, JsonQueryErrorType::SyntaxError)
+                        })?;
+                    // placeholder is skipped (including the closing brace).
+                    for _ in 0..=query.chars().count() {
+                        chars.next();
+                    }
+                    let query =
+                        JsonQuery::new(query.trim()).map_err(|err| {
+                            // chars before the (trimmed) query, in the template.
+                            let skipped = query
+                                .chars()
+                                .take_while(|ch| ch.is_whitespace())
+                                .count();
+                            error(
+                                cursor + 1 + skipped + err.cursor,
+                                err.error_type,
+                            )
+                        })?;
+                    if !text.is_empty() {
+                        segments
+                            .push(Segment::Text(core::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Query(query.into_owned()));
+                }
+                ch => text.push(ch),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Self(segments))
+    }
+
+    /// text with every placeholder replaced by the selected value (strings
+    /// unquoted and unescaped, other values as json text).
+    pub fn render(&self, json: &Json) -> Result<String, JsonEvalError> {
+        self.render_with(json, ApplyOptions::default())
+    }
+
+    /// same as [`render`](Self::render), with evaluation `options` (see
+    /// [`Json::apply_with`]).
+    pub fn render_with(
+        &self,
+        json: &Json,
+        options: ApplyOptions,
+    ) -> Result<String, JsonEvalError> {
+        let mut rendered = String::new();
+        for segment in self.0.iter() {
+            match segment {
+                Segment::Text(text) => rendered.push_str(text),
+                Segment::Query(query) => {
+                    match json.apply_with(query, options)? {
+                        Json::QString(ref string) => {
+                            rendered.push_str(&unescape(string))
+                        }
+                        value => rendered
+                            .push_str(&RawJson { color: false }.dump(&value)),
+                    }
+                }
+            }
+        }
+        Ok(rendered)
+    }
+}
+
+impl core::str::FromStr for Template {
+    type Err = JsonQueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+/// query text of the placeholder `s` starts with (without the closing
+/// `}`), braces of the query (`.{a, b}`) and quoted keys are skipped.
+fn placeholder(s: &str) -> Option<&str> {
+    let (mut depth, mut quoted, mut key) = (0, false, key_char());
+    for (end, ch) in s.char_indices() {
+        // inside a quoted key, up to the closing (unescaped) quote.
+        if quoted {
+            quoted = key(ch);
+            continue;
+        }
+        match ch {
+            '"' => quoted = true,
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(&s[..end]),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
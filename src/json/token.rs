@@ -1,71 +1,357 @@
 //! AST.
-use super::query::JsonQuery;
-use std::{collections::HashMap, fmt};
+use super::{
+    error::{JsonConvertError, JsonEvalError, RusonError},
+    hash_map, memo,
+    parser::dot_key_char,
+    query::{ApplyOptions, JsonQuery, Missing, Page, Predicate},
+    string::JsonString,
+    HashMap,
+};
+use crate::{prelude::*, regex::Regex};
+use alloc::borrow::Cow;
+use core::{
+    cmp::Ordering,
+    convert::{TryFrom, TryInto},
+    fmt,
+    hash::{Hash, Hasher},
+    iter::FromIterator,
+    mem::size_of,
+    ops::Range,
+};
 
+/// Query property, keys and names borrow from the query text (if parsed
+/// from one, see [`into_owned`](Property::into_owned)).
 #[derive(Debug, Clone, PartialEq)]
-pub enum Property {
-    /// equivalent to `jsonObject.prop`
-    Dot(String),
-    /// equivalent to `jsonObject["prop"]`
-    Bracket(String),
-    /// equivalent to `jsonArray[0]`
-    Index(i32),
+pub enum Property<'a> {
+    /// equivalent to `jsonObject.prop` (`true` for optional access, see
+    /// [`is_optional`](Self::is_optional)).
+    Dot(Cow<'a, str>, bool),
+    /// equivalent to `jsonObject["prop"]` (contents of a json string,
+    /// escaped same as the object keys, example: `["say \"hi\""]`).
+    Bracket(Cow<'a, str>, bool),
+    /// equivalent to `jsonArray[0]` (negative indices count from the end,
+    /// `[-1]` is the last element).
+    Index(i32, bool),
+    /// elements of [`Json::Array`](Json::Array) from `start` up to (not
+    /// including) `end` (example: `[1:4]`, `[:3]`, `[2:]`), negative bounds
+    /// count from the end, out of range bounds are clamped.
+    Slice(Option<i32>, Option<i32>),
     /// [`Json::Object`](Json::Object) keys.
     Keys,
     /// [`Json::Object`](Json::Object) values.
     Values,
-    /// length of [`Json::Array`](Json::Array).
+    /// length of [`Json::Array`](Json::Array) or
+    /// [`Json::QString`](Json::QString) (in unicode scalar values, after
+    /// unescaping), number of keys of [`Json::Object`](Json::Object) (`0`
+    /// for [`Json::Null`](Json::Null)).
     Length,
+    /// length of [`Json::QString`](Json::QString) in bytes (utf-8, after
+    /// unescaping).
+    ByteLength,
+    /// first element of [`Json::Array`](Json::Array) (errors if empty).
+    First,
+    /// last element of [`Json::Array`](Json::Array) (errors if empty).
+    Last,
+    /// name of the variant, same as `jq` (example: `"number"`).
+    Type,
+    /// paths of the leaves (example: `[".a.b[0]", ".c"]`), see
+    /// [`Json::leaves`].
+    Paths,
+    /// [`Json::Object`](Json::Object) with only the listed keys (example:
+    /// `.{id, "my key"}`), missing keys are `null` (same as `jq`).
+    Pick(Vec<Cow<'a, str>>),
+    /// [`Json::Object`](Json::Object) without the listed keys (example:
+    /// `.omit("password", "token")`).
+    Omit(Vec<Cow<'a, str>>),
+    /// values of the matching keys anywhere in the value (example:
+    /// `.find("id")`), see [`Json::find`].
+    Find(Cow<'a, str>),
+    /// same as [`Find`](Self::Find), along with the paths of the values
+    /// (example: `.findpaths("id")` gives `[{"path": ".a[0].id", ...}]`).
+    FindPaths(Cow<'a, str>),
+    /// sum of [`Json::Array`](Json::Array) of numbers (`0` if empty).
+    Sum,
+    /// minimum of [`Json::Array`](Json::Array) of numbers (`null` if empty).
+    Min,
+    /// maximum of [`Json::Array`](Json::Array) of numbers (`null` if empty).
+    Max,
+    /// mean of [`Json::Array`](Json::Array) of numbers (`null` if empty).
+    Avg,
+    /// nested arrays of [`Json::Array`](Json::Array) flattened up to the
+    /// given depth (example: `.flatten()` is `.flatten(1)`, `.flatten(2)`).
+    Flatten(u32),
+    /// [`Json::Object`](Json::Object) has the key (example: `.has("id")`,
+    /// escaped same as the object keys).
+    HasKey(Cow<'a, str>),
+    /// [`Json::Array`](Json::Array) has the index (example: `.has(0)`,
+    /// negative indices count from the end).
+    HasIndex(i32),
+    /// [`Json::Object`](Json::Object) without the key (example:
+    /// `.del("password")`), same as [`Omit`](Self::Omit) of a single key.
+    DelKey(Cow<'a, str>),
+    /// [`Json::Array`](Json::Array) without the element (example: `.del(3)`,
+    /// negative indices count from the end), out of range indices are
+    /// ignored.
+    DelIndex(i32),
+    /// [`Json::QString`](Json::QString) contains a match of the regex
+    /// (example: `.test("@example\\.com$")`), see [`Regex`].
+    Test(Regex),
+    /// first match of the regex in [`Json::QString`](Json::QString), as
+    /// `{"offset", "length", "string", "captures"}` (same as `jq`, offsets in
+    /// unicode scalar values), `null` if there is no match.
+    Match(Regex),
     /// map function.
-    Map(JsonQuery),
+    Map(JsonQuery<'a>),
+    /// elements of [`Json::Array`](Json::Array) grouped by the result of the
+    /// query (example: `.group_by(.user)`), groups are ordered by the result
+    /// and keep the order of the elements (same as `jq`).
+    GroupBy(JsonQuery<'a>),
+    /// some element of [`Json::Array`](Json::Array) satisfies the predicate
+    /// (example: `.any(. == true)`, `false` if empty).
+    Any(Predicate<'a>),
+    /// every element of [`Json::Array`](Json::Array) satisfies the predicate
+    /// (example: `.all(. > 0)`, `true` if empty).
+    All(Predicate<'a>),
+    /// rest of the query applied on every element of
+    /// [`Json::Array`](Json::Array), or value of [`Json::Object`](Json::Object)
+    /// (ordered by key), results are collected in an array (example:
+    /// `.items[].id`).
+    Each(JsonQuery<'a>),
+    /// `|` between stages (example: `.a | .length()`), the value is
+    /// unchanged (the next stage is applied on the result of the previous
+    /// one).
+    Pipe,
+    /// user defined function (example: `.slugify()`), see
+    /// [`QueryEngine`](super::engine::QueryEngine).
+    Call(Cow<'a, str>),
 }
 
-impl fmt::Display for Property {
+/// valid query syntax (parses back into the same property).
+impl fmt::Display for Property<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let mark = |optional: bool| if optional { "?" } else { "" };
         match self {
-            Self::Dot(s) => write!(f, ".{}", s),
-            Self::Bracket(s) => write!(f, "[\"{}\"]", s),
-            Self::Index(i) => write!(f, "[{}]", i),
-            Self::Map(_) => write!(f, ".map()"),
-            _ => write!(f, "{}", format!(".{:?}()", self).to_ascii_lowercase()),
+            // dot keys that wouldn't parse back, are written as brackets.
+            Self::Dot(s, optional) if Self::is_dot_key(s) => {
+                write!(f, ".{}{}", s, mark(*optional))
+            }
+            Self::Dot(s, optional) | Self::Bracket(s, optional) => {
+                write!(f, "[\"{}\"]{}", s, mark(*optional))
+            }
+            Self::Index(i, optional) => write!(f, "[{}]{}", i, mark(*optional)),
+            Self::Slice(start, end) => {
+                let bound = |bound: &Option<i32>| {
+                    bound.map_or_else(String::new, |bound| bound.to_string())
+                };
+                write!(f, "[{}:{}]", bound(start), bound(end))
+            }
+            Self::Keys => write!(f, ".keys()"),
+            Self::Values => write!(f, ".values()"),
+            Self::Length => write!(f, ".length()"),
+            Self::ByteLength => write!(f, ".bytelength()"),
+            Self::First => write!(f, ".first()"),
+            Self::Last => write!(f, ".last()"),
+            Self::Type => write!(f, ".type()"),
+            Self::Paths => write!(f, ".paths()"),
+            Self::Pick(keys) => {
+                write!(f, ".{{")?;
+                for (index, key) in keys.iter().enumerate() {
+                    let separator = if index == 0 { "" } else { ", " };
+                    if Self::is_dot_key(key) {
+                        write!(f, "{}{}", separator, key)?;
+                    } else {
+                        write!(f, "{}\"{}\"", separator, key)?;
+                    }
+                }
+                write!(f, "}}")
+            }
+            Self::Omit(keys) => {
+                let keys: Vec<_> =
+                    keys.iter().map(|key| format!("\"{}\"", key)).collect();
+                write!(f, ".omit({})", keys.join(", "))
+            }
+            Self::Find(pattern) => write!(f, ".find(\"{}\")", pattern),
+            Self::FindPaths(pattern) => {
+                write!(f, ".findpaths(\"{}\")", pattern)
+            }
+            Self::Sum => write!(f, ".sum()"),
+            Self::Min => write!(f, ".min()"),
+            Self::Max => write!(f, ".max()"),
+            Self::Avg => write!(f, ".avg()"),
+            Self::Flatten(1) => write!(f, ".flatten()"),
+            Self::Flatten(depth) => write!(f, ".flatten({})", depth),
+            Self::HasKey(key) => write!(f, ".has(\"{}\")", key),
+            Self::HasIndex(index) => write!(f, ".has({})", index),
+            Self::DelKey(key) => write!(f, ".del(\"{}\")", key),
+            Self::DelIndex(index) => write!(f, ".del({})", index),
+            Self::Test(regex) => {
+                write!(f, ".test(\"{}\")", escape(regex.as_str()))
+            }
+            Self::Match(regex) => {
+                write!(f, ".match(\"{}\")", escape(regex.as_str()))
+            }
+            Self::Map(query) => write!(f, ".map({})", query),
+            Self::GroupBy(query) => write!(f, ".group_by({})", query),
+            Self::Any(predicate) => write!(f, ".any({})", predicate),
+            Self::All(predicate) => write!(f, ".all({})", predicate),
+            Self::Each(query) => {
+                write!(f, "[]")?;
+                query
+                    .properties()
+                    .try_for_each(|property| write!(f, "{}", property))
+            }
+            Self::Pipe => write!(f, " | "),
+            Self::Call(name) => write!(f, ".{}()", name),
         }
     }
 }
 
-impl Property {
+impl Property<'_> {
+    /// same property, not borrowing from the query text.
+    pub fn into_owned(self) -> Property<'static> {
+        let owned = |s: Cow<str>| Cow::Owned(s.into_owned());
+        match self {
+            Self::Dot(key, optional) => Property::Dot(owned(key), optional),
+            Self::Bracket(key, optional) => {
+                Property::Bracket(owned(key), optional)
+            }
+            Self::Index(index, optional) => Property::Index(index, optional),
+            Self::Slice(start, end) => Property::Slice(start, end),
+            Self::Keys => Property::Keys,
+            Self::Values => Property::Values,
+            Self::Length => Property::Length,
+            Self::ByteLength => Property::ByteLength,
+            Self::First => Property::First,
+            Self::Last => Property::Last,
+            Self::Type => Property::Type,
+            Self::Paths => Property::Paths,
+            Self::Pick(keys) => {
+                Property::Pick(keys.into_iter().map(owned).collect())
+            }
+            Self::Omit(keys) => {
+                Property::Omit(keys.into_iter().map(owned).collect())
+            }
+            Self::Find(pattern) => Property::Find(owned(pattern)),
+            Self::FindPaths(pattern) => Property::FindPaths(owned(pattern)),
+            Self::Sum => Property::Sum,
+            Self::Min => Property::Min,
+            Self::Max => Property::Max,
+            Self::Avg => Property::Avg,
+            Self::Flatten(depth) => Property::Flatten(depth),
+            Self::HasKey(key) => Property::HasKey(owned(key)),
+            Self::HasIndex(index) => Property::HasIndex(index),
+            Self::DelKey(key) => Property::DelKey(owned(key)),
+            Self::Test(regex) => Property::Test(regex),
+            Self::Match(regex) => Property::Match(regex),
+            Self::DelIndex(index) => Property::DelIndex(index),
+            Self::Map(query) => Property::Map(query.into_owned()),
+            Self::GroupBy(query) => Property::GroupBy(query.into_owned()),
+            Self::Any(predicate) => Property::Any(predicate.into_owned()),
+            Self::All(predicate) => Property::All(predicate.into_owned()),
+            Self::Each(query) => Property::Each(query.into_owned()),
+            Self::Pipe => Property::Pipe,
+            Self::Call(name) => Property::Call(owned(name)),
+        }
+    }
+
+    /// optional access (`.key?`, `["key"]?`, `[0]?`): a missing key, out of
+    /// range index or type mismatch makes the rest of the stage `null`
+    /// (instead of an error, example: `.a?.b | .length()` is `0` on `{}`).
+    pub fn is_optional(&self) -> bool {
+        matches!(
+            self,
+            Self::Dot(_, true) | Self::Bracket(_, true) | Self::Index(_, true)
+        )
+    }
+
+    /// same property with optional access (only dot, bracket and index
+    /// properties, others are unchanged).
+    pub fn optional(self) -> Self {
+        match self {
+            Self::Dot(key, _) => Self::Dot(key, true),
+            Self::Bracket(key, _) => Self::Bracket(key, true),
+            Self::Index(index, _) => Self::Index(index, true),
+            property => property,
+        }
+    }
+
+    /// `key` can be written in dot notation (`.key`): unicode letters and
+    /// digits, `_`, `-` and `$` only, other keys need bracket notation
+    /// (`["key"]`).
+    pub fn is_dot_key(key: &str) -> bool {
+        !key.is_empty() && key.chars().all(dot_key_char)
+    }
+
     #[inline(always)]
     fn invalid(&self) -> String {
         match self {
-            Self::Dot(_) | Self::Bracket(_) => {
+            Self::Dot(..) | Self::Bracket(..) => {
                 "Dot/Bracket properties are only valid on 'Object'".into()
             }
-            Self::Index(_) => "Indexing is only valid on 'Array'".into(),
-            Self::Keys | Self::Values => {
+            Self::Index(..) => "Indexing is only valid on 'Array'".into(),
+            Self::Slice(..) => "Slicing is only valid on 'Array'".into(),
+            Self::Keys
+            | Self::Values
+            | Self::Pick(_)
+            | Self::Omit(_)
+            | Self::HasKey(_)
+            | Self::DelKey(_) => {
                 format!("'{}' can only be applied on 'Object'", self)
             }
             Self::Length => {
-                format!("'{}' can only be applied on 'Array' or 'String'", self)
+                format!(
+                    "'{}' can only be applied on 'Array', 'String', 'Object' \
+                     or 'Null'",
+                    self
+                )
             }
-            Self::Map(_) => {
+            Self::ByteLength | Self::Test(_) | Self::Match(_) => {
+                format!("'{}' can only be applied on 'String'", self)
+            }
+            Self::First
+            | Self::Last
+            | Self::Map(_)
+            | Self::GroupBy(_)
+            | Self::Any(_)
+            | Self::All(_)
+            | Self::Flatten(_)
+            | Self::HasIndex(_)
+            | Self::DelIndex(_) => {
                 format!("'{}' can only be applied on 'Array'", self)
             }
+            Self::Sum | Self::Min | Self::Max | Self::Avg => {
+                format!("'{}' can only be applied on 'Array' of numbers", self)
+            }
+            Self::Each(_) => {
+                "'[]' can only be applied on 'Array' or 'Object'".into()
+            }
+            Self::Call(_) => format!("'{}' is not a builtin function", self),
+            // applicable on any value.
+            Self::Type
+            | Self::Paths
+            | Self::Find(_)
+            | Self::FindPaths(_)
+            | Self::Pipe => String::new(),
         }
     }
 }
 
-#[derive(Clone, PartialEq)]
+/// values are totally ordered (same as `jq`):
+/// `null < false < true < numbers < strings < arrays < objects`, see
+/// [`Ord`](#impl-Ord-for-Json) for details.
 pub enum Json {
     Null,
     Boolean(bool),
     Number(f32),
-    QString(String),
+    QString(JsonString),
     Array(Vec<Json>),
-    Object(HashMap<String, Json>),
+    /// boxed, so that every value stays the size of a string.
+    Object(Box<HashMap<JsonString, Json>>),
 }
 
 impl Json {
     #[inline(always)]
-    fn variant(&self) -> &str {
+    pub(crate) fn variant(&self) -> &'static str {
         match self {
             Self::Null => "Null",
             Self::Boolean(_) => "Boolean",
@@ -76,79 +362,1391 @@ impl Json {
         }
     }
 
+    /// variant, along with the length (array) or keys (object), used in
+    /// error messages.
+    pub(crate) fn shape(&self) -> String {
+        const MAX_KEYS: usize = 5;
+        match self {
+            Self::Array(array) => format!("Array of len {}", array.len()),
+            Self::Object(hashmap) if hashmap.is_empty() => {
+                "empty Object".into()
+            }
+            Self::Object(hashmap) => {
+                let mut keys: Vec<_> = hashmap.keys().collect();
+                keys.sort_unstable();
+                let mut shape = keys
+                    .iter()
+                    .take(MAX_KEYS)
+                    .map(|key| format!("'{}'", key))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if keys.len() > MAX_KEYS {
+                    shape.push_str(", ...");
+                }
+                format!("Object with keys {}", shape)
+            }
+            json => json.variant().into(),
+        }
+    }
+
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    #[inline]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Boolean(boolean) => Some(*boolean),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(float) => Some(*float as f64),
+            _ => None,
+        }
+    }
+
+    /// unescaped string (allocating only if it contains escapes).
+    #[inline]
+    pub fn as_str(&self) -> Option<Cow<'_, str>> {
+        self.as_raw_str().map(unescaped)
+    }
+
+    /// escaped contents (without the quotes), as in the json text.
+    #[inline]
+    pub fn as_raw_str(&self) -> Option<&str> {
+        match self {
+            Self::QString(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            Self::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    /// elements of [`Json::Array`](Json::Array), or values of
+    /// [`Json::Object`](Json::Object) ordered by key (see
+    /// [`Property::Each`]).
+    pub fn iterate(&self) -> Option<Cow<'_, [Json]>> {
+        match self {
+            Self::Array(array) => Some(Cow::Borrowed(array)),
+            Self::Object(hashmap) => {
+                let mut pairs: Vec<_> = hashmap.iter().collect();
+                pairs.sort_unstable_by_key(|(key, _)| *key);
+                Some(Cow::Owned(
+                    pairs
+                        .into_iter()
+                        .map(|(_, value)| value.clone())
+                        .collect::<Vec<_>>(),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_object(&self) -> Option<&HashMap<JsonString, Json>> {
+        match self {
+            Self::Object(hashmap) => Some(hashmap),
+            _ => None,
+        }
+    }
+
+    /// value of `key` (unescaped), if `self` is an object containing `key`.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        let hashmap = self.as_object()?;
+        hashmap.get(&*escape(key)).or_else(|| {
+            // same key, escaped differently (example: `"\u0041"` for `"A"`).
+            hashmap
+                .iter()
+                .find(|(found, _)| {
+                    found.contains('\\') && unescape(found) == key
+                })
+                .map(|(_, value)| value)
+        })
+    }
+
+    /// value of `key` (escaped, as in the json text), if `self` is an object
+    /// containing `key`.
+    #[inline]
+    pub fn get_raw(&self, key: &str) -> Option<&Json> {
+        self.as_object().and_then(|hashmap| hashmap.get(key))
+    }
+
+    /// element at `index`, if `self` is an array with more than `index` elements.
+    #[inline]
+    pub fn get_index(&self, index: usize) -> Option<&Json> {
+        self.as_array().and_then(|array| array.get(index))
+    }
+
+    /// total number of nodes in the tree (including `self`).
+    pub fn node_count(&self) -> usize {
+        match self {
+            Self::Array(array) => {
+                1 + array.iter().map(Self::node_count).sum::<usize>()
+            }
+            Self::Object(hashmap) => {
+                1 + hashmap.values().map(Self::node_count).sum::<usize>()
+            }
+            _ => 1,
+        }
+    }
+
+    /// values of the keys matching `pattern` anywhere in the tree (depth
+    /// first, object keys in order), along with their paths. `*` in
+    /// `pattern` matches any chars (example: `*_id`).
+    pub fn find(&self, pattern: &str) -> Vec<(JsonQuery<'_>, &Self)> {
+        fn visit<'j>(
+            json: &'j Json,
+            pattern: &str,
+            path: &mut Vec<Property<'j>>,
+            found: &mut Vec<(JsonQuery<'j>, &'j Json)>,
+        ) {
+            match json {
+                Json::Array(array) => {
+                    for (index, json) in array.iter().enumerate() {
+                        path.push(Property::Index(index as i32, false));
+                        visit(json, pattern, path, found);
+                        path.pop();
+                    }
+                }
+                Json::Object(hashmap) => {
+                    let mut entries: Vec<_> = hashmap.iter().collect();
+                    entries.sort_unstable_by_key(|(key, _)| *key);
+                    for (key, json) in entries {
+                        path.push(Property::Dot(Cow::Borrowed(key), false));
+                        if glob_match(pattern, key) {
+                            found.push((JsonQuery(path.clone()), json));
+                        }
+                        visit(json, pattern, path, found);
+                        path.pop();
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut found = vec![];
+        visit(self, pattern, &mut vec![], &mut found);
+        found
+    }
+
+    /// paths of the leaves (scalars, empty arrays and objects) of the tree
+    /// (depth first, object keys in order), along with their values.
+    pub fn leaves(&self) -> Vec<(JsonQuery<'_>, &Self)> {
+        fn visit<'j>(
+            json: &'j Json,
+            path: &mut Vec<Property<'j>>,
+            leaves: &mut Vec<(JsonQuery<'j>, &'j Json)>,
+        ) {
+            match json {
+                Json::Array(array) if !array.is_empty() => {
+                    for (index, json) in array.iter().enumerate() {
+                        path.push(Property::Index(index as i32, false));
+                        visit(json, path, leaves);
+                        path.pop();
+                    }
+                }
+                Json::Object(hashmap) if !hashmap.is_empty() => {
+                    let mut entries: Vec<_> = hashmap.iter().collect();
+                    entries.sort_unstable_by_key(|(key, _)| *key);
+                    for (key, json) in entries {
+                        path.push(Property::Dot(Cow::Borrowed(key), false));
+                        visit(json, path, leaves);
+                        path.pop();
+                    }
+                }
+                _ => leaves.push((JsonQuery(path.clone()), json)),
+            }
+        }
+        let mut leaves = vec![];
+        visit(self, &mut vec![], &mut leaves);
+        leaves
+    }
+
+    /// size and shape of the tree (see [`Metrics`]), for budgeting memory or
+    /// rejecting oversized values before processing them.
+    pub fn metrics(&self) -> Metrics {
+        fn visit(json: &Json, depth: usize, metrics: &mut Metrics) {
+            metrics.depth = metrics.depth.max(depth);
+            match json {
+                Json::Null => metrics.nulls += 1,
+                Json::Boolean(_) => metrics.booleans += 1,
+                Json::Number(_) => metrics.numbers += 1,
+                Json::QString(string) => {
+                    metrics.strings += 1;
+                    metrics.string_bytes += string.len();
+                }
+                Json::Array(array) => {
+                    metrics.arrays += 1;
+                    for json in array {
+                        visit(json, depth + 1, metrics);
+                    }
+                }
+                Json::Object(hashmap) => {
+                    metrics.objects += 1;
+                    for (key, json) in hashmap.iter() {
+                        metrics.string_bytes += key.len();
+                        visit(json, depth + 1, metrics);
+                    }
+                }
+            }
+        }
+        let mut metrics = Metrics {
+            memory: self.memory_size(),
+            ..Metrics::default()
+        };
+        visit(self, 0, &mut metrics);
+        metrics
+    }
+
+    /// approximate bytes allocated for `self` (including the value itself,
+    /// excluding spare capacity), same accounting as the parser's
+    /// [`max_memory`](super::parser::JsonLimits::max_memory) budget.
+    pub fn memory_size(&self) -> usize {
+        let heap = match self {
+            Self::QString(string) => string.heap_size(),
+            Self::Array(array) => array.iter().map(Self::memory_size).sum(),
+            Self::Object(hashmap) => {
+                let entries: usize = hashmap
+                    .iter()
+                    .map(|(key, value)| {
+                        size_of::<JsonString>()
+                            + key.heap_size()
+                            + value.memory_size()
+                    })
+                    .sum();
+                size_of::<HashMap<JsonString, Self>>() + entries
+            }
+            _ => 0,
+        };
+        size_of::<Self>() + heap
+    }
+
+    /// value selected by `property`, borrowed from `self` for the path
+    /// properties (dot, bracket, index), computed otherwise.
     #[inline]
-    pub fn update(&mut self, property: &Property) -> Result<&Self, String> {
+    pub fn select(
+        &self,
+        property: &Property,
+    ) -> Result<Cow<'_, Self>, JsonEvalError> {
         macro_rules! match_only {
             ($($pattern:pat => $expr:expr),*) => {
                 match self {
                     $($pattern => $expr),*,
-                    _ => Err(format!(" {}, found '{}' instead.",
-                                     property.invalid(), self.variant())),
+                    _ => Err(JsonEvalError::InvalidType(property.invalid(),
+                                                        self.variant())),
                 }
             }
         }
-        *self = match property {
-            Property::Dot(s) | Property::Bracket(s) => match_only! {
+        match property {
+            Property::Dot(s, _) | Property::Bracket(s, _) => match_only! {
                 Self::Object(hashmap) => hashmap
-                    .get(s)
-                    .cloned()
-                    .ok_or(format!(" key doesn't exist: '{}'", s))
+                    .get(&**s)
+                    .map(Cow::Borrowed)
+                    .ok_or_else(|| {
+                        JsonEvalError::missing_key(s, hashmap.keys().map(|key| &**key))
+                    })
             },
-            Property::Index(i) => match_only! {
+            Property::Index(i, _) => match_only! {
                 Self::Array(array) => {
-                    array.get(*i as usize).cloned().ok_or(format!(
-                        " Invalid index {} (for array of len {})",
-                        i,
-                        array.len()
-                    ))
+                    array_index(*i, array.len())
+                        .map(|index| Cow::Borrowed(&array[index]))
+                        .ok_or(JsonEvalError::InvalidIndex(*i, array.len()))
                 }
             },
+            Property::Slice(start, end) => match_only! {
+                Self::Array(array) => {
+                    let range = array_range(*start, *end, array.len());
+                    Ok(Cow::Owned(Self::Array(array[range].to_vec())))
+                }
+            },
+            Property::First => match_only! {
+                Self::Array(array) => array
+                    .first()
+                    .map(Cow::Borrowed)
+                    .ok_or_else(|| JsonEvalError::EmptyArray(property.to_string()))
+            },
+            Property::Last => match_only! {
+                Self::Array(array) => array
+                    .last()
+                    .map(Cow::Borrowed)
+                    .ok_or_else(|| JsonEvalError::EmptyArray(property.to_string()))
+            },
             Property::Keys => match_only! {
-                Self::Object(hashmap) => Ok(Self::Array(
+                Self::Object(hashmap) => Ok(Cow::Owned(Self::Array(
                     hashmap.keys().cloned().map(Json::QString).collect()
-                ))
+                )))
+            },
+            Property::Pick(keys) => match_only! {
+                Self::Object(hashmap) => Ok(Cow::Owned(Self::Object(Box::new(
+                    keys.iter()
+                        .map(|key| {
+                            let value = hashmap.get(&**key).cloned();
+                            (JsonString::from(&**key), value.unwrap_or(Self::Null))
+                        })
+                        .collect()
+                ))))
+            },
+            Property::Omit(keys) => match_only! {
+                Self::Object(hashmap) => Ok(Cow::Owned(Self::Object(Box::new(
+                    hashmap
+                        .iter()
+                        .filter(|(key, _)| !keys.iter().any(|omit| **key == **omit))
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect()
+                ))))
             },
             Property::Values => match_only! {
+                Self::Object(hashmap) => Ok(Cow::Owned(Self::Array(
+                    hashmap.values().cloned().collect()
+                )))
+            },
+            Property::Length => match_only! {
+                Self::Array(array) => {
+                    Ok(Cow::Owned(Self::Number(array.len() as f32)))
+                },
+                Self::QString(string) => {
+                    let length = unescaped(string).chars().count();
+                    Ok(Cow::Owned(Self::Number(length as f32)))
+                },
                 Self::Object(hashmap) => {
-                    Ok(Self::Array(hashmap.values().cloned().collect()))
+                    Ok(Cow::Owned(Self::Number(hashmap.len() as f32)))
+                },
+                // same as `jq` (`null | length` is `0`).
+                Self::Null => Ok(Cow::Owned(Self::Number(0.)))
+            },
+            Property::ByteLength => match_only! {
+                Self::QString(string) => {
+                    let length = unescaped(string).len();
+                    Ok(Cow::Owned(Self::Number(length as f32)))
                 }
             },
-            Property::Length => match_only! {
-                Self::Array(array) => Ok(Self::Number(array.len() as f32)),
-                Self::QString(string) => Ok(Self::Number(string.len() as f32))
+            Property::Test(regex) => match_only! {
+                Self::QString(string) => Ok(Cow::Owned(Self::Boolean(
+                    regex.is_match(&unescaped(string))
+                )))
+            },
+            Property::Match(regex) => match_only! {
+                Self::QString(string) => {
+                    Ok(Cow::Owned(regex_match(regex, &unescaped(string))))
+                }
+            },
+            Property::Type => {
+                Ok(Cow::Owned(Self::from(self.variant().to_lowercase())))
+            }
+            Property::Paths => Ok(Cow::Owned(Self::Array(
+                self.leaves()
+                    .into_iter()
+                    .map(|(path, _)| Self::from(path.to_string()))
+                    .collect(),
+            ))),
+            Property::Find(pattern) => Ok(Cow::Owned(Self::Array(
+                self.find(pattern)
+                    .into_iter()
+                    .map(|(_, value)| value.clone())
+                    .collect(),
+            ))),
+            Property::FindPaths(pattern) => Ok(Cow::Owned(Self::Array(
+                self.find(pattern)
+                    .into_iter()
+                    .map(|(path, value)| {
+                        Self::from_iter([
+                            ("path".to_string(), Self::from(path.to_string())),
+                            ("value".to_string(), value.clone()),
+                        ])
+                    })
+                    .collect(),
+            ))),
+            // user defined functions are only known to `QueryEngine`.
+            Property::Call(name) => {
+                Err(JsonEvalError::UnknownFunction(name.to_string()))
+            }
+            Property::HasKey(key) => match_only! {
+                Self::Object(hashmap) => {
+                    Ok(Cow::Owned(Self::Boolean(hashmap.contains_key(&**key))))
+                }
+            },
+            Property::HasIndex(index) => match_only! {
+                Self::Array(array) => {
+                    let has = array_index(*index, array.len()).is_some();
+                    Ok(Cow::Owned(Self::Boolean(has)))
+                }
+            },
+            Property::DelKey(key) => match_only! {
+                Self::Object(hashmap) => {
+                    let mut hashmap = hashmap.clone();
+                    hashmap.remove(&**key);
+                    Ok(Cow::Owned(Self::Object(hashmap)))
+                }
+            },
+            Property::DelIndex(index) => match_only! {
+                Self::Array(array) => {
+                    let mut array = array.clone();
+                    if let Some(index) = array_index(*index, array.len()) {
+                        array.remove(index);
+                    }
+                    Ok(Cow::Owned(Self::Array(array)))
+                }
+            },
+            Property::Sum => match_only! {
+                Self::Array(array) => {
+                    // (`Iterator::sum` of no floats is `-0`, not `0`).
+                    let sum = numbers(property, array)?
+                        .into_iter()
+                        .fold(0., |sum, number| sum + number);
+                    Ok(Cow::Owned(Self::from_lossy(sum)))
+                }
+            },
+            Property::Min => match_only! {
+                Self::Array(array) => {
+                    let numbers = numbers(property, array)?;
+                    let min = numbers.into_iter().reduce(f64::min);
+                    Ok(Cow::Owned(min.map_or(Self::Null, Self::from_lossy)))
+                }
+            },
+            Property::Max => match_only! {
+                Self::Array(array) => {
+                    let numbers = numbers(property, array)?;
+                    let max = numbers.into_iter().reduce(f64::max);
+                    Ok(Cow::Owned(max.map_or(Self::Null, Self::from_lossy)))
+                }
+            },
+            Property::Avg => match_only! {
+                Self::Array(array) => {
+                    let numbers = numbers(property, array)?;
+                    let avg = (!numbers.is_empty()).then(|| {
+                        numbers.iter().sum::<f64>() / numbers.len() as f64
+                    });
+                    Ok(Cow::Owned(avg.map_or(Self::Null, Self::from_lossy)))
+                }
+            },
+            Property::Flatten(depth) => match_only! {
+                Self::Array(array) => {
+                    let mut flat = Vec::with_capacity(array.len());
+                    flatten(array, *depth, &mut flat);
+                    Ok(Cow::Owned(Self::Array(flat)))
+                }
             },
             Property::Map(query) => match_only! {
-                Self::Array(array) => Ok(Self::Array(
-                    array
-                        .iter_mut()
-                        .map(|token| token.apply(query))
-                        .collect::<Result<Vec<Json>, String>>()?,
-                ))
+                Self::Array(array) => {
+                    let array =
+                        memo::map(array, query, ApplyOptions::default())?;
+                    Ok(Cow::Owned(Self::Array(array)))
+                }
+            },
+            Property::GroupBy(query) => match_only! {
+                Self::Array(array) => {
+                    let keys =
+                        memo::map(array, query, ApplyOptions::default())?;
+                    Ok(Cow::Owned(group_by(array, keys)))
+                }
             },
-        }?;
+            Property::Any(predicate) | Property::All(predicate) => {
+                match_only! {
+                    Self::Array(array) => {
+                        let all = matches!(property, Property::All(_));
+                        quantify(array, predicate, all, |json| json.apply(&predicate.query))
+                            .map(Cow::Owned)
+                    }
+                }
+            }
+            Property::Each(query) => match self.iterate() {
+                Some(elements) => {
+                    let array =
+                        memo::map(&elements, query, ApplyOptions::default())?;
+                    Ok(Cow::Owned(each(query, array)))
+                }
+                None => Err(JsonEvalError::InvalidType(
+                    property.invalid(),
+                    self.variant(),
+                )),
+            },
+            Property::Pipe => Ok(Cow::Borrowed(self)),
+        }
+    }
+
+    /// value selected by `property`, moved out of `self` for the path
+    /// properties (no clone).
+    pub fn take(mut self, property: &Property) -> Result<Self, JsonEvalError> {
+        match (property, &mut self) {
+            (
+                Property::Dot(s, _) | Property::Bracket(s, _),
+                Self::Object(hashmap),
+            ) => hashmap.remove(&**s).ok_or_else(|| {
+                JsonEvalError::missing_key(s, hashmap.keys().map(|key| &**key))
+            }),
+            (Property::Index(i, _), Self::Array(array)) => {
+                match array_index(*i, array.len()) {
+                    Some(index) => Ok(array.swap_remove(index)),
+                    None => Err(JsonEvalError::InvalidIndex(*i, array.len())),
+                }
+            }
+            (Property::First, Self::Array(array)) if !array.is_empty() => {
+                Ok(array.swap_remove(0))
+            }
+            (Property::Last, Self::Array(array)) => array
+                .pop()
+                .ok_or_else(|| JsonEvalError::EmptyArray(property.to_string())),
+            (Property::Slice(start, end), Self::Array(array)) => {
+                let range = array_range(*start, *end, array.len());
+                array.truncate(range.end);
+                array.drain(..range.start);
+                Ok(self)
+            }
+            (Property::Pick(keys), Self::Object(hashmap)) => {
+                let mut picked = HashMap::with_capacity(keys.len());
+                for key in keys {
+                    // repeated keys are moved out only once.
+                    picked.entry(JsonString::from(&**key)).or_insert_with(
+                        || hashmap.remove(&**key).unwrap_or(Self::Null),
+                    );
+                }
+                Ok(Self::Object(Box::new(picked)))
+            }
+            (Property::Omit(keys), Self::Object(hashmap)) => {
+                for key in keys {
+                    hashmap.remove(&**key);
+                }
+                Ok(self)
+            }
+            (Property::DelKey(key), Self::Object(hashmap)) => {
+                hashmap.remove(&**key);
+                Ok(self)
+            }
+            (Property::DelIndex(index), Self::Array(array)) => {
+                if let Some(index) = array_index(*index, array.len()) {
+                    array.remove(index);
+                }
+                Ok(self)
+            }
+            (Property::Pipe, _) => Ok(self),
+            (_, json) => json.select(property).map(Cow::into_owned),
+        }
+    }
+
+    /// replace `self` with the value selected by `property` (`null`, if an
+    /// optional property doesn't select a value).
+    #[inline]
+    pub fn update(
+        &mut self,
+        property: &Property,
+    ) -> Result<&Self, JsonEvalError> {
+        let selected = match self.select(property) {
+            Ok(selected) => selected.into_owned(),
+            Err(err) if property.is_optional() && err.is_missing() => {
+                Self::Null
+            }
+            Err(err) => return Err(err),
+        };
+        *self = selected;
         Ok(self)
     }
 
     /// This is used for extracting a `Json` value that matches the given
     /// [`JsonQuery`](JsonQuery), from the current object.
-    pub fn apply(&self, query: &JsonQuery) -> Result<Self, String> {
-        let mut json = self.clone();
-        for property in query.properties() {
-            json.update(&property)?;
+    /// The document is walked by reference, only the result is cloned.
+    pub fn apply(&self, query: &JsonQuery) -> Result<Self, JsonEvalError> {
+        self.apply_with(query, ApplyOptions::default())
+    }
+
+    /// parse `query` and apply it (example: `json.query(".items[0].name")?`).
+    pub fn query(&self, query: &str) -> Result<Self, RusonError> {
+        Ok(self.apply(&JsonQuery::new(query)?)?)
+    }
+
+    /// same as [`apply`](Self::apply), erroring out if any computed value
+    /// (or the result) exceeds `max_memory` bytes (see
+    /// [`memory_size`](Self::memory_size)).
+    pub fn apply_within(
+        &self,
+        query: &JsonQuery,
+        max_memory: usize,
+    ) -> Result<Self, JsonEvalError> {
+        let options = ApplyOptions {
+            max_memory: Some(max_memory),
+            ..ApplyOptions::default()
+        };
+        self.apply_with(query, options)
+    }
+
+    /// same as [`apply`](Self::apply), with evaluation `options` (applied
+    /// inside `.map()` as well). errors of properties after the first one
+    /// are located (see [`JsonEvalError::At`]).
+    pub fn apply_with(
+        &self,
+        query: &JsonQuery,
+        options: ApplyOptions,
+    ) -> Result<Self, JsonEvalError> {
+        let within = |json: &Self| match options.max_memory {
+            Some(max_memory) if json.memory_size() > max_memory => {
+                Err(JsonEvalError::MemoryLimit(max_memory))
+            }
+            _ => Ok(()),
+        };
+        let map = |array: &[Self], query| {
+            memo::map(array, query, options)
+                .map(|array| Cow::Owned(Self::Array(array)))
+        };
+        let mut json = Cow::Borrowed(self);
+        let mut skipping = false;
+        for (index, property) in query.properties().enumerate() {
+            // up to the next stage (see `Property::is_optional`).
+            if skipping {
+                skipping = !matches!(property, Property::Pipe);
+                continue;
+            }
+            let selected = match (property, json) {
+                (Property::Each(query), json) => match json.iterate() {
+                    Some(elements) => memo::map(&elements, query, options)
+                        .map(|array| Cow::Owned(each(query, array))),
+                    None => Err(JsonEvalError::InvalidType(
+                        property.invalid(),
+                        json.variant(),
+                    )),
+                },
+                (Property::Map(query), Cow::Borrowed(Self::Array(array))) => {
+                    map(array, query)
+                }
+                (Property::Map(query), Cow::Owned(Self::Array(ref array))) => {
+                    map(array, query)
+                }
+                (Property::GroupBy(query), json) => match json.as_array() {
+                    Some(array) => memo::map(array, query, options)
+                        .map(|keys| Cow::Owned(group_by(array, keys))),
+                    None => Err(JsonEvalError::InvalidType(
+                        property.invalid(),
+                        json.variant(),
+                    )),
+                },
+                (Property::Any(predicate) | Property::All(predicate), json) => {
+                    match json.as_array() {
+                        Some(array) => {
+                            let all = matches!(property, Property::All(_));
+                            quantify(array, predicate, all, |json| {
+                                json.apply_with(&predicate.query, options)
+                            })
+                            .map(Cow::Owned)
+                        }
+                        None => Err(JsonEvalError::InvalidType(
+                            property.invalid(),
+                            json.variant(),
+                        )),
+                    }
+                }
+                (_, Cow::Borrowed(json)) => json.select(property),
+                (_, Cow::Owned(json)) => json.take(property).map(Cow::Owned),
+            };
+            json = match selected {
+                Ok(json) => json,
+                // rest of the stage is short-circuited.
+                Err(err) if property.is_optional() && err.is_missing() => {
+                    skipping = true;
+                    Cow::Owned(Self::Null)
+                }
+                Err(err)
+                    if options.missing == Missing::Null
+                        && err.cause().is_missing() =>
+                {
+                    Cow::Owned(Self::Null)
+                }
+                Err(err) => return Err(self.locate(query, index, options, err)),
+            };
+            if let Cow::Owned(json) = &json {
+                within(json)?;
+            }
+        }
+        if let Cow::Borrowed(json) = json {
+            within(json)?;
         }
-        Ok(json)
+        Ok(json.into_owned())
     }
+
+    /// elements of [`Json::Array`](Json::Array) in `page`, other values are
+    /// unchanged.
+    pub fn page(mut self, page: Page) -> Self {
+        match &mut self {
+            Self::Array(array) if !page.is_all() => {
+                let range = page.range(array.len());
+                array.truncate(range.end);
+                array.drain(..range.start);
+                self
+            }
+            _ => self,
+        }
+    }
+
+    /// `err` of the property at `index` in `query`, located after the
+    /// properties preceding it.
+    #[cold]
+    fn locate(
+        &self,
+        query: &JsonQuery,
+        index: usize,
+        options: ApplyOptions,
+        err: JsonEvalError,
+    ) -> JsonEvalError {
+        if index == 0 && !matches!(err, JsonEvalError::At(..)) {
+            return err;
+        }
+        let prefix = JsonQuery::from_stages(query.0[..index].to_vec());
+        err.after(&prefix.to_string(), || {
+            // prefix was applied already (selects the same value again).
+            self.apply_with(&prefix, options)
+                .map_or_else(|_| String::new(), |json| json.shape())
+        })
+    }
+}
+
+/// Size and shape of a [`Json`] value, see [`Json::metrics`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Metrics {
+    /// nesting depth (`0` for scalars, `1` for arrays/objects of scalars).
+    pub depth: usize,
+    pub nulls: usize,
+    pub booleans: usize,
+    pub numbers: usize,
+    pub strings: usize,
+    pub arrays: usize,
+    pub objects: usize,
+    /// bytes of the strings and object keys (escaped, as in json text).
+    pub string_bytes: usize,
+    /// approximate bytes allocated, see [`Json::memory_size`].
+    pub memory: usize,
+}
+
+impl Metrics {
+    /// total number of nodes, same as [`Json::node_count`].
+    pub fn nodes(&self) -> usize {
+        self.nulls
+            + self.booleans
+            + self.numbers
+            + self.strings
+            + self.arrays
+            + self.objects
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            " depth: {}, nodes: {} (null: {}, boolean: {}, number: {}, \
+             string: {}, array: {}, object: {}), string bytes: {}, \
+             memory: {} bytes",
+            self.depth,
+            self.nodes(),
+            self.nulls,
+            self.booleans,
+            self.numbers,
+            self.strings,
+            self.arrays,
+            self.objects,
+            self.string_bytes,
+            self.memory
+        )
+    }
+}
+
+/// escape `s` as the contents of a json string (without the quotes).
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\u{08}' => escaped.push_str("\\b"),
+            '\u{0c}' => escaped.push_str("\\f"),
+            ch if (ch as u32) < 0x20 => {
+                escaped.push_str(&format!("\\u{:04x}", ch as u32))
+            }
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// `s` matches `pattern`, where `*` matches any (possibly empty) sequence of
+/// chars.
+pub fn glob_match(pattern: &str, s: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == s,
+        Some((prefix, rest)) => {
+            let s = match s.strip_prefix(prefix) {
+                Some(s) => s,
+                None => return false,
+            };
+            // shortest match first, `*` at the end matches the rest.
+            s.char_indices()
+                .map(|(index, _)| index)
+                .chain(core::iter::once(s.len()))
+                .any(|index| glob_match(rest, &s[index..]))
+        }
+    }
+}
+
+/// position of `index` in an array of `len` elements (negative indices count
+/// from the end), `None` if out of range.
+#[inline]
+pub fn array_index(index: i32, len: usize) -> Option<usize> {
+    let index = match usize::try_from(index) {
+        Ok(index) => index,
+        Err(_) => len.checked_sub(index.unsigned_abs() as usize)?,
+    };
+    (index < len).then_some(index)
+}
+
+/// elements of `array` (for the aggregate `property`), erroring out on the
+/// first element that isn't a number.
+fn numbers(
+    property: &Property,
+    array: &[Json],
+) -> Result<Vec<f64>, JsonEvalError> {
+    array
+        .iter()
+        .enumerate()
+        .map(|(index, json)| match json {
+            Json::Number(number) => Ok(*number as f64),
+            json => Err(JsonEvalError::InvalidType(
+                format!(
+                    "'{}' can only be applied on numbers (element at index {})",
+                    property, index
+                ),
+                json.variant(),
+            )),
+        })
+        .collect()
+}
+
+/// elements of `array` pushed to `flat`, nested arrays are flattened up to
+/// `depth` levels (see [`Property::Flatten`]).
+fn flatten(array: &[Json], depth: u32, flat: &mut Vec<Json>) {
+    for json in array {
+        match json {
+            Json::Array(nested) if depth > 0 => {
+                flatten(nested, depth - 1, flat)
+            }
+            json => flat.push(json.clone()),
+        }
+    }
+}
+
+/// results of [`Property::Each`] (`query` applied on every element), results
+/// of a nested `[]` are spliced in (same as the multiple results of `jq`).
+pub(super) fn each(query: &JsonQuery, results: Vec<Json>) -> Json {
+    if !matches!(query.0.last(), Some(Property::Each(_))) {
+        return Json::Array(results);
+    }
+    Json::Array(
+        results
+            .into_iter()
+            .flat_map(|mut json| match &mut json {
+                Json::Array(array) => core::mem::take(array),
+                _ => vec![json],
+            })
+            .collect(),
+    )
+}
+
+/// elements of `array` grouped by their `keys` (results of
+/// [`Property::GroupBy`]), groups ordered by key.
+pub(super) fn group_by(array: &[Json], keys: Vec<Json>) -> Json {
+    let mut keyed: Vec<_> = keys.into_iter().zip(array).collect();
+    // stable, elements of a group stay in order.
+    keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut groups: Vec<(Json, Vec<Json>)> = vec![];
+    for (key, json) in keyed {
+        match groups.last_mut() {
+            Some((last, group)) if (*last).cmp(&key).is_eq() => {
+                group.push(json.clone())
+            }
+            _ => groups.push((key, vec![json.clone()])),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(_, group)| Json::Array(group))
+        .collect()
+}
+
+/// `predicate` holds for some (or every, if `all`) element of `array`,
+/// results of the predicate query are evaluated by `apply` (elements are
+/// only evaluated up to the first one deciding the result).
+pub(super) fn quantify<F>(
+    array: &[Json],
+    predicate: &Predicate,
+    all: bool,
+    apply: F,
+) -> Result<Json, JsonEvalError>
+where
+    F: Fn(&Json) -> Result<Json, JsonEvalError>,
+{
+    for (index, json) in array.iter().enumerate() {
+        let value = apply(json).map_err(|err| {
+            err.after(&format!("[{}]", index), || json.shape())
+        })?;
+        if predicate.matches(&value) != all {
+            return Ok(Json::Boolean(!all));
+        }
+    }
+    Ok(Json::Boolean(all))
+}
+
+/// first match of `regex` in `s`, see [`Property::Match`].
+fn regex_match(regex: &Regex, s: &str) -> Json {
+    let captures = match regex.captures(s) {
+        Some(captures) => captures,
+        None => return Json::Null,
+    };
+    let chars: Vec<char> = s.chars().collect();
+    // same as `jq` (`-1`, `0` and `null` for groups that didn't match).
+    let group = |range: &Option<Range<usize>>| {
+        let (offset, length, string) = match range {
+            Some(range) => (
+                range.start as f64,
+                range.len() as f64,
+                Json::from(chars[range.clone()].iter().collect::<String>()),
+            ),
+            None => (-1., 0., Json::Null),
+        };
+        vec![
+            ("offset".to_string(), Json::from_lossy(offset)),
+            ("length".to_string(), Json::from_lossy(length)),
+            ("string".to_string(), string),
+        ]
+    };
+    let groups = captures[1..]
+        .iter()
+        .map(|range| group(range).into_iter().collect())
+        .collect();
+    let mut matched = group(&captures[0]);
+    matched.push(("captures".to_string(), Json::Array(groups)));
+    matched.into_iter().collect()
+}
+
+/// positions of the elements from `start` up to `end` in an array of `len`
+/// elements (negative bounds count from the end, clamped to the array).
+#[inline]
+pub fn array_range(
+    start: Option<i32>,
+    end: Option<i32>,
+    len: usize,
+) -> Range<usize> {
+    let position = |bound: i32| match usize::try_from(bound) {
+        Ok(bound) => bound.min(len),
+        Err(_) => len.saturating_sub(bound.unsigned_abs() as usize),
+    };
+    let start = start.map_or(0, position);
+    let end = end.map_or(len, position);
+    start..end.max(start)
+}
+
+/// unescape the contents of a json string (without the quotes).
+/// invalid escape sequences are kept as is.
+pub fn unescape(s: &str) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    let hex = |at: usize| {
+        let digits = chars.get(at..at + 4)?;
+        if !digits.iter().all(char::is_ascii_hexdigit) {
+            return None;
+        }
+        u32::from_str_radix(&digits.iter().collect::<String>(), 16).ok()
+    };
+    // unescaped char and length of the escape sequence, starting at `at`.
+    let escaped = |at: usize| {
+        if chars[at] != '\\' {
+            return None;
+        }
+        let ch = match chars.get(at + 1)? {
+            '"' => '"',
+            '\\' => '\\',
+            '/' => '/',
+            'b' => '\u{08}',
+            'f' => '\u{0c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let high = hex(at + 2)?;
+                if !(0xd800..=0xdbff).contains(&high) {
+                    return char::from_u32(high).map(|ch| (ch, 6));
+                }
+                // utf-16 surrogate pair (example: `\ud83d\ude00`).
+                if *chars.get(at + 6..at + 8)? != ['\\', 'u'] {
+                    return None;
+                }
+                let low = hex(at + 8)
+                    .filter(|low| (0xdc00..=0xdfff).contains(low))?;
+                let code = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+                return char::from_u32(code).map(|ch| (ch, 12));
+            }
+            _ => return None,
+        };
+        Some((ch, 2))
+    };
+
+    let (mut unescaped, mut cursor) = (String::with_capacity(s.len()), 0);
+    while cursor < chars.len() {
+        let (ch, len) = escaped(cursor).unwrap_or((chars[cursor], 1));
+        unescaped.push(ch);
+        cursor += len;
+    }
+    unescaped
+}
+
+/// strings are escaped, as `Json::QString` holds the raw json text.
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Self::QString(escape(s).into())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Self::from(s.as_str())
+    }
+}
+
+impl Json {
+    /// nearest number to `float` (`Json::Number` is an `f32`), see
+    /// [`TryFrom<f64>`](#impl-TryFrom%3Cf64%3E-for-Json) for the exact
+    /// conversion.
+    #[inline]
+    pub fn from_lossy(float: f64) -> Self {
+        Self::Number(float as f32)
+    }
+}
+
+/// only numbers that `Json::Number` (an `f32`) represents exactly are
+/// converted (see [`Json::from_lossy`] for the nearest number instead).
+impl TryFrom<f64> for Json {
+    type Error = JsonConvertError;
+    fn try_from(float: f64) -> Result<Self, Self::Error> {
+        match float as f32 {
+            number if float.is_finite() && number as f64 == float => {
+                Ok(Self::Number(number))
+            }
+            _ => Err(JsonConvertError::inexact("f64", float)),
+        }
+    }
+}
+
+/// only integers that `Json::Number` (an `f32`) represents exactly are
+/// converted, i.e. all integers in `-2^24..=2^24` (and some beyond).
+impl TryFrom<i64> for Json {
+    type Error = JsonConvertError;
+    fn try_from(int: i64) -> Result<Self, Self::Error> {
+        // compared as `i128`, as `f32 -> i64` saturates.
+        match int as f32 {
+            number if number as i128 == int as i128 => Ok(Self::Number(number)),
+            _ => Err(JsonConvertError::inexact("i64", int)),
+        }
+    }
+}
+
+impl From<bool> for Json {
+    fn from(boolean: bool) -> Self {
+        Self::Boolean(boolean)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(array: Vec<Json>) -> Self {
+        Self::Array(array)
+    }
+}
+
+/// keys are escaped (same as the strings of `Json::from(&str)`).
+impl From<HashMap<String, Json>> for Json {
+    fn from(hashmap: HashMap<String, Json>) -> Self {
+        hashmap.into_iter().collect()
+    }
+}
+
+impl FromIterator<Json> for Json {
+    fn from_iter<I: IntoIterator<Item = Json>>(iter: I) -> Self {
+        Self::Array(iter.into_iter().collect())
+    }
+}
+
+/// keys are escaped (same as the strings of `Json::from(&str)`).
+impl FromIterator<(String, Json)> for Json {
+    fn from_iter<I: IntoIterator<Item = (String, Json)>>(iter: I) -> Self {
+        iter.into_iter()
+            .map(|(key, value)| (JsonString::from(escape(&key)), value))
+            .collect()
+    }
+}
+
+/// keys are expected to be escaped already (same as [`Json::Object`]).
+impl FromIterator<(JsonString, Json)> for Json {
+    fn from_iter<I: IntoIterator<Item = (JsonString, Json)>>(iter: I) -> Self {
+        Self::Object(Box::new(iter.into_iter().collect()))
+    }
+}
+
+impl JsonConvertError {
+    /// error for converting `found` into the rust type named `expected`.
+    pub fn new(expected: &'static str, found: &Json) -> Self {
+        let found = match found {
+            Json::Array(_) | Json::Object(_) => {
+                format!("'{}'", found.variant())
+            }
+            _ => format!("'{}' ({})", found.variant(), found),
+        };
+        Self { expected, found }
+    }
+
+    /// error for converting the rust number `found` (of type named `name`)
+    /// into a [`Json::Number`], that can't represent it exactly.
+    fn inexact(name: &'static str, found: impl fmt::Display) -> Self {
+        let found = format!("'{}' ({})", name, found);
+        Self {
+            expected: "Json",
+            found,
+        }
+    }
+}
+
+impl TryFrom<&Json> for String {
+    type Error = JsonConvertError;
+    fn try_from(json: &Json) -> Result<Self, Self::Error> {
+        json.as_str()
+            .map(Cow::into_owned)
+            .ok_or_else(|| JsonConvertError::new("String", json))
+    }
+}
+
+impl TryFrom<&Json> for f64 {
+    type Error = JsonConvertError;
+    fn try_from(json: &Json) -> Result<Self, Self::Error> {
+        json.as_f64()
+            .ok_or_else(|| JsonConvertError::new("f64", json))
+    }
+}
+
+/// only numbers without fractional part (and in range of `i64`) are
+/// converted.
+impl TryFrom<&Json> for i64 {
+    type Error = JsonConvertError;
+    fn try_from(json: &Json) -> Result<Self, Self::Error> {
+        // `-(i64::MIN as f64)` is `2^63` (exactly), one more than `i64::MAX`.
+        let range = i64::MIN as f64..-(i64::MIN as f64);
+        json.as_f64()
+            .filter(|float| float % 1. == 0. && range.contains(float))
+            .map(|float| float as i64)
+            .ok_or_else(|| JsonConvertError::new("i64", json))
+    }
+}
+
+impl TryFrom<&Json> for bool {
+    type Error = JsonConvertError;
+    fn try_from(json: &Json) -> Result<Self, Self::Error> {
+        json.as_bool()
+            .ok_or_else(|| JsonConvertError::new("bool", json))
+    }
+}
+
+impl<'a, T> TryFrom<&'a Json> for Vec<T>
+where
+    T: TryFrom<&'a Json, Error = JsonConvertError>,
+{
+    type Error = JsonConvertError;
+    fn try_from(json: &'a Json) -> Result<Self, Self::Error> {
+        json.as_array()
+            .ok_or_else(|| JsonConvertError::new("Vec", json))?
+            .iter()
+            .map(T::try_from)
+            .collect()
+    }
+}
+
+macro_rules! try_from_owned {
+    ($($t:ty),*) => {$(
+        impl TryFrom<Json> for $t {
+            type Error = JsonConvertError;
+            fn try_from(json: Json) -> Result<Self, Self::Error> {
+                (&json).try_into()
+            }
+        }
+    )*};
+}
+
+try_from_owned!(String, f64, i64, bool);
+
+impl<T> TryFrom<Json> for Vec<T>
+where
+    T: TryFrom<Json, Error = JsonConvertError>,
+{
+    type Error = JsonConvertError;
+    fn try_from(mut json: Json) -> Result<Self, Self::Error> {
+        match &mut json {
+            Json::Array(array) => core::mem::take(array)
+                .into_iter()
+                .map(T::try_from)
+                .collect(),
+            _ => Err(JsonConvertError::new("Vec", &json)),
+        }
+    }
+}
+
+/// nested values are dropped from a heap stack (instead of recursively), so
+/// that dropping deeply nested documents can't overflow the call stack.
+impl Drop for Json {
+    fn drop(&mut self) {
+        let mut stack = match self {
+            Self::Array(array) => core::mem::take(array),
+            Self::Object(hashmap) => {
+                hashmap.drain().map(|(_, json)| json).collect()
+            }
+            _ => return,
+        };
+        while let Some(mut json) = stack.pop() {
+            match &mut json {
+                Self::Array(array) => stack.append(array),
+                Self::Object(hashmap) => {
+                    stack.extend(hashmap.drain().map(|(_, json)| json))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// array/object being written (with an explicit stack of frames, so nesting
+/// depth isn't bound by the call stack).
+enum Frame<'j> {
+    Array(core::slice::Iter<'j, Json>),
+    Object(hash_map::Iter<'j, JsonString, Json>),
 }
 
 impl fmt::Display for Json {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::Null => write!(f, "null"),
-            Self::Boolean(boolean) => write!(f, "{}", boolean),
-            Self::Number(float) => write!(f, "{}", float),
-            Self::QString(string) => write!(f, "\"{}\"", string),
-            Self::Array(array) => write!(f, "{:?}", array),
-            Self::Object(hashmap) => write!(f, "{:?}", hashmap),
+        let mut frames: Vec<(Frame, bool)> = vec![];
+        let mut next = Some(self);
+        loop {
+            match next.take() {
+                Some(Self::Null) => write!(f, "null")?,
+                Some(Self::Boolean(boolean)) => write!(f, "{}", boolean)?,
+                Some(Self::Number(float)) => write!(f, "{}", float)?,
+                Some(Self::QString(string)) => write!(f, "\"{}\"", string)?,
+                Some(Self::Array(array)) => {
+                    write!(f, "[")?;
+                    frames.push((Frame::Array(array.iter()), true));
+                }
+                Some(Self::Object(hashmap)) => {
+                    write!(f, "{{")?;
+                    frames.push((Frame::Object(hashmap.iter()), true));
+                }
+                None => {}
+            }
+            let (frame, first) = match frames.last_mut() {
+                Some(frame) => frame,
+                None => return Ok(()),
+            };
+            let separator = if *first { "" } else { ", " };
+            *first = false;
+            match frame {
+                Frame::Array(array) => match array.next() {
+                    Some(json) => {
+                        write!(f, "{}", separator)?;
+                        next = Some(json);
+                    }
+                    None => {
+                        write!(f, "]")?;
+                        frames.pop();
+                    }
+                },
+                // keys are written as is (escaped, same as the strings).
+                Frame::Object(hashmap) => match hashmap.next() {
+                    Some((key, value)) => {
+                        write!(f, "{}\"{}\": ", separator, key)?;
+                        next = Some(value);
+                    }
+                    None => {
+                        write!(f, "}}")?;
+                        frames.pop();
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// cloned with an explicit stack of frames (instead of recursively), so that
+/// cloning deeply nested documents can't overflow the call stack.
+impl Clone for Json {
+    fn clone(&self) -> Self {
+        // frames with the array/object cloned so far (and the key of the
+        // object value being cloned).
+        let mut frames: Vec<(Frame, Json, Option<&JsonString>)> = vec![];
+        let mut next = self;
+        loop {
+            let mut value = match next {
+                Self::Null => Some(Self::Null),
+                Self::Boolean(boolean) => Some(Self::Boolean(*boolean)),
+                Self::Number(number) => Some(Self::Number(*number)),
+                Self::QString(string) => Some(Self::QString(string.clone())),
+                Self::Array(array) => {
+                    let cloned = Vec::with_capacity(array.len());
+                    frames.push((
+                        Frame::Array(array.iter()),
+                        Self::Array(cloned),
+                        None,
+                    ));
+                    None
+                }
+                Self::Object(hashmap) => {
+                    let cloned = HashMap::with_capacity(hashmap.len());
+                    frames.push((
+                        Frame::Object(hashmap.iter()),
+                        Self::Object(Box::new(cloned)),
+                        None,
+                    ));
+                    None
+                }
+            };
+            // completed values are moved into their parent, until a parent
+            // has another value left to clone.
+            next = loop {
+                let (frame, cloned, key) = match frames.last_mut() {
+                    Some(frame) => frame,
+                    // `self` is complete, once there are no frames left.
+                    None => return value.unwrap_or(Self::Null),
+                };
+                match (cloned, *key, value.take()) {
+                    (Self::Array(array), _, Some(value)) => array.push(value),
+                    (Self::Object(hashmap), Some(key), Some(value)) => {
+                        hashmap.insert(key.clone(), value);
+                    }
+                    _ => {}
+                }
+                match frame {
+                    Frame::Array(array) => {
+                        if let Some(json) = array.next() {
+                            break json;
+                        }
+                    }
+                    Frame::Object(hashmap) => {
+                        if let Some((next_key, json)) = hashmap.next() {
+                            *key = Some(next_key);
+                            break json;
+                        }
+                    }
+                }
+                value = frames.pop().map(|(_, cloned, _)| cloned);
+            };
         }
     }
 }
@@ -158,3 +1756,141 @@ impl fmt::Debug for Json {
         fmt::Display::fmt(self, f)
     }
 }
+
+/// string contents, unescaped only if required.
+fn unescaped(s: &str) -> Cow<'_, str> {
+    if s.contains('\\') {
+        Cow::Owned(unescape(s))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+/// strings (and object keys) are compared by their unescaped contents.
+fn cmp_strings(a: &str, b: &str) -> Ordering {
+    unescaped(a).cmp(&unescaped(b))
+}
+
+/// `NaN` (not produced by the parser) is equal to itself, and less than
+/// any other number.
+fn cmp_numbers(a: f32, b: f32) -> Ordering {
+    a.partial_cmp(&b)
+        .unwrap_or_else(|| b.is_nan().cmp(&a.is_nan()))
+}
+
+impl Json {
+    /// rank of the variant, in the cross type ordering.
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Null => 0,
+            Self::Boolean(false) => 1,
+            Self::Boolean(true) => 2,
+            Self::Number(_) => 3,
+            Self::QString(_) => 4,
+            Self::Array(_) => 5,
+            Self::Object(_) => 6,
+        }
+    }
+
+    /// object keys, in sorted order.
+    fn sorted_keys(hashmap: &HashMap<JsonString, Json>) -> Vec<&JsonString> {
+        let mut keys: Vec<&JsonString> = hashmap.keys().collect();
+        keys.sort_by(|a, b| cmp_strings(a, b));
+        keys
+    }
+}
+
+/// Values of different types are ordered by type
+/// (`null < false < true < numbers < strings < arrays < objects`), values of
+/// the same type are ordered as follows:
+/// - numbers: numerically.
+/// - strings: by unicode codepoints (of the unescaped contents).
+/// - arrays: lexicographically (element by element).
+/// - objects: by their sorted keys first (as arrays of strings), then by the
+///   values, key by key (in sorted key order).
+impl Ord for Json {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Number(a), Self::Number(b)) => cmp_numbers(*a, *b),
+            (Self::QString(a), Self::QString(b)) => cmp_strings(a, b),
+            (Self::Array(a), Self::Array(b)) => a.iter().cmp(b.iter()),
+            (Self::Object(a), Self::Object(b)) => {
+                let (a_keys, b_keys) =
+                    (Self::sorted_keys(a), Self::sorted_keys(b));
+                a_keys
+                    .iter()
+                    .map(|key| unescaped(key))
+                    .cmp(b_keys.iter().map(|key| unescaped(key)))
+                    .then_with(|| {
+                        a_keys
+                            .iter()
+                            .zip(b_keys.iter())
+                            .map(|(a_key, b_key)| a[*a_key].cmp(&b[*b_key]))
+                            .find(|ordering| *ordering != Ordering::Equal)
+                            .unwrap_or(Ordering::Equal)
+                    })
+            }
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for Json {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// consistent with [`Ord`](#impl-Ord-for-Json) (`0 == -0`, and strings are
+/// compared unescaped, `"\u0041" == "A"`).
+impl PartialEq for Json {
+    fn eq(&self, other: &Self) -> bool {
+        let escaped = |s: &JsonString| s.contains('\\');
+        match (self, other) {
+            (Self::QString(a), Self::QString(b)) => {
+                a == b
+                    || (escaped(a) || escaped(b)) && cmp_strings(a, b).is_eq()
+            }
+            (Self::Array(a), Self::Array(b)) => a == b,
+            // fast path (by key lookup), unless keys need unescaping.
+            (Self::Object(a), Self::Object(b)) => {
+                a.len() == b.len()
+                    && (a.iter().all(|(key, value)| b.get(key) == Some(value))
+                        || a.keys().chain(b.keys()).any(escaped)
+                            && self.cmp(other).is_eq())
+            }
+            _ => self.cmp(other).is_eq(),
+        }
+    }
+}
+
+impl Eq for Json {}
+
+/// consistent with [`Eq`](#impl-PartialEq-for-Json): numbers are hashed
+/// canonically (`0` and `-0`), strings and keys are hashed unescaped, and
+/// objects are hashed in sorted key order.
+impl Hash for Json {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            Self::Null | Self::Boolean(_) => {}
+            Self::Number(float) => {
+                let canonical = match float {
+                    float if float.is_nan() => f32::NAN,
+                    float if *float == 0.0 => 0.0,
+                    float => *float,
+                };
+                canonical.to_bits().hash(state)
+            }
+            Self::QString(string) => unescaped(string).hash(state),
+            Self::Array(array) => array.hash(state),
+            Self::Object(hashmap) => {
+                hashmap.len().hash(state);
+                for key in Self::sorted_keys(hashmap) {
+                    unescaped(key).hash(state);
+                    hashmap[key].hash(state);
+                }
+            }
+        }
+    }
+}
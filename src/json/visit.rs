@@ -0,0 +1,60 @@
+//! Visitor for analyzing [`Json`](Json) values (statistics, schema inference
+//! etc) without matching on every variant.
+//! Default `visit_array`/`visit_object` visit the children (via
+//! [`walk_array`]/[`walk_object`]), overriding methods can call the same
+//! functions to keep descending.
+use super::{string::JsonString, token::Json, HashMap};
+
+pub trait JsonVisitor {
+    fn visit_null(&mut self) {}
+
+    fn visit_boolean(&mut self, _boolean: bool) {}
+
+    fn visit_number(&mut self, _number: f32) {}
+
+    /// raw (escaped) contents of the string.
+    fn visit_string(&mut self, _string: &str) {}
+
+    fn visit_array(&mut self, array: &[Json]) {
+        walk_array(self, array);
+    }
+
+    fn visit_object(&mut self, object: &HashMap<JsonString, Json>) {
+        walk_object(self, object);
+    }
+
+    /// called for every key of an object, before visiting its value.
+    fn visit_key(&mut self, _key: &str) {}
+}
+
+/// visit every element of `array`.
+pub fn walk_array<V: JsonVisitor + ?Sized>(visitor: &mut V, array: &[Json]) {
+    for element in array {
+        element.accept(visitor);
+    }
+}
+
+/// visit every key and value of `object`.
+pub fn walk_object<V: JsonVisitor + ?Sized>(
+    visitor: &mut V,
+    object: &HashMap<JsonString, Json>,
+) {
+    for (key, value) in object {
+        visitor.visit_key(key);
+        value.accept(visitor);
+    }
+}
+
+impl Json {
+    /// call the `visitor` method, corresponding to the variant of `self`.
+    pub fn accept<V: JsonVisitor + ?Sized>(&self, visitor: &mut V) {
+        match self {
+            Self::Null => visitor.visit_null(),
+            Self::Boolean(boolean) => visitor.visit_boolean(*boolean),
+            Self::Number(number) => visitor.visit_number(*number),
+            Self::QString(string) => visitor.visit_string(string),
+            Self::Array(array) => visitor.visit_array(array),
+            Self::Object(object) => visitor.visit_object(object),
+        }
+    }
+}
@@ -1,4 +1,7 @@
 //! Text parsing utility struct.
+use crate::prelude::*;
+use core::cell::OnceCell;
+
 pub type Stack = Vec<char>;
 pub type Cursor = usize;
 
@@ -20,10 +23,19 @@ impl Position {
     }
 }
 
+/// json whitespace: space, tab, line feed and carriage return only (RFC 8259).
+#[inline]
+pub fn is_whitespace(ch: char) -> bool {
+    matches!(ch, ' ' | '\n' | '\t' | '\r')
+}
+
 #[derive(Debug)]
 pub struct Lexer {
     pub stack: Stack,
     pub cursor: Cursor,
+    /// start (cursor) of every line, built on first use (only needed for
+    /// error reporting).
+    lines: OnceCell<Vec<Cursor>>,
 }
 
 impl Lexer {
@@ -31,9 +43,18 @@ impl Lexer {
         Self {
             stack: s.chars().collect(),
             cursor: 0,
+            lines: OnceCell::new(),
         }
     }
 
+    /// replace the input with `s` (reusing the allocated buffer).
+    pub fn reset(&mut self, s: &str) {
+        self.stack.clear();
+        self.stack.extend(s.chars());
+        self.cursor = 0;
+        self.lines.take();
+    }
+
     #[inline]
     pub fn peek(&self) -> Option<&char> {
         self.peek_at(self.cursor)
@@ -44,15 +65,51 @@ impl Lexer {
         self.stack.get(cursor)
     }
 
-    #[rustfmt::skip]
+    /// consume chars while `f` holds, returns the consumed slice of the
+    /// input (no allocation, collect if an owned string is needed).
+    #[inline]
+    pub fn consume_while<F: FnMut(&char) -> bool>(&mut self, f: F) -> &[char] {
+        let start = self.cursor;
+        self.skip_while(f);
+        &self.stack[start..self.cursor]
+    }
+
+    /// same as [`consume_while`](Self::consume_while), returns the number of
+    /// chars skipped instead.
     #[inline]
-    pub fn consume_while<F: FnMut(&char) -> bool>(&mut self, mut f: F) -> String {
-        let string: String = self.stack[self.cursor..]
-            .iter()
-            .take_while(|&ch| (f)(ch))
-            .collect();
-        self.cursor += string.chars().count();
-        string
+    pub fn skip_while<F: FnMut(&char) -> bool>(&mut self, mut f: F) -> usize {
+        let rest = &self.stack[self.cursor..];
+        let skipped = rest.iter().position(|ch| !f(ch)).unwrap_or(rest.len());
+        self.cursor += skipped;
+        skipped
+    }
+
+    /// skip json whitespace (see [`is_whitespace`]).
+    #[inline]
+    pub fn skip_whitespace(&mut self) -> usize {
+        let start = self.cursor;
+        while let Some(' ' | '\n' | '\t' | '\r') = self.stack.get(self.cursor) {
+            self.cursor += 1;
+        }
+        self.cursor - start
+    }
+
+    /// consume string body, up to the first unescaped `quote` (not
+    /// consumed). runs of plain chars are skipped in one scan, stopping only
+    /// at `quote` and `\\`.
+    pub fn consume_quoted(&mut self, quote: char) -> String {
+        let start = self.cursor;
+        loop {
+            self.skip_while(|&ch| ch != quote && ch != '\\');
+            match self.peek() {
+                // escaped char (possibly `quote`) is skipped along with `\\`.
+                Some('\\') => {
+                    self.cursor = (self.cursor + 2).min(self.stack.len())
+                }
+                _ => break,
+            }
+        }
+        self.stack[start..self.cursor].iter().collect()
     }
 
     #[inline]
@@ -71,10 +128,9 @@ impl Lexer {
         let mut cs = ys.chars();
         let mut next_index: usize = self.cursor;
         while let Some(c) = cs.next() {
-            if let Some(&x) = self.stack.get(next_index) {
-                if c != x {
-                    return None;
-                }
+            // end of input doesn't match (`.key` is not `.keys()`).
+            if self.stack.get(next_index) != Some(&c) {
+                return None;
             }
             next_index += 1;
         }
@@ -84,7 +140,14 @@ impl Lexer {
 
     #[inline]
     pub fn consume_uint(&mut self) -> Option<u32> {
-        self.consume_while(|&ch| ch.is_digit(10)).parse().ok()
+        // same as `str::parse` (`None` for no digits or on overflow).
+        let digits = self.consume_while(|&ch| ch.is_ascii_digit());
+        if digits.is_empty() {
+            return None;
+        }
+        digits.iter().try_fold(0u32, |number, ch| {
+            number.checked_mul(10)?.checked_add(ch.to_digit(10)?)
+        })
     }
 
     #[inline]
@@ -99,12 +162,56 @@ impl Lexer {
     }
 
     #[inline]
-    pub fn position(&self, cursor: Cursor) -> Position {
-        let string: String = self.stack.iter().take(cursor).collect();
+    fn line_starts(&self) -> &[Cursor] {
+        self.lines.get_or_init(|| {
+            core::iter::once(0)
+                .chain(self.stack.iter().enumerate().filter_map(
+                    |(cursor, &ch)| (ch == '\n').then_some(cursor + 1),
+                ))
+                .collect()
+        })
+    }
+
+    /// chars of the line from `start`, up to the line break at `end`
+    /// (without `\r`).
+    fn line_at(&self, start: Cursor, end: Cursor) -> &[char] {
+        let line = &self.stack[start..end];
+        line.strip_suffix(&['\r']).unwrap_or(line)
+    }
+
+    /// text of line `row` (starting at 1, `0` is same as `1`).
+    pub fn line(&self, row: usize) -> String {
+        let starts = self.line_starts();
+        match starts.get(row.saturating_sub(1)) {
+            Some(&start) => match starts.get(row.max(1)) {
+                Some(&next) => self.line_at(start, next - 1).iter().collect(),
+                // last line (`\r` is kept, without a line break).
+                None => self.stack[start..].iter().collect(),
+            },
+            None => String::new(),
+        }
+    }
 
+    /// row and column (in bytes) of `cursor`, same as counting the
+    /// `.lines()` of the text before `cursor` (binary search over the line
+    /// starts).
+    #[inline]
+    pub fn position(&self, cursor: Cursor) -> Position {
+        let cursor = cursor.min(self.stack.len());
+        if cursor == 0 {
+            return Position { row: 0, col: 0 };
+        }
+        let starts = self.line_starts();
+        // number of line breaks before `cursor`.
+        let breaks = starts.partition_point(|&start| start <= cursor) - 1;
+        let (row, line) = if self.stack[cursor - 1] == '\n' {
+            (breaks, self.line_at(starts[breaks - 1], cursor - 1))
+        } else {
+            (breaks + 1, &self.stack[starts[breaks]..cursor])
+        };
         Position {
-            row: string.lines().count(),
-            col: string.lines().last().unwrap_or("").len(),
+            row,
+            col: line.iter().map(|ch| ch.len_utf8()).sum(),
         }
     }
 }
@@ -24,9 +24,22 @@
 //! ```
 //! # USAGE
 //! ```txt
-//! USAGE: ruson [FLAGS|OPTIONS]... FILE
+//! USAGE: ruson [FLAGS|OPTIONS]... [FILE]...
 //! Extract sub tree from valid 'json' text.
-//! Use standard input, if FILE not provided.
+//! Use standard input, if FILE not provided (multiple FILEs allowed).
+//! FILE can also be an 'http://' or 'https://' url.
+//!
+//! ARGUMENTS:
+//!   [FILE]...
+//!                 Path or 'http(s)://' url of 'json' text (default: stdin).
+//!
+//! COMMANDS:
+//!   validate
+//!                 Check that every FILE is valid 'json' text.
+//!   stats
+//!                 Aggregate values selected from records (array or ndjson).
+//!   hash
+//!                 Print SHA-256 digest of canonical (RFC 8785) 'json'.
 //!
 //! FLAGS:
 //!   -h, --help
@@ -37,52 +50,267 @@
 //!                 Print pretty formatted 'json'.
 //!   -t, --table
 //!                 Print table formatted 'json'.
+//!   -w, --watch
+//!                 Re-run query whenever FILE changes (polling mtime).
+//!   -s, --stream
+//!                 Process elements of the top-level array one at a time.
+//!                 (memory usage stays flat regardless of input size).
+//!   --ndjson
+//!                 Process newline delimited 'json' (one record per line).
+//!                 Records are processed in parallel (see '--jobs').
+//!   --seq
+//!                 Print results as json text sequences (RFC 7464).
+//!   --unbuffered
+//!                 Flush output after every result (for live pipelines,
+//!                 example: 'tail -f log.jsonl | ruson --ndjson --unbuffered').
+//!   --timing
+//!                 Print timing, node count and output size to stderr.
+//!   --stats
+//!                 Print metrics of the input to stderr (depth, node counts
+//!                 by type, string bytes and approximate memory usage).
+//!   --verbatim
+//!                 Print values selected by path queries as in the input
+//!                 (byte for byte, '--pretty', '--color' etc. are ignored).
+//!   --lazy
+//!                 Evaluate path queries while scanning the input, the rest
+//!                 of the input is not validated (ignored with '--max-depth',
+//!                 '--max-memory', '--timing' and '--stats').
+//!   --no-map-cache
+//!                 Evaluate '.map()' on every element (identical elements
+//!                 are evaluated once by default).
+//!   --jq
+//!                 Read queries in 'jq' syntax ('.', '.[]', '|', 'length').
+//!   -Q, --quiet
+//!                 Suppress error messages (exit status is kept).
 //!
 //! OPTIONS:
-//!   -q, --query <query>
+//!   -q, --query <query>...
 //!                 Query for extracting desired 'json' subtree.
+//!                 ('.', same as the default, is the whole document).
+//!                 If repeated, each query is applied on the previous result.
+//!   -C, --color <color>
+//!                 Colorize output: auto|always|never (default: auto).
+//!                 'auto' disables color if NO_COLOR is set or not a tty.
+//!   --template <template>
+//!                 Print text with '{query}' placeholders replaced by the
+//!                 values selected from the result ('{{', '}}' for braces).
+//!   --join <separator>
+//!                 Print array of strings (or scalars) joined by 'separator'
+//!                 (json escapes allowed, example: '\n'), strings unquoted.
+//!   -j, --jobs <jobs>
+//!                 Process multiple FILEs across 'jobs' threads.
+//!                 Output order follows the order of FILEs.
+//!                 (a single large top-level array is parsed in parallel).
+//!   --max-depth <depth>
+//!                 Maximum nesting depth of arrays/objects in input.
+//!   --max-size <bytes>
+//!                 Maximum size of input json text (in bytes).
+//!   --max-memory <memory>
+//!                 Approximate memory budget for parsing and queries.
+//!                 (in bytes, excluding the input text).
+//!   --missing <missing>
+//!                 Missing keys, out of range indices and type mismatches in
+//!                 queries: error|null (default: error).
+//!   --default <default>
+//!                 Json value printed instead of a 'null' result, or if the
+//!                 query fails on a missing key or index.
+//!   --limit <limit>
+//!                 Print at most 'limit' elements of an array result (or
+//!                 results, with '--stream' or '--ndjson').
+//!   --offset <offset>
+//!                 Skip the first 'offset' elements of an array result (or
+//!                 records, skipped ones are not parsed).
 //! ```
 //!
 //! # Query Syntax.
 //! ```sh
-//! # Dot notation.
+//! # Identity, the whole document (same as an empty query).
+//! echo '{ "prop": "value" }' | ruson --query '.' # {"prop": "value"}
+//!
+//! # Dot notation (keys of letters, digits, '_', '-' and '$', see bracket notation).
 //! echo '{ "prop": "value" }' | ruson --query '.prop' # "value"
 //!
 //! # Bracket notation.
 //! echo '{ "prop": "value" }' | ruson --query '["prop"]' # "value"
 //!
+//! # Bracket keys are json strings (any key, with escapes).
+//! echo '{ "say \"hi\"": 1 }' | ruson --query '["say \"hi\""]' # 1
+//!
+//! # Query variables, '$name' in brackets is the string set by '--arg name value'.
+//! echo '{ "users": { "alice": 1 } }' | ruson --arg user alice -q '.users[$user]' # 1
+//!
+//! # '--argjson name value' sets a json value (strings are keys, integers are indices, any value in comparisons).
+//! echo '{ "ages": [20, 40] }' | ruson --argjson min 30 -q '.ages.any(. > $min)' # true
+//!
 //! # Array indexing.
 //! echo '{ "prop": [1, 2, 3, 4, 5] }' | ruson --query '.prop[2]' # 3
 //!
+//! # Negative indices count from the end.
+//! echo '{ "prop": [1, 2, 3, 4, 5] }' | ruson --query '.prop[-1]' # 5
+//!
+//! # '.first()' and '.last()' functions (valid for non-empty 'array').
+//! echo '{ "prop": [1, 2, 3, 4, 5] }' | ruson -q '.prop.last()' # 5
+//!
+//! # Array slicing, from start up to (not including) end (bounds are optional).
+//! echo '{ "prop": [1, 2, 3, 4, 5] }' | ruson --query '.prop[1:3]' # [2, 3]
+//!
 //! # '.keys()' function (valid for 'object').
 //! echo '{ "one": 1, "two": 2, "three": 3 }' | ruson -q '.keys()' # ["one", "two", "three"]
 //!
 //! # '.values()' function (valid for 'object').
 //! echo '{ "one": 1, "two": 2, "three": 3 }' | ruson -q '.values()' # [1, 2, 3]
 //!
-//! # '.length()' function (valid for 'array' and 'string').
+//! # '.length()' function (valid for 'array', 'string', 'object' and 'null').
 //! echo '[1, 2, 3]' | ruson -q '.length()' # 3
 //!
+//! # '.bytelength()' function (valid for 'string', utf-8 bytes).
+//! echo '"h\u00e9llo"' | ruson -q '.bytelength()' # 6
+//!
+//! # '.type()' function, name of the value's type (same as 'jq').
+//! echo '{ "a": 1 }' | ruson -q '.a.type()' # "number"
+//!
+//! # '.paths()' function, paths of the leaves (scalars, empty arrays and objects).
+//! echo '{ "a": { "b": [1, 2] } }' | ruson -q '.paths()' # [".a.b[0]", ".a.b[1]"]
+//!
+//! # '.flatten()' function (valid for 'array'), nested arrays up to a depth (default: 1).
+//! echo '{ "matrix": [[1, [2]], [3]] }' | ruson -q '.matrix.flatten()' # [1, [2], 3]
+//!
+//! # '.sum()', '.min()', '.max()' and '.avg()' functions (valid for 'array' of numbers).
+//! echo '{ "prices": [1.5, 2, 4.5] }' | ruson -q '.prices.sum()' # 8
+//!
+//! # '.has()' function, membership of an object key (or array index).
+//! echo '{ "a": 1 }' | ruson -q '.has("a")' # true
+//!
+//! # '.test()' function (valid for 'string'), string contains a match of the regex.
+//! echo '{ "emails": ["a@example.com", "b@test.org"] }' | ruson -q '.emails.map(.test("@example\\.com$"))' # [true, false]
+//!
+//! # '.match()' function (valid for 'string'), first match of the regex with its groups (same as 'jq').
+//! echo '"id: 42"' | ruson -q '.match("(\\w+): (\\d+)").captures.map(.string)' # ["id", "42"]
+//!
+//! # Object projection, only the listed keys (missing keys are 'null').
+//! echo '{ "id": 1, "name": "x", "email": "y" }' | ruson -q '.{id, email}' # {"id": 1, "email": "y"}
+//!
+//! # '.omit()' function (valid for 'object'), without the listed keys.
+//! echo '{ "id": 1, "token": "x" }' | ruson -q '.omit("token")' # {"id": 1}
+//!
+//! # '.del()' function, without an object key (or array index).
+//! echo '{ "list": [1, 2, 3] }' | ruson -q '.list.del(1)' # [1, 3]
+//!
+//! # '.find()' function, values of a key anywhere in the document ('*' matches any chars).
+//! echo '{ "a": { "id": 1 }, "b": [{ "id": 2 }] }' | ruson -q '.find("id")' # [1, 2]
+//!
+//! # '.findpaths()' function, same as '.find()' along with the paths.
+//! echo '{ "a": { "id": 1 } }' | ruson -q '.findpaths("id")' # [{"path": ".a.id", "value": 1}]
+//!
 //! # '.map()' function (valid for 'array').
 //! echo '{ "list": [{ "id": 1 }, { "id": 2 }, { "id": 3 }] }' | ruson -q'.list.map(.id)' # [1, 2, 3]
+//!
+//! # '.group_by()' function (valid for 'array'), groups ordered by the query result.
+//! echo '[{ "u": "b" }, { "u": "a" }, { "u": "b" }]' | ruson -q '.group_by(.u)' # [[{"u": "a"}], [{"u": "b"}, {"u": "b"}]]
+//!
+//! # '.any()' and '.all()' functions (valid for 'array'), predicate of a query compared with a json literal ('==', '!=', '<', '<=', '>', '>='), or truthy without one.
+//! echo '{ "nums": [1, 2, 3] }' | ruson -q '.nums.all(. > 0)' # true
+//!
+//! # Iteration, rest of the query applied on every element (or object value).
+//! echo '{ "list": [{ "tags": ["a"] }, { "tags": ["b", "c"] }] }' | ruson -q'.list[].tags[]' # ["a", "b", "c"]
+//!
+//! # Pipes, each stage is applied on the result of the previous one.
+//! echo '{ "list": [1, 2, 3] }' | ruson -q'.list | .length()' # 3
+//!
+//! # 'jq' syntax (with '--jq'), '[]' collects the results in an array.
+//! echo '{ "list": [{ "id": 1 }, { "id": 2 }] }' | ruson --jq -q '.list[] | .id' # [1, 2]
+//!
+//! # Optional access ('?'), 'null' instead of an error for a missing key or index (rest of the stage is skipped).
+//! echo '{ "a": 1 }' | ruson -q '.maybe?.value' # null
+//!
+//! # Missing values as 'null' (with '--missing=null').
+//! echo '{ "list": [{ "id": 1 }, {}] }' | ruson --missing=null -q '.list.map(.id)' # [1, null]
+//!
+//! # Fallback value for a missing (or 'null') result (with '--default').
+//! echo '{ "name": "x" }' | ruson -q '.version' --default '"0.0.0"' # "0.0.0"
+//!
+//! # Second page of results, 2 per page (with '--offset' and '--limit').
+//! echo '{ "list": [1, 2, 3, 4, 5] }' | ruson -q '.list' --offset 2 --limit 2 # [3, 4]
+//!
+//! # Array of strings joined by a separator (with '--join').
+//! echo '{ "names": ["a", "b", "c"] }' | ruson -q '.names' --join ' ' # a b c
+//!
+//! # Text report, placeholders are queries on the result (with '--template').
+//! echo '{ "name": "ruson", "stars": 5 }' | ruson --template 'Name: {.name}, Stars: {.stars}' # Name: ruson, Stars: 5
 //! ```
 //!
 //! # EXAMPLES
 //! Download latest _**xkcd**_ comic
 //! ```sh
-//! curl https://xkcd.com/info.0.json 2>/dev/null | ruson -q ".img" | xargs wget
+//! ruson -q ".img" https://xkcd.com/info.0.json | xargs wget
 //! ```
 //! Pokemon attack names.
 //! ```sh
-//! curl https://pokeapi.co/api/v2/pokemon/pikachu 2>/dev/null | ruson -q ".moves[0].move.name"
+//! ruson -q ".moves[0].move.name" https://pokeapi.co/api/v2/pokemon/pikachu
+//! ```
+//! Status code counts of an access log (newline delimited json).
+//! ```sh
+//! ruson stats -q ".status_code" access.jsonl
+//! ```
+//! Compare documents regardless of key order and formatting.
+//! ```sh
+//! ruson hash old.json new.json
 //! ```
 //!
+//! # EXIT STATUS
+//! - `0`: success.
+//! - `1`: internal error.
+//! - `2`: usage error (invalid flags, options or query syntax).
+//! - `3`: parse error (invalid 'json' text or limits exceeded).
+//! - `4`: query error (query cannot be applied on the input).
+//! - `5`: I/O error (FILE, url or stdin cannot be read).
+//!
+//! For multiple inputs, the status of the first failure is used.
+//!
 //! # LICENCE
 //! [GPLv3](https://www.gnu.org/licenses/gpl-3.0.en.html)
+// the 'wasm' bindings link 'std' (allocator and panic handler).
+#![cfg_attr(not(any(feature = "std", feature = "wasm", test)), no_std)]
+extern crate alloc;
+
+#[cfg(feature = "cli")]
 pub mod cli;
 pub mod error;
+#[cfg(feature = "cli")]
+pub mod http;
+#[cfg(any(feature = "core", feature = "alloc"))]
 pub mod json;
+#[cfg(any(feature = "core", feature = "alloc"))]
 pub mod lexer;
+#[cfg(feature = "cli")]
+pub mod pool;
+pub mod regex;
+pub mod sha256;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 #[cfg(test)]
 mod tests;
+
+/// value selected by `query` from json text `json` (example:
+/// `ruson::extract(r#"{"a": [1]}"#, ".a[0]")?`).
+#[cfg(any(feature = "core", feature = "alloc"))]
+pub fn extract(
+    json: &str,
+    query: &str,
+) -> Result<json::token::Json, json::error::RusonError> {
+    json.parse::<json::token::Json>()?.query(query)
+}
+
+/// `alloc` counterparts of the `std` prelude (for `no_std` builds).
+#[allow(unused_imports)]
+mod prelude {
+    pub(crate) use alloc::{
+        borrow::ToOwned,
+        boxed::Box,
+        format,
+        string::{String, ToString},
+        vec,
+        vec::Vec,
+    };
+}
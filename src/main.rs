@@ -1,75 +1,995 @@
 use ruson::{
-    cli::{Cli, CliFlag, CliOption},
-    error::RusonResult,
+    cli::{
+        Cli, CliArg, CliArity, CliCommand, CliExample, CliFlag, CliOption,
+        CliSection, CliValue,
+    },
+    error::{self, exit_code, ErrorString, RusonResult},
+    http,
     json::{
-        formatter::{Formatter, PrettyJson, RawJson, TableJson},
-        parser::JsonParser,
-        query::JsonQuery,
-        token::Json,
+        error::{JsonEvalError, RusonError},
+        formatter::{
+            CanonicalJson, Formatter, JoinedJson, PrettyJson, RawJson,
+            TableJson,
+        },
+        jq, memo, parallel,
+        parser::{JsonLimits, JsonParser},
+        query::{ApplyOptions, JsonQuery, Missing, Page, Variables},
+        scan,
+        stats::Stats,
+        stream::ArrayStream,
+        template::Template,
+        token::{unescape, Json, Metrics},
     },
+    pool, sha256,
 };
 use std::{
+    cell::RefCell,
     collections::HashMap,
-    io::{self, Read},
+    io::{self, BufRead, BufReader, BufWriter, IsTerminal, Read, Write},
+    time::{Duration, Instant},
 };
 
 pub const NAME: &'static str = env!("CARGO_PKG_NAME");
 pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+pub const AUTHORS: &'static str = env!("CARGO_PKG_AUTHORS");
+/// interval for polling the FILE modification time, in watch mode.
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// error message, along with the exit code of its class of failure.
+type Failure = (i32, String);
+
+/// library error, with the exit code of its class (see 'EXIT STATUS').
+fn failure<E: Into<RusonError>>(err: E) -> Failure {
+    let err = err.into();
+    let code = match err {
+        RusonError::Parse(_) => exit_code::PARSE,
+        RusonError::Query(_) => exit_code::USAGE,
+        RusonError::Eval(_) => exit_code::QUERY,
+        RusonError::Io(_) => exit_code::IO,
+        RusonError::Convert(_) | RusonError::Patch(_) => exit_code::FAILURE,
+    };
+    (code, err.to_string())
+}
+
+/// diagnostics, printed to stderr for every input (with '--timing', and
+/// '--stats' for the metrics).
+#[derive(Debug, Default)]
+struct Timing {
+    parse: Duration,
+    query: Duration,
+    format: Duration,
+    /// metrics of the parsed input.
+    metrics: Option<Metrics>,
+    output_bytes: usize,
+}
+
+impl std::fmt::Display for Timing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            " parse: {:?}, query: {:?}, format: {:?}, nodes: {}, output: {} bytes",
+            self.parse,
+            self.query,
+            self.format,
+            self.metrics.map_or(0, |metrics| metrics.nodes()),
+            self.output_bytes
+        )
+    }
+}
 
 fn main() -> Result<(), String> {
     let rusoncli = create_cli(NAME);
 
-    let mut args = std::env::args().skip(1);
+    let mut args = std::env::args().skip(1).peekable();
+    // subcommands are only recognized as the first argument.
+    if let Some(command) = args.peek().and_then(|arg| rusoncli.command(arg)) {
+        args.next();
+        run_command(command, args);
+    }
     let mut cliflags: Vec<String> = Vec::new();
-    let mut clioptions: HashMap<&str, String> = HashMap::new();
-    let json_filepath = rusoncli
-        .parse_and_populate(&mut args, &mut cliflags, &mut clioptions)
-        .unwrap_or_exit_with(2);
+    let mut clioptions: HashMap<&str, Vec<String>> = HashMap::new();
+    // flags may follow the FILEs (FILEs that look like flags follow '--').
+    let json_filepaths = rusoncli
+        .parse_all(&mut args, &mut cliflags, &mut clioptions)
+        .unwrap_or_exit_with(exit_code::USAGE);
+    // quiet mode is applied, as early as possible.
+    error::set_quiet(cliflags.iter().any(|flag| flag == "-Q"));
+    // value of the last occurrence of the option.
+    let option = |name| clioptions.get(name).and_then(|values| values.last());
+    let mut clipositionals: HashMap<&str, Vec<String>> = HashMap::new();
+    rusoncli
+        .populate_positionals(json_filepaths.into_iter(), &mut clipositionals)
+        .unwrap_or_exit_with(exit_code::USAGE);
+    let json_filepaths = clipositionals.remove("FILE").unwrap_or_default();
 
-    let mut json_formatter: Box<dyn Formatter<Token = Json>> =
-        Box::new(RawJson {});
+    // 'NO_COLOR' (https://no-color.org) is only honored in 'auto' mode.
+    let color = match option("color").map(String::as_str) {
+        Some("always") => true,
+        Some("never") => false,
+        _ => {
+            io::stdout().is_terminal()
+                && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+        }
+    };
 
+    let mut json_formatter: Box<dyn Formatter<Token = Json> + Sync> =
+        Box::new(RawJson { color });
+
+    let (mut watch, mut stream, mut seq) = (false, false, false);
+    let mut ndjson = false;
+    let (mut timing, mut jq_dialect) = (false, false);
+    let (mut verbatim, mut unbuffered, mut metrics) = (false, false, false);
+    let mut lazy = false;
     for flag in cliflags.iter() {
         match flag.as_str() {
-            "-p" => json_formatter = Box::new(PrettyJson { indent: "  " }),
-            "-t" => json_formatter = Box::new(TableJson {}),
-            "-v" => Err(format!(" {}", VERSION)).unwrap_or_exit_with(0),
+            "-p" => {
+                json_formatter = Box::new(PrettyJson {
+                    indent: "  ",
+                    color,
+                })
+            }
+            "-t" => json_formatter = Box::new(TableJson { color }),
+            "-w" => watch = true,
+            "-s" => stream = true,
+            "--seq" => seq = true,
+            "--ndjson" => ndjson = true,
+            "--timing" => timing = true,
+            "--verbatim" => verbatim = true,
+            "--lazy" => lazy = true,
+            "--unbuffered" => unbuffered = true,
+            "--stats" => metrics = true,
+            "--no-map-cache" => memo::set_enabled(false),
+            "--jq" => jq_dialect = true,
+            "-v" => Err(format!(" {}", VERSION))
+                .unwrap_or_exit_with(exit_code::SUCCESS),
             "-h" => {
-                println!("{}", rusoncli);
+                write_line(&mut io::stdout(), &rusoncli.to_string());
+                std::process::exit(0);
+            }
+            "--dump-man" => {
+                write_line(
+                    &mut io::stdout(),
+                    &rusoncli.manpage(VERSION, AUTHORS).to_string(),
+                );
                 std::process::exit(0);
             }
             _ => continue,
         }
     }
 
-    // construct query.
-    let query_string = clioptions
+    let separator = option("separator");
+    if let Some(separator) = separator {
+        json_formatter = Box::new(JoinedJson {
+            separator: unescape(separator),
+            color,
+        });
+    }
+
+    // construct query (multiple queries are applied in sequence).
+    let query_strings = clioptions
         .get("query")
         .ok_or(format!(" internal error."))
         .unwrap_or_exit();
-    let json_query = JsonQuery::new(query_string).unwrap_or_exit_with(2);
+    // '--arg name value' pairs, stored as consecutive values ('--argjson'
+    // values are already validated as json).
+    let pairs = |name| {
+        clioptions
+            .get(name)
+            .into_iter()
+            .flat_map(|values| values.chunks(2))
+            .map(|pair| (pair[0].clone(), pair[1].as_str()))
+    };
+    let variables: Variables = pairs("arg")
+        .map(|(name, value)| (name, Json::from(value)))
+        .chain(pairs("argjson").filter_map(|(name, value)| {
+            value.parse().ok().map(|value| (name, value))
+        }))
+        .collect();
+    let json_query = if jq_dialect {
+        // piped, so that '[]' in a query also maps over the following ones.
+        jq::translate_with_variables(&query_strings.join(" | "), &variables)
+    } else {
+        query_strings
+            .iter()
+            .map(|query_string| {
+                JsonQuery::with_variables(query_string, &variables)
+            })
+            .try_fold(JsonQuery(vec![]), |query, next| {
+                next.map(|n| query.then(n))
+            })
+    }
+    .unwrap_or_exit_with(exit_code::USAGE);
+
+    // option values are already validated by the cli parser.
+    let jobs = option("jobs")
+        .and_then(|jobs| jobs.parse().ok())
+        .unwrap_or(1);
+    let limit = |name| option(name).and_then(|value| value.parse().ok());
+    let limits = JsonLimits {
+        max_depth: limit("depth"),
+        max_size: limit("bytes"),
+        max_memory: limit("memory"),
+    };
+    let missing = match option("missing").map(String::as_str) {
+        Some("null") => Missing::Null,
+        _ => Missing::Error,
+    };
+    // already validated as json.
+    let default = option("default").and_then(|value| value.parse().ok());
+    let page = Page {
+        offset: limit("offset").unwrap_or(0),
+        limit: limit("limit"),
+    };
+    // with '--stream' or '--ndjson' the records are paged instead.
+    let settings = Settings {
+        limits,
+        missing,
+        default: default.as_ref(),
+        lazy,
+        page: if stream || ndjson {
+            Page::default()
+        } else {
+            page
+        },
+    };
+    let template = option("template")
+        .map(|template| template.parse::<Template>())
+        .transpose()
+        .unwrap_or_exit_with(exit_code::USAGE);
+    // placeholders are evaluated same as the query.
+    let dump = |json: &Json| match &template {
+        Some(template) => {
+            let options = ApplyOptions {
+                missing,
+                ..ApplyOptions::default()
+            };
+            template.render_with(json, options).map_err(failure)
+        }
+        None => Ok(json_formatter.dump(json)),
+    };
 
-    // read json string from file or stdin.
-    let json_string = if let Some(path) = json_filepath {
-        std::fs::read_to_string(&path)
-            .or_else(|err| Err(format!(" '{}' {}", path, err)))
+    if watch {
+        let path = json_filepaths
+            .first()
+            .ok_or(" '--watch' requires FILE.")
+            .unwrap_or_exit_with(exit_code::USAGE);
+        watch_file(path, &json_query, settings, &dump);
+    }
+
+    // read from stdin, if no FILE provided.
+    let inputs: Vec<Option<String>> = if json_filepaths.is_empty() {
+        // avoid appearing frozen, while waiting for input from terminal.
+        if io::stdin().is_terminal() {
+            Err::<(), _>(
+                " no FILE provided (and standard input is a terminal).",
+            )
+            .unwrap_or_exit_with(exit_code::USAGE);
+        }
+        vec![None]
     } else {
-        let mut buffer = String::new();
-        io::stdin()
-            .read_to_string(&mut buffer)
-            .and(Ok(buffer))
-            .or(Err(" cannot read from stdin.".into()))
-    }
-    .unwrap_or_exit();
-
-    // parse json string.
-    let json_token = JsonParser::new(&json_string)
-        .parse()
-        .unwrap_or_exit()
-        .apply(&json_query)
-        .unwrap_or_exit();
+        json_filepaths.into_iter().map(Some).collect()
+    };
+
+    // exit code of the first failure.
+    let mut failure = None;
+    let mut stdout = BufWriter::new(io::stdout().lock());
+    let mut print_result = |result: Result<String, Failure>| {
+        match result {
+            // RFC 7464: every record is prefixed with 'RS' (0x1E).
+            Ok(output) if seq => {
+                write_line(&mut stdout, &format!("\x1e{}", output))
+            }
+            Ok(output) => write_line(&mut stdout, &output),
+            Err((code, err)) => {
+                failure = failure.or(Some(code));
+                // keep the order of results and errors (on a terminal).
+                stdout
+                    .flush()
+                    .unwrap_or_else(|err| exit_on_write_error(err));
+                error::report(&err.errorfmt());
+            }
+        }
+        if unbuffered {
+            stdout
+                .flush()
+                .unwrap_or_else(|err| exit_on_write_error(err));
+        }
+    };
+    // a single document is parsed across 'jobs' threads instead.
+    let parse_jobs = match inputs.len() {
+        1 if !stream && !ndjson => jobs,
+        _ => 1,
+    };
+    let process = |json_string: Result<String, Failure>| {
+        let mut stats = Timing::default();
+        let result = json_string.and_then(|json_string| {
+            let verbatim = verbatim
+                && template.is_none()
+                && separator.is_none()
+                && settings.page.is_all();
+            let raw = || {
+                scan::select_raw(&json_string, &json_query)
+                    .filter(|raw| default.is_none() || *raw != "null")
+                    .map(str::to_string)
+            };
+            // the input is only scanned (see 'evaluate').
+            if verbatim && settings.scans(timing || metrics) {
+                if let Some(raw) = raw() {
+                    return Ok(raw);
+                }
+            }
+            let json_token = evaluate(
+                &json_string,
+                &json_query,
+                settings,
+                parse_jobs,
+                Some(&mut stats).filter(|_| timing || metrics),
+            )?;
+            // the input is valid (parsed by 'evaluate').
+            if verbatim {
+                if let Some(raw) = raw() {
+                    return Ok(raw);
+                }
+            }
+            let start = Instant::now();
+            let output = dump(&json_token)?;
+            stats.format = start.elapsed();
+            Ok(output)
+        });
+        if timing {
+            stats.output_bytes = result.as_ref().map_or(0, String::len);
+            eprintln!("{}", stats.to_string().errorfmt());
+        }
+        if let Some(metrics) = stats.metrics.filter(|_| metrics) {
+            eprintln!("{}", metrics.to_string().errorfmt());
+        }
+        result
+    };
 
-    Ok(println!("{}", json_formatter.dump(&json_token)))
+    if stream || ndjson {
+        // elements (lines) of each FILE are processed in parallel instead,
+        // skipped records are neither parsed nor printed.
+        let (mut skip, mut left) =
+            (page.offset, page.limit.unwrap_or(usize::MAX));
+        for path in inputs {
+            if left == 0 {
+                break;
+            }
+            let mut records = match open_input(path.as_deref(), None) {
+                Ok(reader) => records(reader, stream),
+                Err(err) => {
+                    print_result(Err(err));
+                    continue;
+                }
+            };
+            skip -= records.by_ref().take(skip).count();
+            let mut taken = 0;
+            pool::map_ordered(
+                records.take(left).inspect(|_| taken += 1),
+                jobs,
+                process,
+                &mut print_result,
+            );
+            left -= taken;
+        }
+    } else {
+        pool::map_ordered(
+            inputs,
+            jobs,
+            |path| process(read_input(path.as_deref(), limits.max_size)),
+            &mut print_result,
+        );
+    }
+    // 'process::exit' doesn't run destructors (buffer must be flushed).
+    stdout
+        .flush()
+        .unwrap_or_else(|err| exit_on_write_error(err));
+    if let Some(code) = failure {
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+/// evaluation settings, same for every input.
+#[derive(Clone, Copy)]
+struct Settings<'a> {
+    limits: JsonLimits,
+    missing: Missing,
+    /// see [`or_default`].
+    default: Option<&'a Json>,
+    /// elements of an array result.
+    page: Page,
+    /// path queries are evaluated while scanning the input ('--lazy').
+    lazy: bool,
+}
+
+impl Settings<'_> {
+    /// the input is only scanned (not validated) for path queries, unless
+    /// limits are set or the input is `measured` ('--timing', '--stats').
+    fn scans(&self, measured: bool) -> bool {
+        self.lazy
+            && self.limits.max_depth.is_none()
+            && self.limits.max_memory.is_none()
+            && !measured
+    }
+}
+
+/// write `line` to `out` (buffered, see [`exit_on_write_error`]).
+fn write_line<W: Write>(out: &mut W, line: &str) {
+    writeln!(out, "{}", line).unwrap_or_else(|err| exit_on_write_error(err));
+}
+
+/// exit successfully if the reading end of the pipe is closed (example:
+/// `ruson ... | head`), report the error otherwise.
+fn exit_on_write_error(err: io::Error) -> ! {
+    if err.kind() == io::ErrorKind::BrokenPipe {
+        std::process::exit(exit_code::SUCCESS);
+    }
+    error::report(&format!(" cannot write to stdout: {}.", err).errorfmt());
+    std::process::exit(exit_code::IO);
+}
+
+/// parse arguments of the subcommand and run it (never returns).
+fn run_command<I: Iterator<Item = String>>(
+    command: &CliCommand,
+    mut args: I,
+) -> ! {
+    let mut cliflags: Vec<String> = Vec::new();
+    let mut clioptions: HashMap<&str, Vec<String>> = HashMap::new();
+    let positionals = command
+        .cli
+        .parse_all(&mut args, &mut cliflags, &mut clioptions)
+        .unwrap_or_exit_with(exit_code::USAGE);
+    let mut clipositionals: HashMap<&str, Vec<String>> = HashMap::new();
+    command
+        .cli
+        .populate_positionals(positionals.into_iter(), &mut clipositionals)
+        .unwrap_or_exit_with(exit_code::USAGE);
+    error::set_quiet(cliflags.iter().any(|flag| flag == "-Q"));
+
+    for flag in cliflags.iter() {
+        match flag.as_str() {
+            "-v" => Err(format!(" {}", VERSION))
+                .unwrap_or_exit_with(exit_code::SUCCESS),
+            "-h" => {
+                write_line(&mut io::stdout(), &command.cli.to_string());
+                std::process::exit(0);
+            }
+            _ => continue,
+        }
+    }
+
+    let code = match command.name {
+        "validate" => {
+            validate(clipositionals.remove("FILE").unwrap_or_default())
+        }
+        "stats" => {
+            let option =
+                |name| clioptions.get(name).and_then(|values| values.last());
+            let query = option("query")
+                .map_or(Ok(JsonQuery::identity()), |query| query.parse())
+                .unwrap_or_exit_with(exit_code::USAGE);
+            let top =
+                option("top").and_then(|top| top.parse().ok()).unwrap_or(10);
+            let pretty = cliflags.iter().any(|flag| flag == "-p");
+            stats(
+                clipositionals.remove("FILE").unwrap_or_default(),
+                &query,
+                top,
+                pretty,
+            )
+        }
+        "hash" => {
+            let query = clioptions
+                .get("query")
+                .and_then(|values| values.last())
+                .map_or(Ok(JsonQuery::identity()), |query| query.parse())
+                .unwrap_or_exit_with(exit_code::USAGE);
+            hash(clipositionals.remove("FILE").unwrap_or_default(), &query)
+        }
+        _ => Err(" internal error.").unwrap_or_exit(),
+    };
+    std::process::exit(code)
+}
+
+/// parse every FILE (or stdin), reporting errors of invalid inputs.
+/// Returns exit code of the first failure, if any of the inputs is invalid.
+fn validate(paths: Vec<String>) -> i32 {
+    let mut failure = None;
+    for path in inputs(paths) {
+        let result =
+            read_input(path.as_deref(), None).and_then(|json_string| {
+                JsonParser::new(&json_string).parse().map(|_| ()).map_err(
+                    |err| match &path {
+                        Some(path) => {
+                            (exit_code::PARSE, format!(" '{}' {}", path, err))
+                        }
+                        None => (exit_code::PARSE, err.to_string()),
+                    },
+                )
+            });
+        if let Err((code, err)) = result {
+            failure = failure.or(Some(code));
+            error::report(&err.errorfmt());
+        }
+    }
+    failure.unwrap_or(exit_code::SUCCESS)
+}
+
+/// aggregate the values selected by `query` from the records of every FILE
+/// (or stdin), a top-level array or newline delimited json otherwise.
+/// Returns exit code of the first failure (invalid records are skipped).
+fn stats(
+    paths: Vec<String>,
+    query: &JsonQuery,
+    top: usize,
+    pretty: bool,
+) -> i32 {
+    // exit code of the first failure.
+    let mut exit = None;
+    let mut report = |(code, err): Failure| {
+        exit = exit.or(Some(code));
+        error::report(&err.errorfmt());
+    };
+    let mut stats = Stats::new();
+    for path in inputs(paths) {
+        let mut reader = match open_input(path.as_deref(), None) {
+            Ok(reader) => reader,
+            Err(err) => {
+                report(err);
+                continue;
+            }
+        };
+        let is_array = reader.fill_buf().is_ok_and(|bytes| {
+            bytes.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'[')
+        });
+        for record in records(reader, is_array) {
+            let json = record
+                .and_then(|record| record.parse::<Json>().map_err(failure));
+            match json.map(|json| json.apply(query)) {
+                Ok(Ok(value)) => stats.add(Some(value)),
+                // records without the value.
+                Ok(Err(err))
+                    if matches!(
+                        err.cause(),
+                        JsonEvalError::MissingKey(..)
+                            | JsonEvalError::InvalidIndex(..)
+                            | JsonEvalError::EmptyArray(..)
+                            | JsonEvalError::InvalidType(..)
+                    ) =>
+                {
+                    stats.add(None)
+                }
+                Ok(Err(err)) => report(failure(err)),
+                Err(err) => report(err),
+            }
+        }
+    }
+    let json = stats.to_json(top);
+    let output = if pretty {
+        PrettyJson {
+            indent: "  ",
+            color: false,
+        }
+        .dump(&json)
+    } else {
+        RawJson { color: false }.dump(&json)
+    };
+    write_line(&mut io::stdout(), &output);
+    exit.unwrap_or(exit_code::SUCCESS)
+}
+
+/// print SHA-256 digest of the canonical text (RFC 8785) of the value
+/// selected by `query` from every FILE (or stdin), formatted as `sha256sum`.
+/// Returns exit code of the first failure, if any.
+fn hash(paths: Vec<String>, query: &JsonQuery) -> i32 {
+    let mut exit = None;
+    for path in inputs(paths) {
+        let result =
+            read_input(path.as_deref(), None).and_then(|json_string| {
+                JsonParser::new(&json_string)
+                    .parse()
+                    .map_err(failure)?
+                    .apply(query)
+                    .map_err(failure)
+            });
+        match result {
+            Ok(json) => write_line(
+                &mut io::stdout(),
+                &format!(
+                    "{}  {}",
+                    sha256::hex_digest(CanonicalJson.dump(&json).as_bytes()),
+                    path.as_deref().unwrap_or("-")
+                ),
+            ),
+            Err((code, err)) => {
+                exit = exit.or(Some(code));
+                error::report(&err.errorfmt());
+            }
+        }
+    }
+    exit.unwrap_or(exit_code::SUCCESS)
+}
+
+/// FILE arguments of a subcommand, stdin (`None`) if there are none.
+fn inputs(paths: Vec<String>) -> Vec<Option<String>> {
+    if paths.is_empty() {
+        vec![None]
+    } else {
+        paths.into_iter().map(Some).collect()
+    }
+}
+
+/// elements of the top-level array (`stream`) or non-empty lines of
+/// `reader`.
+fn records(
+    reader: Box<dyn BufRead + Send>,
+    stream: bool,
+) -> Box<dyn Iterator<Item = Result<String, Failure>> + Send> {
+    if stream {
+        Box::new(
+            ArrayStream::new(reader)
+                .map(|element| element.map_err(|err| (exit_code::PARSE, err))),
+        )
+    } else {
+        Box::new(
+            reader
+                .lines()
+                .filter(|line| {
+                    line.as_ref().map_or(true, |line| !line.trim().is_empty())
+                })
+                .map(|line| {
+                    line.map_err(|err| {
+                        (exit_code::IO, format!(" cannot read input: {}", err))
+                    })
+                }),
+        )
+    }
+}
+
+/// open reader for url, file or stdin (erroring out early on binary input),
+/// responses are read up to `max_size` bytes.
+fn open_input(
+    path: Option<&str>,
+    max_size: Option<usize>,
+) -> Result<Box<dyn BufRead + Send>, Failure> {
+    let mut reader: Box<dyn BufRead + Send> = if let Some(url) =
+        path.filter(|path| http::is_url(path))
+    {
+        let body = match max_size {
+            Some(max_size) => http::get_within(url, max_size),
+            None => http::get(url),
+        }
+        .map_err(|err| (exit_code::IO, err))?;
+        Box::new(io::Cursor::new(body))
+    } else if let Some(path) = path {
+        std::fs::File::open(path)
+            .map(|file| Box::new(BufReader::new(file)) as Box<_>)
+            .map_err(|err| (exit_code::IO, format!(" '{}' {}", path, err)))?
+    } else {
+        Box::new(BufReader::new(io::stdin()))
+    };
+    // only the first buffered bytes (read errors are reported on reading).
+    match reader.fill_buf() {
+        Ok(bytes) if is_binary(bytes) => Err(not_text(path)),
+        _ => Ok(reader),
+    }
+}
+
+/// `bytes` start with a known binary magic number (compressed data, images
+/// etc.), or contain a NUL byte early on (never part of json text).
+fn is_binary(bytes: &[u8]) -> bool {
+    const MAGIC_NUMBERS: &[&[u8]] = &[
+        b"\x1f\x8b",         // gzip
+        b"BZh",              // bzip2
+        b"\xfd7zXZ\x00",     // xz
+        b"\x28\xb5\x2f\xfd", // zstd
+        b"PK\x03\x04",       // zip
+        b"\x89PNG",          // png
+        b"\xff\xd8\xff",     // jpeg
+        b"GIF8",             // gif
+        b"%PDF",             // pdf
+    ];
+    MAGIC_NUMBERS.iter().any(|magic| bytes.starts_with(magic))
+        || bytes.iter().take(1024).any(|&byte| byte == 0)
+}
+
+/// failure for binary input (see [`is_binary`]).
+fn not_text(path: Option<&str>) -> Failure {
+    let input = path.map_or("input".into(), |path| format!("'{}'", path));
+    (
+        exit_code::PARSE,
+        format!(
+            " {} does not look like text (did you forget to decompress?).",
+            input
+        ),
+    )
+}
+
+/// read json string from url, file or stdin (at most `max_size` bytes).
+fn read_input(
+    path: Option<&str>,
+    max_size: Option<usize>,
+) -> Result<String, Failure> {
+    let mut bytes = Vec::new();
+    // read one byte over the limit, to be able to detect oversized input.
+    let limit = max_size.map_or(u64::MAX, |max_size| max_size as u64 + 1);
+    let reader: Box<dyn Read> = match path {
+        // stdin is locked once, for the whole read.
+        None => Box::new(io::stdin().lock()),
+        Some(_) => open_input(path, max_size)?,
+    };
+    let read_error = |err: io::Error| match path {
+        Some(path) => (exit_code::IO, format!(" '{}' {}", path, err)),
+        None => (exit_code::IO, " cannot read from stdin.".into()),
+    };
+    reader
+        .take(limit)
+        .read_to_end(&mut bytes)
+        .map_err(read_error)?;
+    if is_binary(&bytes) {
+        return Err(not_text(path));
+    }
+    let buffer = String::from_utf8(bytes).map_err(|_| {
+        read_error(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8",
+        ))
+    })?;
+    match max_size {
+        Some(max_size) if buffer.len() > max_size => Err((
+            exit_code::PARSE,
+            format!(" input exceeds '--max-size' limit ({} bytes).", max_size),
+        )),
+        _ => Ok(buffer),
+    }
+}
+
+thread_local! {
+    /// parser reused for every document processed by the thread.
+    static PARSER: RefCell<Option<JsonParser>> = const { RefCell::new(None) };
+}
+
+/// parse json string (top-level array across `jobs` threads) and apply query
+/// (recording `timing`, if provided), see [`Settings`].
+fn evaluate(
+    json_string: &str,
+    query: &JsonQuery,
+    settings: Settings,
+    jobs: usize,
+    timing: Option<&mut Timing>,
+) -> Result<Json, Failure> {
+    let Settings {
+        limits,
+        missing,
+        default,
+        page,
+        ..
+    } = settings;
+    // path queries are evaluated while scanning (skipped values are neither
+    // parsed nor validated, see 'Settings::scans').
+    if settings.scans(timing.is_some()) {
+        if let Some(json) = scan::select_page(json_string, query, page) {
+            // already paged (unless replaced by the default).
+            return Ok(match (json, default) {
+                (Json::Null, Some(default)) => default.clone().page(page),
+                (json, _) => json,
+            });
+        }
+    }
+    let start = Instant::now();
+    // limits are only checked by the sequential parser.
+    let (json_token, allocated) = if jobs > 1
+        && limits.max_depth.is_none()
+        && limits.max_memory.is_none()
+    {
+        (parallel::parse(json_string, jobs), 0)
+    } else {
+        PARSER.with(|parser| {
+            // limits are the same for every document.
+            let mut parser = parser.borrow_mut();
+            let parser = parser
+                .get_or_insert_with(|| JsonParser::new("").with_limits(limits));
+            (parser.reset(json_string).parse(), parser.allocated())
+        })
+    };
+    let json_token = json_token.map_err(failure)?;
+    let parsed = Instant::now();
+    // the parsed value counts towards the budget for the query.
+    let options = ApplyOptions {
+        max_memory: limits
+            .max_memory
+            .map(|max_memory| max_memory.saturating_sub(allocated)),
+        missing,
+    };
+    let result = or_default(json_token.apply_with(query, options), default)
+        .map(|json| json.page(page))
+        .map_err(failure);
+    if let Some(timing) = timing {
+        timing.parse = parsed - start;
+        timing.query = parsed.elapsed();
+        timing.metrics = Some(json_token.metrics());
+    }
+    result
+}
+
+/// `default` in place of a `null` result, or of a missing key or index.
+fn or_default(
+    result: Result<Json, JsonEvalError>,
+    default: Option<&Json>,
+) -> Result<Json, JsonEvalError> {
+    match (result, default) {
+        (Ok(Json::Null), Some(default)) => Ok(default.clone()),
+        (Err(err), Some(default))
+            if matches!(
+                err.cause(),
+                JsonEvalError::MissingKey(..)
+                    | JsonEvalError::InvalidIndex(..)
+                    | JsonEvalError::EmptyArray(..)
+            ) =>
+        {
+            Ok(default.clone())
+        }
+        (result, _) => result,
+    }
+}
+
+/// re-run query whenever the file modification time changes (never returns).
+fn watch_file(
+    path: &str,
+    query: &JsonQuery,
+    settings: Settings,
+    dump: &dyn Fn(&Json) -> Result<String, Failure>,
+) -> ! {
+    let mut last_modified = None;
+    loop {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified != last_modified {
+            last_modified = modified;
+            let mut stdout = io::stdout().lock();
+            // clear screen and move cursor to top left.
+            write!(stdout, "\x1b[2J\x1b[H")
+                .unwrap_or_else(|err| exit_on_write_error(err));
+            match read_input(Some(path), settings.limits.max_size)
+                .and_then(|s| evaluate(&s, query, settings, 1, None))
+                .and_then(|token| dump(&token))
+            {
+                Ok(output) => write_line(&mut stdout, &output),
+                Err((_, err)) => error::report(&err.errorfmt()),
+            }
+            stdout
+                .flush()
+                .unwrap_or_else(|err| exit_on_write_error(err));
+        }
+        std::thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+/// cli of the 'validate' subcommand.
+fn create_validate_cli() -> Cli {
+    let mut cli = Cli::new(concat!(env!("CARGO_PKG_NAME"), " validate"));
+    cli.set_description(vec![
+        "Check that every FILE is valid 'json' text.".into(),
+        "Exit with non-zero status, if any of the FILEs is invalid.".into(),
+    ])
+    .add_flag(CliFlag {
+        short: "-Q",
+        long: Some("--quiet"),
+        description: vec![
+            "Suppress error messages (exit status is kept).".into()
+        ],
+    })
+    .add_positional(CliArg {
+        name: "FILE",
+        arity: CliArity::Many,
+        required: false,
+        description: vec![
+            "Path or 'http(s)://' url of 'json' text (default: stdin).".into(),
+        ],
+    });
+    cli
+}
+
+/// cli of the 'stats' subcommand.
+fn create_stats_cli() -> Cli {
+    let mut cli = Cli::new(concat!(env!("CARGO_PKG_NAME"), " stats"));
+    cli.set_description(vec![
+        "Aggregate the values selected by query from every record:".into(),
+        "count, missing, distinct values, min/max/mean of numbers and".into(),
+        "the most frequent values. Records are the elements of a".into(),
+        "top-level array, or lines of newline delimited 'json'.".into(),
+    ])
+    .add_flag(CliFlag {
+        short: "-p",
+        long: Some("--pretty"),
+        description: vec!["Print pretty formatted 'json'.".into()],
+    })
+    .add_flag(CliFlag {
+        short: "-Q",
+        long: Some("--quiet"),
+        description: vec![
+            "Suppress error messages (exit status is kept).".into()
+        ],
+    })
+    .add_option(CliOption {
+        name: "query",
+        default: None,
+        flag: CliFlag {
+            short: "-q",
+            long: Some("--query"),
+            description: vec![
+                "Query selecting the value of each record (default: '.')."
+                    .into(),
+            ],
+        },
+        repeatable: false,
+        value: CliValue::Text,
+        validate: None,
+    })
+    .add_option(CliOption {
+        name: "top",
+        default: None,
+        flag: CliFlag {
+            short: "--top",
+            long: None,
+            description: vec![
+                "Number of most frequent values printed (default: 10).".into(),
+            ],
+        },
+        repeatable: false,
+        value: CliValue::Integer,
+        validate: None,
+    })
+    .add_positional(CliArg {
+        name: "FILE",
+        arity: CliArity::Many,
+        required: false,
+        description: vec![
+            "Path or 'http(s)://' url of 'json' text (default: stdin).".into(),
+        ],
+    });
+    cli
+}
+
+/// cli of the 'hash' subcommand.
+fn create_hash_cli() -> Cli {
+    let mut cli = Cli::new(concat!(env!("CARGO_PKG_NAME"), " hash"));
+    cli.set_description(vec![
+        "Print SHA-256 digest of the canonical 'json' text (RFC 8785) of"
+            .into(),
+        "every FILE: equal documents have equal digests, regardless of".into(),
+        "key order, whitespace, escapes or number formatting.".into(),
+    ])
+    .add_flag(CliFlag {
+        short: "-Q",
+        long: Some("--quiet"),
+        description: vec![
+            "Suppress error messages (exit status is kept).".into()
+        ],
+    })
+    .add_option(CliOption {
+        name: "query",
+        default: None,
+        flag: CliFlag {
+            short: "-q",
+            long: Some("--query"),
+            description: vec![
+                "Query selecting the hashed value (default: '.').".into(),
+            ],
+        },
+        repeatable: false,
+        value: CliValue::Text,
+        validate: None,
+    })
+    .add_positional(CliArg {
+        name: "FILE",
+        arity: CliArity::Many,
+        required: false,
+        description: vec![
+            "Path or 'http(s)://' url of 'json' text (default: stdin).".into(),
+        ],
+    });
+    cli
 }
 
 #[inline(always)]
@@ -77,12 +997,40 @@ pub fn create_cli(name: &'static str) -> Cli {
     let mut cli = Cli::new(name);
     cli.set_description(vec![
         "Extract sub tree from valid 'json' text.".into(),
-        "Use standard input, if FILE not provided.".into(),
+        "Use standard input, if FILE not provided (multiple FILEs allowed).".into(),
+        "FILE can also be an 'http://' or 'https://' url.".into(),
     ])
     .set_footer(vec![
         "For examples, refer to the manpage. For detailed".into(),
         "documentation Visit: https://github.com/lycuid/ruson#readme".into(),
     ])
+    .add_positional(CliArg {
+        name: "FILE",
+        arity: CliArity::Many,
+        required: false,
+        description: vec![
+            "Path or 'http(s)://' url of 'json' text (default: stdin).".into(),
+        ],
+    })
+    .add_command(CliCommand {
+        name: "validate",
+        description: vec!["Check that every FILE is valid 'json' text.".into()],
+        cli: create_validate_cli(),
+    })
+    .add_command(CliCommand {
+        name: "stats",
+        description: vec![
+            "Aggregate values selected from records (array or ndjson).".into(),
+        ],
+        cli: create_stats_cli(),
+    })
+    .add_command(CliCommand {
+        name: "hash",
+        description: vec![
+            "Print SHA-256 digest of canonical (RFC 8785) 'json'.".into(),
+        ],
+        cli: create_hash_cli(),
+    })
     .add_flag(CliFlag {
         short: "-p",
         long: Some("--pretty"),
@@ -93,6 +1041,98 @@ pub fn create_cli(name: &'static str) -> Cli {
         long: Some("--table"),
         description: vec!["Print table formatted 'json'.".into()],
     })
+    .add_flag(CliFlag {
+        short: "-w",
+        long: Some("--watch"),
+        description: vec![
+            "Re-run query whenever FILE changes (polling mtime).".into(),
+        ],
+    })
+    .add_flag(CliFlag {
+        short: "-s",
+        long: Some("--stream"),
+        description: vec![
+            "Process elements of the top-level array one at a time.".into(),
+            "(memory usage stays flat regardless of input size).".into(),
+        ],
+    })
+    .add_flag(CliFlag {
+        short: "--ndjson",
+        long: None,
+        description: vec![
+            "Process newline delimited 'json' (one record per line).".into(),
+            "Records are processed in parallel (see '--jobs').".into(),
+        ],
+    })
+    .add_flag(CliFlag {
+        short: "--seq",
+        long: None,
+        description: vec![
+            "Print results as json text sequences (RFC 7464).".into(),
+        ],
+    })
+    .add_flag(CliFlag {
+        short: "--unbuffered",
+        long: None,
+        description: vec![
+            "Flush output after every result (for live pipelines,".into(),
+            "example: 'tail -f log.jsonl | ruson --ndjson --unbuffered').".into(),
+        ],
+    })
+    .add_flag(CliFlag {
+        short: "--timing",
+        long: None,
+        description: vec![
+            "Print timing, node count and output size to stderr.".into(),
+        ],
+    })
+    .add_flag(CliFlag {
+        short: "--stats",
+        long: None,
+        description: vec![
+            "Print metrics of the input to stderr (depth, node counts".into(),
+            "by type, string bytes and approximate memory usage).".into(),
+        ],
+    })
+    .add_flag(CliFlag {
+        short: "--verbatim",
+        long: None,
+        description: vec![
+            "Print values selected by path queries as in the input".into(),
+            "(byte for byte, '--pretty', '--color' etc. are ignored).".into(),
+        ],
+    })
+    .add_flag(CliFlag {
+        short: "--lazy",
+        long: None,
+        description: vec![
+            "Evaluate path queries while scanning the input, the rest".into(),
+            "of the input is not validated (ignored with '--max-depth',".into(),
+            "'--max-memory', '--timing' and '--stats').".into(),
+        ],
+    })
+    .add_flag(CliFlag {
+        short: "--no-map-cache",
+        long: None,
+        description: vec![
+            "Evaluate '.map()' on every element (identical elements".into(),
+            "are evaluated once by default).".into(),
+        ],
+    })
+    .add_flag(CliFlag {
+        short: "--jq",
+        long: None,
+        description: vec![
+            "Read queries in 'jq' syntax ('.', '.[]', '|', 'length').".into(),
+        ],
+    })
+    .add_flag(CliFlag {
+        short: "-Q",
+        long: Some("--quiet"),
+        description: vec![
+            "Suppress error messages (exit status is kept).".into(),
+        ],
+    })
     .add_option(CliOption {
         name: "query",
         default: Some("".into()),
@@ -100,9 +1140,468 @@ pub fn create_cli(name: &'static str) -> Cli {
             short: "-q",
             long: Some("--query"),
             description: vec![
-                "Query for extracting desired 'json' subtree.".into()
+                "Query for extracting desired 'json' subtree.".into(),
+                "('.', same as the default, is the whole document).".into(),
+                "If repeated, each query is applied on the previous result.".into(),
+            ],
+        },
+        repeatable: true,
+        value: CliValue::Text,
+        validate: None,
+    })
+    .add_option(CliOption {
+        name: "arg",
+        default: None,
+        flag: CliFlag {
+            short: "--arg",
+            long: None,
+            description: vec![
+                "Set query variable 'name' to the string 'value'".into(),
+                "(used as '.[$name]' in a query).".into(),
+            ],
+        },
+        repeatable: true,
+        value: CliValue::Pair,
+        validate: None,
+    })
+    .add_option(CliOption {
+        name: "argjson",
+        default: None,
+        flag: CliFlag {
+            short: "--argjson",
+            long: None,
+            description: vec![
+                "Set query variable 'name' to the json 'value'".into(),
+                "(example: '--argjson min 5' for '.any(.age > $min)').".into(),
+            ],
+        },
+        repeatable: true,
+        value: CliValue::Pair,
+        validate: Some(|value| match value.parse::<Json>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err("json value"),
+        }),
+    })
+    .add_option(CliOption {
+        name: "color",
+        default: Some("auto".into()),
+        flag: CliFlag {
+            short: "-C",
+            long: Some("--color"),
+            description: vec![
+                "Colorize output: auto|always|never (default: auto).".into(),
+                "'auto' disables color if NO_COLOR is set or not a tty.".into(),
+            ],
+        },
+        repeatable: false,
+        value: CliValue::Choice(&["auto", "always", "never"]),
+        validate: None,
+    })
+    .add_option(CliOption {
+        name: "template",
+        default: None,
+        flag: CliFlag {
+            short: "--template",
+            long: None,
+            description: vec![
+                "Print text with '{query}' placeholders replaced by the".into(),
+                "values selected from the result ('{{', '}}' for braces).".into(),
+            ],
+        },
+        repeatable: false,
+        value: CliValue::Text,
+        validate: None,
+    })
+    .add_option(CliOption {
+        name: "separator",
+        default: None,
+        flag: CliFlag {
+            short: "--join",
+            long: None,
+            description: vec![
+                "Print array of strings (or scalars) joined by 'separator'".into(),
+                "(json escapes allowed, example: '\\n'), strings unquoted.".into(),
+            ],
+        },
+        repeatable: false,
+        value: CliValue::Text,
+        validate: None,
+    })
+    .add_option(CliOption {
+        name: "jobs",
+        default: None,
+        flag: CliFlag {
+            short: "-j",
+            long: Some("--jobs"),
+            description: vec![
+                "Process multiple FILEs across 'jobs' threads.".into(),
+                "Output order follows the order of FILEs.".into(),
+                "(a single large top-level array is parsed in parallel).".into(),
+            ],
+        },
+        repeatable: false,
+        value: CliValue::Integer,
+        validate: Some(|jobs| match jobs.parse::<usize>() {
+            Ok(0) => Err("positive integer"),
+            _ => Ok(()),
+        }),
+    })
+    .add_option(CliOption {
+        name: "depth",
+        default: None,
+        flag: CliFlag {
+            short: "--max-depth",
+            long: None,
+            description: vec![
+                "Maximum nesting depth of arrays/objects in input.".into(),
             ],
         },
+        repeatable: false,
+        value: CliValue::Integer,
+        validate: None,
+    })
+    .add_option(CliOption {
+        name: "bytes",
+        default: None,
+        flag: CliFlag {
+            short: "--max-size",
+            long: None,
+            description: vec!["Maximum size of input json text (in bytes).".into()],
+        },
+        repeatable: false,
+        value: CliValue::Integer,
+        validate: None,
+    })
+    .add_option(CliOption {
+        name: "memory",
+        default: None,
+        flag: CliFlag {
+            short: "--max-memory",
+            long: None,
+            description: vec![
+                "Approximate memory budget for parsing and queries.".into(),
+                "(in bytes, excluding the input text).".into(),
+            ],
+        },
+        repeatable: false,
+        value: CliValue::Integer,
+        validate: None,
+    })
+    .add_option(CliOption {
+        name: "missing",
+        default: Some("error".into()),
+        flag: CliFlag {
+            short: "--missing",
+            long: None,
+            description: vec![
+                "Missing keys, out of range indices and type mismatches in".into(),
+                "queries: error|null (default: error).".into(),
+            ],
+        },
+        repeatable: false,
+        value: CliValue::Choice(&["error", "null"]),
+        validate: None,
+    })
+    .add_option(CliOption {
+        name: "default",
+        default: None,
+        flag: CliFlag {
+            short: "--default",
+            long: None,
+            description: vec![
+                "Json value printed instead of a 'null' result, or if the".into(),
+                "query fails on a missing key or index.".into(),
+            ],
+        },
+        repeatable: false,
+        value: CliValue::Text,
+        validate: Some(|value| match value.parse::<Json>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err("json value"),
+        }),
+    })
+    .add_option(CliOption {
+        name: "limit",
+        default: None,
+        flag: CliFlag {
+            short: "--limit",
+            long: None,
+            description: vec![
+                "Print at most 'limit' elements of an array result (or".into(),
+                "results, with '--stream' or '--ndjson').".into(),
+            ],
+        },
+        repeatable: false,
+        value: CliValue::Integer,
+        validate: None,
+    })
+    .add_option(CliOption {
+        name: "offset",
+        default: None,
+        flag: CliFlag {
+            short: "--offset",
+            long: None,
+            description: vec![
+                "Skip the first 'offset' elements of an array result (or".into(),
+                "records, skipped ones are not parsed).".into(),
+            ],
+        },
+        repeatable: false,
+        value: CliValue::Integer,
+        validate: None,
+    })
+    .add_hidden_flag(CliFlag {
+        short: "--dump-man",
+        long: None,
+        description: vec!["Print roff formatted manpage and exit.".into()],
+    })
+    .add_section(CliSection {
+        title: "Query Syntax",
+        lines: vec![
+            "Query can be any valid javascript syntax of object property".into(),
+            "accessors or array indexing, along with some custom functions.".into(),
+        ],
+        examples: vec![
+            CliExample {
+                description: "Identity, the whole document (same as an empty query).",
+                command: r#"echo '{ "prop": "value" }' | ruson --query '.'"#,
+                output: r#"{"prop": "value"}"#,
+            },
+            CliExample {
+                description: "Dot notation (keys of letters, digits, '_', '-' and '$', see bracket notation).",
+                command: r#"echo '{ "prop": "value" }' | ruson --query '.prop'"#,
+                output: r#""value""#,
+            },
+            CliExample {
+                description: "Bracket notation.",
+                command: r#"echo '{ "prop": "value" }' | ruson --query '["prop"]'"#,
+                output: r#""value""#,
+            },
+            CliExample {
+                description: "Bracket keys are json strings (any key, with escapes).",
+                command: r#"echo '{ "say \"hi\"": 1 }' | ruson --query '["say \"hi\""]'"#,
+                output: "1",
+            },
+            CliExample {
+                description: "Query variables, '$name' in brackets is the string set by '--arg name value'.",
+                command: r#"echo '{ "users": { "alice": 1 } }' | ruson --arg user alice -q '.users[$user]'"#,
+                output: "1",
+            },
+            CliExample {
+                description: "'--argjson name value' sets a json value (strings are keys, integers are indices, any value in comparisons).",
+                command: r#"echo '{ "ages": [20, 40] }' | ruson --argjson min 30 -q '.ages.any(. > $min)'"#,
+                output: "true",
+            },
+            CliExample {
+                description: "Array indexing.",
+                command: r#"echo '{ "prop": [1, 2, 3, 4, 5] }' | ruson --query '.prop[2]'"#,
+                output: "3",
+            },
+            CliExample {
+                description: "Negative indices count from the end.",
+                command: r#"echo '{ "prop": [1, 2, 3, 4, 5] }' | ruson --query '.prop[-1]'"#,
+                output: "5",
+            },
+            CliExample {
+                description: "'.first()' and '.last()' functions (valid for non-empty 'array').",
+                command: r#"echo '{ "prop": [1, 2, 3, 4, 5] }' | ruson -q '.prop.last()'"#,
+                output: "5",
+            },
+            CliExample {
+                description: "Array slicing, from start up to (not including) end (bounds are optional).",
+                command: r#"echo '{ "prop": [1, 2, 3, 4, 5] }' | ruson --query '.prop[1:3]'"#,
+                output: "[2, 3]",
+            },
+            CliExample {
+                description: "'.keys()' function (valid for 'object').",
+                command: r#"echo '{ "one": 1, "two": 2, "three": 3 }' | ruson -q '.keys()'"#,
+                output: r#"["one", "two", "three"]"#,
+            },
+            CliExample {
+                description: "'.values()' function (valid for 'object').",
+                command: r#"echo '{ "one": 1, "two": 2, "three": 3 }' | ruson -q '.values()'"#,
+                output: "[1, 2, 3]",
+            },
+            CliExample {
+                description: "'.length()' function (valid for 'array', 'string', 'object' and 'null').",
+                command: "echo '[1, 2, 3]' | ruson -q '.length()'",
+                output: "3",
+            },
+            CliExample {
+                description: "'.bytelength()' function (valid for 'string', utf-8 bytes).",
+                command: r#"echo '"h\u00e9llo"' | ruson -q '.bytelength()'"#,
+                output: "6",
+            },
+            CliExample {
+                description: "'.type()' function, name of the value's type (same as 'jq').",
+                command: r#"echo '{ "a": 1 }' | ruson -q '.a.type()'"#,
+                output: r#""number""#,
+            },
+            CliExample {
+                description: "'.paths()' function, paths of the leaves (scalars, empty arrays and objects).",
+                command: r#"echo '{ "a": { "b": [1, 2] } }' | ruson -q '.paths()'"#,
+                output: r#"[".a.b[0]", ".a.b[1]"]"#,
+            },
+            CliExample {
+                description: "'.flatten()' function (valid for 'array'), nested arrays up to a depth (default: 1).",
+                command: r#"echo '{ "matrix": [[1, [2]], [3]] }' | ruson -q '.matrix.flatten()'"#,
+                output: "[1, [2], 3]",
+            },
+            CliExample {
+                description: "'.sum()', '.min()', '.max()' and '.avg()' functions (valid for 'array' of numbers).",
+                command: r#"echo '{ "prices": [1.5, 2, 4.5] }' | ruson -q '.prices.sum()'"#,
+                output: "8",
+            },
+            CliExample {
+                description: "'.has()' function, membership of an object key (or array index).",
+                command: r#"echo '{ "a": 1 }' | ruson -q '.has("a")'"#,
+                output: "true",
+            },
+            CliExample {
+                description: "'.test()' function (valid for 'string'), string contains a match of the regex.",
+                command: r#"echo '{ "emails": ["a@example.com", "b@test.org"] }' | ruson -q '.emails.map(.test("@example\\.com$"))'"#,
+                output: r#"[true, false]"#,
+            },
+            CliExample {
+                description: "'.match()' function (valid for 'string'), first match of the regex with its groups (same as 'jq').",
+                command: r#"echo '"id: 42"' | ruson -q '.match("(\\w+): (\\d+)").captures.map(.string)'"#,
+                output: r#"["id", "42"]"#,
+            },
+            CliExample {
+                description: "Object projection, only the listed keys (missing keys are 'null').",
+                command: r#"echo '{ "id": 1, "name": "x", "email": "y" }' | ruson -q '.{id, email}'"#,
+                output: r#"{"id": 1, "email": "y"}"#,
+            },
+            CliExample {
+                description: "'.omit()' function (valid for 'object'), without the listed keys.",
+                command: r#"echo '{ "id": 1, "token": "x" }' | ruson -q '.omit("token")'"#,
+                output: r#"{"id": 1}"#,
+            },
+            CliExample {
+                description: "'.del()' function, without an object key (or array index).",
+                command: r#"echo '{ "list": [1, 2, 3] }' | ruson -q '.list.del(1)'"#,
+                output: "[1, 3]",
+            },
+            CliExample {
+                description: "'.find()' function, values of a key anywhere in the document ('*' matches any chars).",
+                command: r#"echo '{ "a": { "id": 1 }, "b": [{ "id": 2 }] }' | ruson -q '.find("id")'"#,
+                output: "[1, 2]",
+            },
+            CliExample {
+                description: "'.findpaths()' function, same as '.find()' along with the paths.",
+                command: r#"echo '{ "a": { "id": 1 } }' | ruson -q '.findpaths("id")'"#,
+                output: r#"[{"path": ".a.id", "value": 1}]"#,
+            },
+            CliExample {
+                description: "'.map()' function (valid for 'array').",
+                command: r#"echo '{ "list": [{ "id": 1 }, { "id": 2 }] }' | ruson -q '.list.map(.id)'"#,
+                output: "[1, 2]",
+            },
+            CliExample {
+                description: "'.group_by()' function (valid for 'array'), groups ordered by the query result.",
+                command: r#"echo '[{ "u": "b" }, { "u": "a" }, { "u": "b" }]' | ruson -q '.group_by(.u)'"#,
+                output: r#"[[{"u": "a"}], [{"u": "b"}, {"u": "b"}]]"#,
+            },
+            CliExample {
+                description: "'.any()' and '.all()' functions (valid for 'array'), predicate of a query compared with a json literal ('==', '!=', '<', '<=', '>', '>='), or truthy without one.",
+                command: r#"echo '{ "nums": [1, 2, 3] }' | ruson -q '.nums.all(. > 0)'"#,
+                output: "true",
+            },
+            CliExample {
+                description: "Iteration, rest of the query applied on every element (or object value).",
+                command: r#"echo '{ "list": [{ "tags": ["a"] }, { "tags": ["b", "c"] }] }' | ruson -q '.list[].tags[]'"#,
+                output: r#"["a", "b", "c"]"#,
+            },
+            CliExample {
+                description: "Pipes, each stage is applied on the result of the previous one.",
+                command: r#"echo '{ "list": [1, 2, 3] }' | ruson -q '.list | .length()'"#,
+                output: "3",
+            },
+            CliExample {
+                description: "Optional access ('?'), 'null' instead of an error for a missing key or index (rest of the stage is skipped).",
+                command: r#"echo '{ "a": 1 }' | ruson -q '.maybe?.value'"#,
+                output: "null",
+            },
+            CliExample {
+                description: "Missing values as 'null' (with '--missing=null').",
+                command: r#"echo '{ "list": [{ "id": 1 }, {}] }' | ruson --missing=null -q '.list.map(.id)'"#,
+                output: "[1, null]",
+            },
+            CliExample {
+                description: "Fallback value for a missing (or 'null') result (with '--default').",
+                command: r#"echo '{ "name": "x" }' | ruson -q '.version' --default '"0.0.0"'"#,
+                output: r#""0.0.0""#,
+            },
+            CliExample {
+                description: "Second page of results, 2 per page (with '--offset' and '--limit').",
+                command: r#"echo '{ "list": [1, 2, 3, 4, 5] }' | ruson -q '.list' --offset 2 --limit 2"#,
+                output: "[3, 4]",
+            },
+            CliExample {
+                description: "Array of strings joined by a separator (with '--join').",
+                command: r#"echo '{ "names": ["a", "b", "c"] }' | ruson -q '.names' --join ' '"#,
+                output: "a b c",
+            },
+            CliExample {
+                description: "Text report, placeholders are queries on the result (with '--template').",
+                command: r#"echo '{ "name": "ruson", "stars": 5 }' | ruson --template 'Name: {.name}, Stars: {.stars}'"#,
+                output: "Name: ruson, Stars: 5",
+            },
+        ],
+    })
+    .add_section(CliSection {
+        title: "Examples",
+        lines: vec![],
+        examples: vec![
+            CliExample {
+                description: "Processing text from file.",
+                command: "ruson --query='.obj.list[0]' file.json",
+                output: "",
+            },
+            CliExample {
+                description: "Processing text from pipe.",
+                command: r#"echo "[1, 2, 3, 4, 5]" | ruson --query "[3]""#,
+                output: "4",
+            },
+            CliExample {
+                description: "Download latest xkcd comic.",
+                command: "ruson -q '.img' https://xkcd.com/info.0.json | xargs wget",
+                output: "",
+            },
+            CliExample {
+                description: "Status code counts of an access log (newline delimited json).",
+                command: "ruson stats -q '.status_code' access.jsonl",
+                output: "",
+            },
+            CliExample {
+                description: "Compare documents regardless of key order and formatting.",
+                command: "ruson hash old.json new.json",
+                output: "",
+            },
+        ],
+    })
+    .add_section(CliSection {
+        title: "Exit Status",
+        lines: vec![
+            "0: success.".into(),
+            "1: internal error.".into(),
+            "2: usage error (invalid flags, options or query syntax).".into(),
+            "3: parse error (invalid 'json' text or limits exceeded).".into(),
+            "4: query error (query cannot be applied on the input).".into(),
+            "5: I/O error (FILE, url or stdin cannot be read).".into(),
+            "For multiple inputs, the status of the first failure is used.".into(),
+        ],
+        examples: vec![],
+    })
+    .add_section(CliSection {
+        title: "Bugs",
+        lines: vec!["Submit issues at <https://github.com/lycuid/ruson/issues/>".into()],
+        examples: vec![],
+    })
+    .add_section(CliSection {
+        title: "Copyright",
+        lines: vec!["License GPLv3 <https://gnu.org/licenses/gpl.html>.".into()],
+        examples: vec![],
     });
     cli
 }
@@ -0,0 +1,82 @@
+//! Thread pool utilities (built on `std::thread`), preserving input order.
+use std::{
+    collections::BTreeMap,
+    sync::{mpsc, Mutex},
+    thread,
+};
+
+/// maximum number of items in flight (taken, but not emitted yet) per worker
+/// thread, so that a slow item doesn't buffer all of the later results.
+pub const IN_FLIGHT_PER_JOB: usize = 2;
+
+/// number of threads available for parallel processing (fallback 1).
+pub fn available_jobs() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// apply `f` to every item across `jobs` worker threads, calling `emit` with
+/// the results in the same order as `items` (as soon as they are available).
+/// at most `jobs * IN_FLIGHT_PER_JOB` results are buffered at a time.
+pub fn map_ordered<I, R, F, E>(items: I, jobs: usize, f: F, mut emit: E)
+where
+    I: IntoIterator,
+    I::IntoIter: Send,
+    I::Item: Send,
+    R: Send,
+    F: Fn(I::Item) -> R + Sync,
+    E: FnMut(R),
+{
+    let jobs = std::cmp::max(jobs, 1);
+    let items = Mutex::new(items.into_iter().enumerate());
+
+    if jobs == 1 {
+        for (_, item) in items.into_inner().unwrap() {
+            emit(f(item));
+        }
+        return;
+    }
+
+    let window = jobs * IN_FLIGHT_PER_JOB;
+    // a permit is taken for every item, and given back once its result is
+    // emitted.
+    let (permit, permits) = mpsc::sync_channel(window);
+    for _ in 0..window {
+        permit.send(()).unwrap();
+    }
+    let permits = Mutex::new(permits);
+
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::sync_channel(window);
+        for _ in 0..jobs {
+            let (items, permits, sender, f) =
+                (&items, &permits, sender.clone(), &f);
+            scope.spawn(move || loop {
+                // release the locks, before processing the item.
+                if permits.lock().unwrap().recv().is_err() {
+                    break;
+                }
+                let next = items.lock().unwrap().next();
+                match next {
+                    Some((index, item)) => {
+                        if sender.send((index, f(item))).is_err() {
+                            break;
+                        }
+                    }
+                    None => break,
+                }
+            });
+        }
+        drop(sender);
+
+        let (mut pending, mut next_index) = (BTreeMap::new(), 0);
+        for (index, result) in receiver {
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&next_index) {
+                emit(result);
+                next_index += 1;
+                // (never blocks, permits in flight are back in the channel.)
+                let _ = permit.send(());
+            }
+        }
+    });
+}
@@ -0,0 +1,563 @@
+//! Regular expressions (without external dependencies), for
+//! matching strings in queries (example: `.test("^[a-z]+@example\\.com$")`).
+//!
+//! Supported syntax: literals, `.` (any char but a newline), classes
+//! (`[a-z_]`, `[^0-9]`), escapes (`\d`, `\w`, `\s`, negated `\D`, `\W`,
+//! `\S`, `\n`, `\t`, `\r` and escaped metacharacters, example: `\.`),
+//! anchors (`^`, `$`), groups (capturing `(ab)`, non-capturing `(?:ab)`),
+//! alternation (`a|b`) and quantifiers (`*`, `+`, `?`, `{n}`, `{n,}`,
+//! `{n,m}`, lazy with a trailing `?`, example: `.*?`).
+use crate::prelude::*;
+use core::ops::Range;
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Char(char),
+    Range(char, char),
+    /// `\d`, `\w`, `\s` (or negated) inside a class.
+    Escape(char),
+}
+
+#[derive(Debug, Clone)]
+struct Repeat {
+    node: Box<Node>,
+    min: u32,
+    max: Option<u32>,
+    greedy: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    /// `\d`, `\w`, `\s` (or negated).
+    Escape(char),
+    /// items and whether the class is negated.
+    Class(Vec<ClassItem>, bool),
+    Start,
+    End,
+    /// capture index (`None` for non-capturing groups).
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat(Repeat),
+}
+
+/// maximum count of a bounded repetition (example: `a{1000}`), repeated
+/// nodes are copied.
+pub const MAX_REPEAT: u32 = 1000;
+
+/// maximum count of compiled instructions (nested repetitions multiply, for
+/// example: `(?:a{1000}){1000}` is a syntax error).
+pub const MAX_PROGRAM: usize = 100_000;
+
+/// Compiled regular expression, see [`new`](Self::new). matching takes time
+/// proportional to the length of the input times the size of the compiled
+/// program (all alternatives are tried in parallel, without backtracking).
+#[derive(Debug, Clone)]
+pub struct Regex {
+    pattern: String,
+    program: Vec<Inst>,
+    groups: usize,
+}
+
+/// char ranges (offsets in unicode scalar values) of the whole match (at
+/// `0`) and the capture groups (`None`, for groups that didn't match).
+pub type Captures = Vec<Option<Range<usize>>>;
+
+impl Regex {
+    /// compile `pattern`, errors contain the offset (in chars) of the invalid
+    /// syntax.
+    pub fn new(pattern: &str) -> Result<Self, usize> {
+        let mut compiler = Compiler {
+            chars: pattern.chars().collect(),
+            cursor: 0,
+            groups: 0,
+        };
+        let root = compiler.alternate()?;
+        // unmatched `)`.
+        if compiler.cursor < compiler.chars.len() {
+            return Err(compiler.cursor);
+        }
+        // whole match is group `0`.
+        let mut program = vec![Inst::Save(0)];
+        compile(&root, &mut program);
+        program.extend([Inst::Save(1), Inst::Match].iter().cloned());
+        Ok(Self {
+            pattern: pattern.into(),
+            program,
+            groups: compiler.groups,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// `s` contains a match.
+    pub fn is_match(&self, s: &str) -> bool {
+        self.captures(s).is_some()
+    }
+
+    /// leftmost match in `s` along with the capture groups (`None`, if there
+    /// is no match).
+    pub fn captures(&self, s: &str) -> Option<Captures> {
+        let input: Vec<char> = s.chars().collect();
+        let threads = || Threads {
+            threads: vec![],
+            added: vec![0; self.program.len()],
+        };
+        let (mut current, mut next) = (threads(), threads());
+        let mut matched = None;
+        for pos in 0..=input.len() {
+            // match starting at `pos`, less preferred than earlier starts.
+            if matched.is_none() {
+                let slots = vec![None; 2 * (self.groups + 1)];
+                self.add(&mut current, 0, slots, pos, input.len());
+            }
+            for thread in current.threads.drain(..) {
+                match &self.program[thread.pc] {
+                    Inst::Char(node) => {
+                        if input
+                            .get(pos)
+                            .is_some_and(|ch| char_matches(node, *ch))
+                        {
+                            let pc = thread.pc + 1;
+                            self.add(
+                                &mut next,
+                                pc,
+                                thread.slots,
+                                pos + 1,
+                                input.len(),
+                            );
+                        }
+                    }
+                    // less preferred threads are dropped.
+                    _ => {
+                        matched = Some(thread.slots);
+                        break;
+                    }
+                }
+            }
+            if next.threads.is_empty() && matched.is_some() {
+                break;
+            }
+            core::mem::swap(&mut current, &mut next);
+        }
+        matched.map(|slots| {
+            slots
+                .chunks(2)
+                .map(|group| match group {
+                    [Some(start), Some(end)] => Some(*start..*end),
+                    _ => None,
+                })
+                .collect()
+        })
+    }
+}
+
+/// same pattern (compiled the same).
+impl PartialEq for Regex {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl core::fmt::Display for Regex {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+struct Compiler {
+    chars: Vec<char>,
+    cursor: usize,
+    groups: usize,
+}
+
+type CompileResult = Result<Node, usize>;
+
+impl Compiler {
+    #[inline]
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.cursor).copied()
+    }
+
+    #[inline]
+    fn consume(&mut self, ch: char) -> bool {
+        let matches = self.peek() == Some(ch);
+        if matches {
+            self.cursor += 1;
+        }
+        matches
+    }
+
+    /// `concat ('|' concat)*`.
+    fn alternate(&mut self) -> CompileResult {
+        let mut branches = vec![self.concat()?];
+        let mut total = size(&branches[0]);
+        while self.peek() == Some('|') {
+            let at = self.cursor;
+            self.cursor += 1;
+            branches.push(self.concat()?);
+            total = total.saturating_add(size(branches.last().unwrap()) + 2);
+            if total > MAX_PROGRAM {
+                return Err(at);
+            }
+        }
+        Ok(match branches.len() {
+            1 => branches.remove(0),
+            _ => Node::Alternate(branches),
+        })
+    }
+
+    /// quantified atoms, up to `|`, `)` or the end of the pattern.
+    fn concat(&mut self) -> CompileResult {
+        let (mut nodes, mut total) = (vec![], 0usize);
+        while !matches!(self.peek(), None | Some('|' | ')')) {
+            let at = self.cursor;
+            let atom = self.atom()?;
+            nodes.push(self.quantified(atom)?);
+            total = total.saturating_add(size(nodes.last().unwrap()));
+            if total > MAX_PROGRAM {
+                return Err(at);
+            }
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn atom(&mut self) -> CompileResult {
+        let at = self.cursor;
+        self.cursor += 1;
+        Ok(match self.chars[at] {
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '(' => {
+                let index =
+                    if self.chars[self.cursor..].starts_with(&['?', ':']) {
+                        self.cursor += 2;
+                        None
+                    } else {
+                        self.groups += 1;
+                        Some(self.groups)
+                    };
+                let node = self.alternate()?;
+                if !self.consume(')') {
+                    return Err(at);
+                }
+                Node::Group(Box::new(node), index)
+            }
+            '[' => self.class(at)?,
+            '\\' => self.escape()?,
+            // nothing to repeat.
+            '*' | '+' | '?' => return Err(at),
+            ch => Node::Char(ch),
+        })
+    }
+
+    /// `node` followed by an optional quantifier (a `{` that isn't one is a
+    /// literal).
+    fn quantified(&mut self, node: Node) -> CompileResult {
+        let at = self.cursor;
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => match self.bounds() {
+                Some((min, max)) if min.max(max.unwrap_or(0)) > MAX_REPEAT => {
+                    return Err(at)
+                }
+                Some(bounds) => bounds,
+                None => {
+                    self.cursor = at;
+                    return Ok(node);
+                }
+            },
+            _ => return Ok(node),
+        };
+        if self.cursor == at {
+            self.cursor += 1;
+        }
+        if max.is_some_and(|max| max < min) {
+            return Err(at);
+        }
+        let node = Node::Repeat(Repeat {
+            node: Box::new(node),
+            min,
+            max,
+            greedy: !self.consume('?'),
+        });
+        match size(&node) > MAX_PROGRAM {
+            true => Err(at),
+            false => Ok(node),
+        }
+    }
+
+    /// `{n}`, `{n,}` or `{n,m}`.
+    fn bounds(&mut self) -> Option<(u32, Option<u32>)> {
+        self.consume('{');
+        let min = self.number()?;
+        let max = match (self.consume(','), self.peek()) {
+            (false, _) => Some(min),
+            (true, Some('}')) => None,
+            (true, _) => Some(self.number()?),
+        };
+        self.consume('}').then_some((min, max))
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.cursor;
+        while self.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+            self.cursor += 1;
+        }
+        self.chars[start..self.cursor]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+
+    /// char following `\`, as an escape class or a literal.
+    fn escape(&mut self) -> CompileResult {
+        let at = self.cursor;
+        let ch = self.peek().ok_or(at - 1)?;
+        self.cursor += 1;
+        Ok(match ch {
+            'd' | 'D' | 'w' | 'W' | 's' | 'S' => Node::Escape(ch),
+            'n' => Node::Char('\n'),
+            't' => Node::Char('\t'),
+            'r' => Node::Char('\r'),
+            // unknown escapes (example: `\q`) are reserved.
+            ch if ch.is_alphanumeric() => return Err(at),
+            ch => Node::Char(ch),
+        })
+    }
+
+    /// class following `[` (at `open`), a leading `]` is a literal.
+    fn class(&mut self, open: usize) -> CompileResult {
+        let negated = self.consume('^');
+        let mut items = vec![];
+        loop {
+            let ch = self.peek().ok_or(open)?;
+            self.cursor += 1;
+            let item = match ch {
+                ']' if !items.is_empty() => break,
+                '\\' => match self.escape()? {
+                    Node::Escape(class) => ClassItem::Escape(class),
+                    Node::Char(ch) => ClassItem::Char(ch),
+                    _ => unreachable!(),
+                },
+                ch => ClassItem::Char(ch),
+            };
+            // range (example: `a-z`), a trailing `-` is a literal.
+            let item =
+                match (item, self.peek(), self.chars.get(self.cursor + 1)) {
+                    (ClassItem::Char(start), Some('-'), Some(&end))
+                        if end != ']' && end != '\\' =>
+                    {
+                        if end < start {
+                            return Err(self.cursor + 1);
+                        }
+                        self.cursor += 2;
+                        ClassItem::Range(start, end)
+                    }
+                    (item, ..) => item,
+                };
+            items.push(item);
+        }
+        Ok(Node::Class(items, negated))
+    }
+}
+
+fn escape_matches(class: char, ch: char) -> bool {
+    match class {
+        'd' => ch.is_ascii_digit(),
+        'w' => ch.is_alphanumeric() || ch == '_',
+        's' => ch.is_whitespace(),
+        _ => !escape_matches(class.to_ascii_lowercase(), ch),
+    }
+}
+
+/// whether single char `node` (char, `.`, escape or class) matches `ch`.
+fn char_matches(node: &Node, ch: char) -> bool {
+    match node {
+        Node::Char(expected) => *expected == ch,
+        Node::Any => ch != '\n',
+        Node::Escape(class) => escape_matches(*class, ch),
+        Node::Class(items, negated) => {
+            let matches = items.iter().any(|item| match item {
+                ClassItem::Char(expected) => *expected == ch,
+                ClassItem::Range(start, end) => (*start..=*end).contains(&ch),
+                ClassItem::Escape(class) => escape_matches(*class, ch),
+            });
+            matches != *negated
+        }
+        _ => false,
+    }
+}
+
+/// count of instructions `node` compiles to (saturating).
+fn size(node: &Node) -> usize {
+    match node {
+        Node::Group(node, index) => {
+            size(node) + if index.is_some() { 2 } else { 0 }
+        }
+        Node::Concat(nodes) => nodes
+            .iter()
+            .fold(0, |total, node| total.saturating_add(size(node))),
+        Node::Alternate(branches) => branches
+            .iter()
+            .fold(2 * (branches.len() - 1), |total, node| {
+                total.saturating_add(size(node))
+            }),
+        Node::Repeat(repeat) => {
+            let size = size(&repeat.node);
+            let optional = match repeat.max {
+                None => size.saturating_add(2),
+                Some(max) => size
+                    .saturating_add(1)
+                    .saturating_mul((max - repeat.min) as usize),
+            };
+            size.saturating_mul(repeat.min as usize)
+                .saturating_add(optional)
+        }
+        _ => 1,
+    }
+}
+
+/// append the instructions matching `node` to `program`.
+fn compile(node: &Node, program: &mut Vec<Inst>) {
+    // `repeat` and `exit` branches ordered by priority.
+    let split = |greedy: bool, repeat: usize, exit: usize| match greedy {
+        true => Inst::Split(repeat, exit),
+        false => Inst::Split(exit, repeat),
+    };
+    match node {
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+        Node::Group(node, index) => {
+            if let Some(index) = index {
+                program.push(Inst::Save(2 * index));
+            }
+            compile(node, program);
+            if let Some(index) = index {
+                program.push(Inst::Save(2 * index + 1));
+            }
+        }
+        Node::Concat(nodes) => {
+            for node in nodes.iter() {
+                compile(node, program);
+            }
+        }
+        Node::Alternate(branches) => {
+            let (last, branches) = branches.split_last().unwrap();
+            let mut jumps = vec![];
+            for branch in branches.iter() {
+                let at = program.len();
+                program.push(Inst::Split(at + 1, 0));
+                compile(branch, program);
+                jumps.push(program.len());
+                program.push(Inst::Jump(0));
+                program[at] = Inst::Split(at + 1, program.len());
+            }
+            compile(last, program);
+            for jump in jumps {
+                program[jump] = Inst::Jump(program.len());
+            }
+        }
+        Node::Repeat(repeat) => {
+            for _ in 0..repeat.min {
+                compile(&repeat.node, program);
+            }
+            let mut splits = vec![];
+            match repeat.max {
+                None => {
+                    splits.push(program.len());
+                    program.push(Inst::Split(0, 0));
+                    compile(&repeat.node, program);
+                    program.push(Inst::Jump(splits[0]));
+                }
+                // optional copies, each only tried after the previous one.
+                Some(max) => {
+                    for _ in repeat.min..max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(0, 0));
+                        compile(&repeat.node, program);
+                    }
+                }
+            }
+            let exit = program.len();
+            for at in splits {
+                program[at] = split(repeat.greedy, at + 1, exit);
+            }
+        }
+        node => program.push(Inst::Char(node.clone())),
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Inst {
+    /// single char node (char, `.`, escape or class).
+    Char(Node),
+    Start,
+    End,
+    /// branches, the first one is preferred.
+    Split(usize, usize),
+    Jump(usize),
+    /// current position into a capture slot (start and end of group `n` are
+    /// `2 * n` and `2 * n + 1`).
+    Save(usize),
+    Match,
+}
+
+struct Thread {
+    pc: usize,
+    slots: Vec<Option<usize>>,
+}
+
+/// threads at a position (ordered by priority), along with the position
+/// (plus one) each instruction was last added at.
+struct Threads {
+    threads: Vec<Thread>,
+    added: Vec<usize>,
+}
+
+impl Regex {
+    /// add thread at `pc` to `list`, following jumps, splits, saves and
+    /// anchors up to the char and match instructions (depth first, with an
+    /// explicit stack, preferred branches are added first).
+    fn add(
+        &self,
+        list: &mut Threads,
+        pc: usize,
+        slots: Vec<Option<usize>>,
+        pos: usize,
+        len: usize,
+    ) {
+        let mut stack = vec![(pc, slots)];
+        while let Some((pc, mut slots)) = stack.pop() {
+            if list.added[pc] == pos + 1 {
+                continue;
+            }
+            list.added[pc] = pos + 1;
+            match self.program[pc] {
+                Inst::Jump(to) => stack.push((to, slots)),
+                Inst::Split(first, second) => {
+                    stack.push((second, slots.clone()));
+                    stack.push((first, slots));
+                }
+                Inst::Save(slot) => {
+                    slots[slot] = Some(pos);
+                    stack.push((pc + 1, slots));
+                }
+                Inst::Start if pos == 0 => stack.push((pc + 1, slots)),
+                Inst::End if pos == len => stack.push((pc + 1, slots)),
+                Inst::Start | Inst::End => {}
+                Inst::Char(_) | Inst::Match => {
+                    list.threads.push(Thread { pc, slots })
+                }
+            }
+        }
+    }
+}
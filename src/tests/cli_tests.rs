@@ -26,6 +26,9 @@ fn create_cli(name: &'static str) -> Cli {
             long: Some("--option1"),
             description: vec![],
         },
+        repeatable: true,
+        value: CliValue::Text,
+        validate: None,
     })
     .add_option(CliOption {
         name: "option2",
@@ -35,6 +38,9 @@ fn create_cli(name: &'static str) -> Cli {
             long: Some("--option2"),
             description: vec![],
         },
+        repeatable: false,
+        value: CliValue::Text,
+        validate: None,
     })
     .add_option(CliOption {
         name: "option3",
@@ -44,6 +50,9 @@ fn create_cli(name: &'static str) -> Cli {
             long: Some("--option3"),
             description: vec![],
         },
+        repeatable: false,
+        value: CliValue::Text,
+        validate: None,
     })
     .add_option(CliOption {
         name: "option4",
@@ -53,6 +62,9 @@ fn create_cli(name: &'static str) -> Cli {
             long: Some("--option4"),
             description: vec![],
         },
+        repeatable: false,
+        value: CliValue::Text,
+        validate: None,
     })
     .add_option(CliOption {
         name: "option5",
@@ -62,6 +74,21 @@ fn create_cli(name: &'static str) -> Cli {
             long: Some("--option5"),
             description: vec![],
         },
+        repeatable: false,
+        value: CliValue::Text,
+        validate: None,
+    })
+    .add_option(CliOption {
+        name: "pair",
+        default: None,
+        flag: CliFlag {
+            short: "--pair",
+            long: None,
+            description: vec![],
+        },
+        repeatable: true,
+        value: CliValue::Pair,
+        validate: None,
     });
     cli
 }
@@ -71,7 +98,7 @@ fn success_cli() {
     let cli = create_cli(env!("CARGO_PKG_NAME"));
 
     let mut flags: Vec<String> = vec![];
-    let mut options: HashMap<&str, String> = HashMap::new();
+    let mut options: HashMap<&str, Vec<String>> = HashMap::new();
 
     let mut args = vec![
         "-av1".into(),
@@ -98,10 +125,310 @@ fn success_cli() {
     for (key, value) in options.iter() {
         match key {
             &"option1" | &"option2" | &"option3" | &"option4" => {
-                assert_eq!(*value, String::from("value"))
+                assert_eq!(*value, vec![String::from("value")])
             }
-            &"option5" => assert_eq!(*value, String::from("default")),
+            &"option5" => assert_eq!(*value, vec![String::from("default")]),
             _ => panic!("Invalid option: '{}'", key),
         }
     }
 }
+
+#[test]
+fn success_cli_repeated_option() {
+    let cli = create_cli(env!("CARGO_PKG_NAME"));
+
+    let mut flags: Vec<String> = vec![];
+    let mut options: HashMap<&str, Vec<String>> = HashMap::new();
+
+    let mut args = vec![
+        "-1first".into(),
+        "--option1".into(),
+        "second".into(),
+        "--option1=third".into(),
+        "-2first".into(),
+        "--option2=second".into(),
+    ]
+    .into_iter();
+
+    let parsed = cli.parse_and_populate(&mut args, &mut flags, &mut options);
+    assert!(parsed.is_ok(), "{:?}", parsed);
+    assert_eq!(options["option1"], vec!["first", "second", "third"]);
+    assert_eq!(options["option2"], vec!["second"]);
+    assert_eq!(options["option5"], vec!["default"]);
+    assert!(cli.to_string().contains("  -1, --option1 <option1>...\n"));
+    assert!(cli.to_string().contains("  -2, --option2 <option2>\n"));
+}
+
+#[test]
+fn success_cli_pair_option() {
+    let cli = create_cli(env!("CARGO_PKG_NAME"));
+
+    let mut flags: Vec<String> = vec![];
+    let mut options: HashMap<&str, Vec<String>> = HashMap::new();
+
+    let mut args = vec![
+        "--pair".into(),
+        "a".into(),
+        "1".into(),
+        "--pair=b".into(),
+        "-2".into(),
+        "-a".into(),
+    ]
+    .into_iter();
+
+    let parsed = cli.parse_and_populate(&mut args, &mut flags, &mut options);
+    assert!(parsed.is_ok(), "{:?}", parsed);
+    assert_eq!(options["pair"], vec!["a", "1", "b", "-2"]);
+    assert_eq!(flags, vec![String::from("-a")]);
+    assert!(cli.to_string().contains("  --pair <name> <value>...\n"));
+
+    let mut args = vec!["--pair".into(), "a".into()].into_iter();
+    let parsed = cli.parse_and_populate(&mut args, &mut flags, &mut options);
+    assert_eq!(parsed, Err("'pair' cannot be empty.".into()));
+}
+
+#[test]
+fn success_cli_hyphen_value() {
+    let cli = create_cli(env!("CARGO_PKG_NAME"));
+
+    let mut flags: Vec<String> = vec![];
+    let mut options: HashMap<&str, Vec<String>> = HashMap::new();
+
+    let mut args = vec![
+        "--option2".into(),
+        "-a".into(),
+        "-3".into(),
+        "--option1".into(),
+        "--option4=-1".into(),
+        "--".into(),
+        "-file".into(),
+    ]
+    .into_iter();
+
+    let parsed = cli.parse_and_populate(&mut args, &mut flags, &mut options);
+    assert_eq!(parsed, Ok(Some("-file".into())));
+    assert!(flags.is_empty(), "{:?}", flags);
+    assert_eq!(options["option2"], vec!["-a"]);
+    assert_eq!(options["option3"], vec!["--option1"]);
+    assert_eq!(options["option4"], vec!["-1"]);
+}
+
+#[test]
+fn success_cli_interleaved_positionals() {
+    let cli = create_cli(env!("CARGO_PKG_NAME"));
+
+    let mut flags: Vec<String> = vec![];
+    let mut options: HashMap<&str, Vec<String>> = HashMap::new();
+
+    let mut args = vec![
+        "file1".into(),
+        "-a".into(),
+        "--option1".into(),
+        "value".into(),
+        "-".into(),
+        "file2".into(),
+        "--".into(),
+        "-v".into(),
+        "--option2".into(),
+    ]
+    .into_iter();
+
+    let parsed = cli.parse_all(&mut args, &mut flags, &mut options);
+    assert_eq!(
+        parsed,
+        Ok(vec![
+            "file1".into(),
+            "file2".into(),
+            "-v".into(),
+            "--option2".into()
+        ])
+    );
+    assert_eq!(flags, vec![String::from("-a")]);
+    assert_eq!(options["option1"], vec!["value"]);
+    assert!(!options.contains_key("option2"));
+
+    let mut args = vec!["file".into(), "--unknown".into()].into_iter();
+    let parsed = cli.parse_all(&mut args, &mut flags, &mut options);
+    assert_eq!(parsed, Err(" Invalid flag: '--unknown'.".into()));
+}
+
+#[test]
+fn failure_cli_invalid_long_flag() {
+    let cli = create_cli(env!("CARGO_PKG_NAME"));
+
+    let mut flags: Vec<String> = vec![];
+    let mut options: HashMap<&str, Vec<String>> = HashMap::new();
+    let mut args = vec!["--unknown".into(), "file".into()].into_iter();
+
+    let parsed = cli.parse_and_populate(&mut args, &mut flags, &mut options);
+    assert_eq!(parsed, Err(" Invalid flag: '--unknown'.".into()));
+}
+
+#[test]
+fn failure_cli_invalid_value() {
+    let mut cli = create_cli(env!("CARGO_PKG_NAME"));
+    cli.add_option(CliOption {
+        name: "count",
+        default: None,
+        flag: CliFlag {
+            short: "-c",
+            long: Some("--count"),
+            description: vec![],
+        },
+        repeatable: false,
+        value: CliValue::Integer,
+        validate: Some(|count| match count.parse::<usize>() {
+            Ok(0) => Err("positive integer"),
+            _ => Ok(()),
+        }),
+    })
+    .add_option(CliOption {
+        name: "when",
+        default: None,
+        flag: CliFlag {
+            short: "-W",
+            long: Some("--when"),
+            description: vec![],
+        },
+        repeatable: false,
+        value: CliValue::Choice(&["auto", "never"]),
+        validate: None,
+    });
+
+    for (arg, expected) in [
+        ("-cabc", " Invalid value for 'count': 'abc' (expected non-negative integer)."),
+        ("--count=0", " Invalid value for 'count': '0' (expected positive integer)."),
+        ("-Walways", " Invalid value for 'when': 'always' (expected auto|never)."),
+    ] {
+        let mut flags: Vec<String> = vec![];
+        let mut options: HashMap<&str, Vec<String>> = HashMap::new();
+        let mut args = vec![arg.into()].into_iter();
+        let parsed = cli.parse_and_populate(&mut args, &mut flags, &mut options);
+        assert_eq!(parsed, Err(expected.into()));
+    }
+
+    let mut flags: Vec<String> = vec![];
+    let mut options: HashMap<&str, Vec<String>> = HashMap::new();
+    let mut args =
+        vec!["-c3".into(), "--when".into(), "never".into()].into_iter();
+    let parsed = cli.parse_and_populate(&mut args, &mut flags, &mut options);
+    assert!(parsed.is_ok(), "{:?}", parsed);
+    assert_eq!(options["count"], vec!["3"]);
+    assert_eq!(options["when"], vec!["never"]);
+}
+
+#[test]
+fn success_cli_positionals() {
+    let mut cli = create_cli(env!("CARGO_PKG_NAME"));
+    cli.add_positional(CliArg {
+        name: "A",
+        arity: CliArity::One,
+        required: true,
+        description: vec![],
+    })
+    .add_positional(CliArg {
+        name: "B",
+        arity: CliArity::Many,
+        required: false,
+        description: vec![],
+    });
+    assert!(cli
+        .to_string()
+        .starts_with("USAGE: ruson [FLAGS|OPTIONS]... A [B]...\n"));
+
+    let mut positionals: HashMap<&str, Vec<String>> = HashMap::new();
+    let args = vec!["a".into(), "b1".into(), "b2".into()].into_iter();
+    let populated = cli.populate_positionals(args, &mut positionals);
+    assert!(populated.is_ok(), "{:?}", populated);
+    assert_eq!(positionals["A"], vec!["a"]);
+    assert_eq!(positionals["B"], vec!["b1", "b2"]);
+
+    let mut positionals: HashMap<&str, Vec<String>> = HashMap::new();
+    let populated =
+        cli.populate_positionals(vec![].into_iter(), &mut positionals);
+    assert_eq!(populated, Err(" Missing argument: 'A'.".into()));
+}
+
+#[test]
+fn failure_cli_unexpected_positional() {
+    let mut cli = create_cli(env!("CARGO_PKG_NAME"));
+    cli.add_positional(CliArg {
+        name: "A",
+        arity: CliArity::One,
+        required: false,
+        description: vec![],
+    });
+
+    let mut positionals: HashMap<&str, Vec<String>> = HashMap::new();
+    let args = vec!["a".into(), "b".into()].into_iter();
+    let populated = cli.populate_positionals(args, &mut positionals);
+    assert_eq!(populated, Err(" Unexpected argument: 'b'.".into()));
+}
+
+#[test]
+fn success_cli_command() {
+    let mut cli = create_cli(env!("CARGO_PKG_NAME"));
+    let mut command_cli = Cli::new("ruson cmd");
+    command_cli.add_flag(CliFlag {
+        short: "-x",
+        long: None,
+        description: vec![],
+    });
+    cli.add_command(CliCommand {
+        name: "cmd",
+        description: vec!["command.".into()],
+        cli: command_cli,
+    });
+    assert!(cli.command("other").is_none());
+    assert!(cli.to_string().contains("COMMANDS:\n  cmd\n\t\tcommand.\n"));
+
+    let command = cli.command("cmd").unwrap();
+    let mut flags: Vec<String> = vec![];
+    let mut options: HashMap<&str, Vec<String>> = HashMap::new();
+    let mut args = vec!["-x".into()].into_iter();
+    let parsed =
+        command
+            .cli
+            .parse_and_populate(&mut args, &mut flags, &mut options);
+    assert!(parsed.is_ok(), "{:?}", parsed);
+    assert_eq!(flags, vec![String::from("-x")]);
+
+    // flags of the command are not valid for the parent cli.
+    let mut args = vec!["-x".into()].into_iter();
+    let parsed = cli.parse_and_populate(&mut args, &mut flags, &mut options);
+    assert_eq!(parsed, Err(" Invalid flag: '-x'.".into()));
+}
+
+#[test]
+fn success_manpage() {
+    let mut cli = create_cli(env!("CARGO_PKG_NAME"));
+    cli.add_hidden_flag(CliFlag {
+        short: "--hidden",
+        long: None,
+        description: vec![],
+    })
+    .add_section(CliSection {
+        title: "Examples",
+        lines: vec![],
+        examples: vec![CliExample {
+            description: "example.",
+            command: "ruson -a",
+            output: "null",
+        }],
+    });
+
+    let mut flags: Vec<String> = vec![];
+    let mut options: HashMap<&str, Vec<String>> = HashMap::new();
+    let mut args = vec!["--hidden".into()].into_iter();
+    let parsed = cli.parse_and_populate(&mut args, &mut flags, &mut options);
+    assert!(parsed.is_ok(), "{:?}", parsed);
+    assert_eq!(flags, vec![String::from("--hidden")]);
+
+    let manpage = cli.manpage("0.0.0", "author").to_string();
+    assert!(manpage.starts_with(".TH ruson 1 ruson-0.0.0\n"));
+    assert!(manpage.contains("\\fB\\-1, \\-\\-option1\\fR \\fI<option1>\\fR"));
+    assert!(manpage.contains(".SH EXAMPLES\n"));
+    assert!(manpage.contains("ruson \\-a\n"));
+    assert!(!manpage.contains("hidden"));
+    assert!(!cli.to_string().contains("hidden"));
+}
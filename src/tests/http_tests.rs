@@ -0,0 +1,186 @@
+use crate::http::*;
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+
+/// request head (up to the empty line) sent by the client.
+fn read_request(stream: &mut TcpStream) -> String {
+    let mut request = Vec::new();
+    while !request.ends_with(b"\r\n\r\n") {
+        let mut byte = [0; 1];
+        stream.read_exact(&mut byte).unwrap();
+        request.push(byte[0]);
+    }
+    String::from_utf8(request).unwrap()
+}
+
+#[test]
+fn success_url() {
+    for (s, scheme, host, port, path) in [
+        ("http://localhost", "http", "localhost", 80, "/"),
+        (
+            "https://example.com/a/b",
+            "https",
+            "example.com",
+            443,
+            "/a/b",
+        ),
+        (
+            "http://127.0.0.1:8080?q=1",
+            "http",
+            "127.0.0.1",
+            8080,
+            "/?q=1",
+        ),
+        ("http://[::1]:8000/", "http", "::1", 8000, "/"),
+        ("https://[::1]/a", "https", "::1", 443, "/a"),
+    ]
+    .iter()
+    {
+        let url = Url::parse(s).unwrap();
+        assert_eq!(url.scheme, *scheme);
+        assert_eq!(url.host, *host);
+        assert_eq!(url.port, *port);
+        assert_eq!(url.path, *path);
+    }
+
+    let url = Url::parse("http://localhost/a/b").unwrap();
+    assert_eq!(url.join("/c").unwrap().path, "/c");
+    assert_eq!(url.join("c").unwrap().path, "/a/c");
+
+    // default port of the scheme is omitted.
+    for (s, authority) in [
+        ("http://localhost:80/", "localhost"),
+        ("http://localhost:8080/", "localhost:8080"),
+        ("https://localhost:80/", "localhost:80"),
+        ("http://[::1]/", "[::1]"),
+        ("http://[::1]:8000/", "[::1]:8000"),
+    ]
+    .iter()
+    {
+        assert_eq!(Url::parse(s).unwrap().authority(), *authority);
+    }
+}
+
+#[test]
+fn error_url() {
+    for s in [
+        "ftp://localhost",
+        "localhost",
+        "http://",
+        "http://a:port",
+        "http://[::1",
+        "http://[::1]8000",
+        "http://[]:8000",
+    ]
+    .iter()
+    {
+        assert_eq!(Url::parse(s), None, "{}", s);
+    }
+}
+
+#[test]
+fn success_response() {
+    let response = Response::parse(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+          4\r\n[1, \r\n3;ext=1\r\n2]\n\r\n0\r\n\r\n",
+    )
+    .unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.header("transfer-encoding"), Some("chunked"));
+    assert_eq!(response.body().unwrap(), "[1, 2]\n");
+}
+
+#[test]
+fn success_get() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+        for body in ["", "[1, 2]"].iter() {
+            let (mut stream, _) = listener.accept().unwrap();
+            read_request(&mut stream);
+            let response = if body.is_empty() {
+                "HTTP/1.1 301 Moved\r\nLocation: /moved\r\n\r\n".into()
+            } else {
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+            };
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    let body = get(&format!("http://127.0.0.1:{}/", port));
+    server.join().unwrap();
+    assert_eq!(body, Ok("[1, 2]".into()));
+}
+
+#[test]
+fn success_get_host_header() {
+    for host in ["127.0.0.1", "[::1]"].iter() {
+        let listener = TcpListener::bind(format!("{}:0", host)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request = read_request(&mut stream);
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n[]")
+                .unwrap();
+            request
+        });
+        let body = get(&format!("http://{}:{}/", host, port));
+        let request = server.join().unwrap();
+        assert_eq!(body, Ok("[]".into()));
+        assert!(
+            request.contains(&format!("\r\nHost: {}:{}\r\n", host, port)),
+            "{}",
+            request
+        );
+    }
+}
+
+#[test]
+fn error_get_within() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let sizes = [4, 8, MAX_HEAD_SIZE + 8];
+    let server = std::thread::spawn(move || {
+        for size in sizes.iter() {
+            let (mut stream, _) = listener.accept().unwrap();
+            read_request(&mut stream);
+            let response =
+                format!("HTTP/1.1 200 OK\r\n\r\n[{}]", "0".repeat(size - 2));
+            // the client stops reading past the limit.
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    let url = format!("http://127.0.0.1:{}/", port);
+    assert_eq!(get_within(&url, 4), Ok("[00]".into()));
+    for _ in 1..sizes.len() {
+        assert_eq!(
+            get_within(&url, 4),
+            Err(" Response body exceeds 4 bytes.".into())
+        );
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn error_get_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    // accepts the connection, but never responds.
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        read_request(&mut stream);
+        std::thread::sleep(Duration::from_secs(2));
+    });
+    let start = Instant::now();
+    let url = format!("http://127.0.0.1:{}/", port);
+    assert!(get_with_timeout(&url, Duration::from_millis(200)).is_err());
+    assert!(start.elapsed() < Duration::from_secs(2));
+    server.join().unwrap();
+}
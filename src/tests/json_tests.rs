@@ -1,4 +1,6 @@
 use crate::json::{error::JsonErrorType, parser::JsonParser, token::Json};
+use std::collections::HashMap;
+use std::convert::TryFrom;
 
 macro_rules! json {
     ()                           => { Json::Null };
@@ -7,7 +9,7 @@ macro_rules! json {
     ($str:literal)               => { Json::QString($str.into()) };
     ($($item:expr),*)            => { Json::Array(vec![$($item),*]) };
     ($($k:literal => $v:expr),*) => {
-        Json::Object(std::collections::HashMap::from([$(($k.into(), $v)),*]))
+        Json::Object(HashMap::from([$(($k.into(), $v)),*]).into())
     };
 }
 
@@ -80,6 +82,16 @@ fn success_number() {
     }
 }
 
+#[test]
+fn success_pow10() {
+    use crate::json::parser::pow10;
+
+    // same rounding as `f32::powi` (used before, requires `std`).
+    for exp in 0..48 {
+        assert_eq!(pow10(exp).to_bits(), 10f32.powi(exp).to_bits(), "{}", exp);
+    }
+}
+
 #[test]
 fn error_number() {
     let mut json_parser: JsonParser;
@@ -114,6 +126,8 @@ fn success_string() {
             r#""string with \"escaped double quotes\"""#,
             json!("string with \\\"escaped double quotes\\\""),
         ),
+        (r#""escaped backslash \\""#, json!("escaped backslash \\\\")),
+        (r#""\\\"\\""#, json!("\\\\\\\"\\\\")),
     ]
     .iter()
     {
@@ -140,10 +154,88 @@ fn error_string() {
 fn success_array() {
     let xs = r#"["string", null, 1.03, true]"#;
     let mut json_parser = JsonParser::new(xs);
+    let array = json_parser.parse_array().unwrap();
     assert_eq!(
-        json_parser.parse_array().unwrap(),
+        array,
         json![json!("string"), json!(), Json::Number(1.03), json!(true)]
     );
+    assert_eq!(array.node_count(), 5);
+}
+
+#[test]
+fn success_array_capacity() {
+    // commas inside strings and nested collections are not counted.
+    let xs = r#"[ "a,\"]", [1, 2], { "b": 3, "c": 4 }, [] ]"#;
+    match JsonParser::new(xs).parse().unwrap() {
+        Json::Array(ref array) => {
+            assert_eq!(array.len(), 4);
+            assert_eq!(array.capacity(), 4);
+        }
+        _ => unreachable!(),
+    }
+    let xs = format!("[{}]", vec!["1"; 1000].join(", "));
+    assert_eq!(JsonParser::new(&xs).parse().unwrap().node_count(), 1001);
+}
+
+#[test]
+fn success_deeply_nested() {
+    use crate::json::formatter::{Formatter, PrettyJson};
+
+    // deeper than the call stack of a test thread allows for recursion.
+    let depth = 2_000;
+    let xs = format!("{}null{}", "[{\"a\": ".repeat(depth), "}]".repeat(depth));
+    let json = JsonParser::new(&xs).parse().unwrap();
+    assert_eq!(json.to_string(), xs);
+    let pretty = PrettyJson {
+        indent: "",
+        color: false,
+    };
+    assert_eq!(pretty.dump(&json).lines().count(), 4 * depth + 1);
+    let xs = format!("{}{}", "[".repeat(depth), "]".repeat(depth - 1));
+    assert!(JsonParser::new(&xs).parse().is_err());
+}
+
+#[test]
+fn success_deeply_nested_drop_clone() {
+    use crate::json::{query::JsonQuery, token::Property};
+
+    // dropping/cloning recursively overflows the call stack at this depth.
+    let depth = 100_000;
+    let xs = format!("{}null{}", "[{\"a\": ".repeat(depth), "}]".repeat(depth));
+    let json = JsonParser::new(&xs).parse().unwrap();
+    let cloned = json.clone();
+    assert_eq!(cloned.to_string(), xs);
+    drop(cloned);
+    let query = JsonQuery::new(".length()").unwrap();
+    assert_eq!(json.apply(&query).unwrap(), Json::Number(1.));
+    let element = json.take(&Property::Index(0, false)).unwrap();
+    assert_eq!(element.to_string(), xs[1..xs.len() - 1]);
+}
+
+#[test]
+fn success_parse_parallel() {
+    use crate::json::parallel::{self, MIN_SIZE};
+
+    let element = r#"{ "s": "a,]\"[", "n": [1, {"x": []}] }"#;
+    let count = MIN_SIZE / element.len() + 1;
+    let xs = format!("[{}]", vec![element; count].join(",\n"));
+    let json = JsonParser::new(&xs).parse().unwrap();
+    assert_eq!(parallel::parse(&xs, 4).unwrap(), json);
+    assert_eq!(json.as_array().map(Vec::len), Some(count));
+
+    // errors are same as the sequential parser.
+    for xs in [
+        format!("{}, ]", &xs[..xs.len() - 1]),
+        format!("{} 1]", &xs[..xs.len() - 1]),
+        format!("{}}}", &xs[..xs.len() - 1]),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            parallel::parse(xs, 4).unwrap_err().to_string(),
+            JsonParser::new(xs).parse().unwrap_err().to_string()
+        );
+    }
 }
 
 #[test]
@@ -168,6 +260,19 @@ fn error_array() {
     }
 }
 
+#[test]
+fn success_whitespace() {
+    let xs = " \t\r\n[ 1 ,\r\n\t2 ]\n ";
+    assert_eq!(
+        JsonParser::new(xs).parse().unwrap(),
+        json![Json::Number(1.), Json::Number(2.)]
+    );
+    // only whitespace allowed by the RFC.
+    for xs in ["\u{a0}null", "[1,\u{2028}2]", "\u{c}{}"].iter() {
+        assert!(JsonParser::new(xs).parse().is_err(), "{:?}", xs);
+    }
+}
+
 #[test]
 fn success_object() {
     let xs = r#"{
@@ -232,3 +337,862 @@ fn error_object() {
         };
     }
 }
+
+#[test]
+fn success_error_position() {
+    use crate::lexer::Lexer;
+
+    for input in ["", "[1,\n 2,\r\n x]", "\n\n{\"é\": ?}\n", "a\r"] {
+        let lexer = Lexer::new(input);
+        for cursor in 0..=lexer.stack.len() {
+            // same as counting the lines of the text before `cursor`.
+            let before: String = lexer.stack[..cursor].iter().collect();
+            let position = lexer.position(cursor);
+            assert_eq!(position.row, before.lines().count());
+            assert_eq!(position.col, before.lines().last().unwrap_or("").len());
+            let line: String = input
+                .lines()
+                .skip(position.row.saturating_sub(1))
+                .take(1)
+                .collect();
+            assert_eq!(lexer.line(position.row), line);
+        }
+    }
+}
+
+#[test]
+fn success_lexer_consume() {
+    use crate::lexer::Lexer;
+
+    let mut lexer = Lexer::new("ab12c");
+    assert_eq!(lexer.consume_while(|ch| ch.is_alphabetic()), ['a', 'b']);
+    assert_eq!(lexer.consume_while(|ch| ch.is_alphabetic()), []);
+    assert_eq!(lexer.consume_uint(), Some(12));
+    assert_eq!(lexer.consume_uint(), None);
+
+    // same as `str::parse`.
+    for input in ["0", "007", "4294967295", "4294967296", "-1", ""] {
+        assert_eq!(Lexer::new(input).consume_uint(), input.parse().ok());
+    }
+}
+
+#[test]
+fn success_compact_string() {
+    use crate::json::string::{JsonString, INLINE_CAPACITY};
+    use std::mem::size_of;
+
+    assert_eq!(size_of::<JsonString>(), size_of::<String>());
+    assert_eq!(
+        size_of::<Json>(),
+        size_of::<JsonString>() + size_of::<usize>()
+    );
+    let short = JsonString::from("key");
+    let long = JsonString::from("x".repeat(INLINE_CAPACITY + 1));
+    assert!(short.is_inline() && !long.is_inline());
+    assert_eq!(short, "key");
+    assert_eq!(String::from(long.clone()), "x".repeat(INLINE_CAPACITY + 1));
+    assert!(JsonString::from("x".repeat(INLINE_CAPACITY)).is_inline());
+    assert!(short < long);
+
+    let json: Json = r#"{ "key": "value", "é": "ü" }"#.parse().unwrap();
+    assert_eq!(json.get("key"), Some(&json!("value")));
+    assert_eq!(json.get("é").and_then(Json::as_raw_str), Some("ü"));
+}
+
+#[test]
+fn success_color() {
+    use crate::json::formatter::{Formatter, PrettyJson, RawJson};
+
+    let token = json![json!(), json!("string")];
+    assert_eq!(RawJson { color: false }.dump(&token), r#"[null, "string"]"#);
+    assert_eq!(
+        RawJson { color: true }.dump(&token),
+        "\x1b[1;39m[\x1b[0m\x1b[1;30mnull\x1b[0m\x1b[1;39m,\x1b[0m \
+         \x1b[0;32m\"string\"\x1b[0m\x1b[1;39m]\x1b[0m"
+    );
+    assert_eq!(
+        PrettyJson {
+            indent: "  ",
+            color: false
+        }
+        .dump(&token),
+        "[\n  null,\n  \"string\"\n]"
+    );
+}
+
+#[test]
+fn success_joined() {
+    use crate::json::formatter::{Formatter, JoinedJson};
+
+    let joined = |separator: &str, json: &str| {
+        JoinedJson {
+            separator: separator.into(),
+            color: false,
+        }
+        .dump(&json.parse().unwrap())
+    };
+    assert_eq!(
+        joined(" ", r#"["a b", "c\"d", 1, null, true]"#),
+        "a b c\"d 1  true"
+    );
+    assert_eq!(joined("\n", r#"["x"]"#), "x");
+    assert_eq!(joined(",", "[]"), "");
+    // not an array of scalars.
+    assert_eq!(joined(",", r#"[["x"]]"#), r#"[["x"]]"#);
+    assert_eq!(joined(",", r#""x""#), r#""x""#);
+}
+
+#[test]
+fn success_canonical() {
+    use crate::json::formatter::{CanonicalJson, Formatter};
+    use crate::sha256;
+
+    let canonical = |json: &str| CanonicalJson.dump(&json.parse().unwrap());
+    assert_eq!(
+        canonical(r#"{ "b": [1.5e3, -0, 1e-7, 1e21, 0.25], "a": "A\t\/" }"#),
+        r#"{"a":"A\t/","b":[1500,0,1e-7,1e+21,0.25]}"#
+    );
+    // keys ordered by utf-16 code units (not by code points).
+    assert_eq!(
+        canonical(r#"{"😀": 1, "｡": 2, "a": 3}"#),
+        "{\"a\":3,\"\u{1f600}\":1,\"\u{ff61}\":2}"
+    );
+    assert_eq!(
+        sha256::hex_digest(b""),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+        sha256::hex_digest(b"abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    // multiple blocks.
+    assert_eq!(
+        sha256::hex_digest(&[b'a'; 1000]),
+        "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+    );
+}
+
+#[test]
+fn success_stream() {
+    use crate::json::stream::ArrayStream;
+
+    let xs = r#" [ {"a": [1, "]"]}, "\"," , [], 3 ] "#;
+    // tiny buffer, to make sure elements spanning buffer boundaries work.
+    let reader = std::io::BufReader::with_capacity(2, xs.as_bytes());
+    let elements: Vec<String> = ArrayStream::new(reader)
+        .map(|element| element.unwrap().trim().to_string())
+        .collect();
+    assert_eq!(elements, [r#"{"a": [1, "]"]}"#, r#""\",""#, "[]", "3"]);
+
+    assert_eq!(ArrayStream::new("[ ]\n".as_bytes()).count(), 0);
+    // trailing characters are an error (after the elements).
+    let results: Vec<_> =
+        ArrayStream::new("[1,2] garbage".as_bytes()).collect();
+    assert_eq!(
+        results,
+        [
+            Ok("1".into()),
+            Ok("2".into()),
+            Err(" unexpected characters after the array.".into())
+        ]
+    );
+    for xs in ["{}", "[1, 2", "[] []", "[1]]"].iter() {
+        let results: Vec<_> = ArrayStream::new(xs.as_bytes()).collect();
+        assert!(results.last().unwrap().is_err(), "{}", xs);
+    }
+}
+
+#[test]
+fn error_limits() {
+    use crate::json::parser::JsonLimits;
+
+    for (xs, limits, err) in [
+        (
+            r#"[[1], {"a": [2]}]"#,
+            JsonLimits {
+                max_depth: Some(2),
+                max_size: None,
+                max_memory: None,
+            },
+            JsonErrorType::DepthLimitError,
+        ),
+        (
+            r#"[[[]]]"#,
+            JsonLimits {
+                max_depth: Some(2),
+                max_size: None,
+                max_memory: None,
+            },
+            JsonErrorType::DepthLimitError,
+        ),
+        (
+            r#""ünïcödé""#,
+            JsonLimits {
+                max_depth: None,
+                max_size: Some(10),
+                max_memory: None,
+            },
+            JsonErrorType::SizeLimitError,
+        ),
+        (
+            r#"["a long string value, stored on the heap"]"#,
+            JsonLimits {
+                max_memory: Some(64),
+                ..JsonLimits::default()
+            },
+            JsonErrorType::MemoryLimitError,
+        ),
+    ]
+    .iter()
+    {
+        let parsed = JsonParser::new(xs).with_limits(*limits).parse();
+        assert_eq!(parsed.unwrap_err().error_type, *err, "{}", xs);
+    }
+
+    let limits = JsonLimits {
+        max_depth: Some(2),
+        max_size: Some(17),
+        max_memory: None,
+    };
+    assert!(JsonParser::new(r#"[[1], {"a": 2}]"#)
+        .with_limits(limits)
+        .parse()
+        .is_ok());
+}
+
+#[test]
+fn success_memory_limit() {
+    use crate::json::{
+        error::JsonEvalError, parser::JsonLimits, query::JsonQuery,
+    };
+
+    let xs = r#"{"key": [1, 2, 3], "a long key, stored on the heap": "s"}"#;
+    let json = JsonParser::new(xs).parse().unwrap();
+    let mut parser = JsonParser::new(xs).with_limits(JsonLimits {
+        max_memory: Some(json.memory_size()),
+        ..JsonLimits::default()
+    });
+    assert_eq!(parser.parse().unwrap(), json);
+    assert_eq!(parser.allocated(), json.memory_size());
+
+    let query = JsonQuery::new(".keys()").unwrap();
+    let result = json.apply(&query).unwrap();
+    assert_eq!(json.apply_within(&query, result.memory_size()), Ok(result));
+    assert_eq!(
+        json.apply_within(&query, 100),
+        Err(JsonEvalError::MemoryLimit(100))
+    );
+}
+
+#[test]
+fn success_parser_reset() {
+    use crate::json::parser::JsonLimits;
+
+    let mut parser = JsonParser::new("[[[1]]]").with_limits(JsonLimits {
+        max_depth: Some(2),
+        ..JsonLimits::default()
+    });
+    let err = parser.parse().unwrap_err();
+    assert_eq!(err.error_type, JsonErrorType::DepthLimitError);
+
+    // depth (and the line index of the previous input) start over.
+    assert_eq!(
+        parser.reset("[[1], 2]").parse().unwrap(),
+        json![json![Json::Number(1.)], Json::Number(2.)]
+    );
+    let err = parser.reset("[1,\n 2,]").parse().unwrap_err();
+    assert_eq!(err.error_type, JsonErrorType::TrailingCommaError);
+    assert_eq!((err.position.row, err.line.as_str()), (2, " 2,]"));
+    assert_eq!(parser.reset(" null ").parse().unwrap(), json!());
+    assert!(parser.is_done());
+}
+
+#[test]
+fn success_accessors() {
+    let json = JsonParser::new(r#"{ "a": [null, true, 1.5, "s"], "o": {} }"#)
+        .parse()
+        .unwrap();
+    let array = json.get("a").unwrap();
+    assert_eq!(array.as_array().map(Vec::len), Some(4));
+    assert!(array.get_index(0).unwrap().is_null());
+    assert_eq!(array.get_index(1).and_then(Json::as_bool), Some(true));
+    assert_eq!(array.get_index(2).and_then(Json::as_f64), Some(1.5));
+    assert_eq!(array.get_index(3).and_then(Json::as_raw_str), Some("s"));
+    assert_eq!(array.get_index(4), None);
+    assert!(json.get("o").and_then(Json::as_object).unwrap().is_empty());
+
+    assert_eq!(json.get("missing"), None);
+    assert_eq!(array.get("a"), None);
+    assert_eq!(json.get_index(0), None);
+    assert_eq!(json.as_str(), None);
+    assert!(!json.is_null());
+}
+
+#[test]
+fn success_accessors_escaped() {
+    let json = JsonParser::new(r#"{ "say \"hi\"": "a\tb", "\u0041": 1 }"#)
+        .parse()
+        .unwrap();
+    // keys are looked up unescaped (`get`) or as in the json text (`get_raw`).
+    let value = json.get("say \"hi\"").unwrap();
+    assert_eq!(json.get_raw(r#"say \"hi\""#), Some(value));
+    assert_eq!(json.get_raw("say \"hi\""), None);
+    assert_eq!(json.get("A"), Some(&Json::Number(1.)));
+    assert_eq!(json.get_raw("A"), None);
+
+    // strings are unescaped (`as_str`), same as `String::try_from`.
+    assert_eq!(value.as_str().as_deref(), Some("a\tb"));
+    assert_eq!(value.as_raw_str(), Some(r#"a\tb"#));
+    assert_eq!(String::try_from(value).ok().as_deref(), Some("a\tb"));
+}
+
+#[test]
+fn success_from_conversions() {
+    assert_eq!(Json::from("a\"b\\c\n"), json!(r#"a\"b\\c\n"#));
+    assert_eq!(Json::from(String::from("s")), json!("s"));
+    assert_eq!(Json::try_from(1.5), Ok(Json::Number(1.5)));
+    assert_eq!(Json::try_from(-2i64), Ok(Json::Number(-2.)));
+    assert_eq!(Json::from(true), json!(true));
+    assert_eq!(
+        Json::from(vec![json!(), json!(false)]),
+        json!(json!(), json!(false))
+    );
+    assert_eq!(
+        Json::from(HashMap::from([("k".into(), json!())])),
+        json!("k" => json!())
+    );
+
+    let array: Json =
+        (1..=2).map(|n| Json::try_from(n as i64).unwrap()).collect();
+    assert_eq!(array, json!(Json::Number(1.), Json::Number(2.)));
+    let object: Json = vec![("k".to_string(), Json::from("v"))]
+        .into_iter()
+        .collect();
+    assert_eq!(object, json!("k" => json!("v")));
+
+    // escaped strings are parsed back as is.
+    let parsed = JsonParser::new(&Json::from("q\"\t").to_string()).parse();
+    assert_eq!(parsed.unwrap(), Json::from("q\"\t"));
+}
+
+#[test]
+fn success_from_escaped_keys() {
+    let key = "say \"hi\"\n\\";
+    let from_map = Json::from(HashMap::from([(key.to_string(), json!())]));
+    let from_iter: Json = vec![(key.to_string(), Json::from("\t"))]
+        .into_iter()
+        .collect();
+    for json in [from_map, from_iter].iter() {
+        let parsed = JsonParser::new(&json.to_string()).parse().unwrap();
+        assert_eq!(&parsed, json);
+        assert!(parsed.get(key).is_some());
+    }
+}
+
+#[test]
+fn success_try_from() {
+    let json = JsonParser::new(r#"[1, 2.5, "a\"é😀", true, [1, 2]]"#)
+        .parse()
+        .unwrap();
+    let item = |index| json.get_index(index).unwrap();
+    assert_eq!(i64::try_from(item(0)), Ok(1));
+    assert_eq!(f64::try_from(item(1)), Ok(2.5));
+    assert_eq!(String::try_from(item(2)), Ok("a\"é😀".into()));
+    assert_eq!(bool::try_from(item(3)), Ok(true));
+    assert_eq!(Vec::<i64>::try_from(item(4).clone()), Ok(vec![1, 2]));
+
+    assert_eq!(
+        i64::try_from(item(1)).unwrap_err().to_string(),
+        " cannot convert 'Number' (2.5) into 'i64'."
+    );
+    assert_eq!(
+        String::try_from(json.clone()).unwrap_err().to_string(),
+        " cannot convert 'Array' into 'String'."
+    );
+    assert!(Vec::<bool>::try_from(&json).is_err());
+}
+
+#[test]
+fn success_try_from_exact_numbers() {
+    // `2^24 + 1` is the smallest integer an `f32` can't represent.
+    let int = (1i64 << 24) + 1;
+    assert_eq!(
+        Json::try_from(int).unwrap_err().to_string(),
+        " cannot convert 'i64' (16777217) into 'Json'."
+    );
+    assert_eq!(Json::try_from(int - 1), Ok(Json::Number(16777216.)));
+    assert_eq!(i64::try_from(Json::try_from(int - 1).unwrap()), Ok(int - 1));
+    assert!(Json::try_from(i64::MAX).is_err());
+    assert_eq!(Json::try_from(i64::MIN), Ok(Json::Number(i64::MIN as f32)));
+
+    assert!(Json::try_from(0.1).is_err());
+    assert!(Json::try_from(f64::NAN).is_err());
+    assert!(Json::try_from(f64::INFINITY).is_err());
+    assert!(Json::try_from(1e300).is_err());
+
+    // out of range of `i64` (instead of saturating).
+    assert!(i64::try_from(Json::Number(1e19)).is_err());
+    assert!(i64::try_from(Json::Number(-(i64::MIN as f32))).is_err());
+    assert_eq!(i64::try_from(Json::Number(i64::MIN as f32)), Ok(i64::MIN));
+}
+
+#[test]
+fn success_unescape() {
+    use crate::json::token::unescape;
+    for (escaped, unescaped) in [
+        (r#"\"\\\/\b\f\n\r\t"#, "\"\\/\u{08}\u{0c}\n\r\t"),
+        (r"\u00e9\ud83d\ude00", "é😀"),
+        // invalid escape sequences are kept as is.
+        (r"\x\u12\ud83d", r"\x\u12\ud83d"),
+    ] {
+        assert_eq!(unescape(escaped), unescaped, "{}", escaped);
+    }
+}
+
+#[test]
+fn success_convert_traits() {
+    use crate::json::convert::{FromJson, ToJson};
+    let map = HashMap::from([("k\"".to_string(), vec![Some(1u32), None])]);
+    let json = map.to_json();
+    let value = json.get("k\"").unwrap();
+    assert_eq!(value, &json!(Json::Number(1.), json!()));
+    assert_eq!(HashMap::from_json(&json), Ok(map));
+
+    assert_eq!(i32::from_json(&Json::Number(-3.)), Ok(-3));
+    assert!(u32::from_json(&Json::Number(-3.)).is_err());
+    assert!(usize::from_json(&json!("1")).is_err());
+    assert_eq!("s".to_json(), json!("s"));
+}
+
+#[test]
+fn success_deep_merge() {
+    use crate::json::merge::{ArrayMerge, MergeStrategy};
+    let base = r#"{ "a": { "b": 1, "c": [1, { "x": 1 }] }, "d": 1 }"#;
+    let other = r#"{ "a": { "c": [{ "y": 2 }, { "y": 2 }, 3] }, "d": null }"#;
+    for (strategy, expected) in [
+        (
+            MergeStrategy::default(),
+            r#"{ "a": { "b": 1, "c": [{ "y": 2 }, { "y": 2 }, 3] }, "d": null }"#,
+        ),
+        (
+            MergeStrategy {
+                arrays: ArrayMerge::Concat,
+                null_removes: true,
+            },
+            r#"{ "a": { "b": 1, "c": [1, { "x": 1 }, { "y": 2 }, { "y": 2 }, 3] } }"#,
+        ),
+        (
+            MergeStrategy {
+                arrays: ArrayMerge::ByIndex,
+                null_removes: false,
+            },
+            r#"{ "a": { "b": 1, "c": [{ "y": 2 }, { "x": 1, "y": 2 }, 3] }, "d": null }"#,
+        ),
+    ] {
+        let mut merged: Json = base.parse().unwrap();
+        merged.deep_merge(other.parse().unwrap(), strategy);
+        assert_eq!(merged, expected.parse().unwrap(), "{:?}", strategy);
+    }
+
+    let mut merged = json!(true);
+    merged.deep_merge(json!("a" => json!()), MergeStrategy::default());
+    assert_eq!(merged, json!("a" => json!()));
+}
+
+#[test]
+fn success_iterators() {
+    use crate::json::query::JsonQuery;
+    let json: Json =
+        r#"{ "b": [true, { "c d": null }], "a": 1 }"#.parse().unwrap();
+    assert_eq!(json.entries().count(), 2);
+    assert_eq!(json.elements().count(), 0);
+    assert_eq!(json.get("b").unwrap().elements().count(), 2);
+
+    let paths: Vec<String> = json.iter_paths().map(|(path, _)| path).collect();
+    assert_eq!(
+        paths,
+        vec!["", ".a", ".b", ".b[0]", ".b[1]", r#".b[1]["c d"]"#]
+    );
+    // paths are valid queries, resolving to the same node.
+    for (path, node) in json.iter_paths() {
+        let query = JsonQuery::new(&path).unwrap();
+        assert_eq!(&json.apply(&query).unwrap(), node, "{}", path);
+    }
+}
+
+#[test]
+fn success_visitor() {
+    use crate::json::visit::{walk_array, JsonVisitor};
+
+    /// counts strings and keys, skipping arrays nested in arrays.
+    #[derive(Default)]
+    struct Counter {
+        strings: usize,
+        keys: usize,
+        depth: usize,
+    }
+
+    impl JsonVisitor for Counter {
+        fn visit_string(&mut self, _string: &str) {
+            self.strings += 1;
+        }
+
+        fn visit_key(&mut self, _key: &str) {
+            self.keys += 1;
+        }
+
+        fn visit_array(&mut self, array: &[Json]) {
+            if self.depth == 0 {
+                self.depth += 1;
+                walk_array(self, array);
+                self.depth -= 1;
+            }
+        }
+    }
+
+    let json: Json =
+        r#"{ "a": ["x", ["y"]], "b": { "c": "z" } }"#.parse().unwrap();
+    let mut counter = Counter::default();
+    json.accept(&mut counter);
+    assert_eq!((counter.strings, counter.keys), (2, 3));
+}
+
+#[test]
+fn success_borrowed() {
+    use crate::json::{borrowed::JsonRef, query::JsonQuery};
+    use std::borrow::Cow;
+
+    let input =
+        r#"{ "a": ["x\ty", "plain"], "b": { "c": -1.5e2 }, "d": null }"#;
+    let json = JsonRef::parse(input).unwrap();
+    let array = json.get("a").unwrap();
+    // unescaped on demand.
+    assert!(matches!(
+        array.get_index(0),
+        Some(JsonRef::QString(string)) if string.as_raw() == "x\\ty"
+            && matches!(string.unescaped(), Cow::Owned(s) if s == "x\ty")
+    ));
+    assert!(matches!(
+        array.get_index(1),
+        Some(JsonRef::QString(string))
+            if matches!(string.unescaped(), Cow::Borrowed("plain"))
+    ));
+    let (a, b) = (JsonRef::parse(r#""\u0041""#), JsonRef::parse(r#""A""#));
+    assert_eq!(a.unwrap(), b.unwrap());
+    assert_eq!(json.to_json(), input.parse::<Json>().unwrap());
+
+    let query: JsonQuery = ".b.c".parse().unwrap();
+    assert_eq!(json.apply(&query).unwrap(), Json::Number(-150.0));
+    let query: JsonQuery = ".a.length()".parse().unwrap();
+    assert_eq!(json.apply(&query).unwrap(), Json::Number(2.0));
+    let query: JsonQuery = ".b.x".parse().unwrap();
+    assert!(json.apply(&query).is_err());
+    let query: JsonQuery = ".b.x?.y".parse().unwrap();
+    assert_eq!(json.apply(&query).unwrap(), Json::Null);
+    let query: JsonQuery = ".b.x?.y | .length()".parse().unwrap();
+    assert_eq!(json.apply(&query).unwrap(), Json::Number(0.0));
+}
+
+#[test]
+fn success_arena() {
+    use crate::json::{arena::JsonArena, query::JsonQuery};
+
+    let input =
+        r#"{ "a": ["x\ty", "plain"], "b": { "c": -1.5e2 }, "d": null }"#;
+    let arena = JsonArena::parse(input).unwrap();
+    assert_eq!(arena.len(), 7);
+    let root = arena.root();
+    let array = root.get("a").unwrap();
+    assert_eq!(array.size(), Some(2));
+    assert_eq!(
+        array.get_index(0).unwrap().as_str().as_deref(),
+        Some("x\ty")
+    );
+    assert_eq!(
+        root.get("b").unwrap().get("c").unwrap().as_number(),
+        Some(-150.0)
+    );
+    assert!(root.get("d").unwrap().is_null());
+    assert!(root.get("e").is_none());
+    assert_eq!(root.to_json(), input.parse::<Json>().unwrap());
+
+    let query: JsonQuery = ".b.c".parse().unwrap();
+    assert_eq!(root.apply(&query).unwrap(), Json::Number(-150.0));
+    let query: JsonQuery = ".a.length()".parse().unwrap();
+    assert_eq!(root.apply(&query).unwrap(), Json::Number(2.0));
+    let query: JsonQuery = ".a[2]".parse().unwrap();
+    assert!(root.apply(&query).is_err());
+    let query: JsonQuery = ".a[2]?.length()".parse().unwrap();
+    assert_eq!(root.apply(&query).unwrap(), Json::Null);
+    let query: JsonQuery = ".a[2]? | .length()".parse().unwrap();
+    assert_eq!(root.apply(&query).unwrap(), Json::Number(0.0));
+
+    // duplicate keys, beyond the linear scan.
+    let keys: Vec<_> = (0..40).map(|n| format!("\"k{}\": {}", n, n)).collect();
+    let input = format!("{{ {}, \"k39\": 0 }}", keys.join(", "));
+    let error = JsonArena::parse(&input).unwrap_err();
+    assert_eq!(error.error_type, JsonErrorType::DuplicateKeyError);
+    let input = format!("{{ {} }}", keys.join(", "));
+    assert_eq!(JsonArena::parse(&input).unwrap().root().size(), Some(40));
+}
+
+#[test]
+fn error_borrowed() {
+    use crate::json::borrowed::JsonRef;
+
+    for &(input, ref error_type, position) in [
+        ("[1, 2,]", JsonErrorType::TrailingCommaError, (1, 5)),
+        (
+            "{\n \"a\": 1,\n \"a\": 2 }",
+            JsonErrorType::DuplicateKeyError,
+            (3, 1),
+        ),
+        ("[01x]", JsonErrorType::SyntaxError, (1, 3)),
+        ("\"unterminated", JsonErrorType::SyntaxError, (1, 13)),
+        ("nul", JsonErrorType::SyntaxError, (1, 0)),
+    ]
+    .iter()
+    {
+        let error = JsonRef::parse(input).unwrap_err();
+        assert_eq!(&error.error_type, error_type, "{}", input);
+        assert_eq!(
+            (error.position.row, error.position.col),
+            position,
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn success_reader_writer() {
+    use crate::json::{error::JsonReadError, formatter::PrettyJson};
+
+    let json = Json::from_reader(&br#"{ "a": [1, true] }"#[..]).unwrap();
+    let mut output = Vec::new();
+    let style = PrettyJson {
+        indent: "  ",
+        color: false,
+    };
+    json.write(&mut output, &style).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "{\n  \"a\": [\n    1,\n    true\n  ]\n}"
+    );
+
+    assert!(matches!(
+        Json::from_reader(&b"[1,]"[..]),
+        Err(JsonReadError::Parse(_))
+    ));
+    assert!(matches!(
+        Json::from_reader(&b"\"\xff\""[..]),
+        Err(JsonReadError::Io(_))
+    ));
+}
+
+#[test]
+fn success_ordering() {
+    let parse = |s: &str| s.parse::<Json>().unwrap();
+    let ordered: Vec<Json> = vec![
+        "null",
+        "false",
+        "true",
+        "-1",
+        "0.5",
+        "2",
+        r#""""#,
+        r#""A""#,
+        r#""a""#,
+        "[]",
+        "[1]",
+        "[1, 2]",
+        "[2]",
+        "{}",
+        r#"{ "a": 2 }"#,
+        r#"{ "a": 1, "b": 0 }"#,
+        r#"{ "b": 0 }"#,
+    ]
+    .into_iter()
+    .map(parse)
+    .collect();
+    for (index, a) in ordered.iter().enumerate() {
+        for (other, b) in ordered.iter().enumerate() {
+            assert_eq!(a.cmp(b), index.cmp(&other), "{} <=> {}", a, b);
+        }
+    }
+
+    let mut shuffled: Vec<Json> = ordered.iter().rev().cloned().collect();
+    shuffled.sort();
+    assert_eq!(shuffled, ordered);
+
+    assert_eq!(parse(r#""\u0041""#), parse(r#""A""#));
+    assert_eq!(parse(r#"{ "\u0041": 0 }"#), parse(r#"{ "A": 0 }"#));
+    assert_eq!(parse("-0"), parse("0"));
+    assert!(parse(r#""\n""#) < parse(r#""A""#));
+}
+
+#[test]
+fn success_hash() {
+    use std::collections::HashSet;
+
+    let values: HashSet<Json> = vec![
+        "0",
+        "-0",
+        r#""A""#,
+        r#""A""#,
+        r#"{ "a": 1, "b": [null] }"#,
+        r#"{ "b": [null], "a": 1 }"#,
+        "[1, 2]",
+        "[2, 1]",
+    ]
+    .into_iter()
+    .map(|s| s.parse().unwrap())
+    .collect();
+    assert_eq!(values.len(), 5);
+    assert!(values.contains(&Json::Number(0.0)));
+    assert!(values.contains(&Json::from("A")));
+}
+
+#[test]
+fn success_shared() {
+    use crate::json::{query::JsonQuery, shared::SharedJson};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SharedJson>();
+
+    let json: Json =
+        r#"{ "a": [1, { "b": "x" }], "c": null }"#.parse().unwrap();
+    let shared = SharedJson::from(json.clone());
+    let mut copy = shared.clone();
+    // clones share the nodes.
+    assert!(std::ptr::eq(
+        shared.get("a").unwrap().as_array().unwrap(),
+        copy.get("a").unwrap().as_array().unwrap()
+    ));
+
+    // copy on write, the original is left unchanged.
+    *copy.get_mut("a").unwrap().get_index_mut(0).unwrap() = SharedJson::Null;
+    assert_eq!(shared.to_json(), json);
+    assert_eq!(copy.get("a").unwrap().get_index(0), Some(&SharedJson::Null));
+    assert!(std::ptr::eq(
+        shared
+            .get("a")
+            .unwrap()
+            .get_index(1)
+            .unwrap()
+            .as_object()
+            .unwrap(),
+        copy.get("a")
+            .unwrap()
+            .get_index(1)
+            .unwrap()
+            .as_object()
+            .unwrap()
+    ));
+
+    let query: JsonQuery = ".a[1]".parse().unwrap();
+    let subtree = shared.apply(&query).unwrap();
+    assert!(std::ptr::eq(
+        subtree.as_object().unwrap(),
+        shared
+            .get("a")
+            .unwrap()
+            .get_index(1)
+            .unwrap()
+            .as_object()
+            .unwrap()
+    ));
+    let query: JsonQuery = ".a.length()".parse().unwrap();
+    assert_eq!(shared.apply(&query).unwrap(), SharedJson::Number(2.0));
+    let query: JsonQuery = ".c.x".parse().unwrap();
+    assert!(shared.apply(&query).is_err());
+    let query: JsonQuery = ".c.x? | .length()".parse().unwrap();
+    assert_eq!(shared.apply(&query).unwrap(), SharedJson::Number(0.0));
+}
+
+#[test]
+fn success_shared_interned_keys() {
+    use crate::json::shared::SharedJson;
+    use std::sync::Arc;
+
+    let input = r#"[{ "id": 1, "tab\t": "a" }, { "id": 2, "tab\t": "b" }]"#;
+    for shared in &[
+        SharedJson::parse(input).unwrap(),
+        SharedJson::from(input.parse::<Json>().unwrap()),
+    ] {
+        let keys = |index| {
+            let object = shared.get_index(index).unwrap().as_object().unwrap();
+            let mut keys: Vec<_> = object.keys().cloned().collect();
+            keys.sort();
+            keys
+        };
+        let (first, second) = (keys(0), keys(1));
+        assert_eq!(first.len(), 2);
+        for (a, b) in first.iter().zip(second.iter()) {
+            assert!(Arc::ptr_eq(a, b), "{}", a);
+        }
+        assert_eq!(shared.to_json(), input.parse::<Json>().unwrap());
+    }
+    assert!(SharedJson::parse("[1,]").is_err());
+}
+
+#[test]
+fn success_stats() {
+    use crate::json::stats::Stats;
+
+    let mut stats = Stats::new();
+    for value in [
+        Json::Number(200.),
+        Json::Number(404.),
+        json!("x"),
+        Json::Number(200.),
+    ] {
+        stats.add(Some(value));
+    }
+    stats.add(None);
+    assert_eq!((stats.count, stats.missing, stats.values.len()), (5, 1, 3));
+    assert_eq!((stats.min, stats.max), (Some(200.), Some(404.)));
+    assert_eq!(stats.mean(), Some(268.));
+    // most frequent first, ties ordered by value.
+    assert_eq!(
+        stats.top(2),
+        [(&Json::Number(200.), 2), (&Json::Number(404.), 1)]
+    );
+    let expected: Json = r#"{
+        "count": 5, "missing": 1, "distinct": 3,
+        "min": 200, "max": 404, "mean": 268,
+        "top": [{ "value": 200, "count": 2 }]
+    }"#
+    .parse()
+    .unwrap();
+    assert_eq!(stats.to_json(1), expected);
+
+    let json = Stats::new().to_json(10);
+    assert_eq!(json.get("mean"), Some(&Json::Null));
+    assert_eq!(json.get("top"), Some(&Json::Array(vec![])));
+}
+
+#[test]
+fn success_metrics() {
+    let json: Json =
+        r#"{ "a": [1, "xy", null, [true]], "b\n": {} }"#.parse().unwrap();
+    let metrics = json.metrics();
+    assert_eq!(metrics.depth, 3);
+    assert_eq!(
+        (
+            metrics.nulls,
+            metrics.booleans,
+            metrics.numbers,
+            metrics.strings
+        ),
+        (1, 1, 1, 1)
+    );
+    assert_eq!((metrics.arrays, metrics.objects), (2, 2));
+    assert_eq!(metrics.nodes(), json.node_count());
+    // keys count towards the string bytes (escaped).
+    assert_eq!(metrics.string_bytes, "xy".len() + "a".len() + "b\\n".len());
+    assert_eq!(metrics.memory, json.memory_size());
+
+    let metrics = Json::Null.metrics();
+    assert_eq!((metrics.depth, metrics.nodes()), (0, 1));
+}
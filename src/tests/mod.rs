@@ -1,3 +1,17 @@
+#[cfg(feature = "cli")]
 pub mod cli_tests;
+#[cfg(feature = "cli")]
+pub mod http_tests;
+#[cfg(feature = "core")]
 pub mod json_tests;
+#[cfg(feature = "core")]
+pub mod patch_tests;
+#[cfg(feature = "cli")]
+pub mod pool_tests;
+#[cfg(feature = "core")]
 pub mod query_tests;
+pub mod regex_tests;
+#[cfg(feature = "serde")]
+pub mod serde_tests;
+#[cfg(feature = "wasm")]
+pub mod wasm_tests;
@@ -0,0 +1,141 @@
+use crate::json::{
+    convert::ToJson,
+    error::JsonPatchError,
+    patch::{Operation, Patch},
+    token::Json,
+};
+
+fn json(s: &str) -> Json {
+    s.parse().unwrap()
+}
+
+fn patched(document: &str, patch: &str) -> Result<Json, JsonPatchError> {
+    let mut document = json(document);
+    Patch::from_json(&json(patch))?.apply(&mut document)?;
+    Ok(document)
+}
+
+#[test]
+fn success_patch_apply() {
+    for (document, patch, expected) in [
+        (
+            r#"{ "foo": "bar" }"#,
+            r#"[{ "op": "add", "path": "/baz", "value": "qux" }]"#,
+            r#"{ "baz": "qux", "foo": "bar" }"#,
+        ),
+        (
+            r#"{ "foo": ["bar", "baz"] }"#,
+            r#"[{ "op": "add", "path": "/foo/1", "value": "qux" }]"#,
+            r#"{ "foo": ["bar", "qux", "baz"] }"#,
+        ),
+        (
+            r#"{ "foo": [1] }"#,
+            r#"[{ "op": "add", "path": "/foo/-", "value": 2 }]"#,
+            r#"{ "foo": [1, 2] }"#,
+        ),
+        (
+            r#"{ "baz": "qux", "foo": "bar" }"#,
+            r#"[{ "op": "remove", "path": "/baz" }]"#,
+            r#"{ "foo": "bar" }"#,
+        ),
+        (
+            r#"{ "baz": "qux", "foo": "bar" }"#,
+            r#"[{ "op": "replace", "path": "/baz", "value": "boo" }]"#,
+            r#"{ "baz": "boo", "foo": "bar" }"#,
+        ),
+        (
+            r#"{ "foo": { "bar": "baz", "waldo": "fred" }, "qux": { "corge": "grault" } }"#,
+            r#"[{ "op": "move", "from": "/foo/waldo", "path": "/qux/thud" }]"#,
+            r#"{ "foo": { "bar": "baz" }, "qux": { "corge": "grault", "thud": "fred" } }"#,
+        ),
+        (
+            r#"{ "a/b": 1, "m~n": [1, 2] }"#,
+            r#"[{ "op": "copy", "from": "/a~1b", "path": "/m~0n/0" }, { "op": "test", "path": "/m~0n", "value": [1, 1, 2] }]"#,
+            r#"{ "a/b": 1, "m~n": [1, 1, 2] }"#,
+        ),
+        (
+            "[1]",
+            r#"[{ "op": "replace", "path": "", "value": {} }]"#,
+            "{}",
+        ),
+    ] {
+        assert_eq!(patched(document, patch), Ok(json(expected)), "{}", patch);
+    }
+}
+
+#[test]
+fn error_patch_apply() {
+    for (document, patch, error) in [
+        (
+            r#"{ "a": 1 }"#,
+            r#"[{ "op": "remove", "path": "/b" }]"#,
+            JsonPatchError::PathNotFound("/b".into()),
+        ),
+        (
+            "[1, 2]",
+            r#"[{ "op": "add", "path": "/01", "value": 0 }]"#,
+            JsonPatchError::PathNotFound("/01".into()),
+        ),
+        (
+            r#"{ "a": 1 }"#,
+            r#"[{ "op": "test", "path": "/a", "value": 2 }]"#,
+            JsonPatchError::TestFailed("/a".into()),
+        ),
+        (
+            r#"{ "a": 1 }"#,
+            r#"[{ "op": "remove", "path": "a" }]"#,
+            JsonPatchError::InvalidPointer("a".into()),
+        ),
+    ] {
+        assert_eq!(patched(document, patch), Err(error), "{}", patch);
+    }
+    assert!(patched("{}", r#"[{ "op": "add", "path": "/a" }]"#).is_err());
+    assert!(patched("{}", r#"[{ "op": "noop", "path": "/a" }]"#).is_err());
+    assert!(patched("{}", r#"{ "op": "remove", "path": "/a" }"#).is_err());
+
+    // failing patch leaves the document unmodified.
+    let mut document = json(r#"{ "a": 1 }"#);
+    let patch = json(
+        r#"[{ "op": "remove", "path": "/a" }, { "op": "remove", "path": "/a" }]"#,
+    );
+    assert!(Patch::from_json(&patch)
+        .unwrap()
+        .apply(&mut document)
+        .is_err());
+    assert_eq!(document, json(r#"{ "a": 1 }"#));
+}
+
+#[test]
+fn success_patch_diff() {
+    for (from, to) in [
+        (
+            r#"{ "a": 1, "b": [1, 2, 3] }"#,
+            r#"{ "a": 1, "b": [1, 4] }"#,
+        ),
+        (
+            r#"{ "a": { "x/y": null } }"#,
+            r#"{ "a": { "x/y": true, "c": [] } }"#,
+        ),
+        ("[1]", r#"[1, { "a": 2 }, 3]"#),
+        ("[]", "{}"),
+        ("null", "null"),
+    ] {
+        let (from, to) = (json(from), json(to));
+        let patch = Patch::diff(&from, &to);
+        let mut patched = from.clone();
+        patch.apply(&mut patched).unwrap();
+        assert_eq!(patched, to, "{:?}", patch);
+        // patch survives the roundtrip through json.
+        assert_eq!(Patch::from_json(&patch.to_json()), Ok(patch));
+    }
+
+    let patch =
+        Patch::diff(&json(r#"{ "a": [1, 2] }"#), &json(r#"{ "a": [1, 3] }"#));
+    assert_eq!(
+        patch,
+        Patch(vec![Operation::Replace {
+            path: "/a/1".into(),
+            value: json("3"),
+        }])
+    );
+}
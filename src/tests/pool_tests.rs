@@ -0,0 +1,49 @@
+use crate::pool::*;
+
+#[test]
+fn success_map_ordered() {
+    for jobs in [1, 4].iter() {
+        let mut results = vec![];
+        map_ordered(
+            0..100u64,
+            *jobs,
+            |n| {
+                // make later items finish first.
+                std::thread::sleep(std::time::Duration::from_micros(100 - n));
+                n * 2
+            },
+            |n| results.push(n),
+        );
+        assert_eq!(results, (0..100).map(|n| n * 2).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn success_map_ordered_bounded() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let jobs = 4;
+    let (taken, emitted) = (AtomicUsize::new(0), AtomicUsize::new(0));
+    let items = (0..200u64).inspect(|_| {
+        let in_flight = taken.fetch_add(1, Ordering::SeqCst) + 1
+            - emitted.load(Ordering::SeqCst);
+        assert!(in_flight <= jobs * IN_FLIGHT_PER_JOB, "{}", in_flight);
+    });
+    let mut results = vec![];
+    map_ordered(
+        items,
+        jobs,
+        |n| {
+            // every later item is blocked behind the first one.
+            if n == 0 {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+            }
+            n
+        },
+        |n| {
+            emitted.fetch_add(1, Ordering::SeqCst);
+            results.push(n)
+        },
+    );
+    assert_eq!(results, (0..200).collect::<Vec<_>>());
+}
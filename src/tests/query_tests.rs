@@ -1,4 +1,10 @@
-use crate::json::{query::JsonQuery, token::Property};
+use crate::json::{
+    error::JsonEvalError,
+    parser::JsonParser,
+    query::JsonQuery,
+    token::{Json, Property},
+};
+use std::error::Error;
 
 macro_rules! query {
     ($($prop:expr),*) => {
@@ -10,19 +16,19 @@ macro_rules! query {
 fn success_query() {
     let string = r#"[1].array.map(.obj.list.keys())[0].values()["property"].another_property["another_array"][90].length()"#;
     let query1 = query![
-        Property::Index(1),
-        Property::Dot("array".into()),
+        Property::Index(1, false),
+        Property::Dot("array".into(), false),
         Property::Map(query![
-            Property::Dot("obj".into()),
-            Property::Dot("list".into()),
+            Property::Dot("obj".into(), false),
+            Property::Dot("list".into(), false),
             Property::Keys
         ]),
-        Property::Index(0),
+        Property::Index(0, false),
         Property::Values,
-        Property::Bracket("property".into()),
-        Property::Dot("another_property".into()),
-        Property::Bracket("another_array".into()),
-        Property::Index(90),
+        Property::Bracket("property".into(), false),
+        Property::Dot("another_property".into(), false),
+        Property::Bracket("another_array".into(), false),
+        Property::Index(90, false),
         Property::Length
     ];
 
@@ -30,3 +36,1574 @@ fn success_query() {
     assert!(query2.is_ok());
     assert_eq!(query2.unwrap(), query1);
 }
+
+#[test]
+fn success_query_identity() {
+    use crate::json::jq;
+
+    let json: Json = r#"{ "a": [{ "b": 1 }, { "b": 2 }] }"#.parse().unwrap();
+    for query in ["", "."] {
+        let query = JsonQuery::new(query).unwrap();
+        assert!(query.is_identity());
+        assert_eq!(query, JsonQuery::identity());
+        assert_eq!(query.to_string(), ".");
+        assert_eq!(json.apply(&query).unwrap(), json);
+    }
+    for (query, expected) in [
+        (".[0]", query![Property::Index(0, false)]),
+        (".map(.)", query![Property::Map(JsonQuery::identity())]),
+        (
+            ".map(.[1])",
+            query![Property::Map(query![Property::Index(1, false)])],
+        ),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        assert_eq!(query, expected);
+        assert_eq!(JsonQuery::new(&query.to_string()).unwrap(), query);
+    }
+    assert_eq!(
+        jq::translate("map(.)").unwrap(),
+        query![Property::Map(JsonQuery::identity())]
+    );
+    // identity composes with other queries.
+    let query = JsonQuery::new(".a").unwrap().then(JsonQuery::identity());
+    assert_eq!(query, JsonQuery::new(".a").unwrap());
+    let query = JsonQuery::new(".a.map(.)").unwrap();
+    assert_eq!(json.apply(&query).unwrap(), *json.get("a").unwrap());
+
+    for query in [".a.", ".a.[0]", "..", ".)"] {
+        assert!(JsonQuery::new(query).is_err(), "{}", query);
+    }
+}
+
+#[test]
+fn success_query_dot_keys() {
+    use crate::json::error::JsonQueryErrorType;
+
+    let json: Json = r#"{ "a-b": 1, "é_$1": 2, "my key": 3 }"#.parse().unwrap();
+    for &(query, expected) in
+        &[(".a-b", 1.), (".é_$1", 2.), (r#"["my key"]"#, 3.)]
+    {
+        let query = JsonQuery::new(query).unwrap();
+        assert_eq!(json.apply(&query).unwrap(), Json::Number(expected));
+    }
+    for &(query, cursor) in
+        &[(".my key", 4), (".a@b", 3), (".@", 2), (".a.b c", 5)]
+    {
+        let err = JsonQuery::new(query).unwrap_err();
+        assert_eq!(err.error_type, JsonQueryErrorType::InvalidKeyError);
+        assert_eq!(err.cursor, cursor, "{}", query);
+        assert!(err.to_string().contains("bracket notation"));
+    }
+    for query in ["..a", ".a.", "a"] {
+        let err = JsonQuery::new(query).unwrap_err();
+        assert_eq!(
+            err.error_type,
+            JsonQueryErrorType::SyntaxError,
+            "{}",
+            query
+        );
+    }
+
+    assert!(Property::is_dot_key("a-b_$é1"));
+    for key in ["", "my key", "a.b", "a[0]", "keys()", "a\"b"] {
+        assert!(!Property::is_dot_key(key), "{}", key);
+        let property = Property::Dot(key.into(), false);
+        assert!(property.to_string().starts_with("[\""), "{}", key);
+    }
+}
+
+#[test]
+fn success_query_bracket_escapes() {
+    use crate::json::{borrowed::JsonRef, jq, scan};
+
+    let input = r#"{ "say \"hi\"": 1, "a]b": 2, "": 3, "back\\slash": 4 }"#;
+    let json: Json = input.parse().unwrap();
+    let borrowed = JsonRef::parse(input).unwrap();
+    for &(query, expected) in &[
+        (r#"["say \"hi\""]"#, 1.),
+        (r#"["a]b"]"#, 2.),
+        (r#"[""]"#, 3.),
+        (r#"["back\\slash"]"#, 4.),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        assert_eq!(json.apply(&query).unwrap(), Json::Number(expected));
+        assert_eq!(borrowed.apply(&query).unwrap(), Json::Number(expected));
+        assert_eq!(scan::select(input, &query), Some(Json::Number(expected)));
+        assert_eq!(JsonQuery::new(&query.to_string()).unwrap(), query);
+        let jq_query = jq::translate(&format!(".{}", query)).unwrap();
+        assert_eq!(jq_query.into_owned(), query.into_owned());
+    }
+    for query in [r#"["unterminated]"#, r#"["escaped\"]"#] {
+        assert!(JsonQuery::new(query).is_err(), "{}", query);
+    }
+}
+
+#[test]
+fn error_query_eval() {
+    let json = JsonParser::new(r#"{ "list": [1, 2] }"#).parse().unwrap();
+    for (query, error) in [
+        (
+            ".missing",
+            JsonEvalError::MissingKey("missing".into(), vec![]),
+        ),
+        (".list[5]", JsonEvalError::InvalidIndex(5, 2)),
+        (".list[-3]", JsonEvalError::InvalidIndex(-3, 2)),
+        (
+            ".list.keys()",
+            JsonEvalError::InvalidType(
+                "'.keys()' can only be applied on 'Object'".into(),
+                "Array",
+            ),
+        ),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        assert_eq!(json.apply(&query).unwrap_err().cause(), &error);
+    }
+}
+
+#[test]
+fn error_query_eval_path() {
+    let json: Json = r#"{
+        "a": { "b": [1, 2, 3, { "x": 1, "y": 2 }] },
+        "list": [{ "id": { "k": 1 } }, { "id": {} }]
+    }"#
+    .parse()
+    .unwrap();
+    for &(query, path, shape) in &[
+        (".a.b[3].c", ".a.b[3]", "Object with keys 'x', 'y'"),
+        (".a.b[-5]", ".a.b", "Array of len 4"),
+        (".a.b[0].x", ".a.b[0]", "Number"),
+        (".list.map(.id.k)", ".list[1].id", "empty Object"),
+        (".map(.x)", "", ""),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        match json.apply(&query).unwrap_err() {
+            JsonEvalError::At(at, found, _) => {
+                assert_eq!((at.as_str(), found.as_str()), (path, shape))
+            }
+            err => assert_eq!(path, "", "{}", err),
+        }
+    }
+
+    let query = JsonQuery::new(".a.b[3].c").unwrap();
+    assert_eq!(
+        json.apply(&query).unwrap_err().to_string(),
+        " after '.a.b[3]' (Object with keys 'x', 'y'): key doesn't exist: 'c'"
+    );
+    let keys: Json = r#"{ "f": 6, "e": 5, "d": 4, "c": 3, "b": 2, "a": 1 }"#
+        .parse()
+        .unwrap();
+    assert_eq!(
+        keys.shape(),
+        "Object with keys 'a', 'b', 'c', 'd', 'e', ..."
+    );
+}
+
+#[test]
+fn success_query_negative_index() {
+    use crate::json::{
+        arena::JsonArena, borrowed::JsonRef, shared::SharedJson,
+    };
+
+    let input = r#"{ "list": [[1, 2], [3, 4], [5, 6]] }"#;
+    let json: Json = input.parse().unwrap();
+    for &(query, expected) in &[
+        (".list[-1]", "[5, 6]"),
+        (".list[-3][-2]", "1"),
+        (".list[1][-1]", "4"),
+        (".list.map([-1])", "[2, 4, 6]"),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        let expected: Json = expected.parse().unwrap();
+        assert_eq!(json.apply(&query).unwrap(), expected);
+        let borrowed = JsonRef::parse(input).unwrap();
+        assert_eq!(borrowed.apply(&query).unwrap(), expected);
+        let arena = JsonArena::parse(input).unwrap();
+        assert_eq!(arena.root().apply(&query).unwrap(), expected);
+        let shared = SharedJson::parse(input).unwrap();
+        assert_eq!(shared.apply(&query).unwrap().to_json(), expected);
+    }
+
+    let list = json.get("list").unwrap().clone();
+    assert_eq!(
+        list.take(&Property::Index(-2, false)).unwrap(),
+        "[3, 4]".parse().unwrap()
+    );
+
+    assert_eq!(
+        JsonEvalError::InvalidIndex(-4, 3).to_string(),
+        " Index -4 is out of range (valid indices: 0 to 2, or -3 to -1)."
+    );
+    assert_eq!(
+        JsonEvalError::InvalidIndex(0, 0).to_string(),
+        " Index 0 is out of range (array is empty)."
+    );
+}
+
+#[test]
+fn success_query_missing_null() {
+    use crate::json::{
+        memo,
+        query::{ApplyOptions, Missing},
+    };
+
+    let json: Json = r#"{ "list": [{ "id": 1 }, {}, 3, { "id": 2 }, {}] }"#
+        .parse()
+        .unwrap();
+    let options = ApplyOptions {
+        missing: Missing::Null,
+        ..ApplyOptions::default()
+    };
+    for &(query, expected) in &[
+        (".missing", "null"),
+        (".missing.a[2].keys()", "null"),
+        (".list[9].id", "null"),
+        (".list.map(.id)", "[1, null, null, 2, null]"),
+        (".list.map(.keys().length())", "[1, 0, 0, 1, 0]"),
+        (".list[0].id", "1"),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        let expected: Json = expected.parse().unwrap();
+        assert_eq!(json.apply_with(&query, options).unwrap(), expected);
+        memo::set_enabled(false);
+        assert_eq!(json.apply_with(&query, options).unwrap(), expected);
+        memo::set_enabled(true);
+    }
+    // only missing values are lenient.
+    let query = JsonQuery::new(".list.map(.upper())").unwrap();
+    assert_eq!(
+        json.apply_with(&query, options).unwrap_err().cause(),
+        &JsonEvalError::UnknownFunction("upper".into())
+    );
+    let query = JsonQuery::new(".list.map(.id)").unwrap();
+    assert!(json.apply(&query).is_err());
+}
+
+#[test]
+fn success_query_error_trait() {
+    fn extract(json: &str, query: &str) -> Result<Json, Box<dyn Error>> {
+        Ok(JsonParser::new(json)
+            .parse()?
+            .apply(&JsonQuery::new(query)?)?)
+    }
+    assert!(extract("[1]", "[0]").is_ok());
+    assert!(extract("[1", "[0]").is_err());
+    assert!(extract("[1]", "..").is_err());
+    assert!(extract("[1]", ".a").is_err());
+}
+
+#[test]
+fn error_query_similar_keys() {
+    use crate::json::borrowed::JsonRef;
+
+    let json: Json =
+        r#"{ "user": 1, "users": 2, "usr": 3, "name": 4 }"#.parse().unwrap();
+    let error =
+        |query| json.apply(&JsonQuery::new(query).unwrap()).unwrap_err();
+    // transposed chars are a single edit.
+    assert_eq!(
+        error(".usre"),
+        JsonEvalError::MissingKey(
+            "usre".into(),
+            vec!["user".into(), "usr".into()]
+        )
+    );
+    assert_eq!(
+        error(".usre").to_string(),
+        " key doesn't exist: 'usre' (did you mean 'user' or 'usr'?)"
+    );
+    assert_eq!(
+        error(".nmae").to_string(),
+        " key doesn't exist: 'nmae' (did you mean 'name'?)"
+    );
+    assert_eq!(error(".zzz").to_string(), " key doesn't exist: 'zzz'");
+    // same suggestions, when walked by reference.
+    let query = JsonQuery::new(".usre").unwrap();
+    let borrowed = JsonRef::parse(r#"{ "user": 1 }"#).unwrap();
+    assert_eq!(
+        borrowed.apply(&query).unwrap_err(),
+        JsonEvalError::MissingKey("usre".into(), vec!["user".into()])
+    );
+}
+
+#[test]
+fn success_ruson_error() {
+    use crate::json::error::RusonError;
+    use std::convert::TryFrom;
+
+    use crate::extract;
+    assert_eq!(extract("[1]", "[0]").unwrap(), Json::Number(1.));
+    assert!(matches!(extract("[1", "[0]"), Err(RusonError::Parse(_))));
+    assert!(matches!(extract("[1]", ".."), Err(RusonError::Query(_))));
+    let err = extract("[1]", ".a").unwrap_err();
+    assert!(matches!(err, RusonError::Eval(_)));
+    // same message as the underlying error.
+    assert_eq!(err.to_string(), err.source().unwrap().to_string());
+
+    let err = RusonError::from(String::try_from(Json::Null).unwrap_err());
+    assert!(matches!(err, RusonError::Convert(_)));
+    let err = Json::from_reader(&[0xff][..]).unwrap_err();
+    assert!(matches!(RusonError::from(err), RusonError::Io(_)));
+}
+
+#[test]
+fn success_query_str() -> Result<(), Box<dyn Error>> {
+    let json: Json = r#"{ "items": [{ "name": "a" }] }"#.parse()?;
+    assert_eq!(json.query(".items[0].name")?, Json::QString("a".into()));
+    assert_eq!(json.query("")?, json);
+    assert!(json.query(".items[").is_err());
+    assert!(json.query(".items[1]").is_err());
+    assert_eq!(crate::extract("[[1, 2]]", "[0][-1]")?, Json::Number(2.));
+    Ok(())
+}
+
+#[test]
+fn success_template() {
+    use crate::json::{
+        error::JsonQueryErrorType,
+        query::{ApplyOptions, Missing},
+        template::Template,
+    };
+
+    let json: Json =
+        r#"{ "name": "say \"hi\"", "stars": 5, "tags": ["a"], "}": 1 }"#
+            .parse()
+            .unwrap();
+    let render = |template: &str| {
+        template.parse::<Template>().unwrap().render(&json).unwrap()
+    };
+    assert_eq!(
+        render("Name: {.name}, Stars: { .stars }"),
+        r#"Name: say "hi", Stars: 5"#
+    );
+    assert_eq!(render("{.tags} {.tags[0]}"), r#"["a"] a"#);
+    assert_eq!(render("{{.name}} }"), "{.name} }");
+    // braces and quotes inside placeholders.
+    assert_eq!(render(r#"{.{stars}} {["}"]}"#), r#"{"stars": 5} 1"#);
+    assert_eq!(render("no placeholders"), "no placeholders");
+
+    let err = Template::new("ab {.a..b}").unwrap_err();
+    assert_eq!(
+        (err.cursor, err.error_type),
+        (7, JsonQueryErrorType::SyntaxError)
+    );
+    assert!(Template::new("{.a").is_err());
+
+    let template = Template::new("{.missing}").unwrap();
+    assert!(matches!(
+        template.render(&json),
+        Err(JsonEvalError::MissingKey(..))
+    ));
+    let options = ApplyOptions {
+        missing: Missing::Null,
+        ..ApplyOptions::default()
+    };
+    assert_eq!(template.render_with(&json, options).unwrap(), "null");
+}
+
+#[test]
+fn success_query_find() {
+    use crate::json::token::glob_match;
+
+    let json: Json = r#"{ "data": {
+        "user": { "id": 1, "my id": 4 },
+        "items": [{ "id": 2, "item_id": 5 }, { "x": { "id": { "id": 3 } } }]
+    } }"#
+        .parse()
+        .unwrap();
+    let paths: Vec<_> = json
+        .find("id")
+        .into_iter()
+        .map(|(path, value)| (path.to_string(), value.clone()))
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            (".data.items[0].id".into(), Json::Number(2.)),
+            (
+                ".data.items[1].x.id".into(),
+                r#"{"id": 3}"#.parse().unwrap()
+            ),
+            (".data.items[1].x.id.id".into(), Json::Number(3.)),
+            (".data.user.id".into(), Json::Number(1.)),
+        ]
+    );
+    // paths select the same values.
+    for (path, value) in json.find("*id") {
+        assert_eq!(&json.apply(&path).unwrap(), value);
+    }
+
+    let query = JsonQuery::new(r#".find("*id").length()"#).unwrap();
+    assert_eq!(json.apply(&query).unwrap(), Json::Number(6.));
+    let query = JsonQuery::new(r#".data.user.findpaths("my id")"#).unwrap();
+    let expected: Json =
+        r#"[{ "path": "[\"my id\"]", "value": 4 }]"#.parse().unwrap();
+    assert_eq!(json.apply(&query).unwrap(), expected);
+    let query = JsonQuery::new(r#".find("missing")"#).unwrap();
+    assert_eq!(json.apply(&query).unwrap(), Json::Array(vec![]));
+
+    for query in [r#".find( "a" )"#, r#".findpaths("a*")"#] {
+        let query = JsonQuery::new(query).unwrap();
+        assert_eq!(JsonQuery::new(&query.to_string()).unwrap(), query);
+    }
+    for query in [
+        ".find(a)",
+        ".find()",
+        r#".find("a""#,
+        r#".findpaths("a", "b")"#,
+    ] {
+        assert!(JsonQuery::new(query).is_err(), "{}", query);
+    }
+
+    assert!(glob_match("*_id", "item_id"));
+    assert!(glob_match("a*b*c", "aXbYbc"));
+    assert!(glob_match("*", ""));
+    assert!(!glob_match("*_id", "id"));
+    assert!(!glob_match("id", "ids"));
+}
+
+#[test]
+fn success_from_str() -> Result<(), Box<dyn Error>> {
+    let json: Json = r#"{ "a": { "b": [true] } }"#.parse()?;
+    let query: JsonQuery = ".a.b[0]".parse()?;
+    assert_eq!(json.apply(&query)?, Json::Boolean(true));
+    assert!("[1,]".parse::<Json>().is_err());
+    assert!(".a..b".parse::<JsonQuery>().is_err());
+    Ok(())
+}
+
+#[test]
+fn success_query_display() {
+    for &string in &[
+        r#"[1].array.map(.obj.list.keys())[0].values()["property"].length()"#,
+        ".map(.map([-1]).map(.))",
+        r#"["a.b"]["with space"].key"#,
+        ".",
+    ] {
+        let query = JsonQuery::new(string).unwrap();
+        assert_eq!(query.to_string(), string);
+        assert_eq!(JsonQuery::new(&query.to_string()).unwrap(), query);
+    }
+
+    let query = query![
+        Property::Dot("a.b".into(), false),
+        Property::Dot("keys()".into(), false),
+        Property::Dot("map(".into(), false)
+    ];
+    assert_eq!(query.to_string(), r#"["a.b"]["keys()"]["map("]"#);
+    assert_eq!(JsonQuery::new(&query.to_string()).unwrap().0.len(), 3);
+}
+
+#[test]
+fn success_query_borrowed() {
+    use std::borrow::Cow;
+
+    let string = String::from(r#".a["b c"].map(.d.slugify())"#);
+    let query = JsonQuery::new(&string).unwrap();
+    let borrowed = |property: &Property| match property {
+        Property::Dot(s, _) | Property::Bracket(s, _) | Property::Call(s) => {
+            matches!(s, Cow::Borrowed(_))
+        }
+        _ => false,
+    };
+    assert!(borrowed(&query.0[0]) && borrowed(&query.0[1]));
+    match &query.0[2] {
+        Property::Map(query) => assert!(query.properties().all(borrowed)),
+        _ => unreachable!(),
+    }
+
+    let owned = query.clone().into_owned();
+    drop(string);
+    assert_eq!(owned.to_string(), r#".a["b c"].map(.d.slugify())"#);
+    assert!(!owned.properties().any(borrowed));
+}
+
+#[test]
+fn success_jq_translate() {
+    use crate::json::jq;
+
+    for (jq_query, query) in &[
+        (".", ""),
+        ("", ""),
+        (".a.b[0]", ".a.b[0]"),
+        (r#".["a"] | .[-1]"#, r#"["a"] | [-1]"#),
+        (".a[].b", ".a[].b"),
+        (".a[] | .b | length", ".a[].b | .length()"),
+        (".a | utf8bytelength", ".a | .bytelength()"),
+        (".[][]", "[][]"),
+        ("map(.id) | keys", ".map(.id) | .keys()"),
+        (" .a | map( .b[] ) ", ".a | .map(.b[])"),
+    ] {
+        assert_eq!(
+            jq::translate(jq_query).unwrap(),
+            JsonQuery::new(query).unwrap(),
+            "{}",
+            jq_query
+        );
+    }
+}
+
+#[test]
+fn error_jq_translate() {
+    use crate::json::jq;
+
+    for &(jq_query, cursor) in &[
+        (".a)", 3),
+        (".a.", 3),
+        ("map(.a", 7),
+        (".[x]", 3),
+        ("select(.a)", 7),
+        (".a |", 5),
+    ] {
+        assert_eq!(
+            jq::translate(jq_query).unwrap_err().cursor,
+            cursor,
+            "{}",
+            jq_query
+        );
+    }
+}
+
+#[test]
+fn success_query_engine() {
+    use crate::json::engine::QueryEngine;
+
+    let mut engine = QueryEngine::new();
+    engine
+        .register("slugify", |json| {
+            json.as_str()
+                .map(|s| {
+                    Json::from(s.to_lowercase().replace(' ', "-").as_str())
+                })
+                .ok_or_else(|| "expected 'String'".into())
+        })
+        .register("length", |_| Ok(Json::Null));
+    let json =
+        JsonParser::new(r#"{ "titles": ["Hello World", "A B"], "n": 1 }"#)
+            .parse()
+            .unwrap();
+
+    let query = JsonQuery::new(".titles.map(.slugify())").unwrap();
+    assert_eq!(
+        engine.apply(&json, &query).unwrap(),
+        JsonParser::new(r#"["hello-world", "a-b"]"#)
+            .parse()
+            .unwrap()
+    );
+    // builtins can't be overridden.
+    let query = JsonQuery::new(".titles.length()").unwrap();
+    assert_eq!(engine.apply(&json, &query).unwrap(), Json::Number(2.0));
+
+    let query = JsonQuery::new(".n.slugify()").unwrap();
+    assert_eq!(
+        engine.apply(&json, &query).unwrap_err(),
+        JsonEvalError::Function("slugify".into(), "expected 'String'".into())
+    );
+    let query = JsonQuery::new(".titles.map(.upper())").unwrap();
+    assert_eq!(
+        engine.apply(&json, &query).unwrap_err(),
+        JsonEvalError::UnknownFunction("upper".into())
+    );
+    let slugify = JsonQuery::new(".titles[0].slugify()").unwrap();
+    assert_eq!(
+        json.apply(&slugify).unwrap_err().cause(),
+        &JsonEvalError::UnknownFunction("slugify".into())
+    );
+    assert_eq!(query.to_string(), ".titles.map(.upper())");
+}
+
+#[test]
+fn success_query_length() {
+    let json = JsonParser::new(
+        r#"{ "a": [1, 2], "o": { "x": 1, "y": 2, "z": 3 }, "e": {}, "n": null }"#,
+    )
+    .parse()
+    .unwrap();
+    for &(query, length) in
+        &[(".a", 2.), (".o", 3.), (".e", 0.), (".n", 0.), ("", 4.)]
+    {
+        let query = format!("{}.length()", query).parse::<JsonQuery>().unwrap();
+        assert_eq!(json.apply(&query).unwrap(), Json::Number(length));
+    }
+    let query = JsonQuery::new(".a[0].length()").unwrap();
+    assert!(matches!(
+        json.apply(&query).unwrap_err().cause(),
+        JsonEvalError::InvalidType(_, "Number")
+    ));
+    // unicode scalar values, after unescaping.
+    let json: Json =
+        r#"["héllo", "\u0041\n", "\ud83d\ude00", "a\\"]"#.parse().unwrap();
+    for &(query, expected) in &[
+        (".map(.length())", "[5, 2, 1, 2]"),
+        (".map(.bytelength())", "[6, 2, 4, 2]"),
+    ] {
+        let query: JsonQuery = query.parse().unwrap();
+        assert_eq!(json.apply(&query).unwrap().to_string(), expected);
+        assert_eq!(query.to_string().parse::<JsonQuery>().unwrap(), query);
+    }
+    let query = JsonQuery::new(".bytelength()").unwrap();
+    assert!(matches!(
+        json.apply(&query),
+        Err(JsonEvalError::InvalidType(_, "Array"))
+    ));
+}
+
+#[test]
+fn success_query_type() {
+    use crate::json::jq;
+
+    let json: Json = r#"[null, true, 1.5, "x", [], {}]"#.parse().unwrap();
+    let query = JsonQuery::new(".map(.type())").unwrap();
+    assert_eq!(
+        json.apply(&query).unwrap().to_string(),
+        r#"["null", "boolean", "number", "string", "array", "object"]"#
+    );
+    assert_eq!(query.to_string().parse::<JsonQuery>().unwrap(), query);
+    assert_eq!(
+        jq::translate("map(type)").unwrap(),
+        JsonQuery::new(".map(.type())").unwrap()
+    );
+}
+
+#[test]
+fn success_query_first_last() {
+    use crate::json::{
+        jq,
+        query::{ApplyOptions, Missing},
+    };
+
+    let json: Json = r#"{ "list": [1, 2, 3], "empty": [] }"#.parse().unwrap();
+    let apply = |query: &str| json.apply(&query.parse().unwrap());
+    assert_eq!(apply(".list.first()").unwrap(), Json::Number(1.));
+    assert_eq!(apply(".list.last()").unwrap(), Json::Number(3.));
+    for query in [".empty.first()", ".empty.last()"].iter() {
+        let err = apply(query).unwrap_err();
+        assert!(err.cause().is_missing(), "{}", query);
+        assert!(
+            err.to_string()
+                .ends_with("can't be applied on an empty array."),
+            "{}",
+            err
+        );
+    }
+    assert!(apply(".first()").is_err());
+    let options = ApplyOptions {
+        missing: Missing::Null,
+        ..ApplyOptions::default()
+    };
+    let query = ".empty.last()".parse().unwrap();
+    assert_eq!(json.apply_with(&query, options).unwrap(), Json::Null);
+
+    // moved out of an owned array.
+    let mut list = apply(".list").unwrap();
+    list.update(&Property::Last).unwrap();
+    assert_eq!(list, Json::Number(3.));
+    let list = apply(".list").unwrap();
+    assert_eq!(list.take(&Property::First).unwrap(), Json::Number(1.));
+    let empty = apply(".empty").unwrap();
+    assert!(empty.take(&Property::First).is_err());
+
+    assert_eq!(
+        jq::translate(".list | first").unwrap(),
+        JsonQuery::new(".list | .first()").unwrap()
+    );
+    // keys named the same are still dot keys.
+    assert_eq!(
+        JsonQuery::new(".first.last").unwrap(),
+        query![
+            Property::Dot("first".into(), false),
+            Property::Dot("last".into(), false)
+        ]
+    );
+}
+
+#[test]
+fn success_query_paths() {
+    let json: Json =
+        r#"{ "b": { "c": [1, { "d": null }] }, "my key": [], "a": {} }"#
+            .parse()
+            .unwrap();
+    let query = JsonQuery::new(".paths()").unwrap();
+    let paths = json.apply(&query).unwrap();
+    assert_eq!(
+        paths.to_string(),
+        r#"[".a", ".b.c[0]", ".b.c[1].d", "[\"my key\"]"]"#
+    );
+    assert_eq!(query.to_string(), ".paths()");
+    // every path selects its leaf.
+    for (path, leaf) in json.leaves() {
+        let parsed: JsonQuery = path.to_string().parse().unwrap();
+        assert_eq!(json.apply(&parsed).unwrap(), *leaf, "{}", path);
+    }
+    let scalar: Json = "1".parse().unwrap();
+    assert_eq!(scalar.apply(&query).unwrap().to_string(), r#"["."]"#);
+}
+
+#[test]
+fn success_query_regex() {
+    use crate::json::{error::JsonQueryErrorType, jq};
+
+    let json: Json = r#"["bob@example.com", "eve@evil.org", "tab\there"]"#
+        .parse()
+        .unwrap();
+    let apply = |query: &str| json.apply(&query.parse().unwrap()).unwrap();
+    assert_eq!(
+        apply(r#".map(.test("@example\\.com$"))"#).to_string(),
+        "[true, false, false]"
+    );
+    assert_eq!(apply(r#".any(.test("^e"))"#), Json::Boolean(true));
+    // strings are matched unescaped.
+    assert_eq!(apply(r#"[2].test("b\t")"#), Json::Boolean(true));
+    assert_eq!(
+        apply(r#"[0].match("(\\w+)@(x)?")"#),
+        r#"{ "offset": 0, "length": 4, "string": "bob@", "captures": [
+            { "offset": 0, "length": 3, "string": "bob" },
+            { "offset": -1, "length": 0, "string": null }
+        ] }"#
+            .parse()
+            .unwrap()
+    );
+    assert_eq!(apply(r#"[1].match("z")"#), Json::Null);
+
+    let query: JsonQuery = r#".test("a\\.b")"#.parse().unwrap();
+    assert_eq!(query.to_string(), r#".test("a\\.b")"#);
+    assert_eq!(jq::translate(r#"test("a\\.b")"#).unwrap(), query);
+    let err = json.apply(&query).unwrap_err();
+    assert!(err.to_string().contains("can only be applied on 'String'"));
+
+    for (query, cursor) in [
+        (r#".test("a(")"#, 9),
+        (r#".match( "*")"#, 10),
+        (r#".test("(?:(?:a?){1000}){1000}")"#, 24),
+    ]
+    .iter()
+    {
+        let err = JsonQuery::new(query).unwrap_err();
+        assert_eq!(err.error_type, JsonQueryErrorType::InvalidRegexError);
+        assert_eq!(err.cursor, *cursor, "{}", query);
+    }
+    let err = jq::translate(r#"match("a{2,1}")"#).unwrap_err();
+    assert_eq!(err.error_type, JsonQueryErrorType::InvalidRegexError);
+}
+
+#[test]
+fn success_query_int_range() {
+    use crate::json::error::JsonQueryErrorType;
+
+    assert_eq!(
+        JsonQuery::new("[2147483647]").unwrap(),
+        query!(Property::Index(i32::MAX, false))
+    );
+    assert_eq!(
+        JsonQuery::new("[-2147483648]").unwrap(),
+        query!(Property::Index(i32::MIN, false))
+    );
+    let json: Json = "[1, 2, 3]".parse().unwrap();
+    for query in ["[-2147483648]", "[2147483647]"].iter() {
+        let query = JsonQuery::new(query).unwrap();
+        assert!(matches!(
+            json.apply(&query).unwrap_err(),
+            JsonEvalError::InvalidIndex(..)
+        ));
+    }
+    assert!(JsonQuery::new(".has(-2147483648)").is_ok());
+    assert!(JsonQuery::new(".del(2147483647)").is_ok());
+
+    for (query, cursor) in [
+        ("[2147483648]", 2),
+        ("[-2147483649]", 2),
+        ("[4294967295]", 2),
+        ("[99999999999999999999]", 2),
+        ("[1:99999999999999999999]", 4),
+        (".has(4294967296)", 6),
+        (".del(-2147483649)", 6),
+    ]
+    .iter()
+    {
+        let err = JsonQuery::new(query).unwrap_err();
+        assert_eq!(
+            err.error_type,
+            JsonQueryErrorType::OutOfRangeError,
+            "{}",
+            query
+        );
+        assert_eq!(err.cursor, *cursor, "{}", query);
+    }
+}
+
+#[test]
+fn success_query_variables() {
+    use crate::json::{error::JsonQueryErrorType, jq, query::Variables};
+
+    let json: Json =
+        r#"{ "users": { "alice": 1, "say \"hi\"": 2 } }"#.parse().unwrap();
+    let variables: Variables = [
+        ("user".to_string(), Json::from("alice")),
+        ("quoted".to_string(), Json::from(r#"say "hi""#)),
+    ]
+    .iter()
+    .cloned()
+    .collect();
+    let query = JsonQuery::with_variables(".users[$user]", &variables).unwrap();
+    assert_eq!(json.apply(&query).unwrap(), Json::Number(1.));
+    // value is the literal key (escaped in the query).
+    let query =
+        JsonQuery::with_variables(".users[$quoted]", &variables).unwrap();
+    assert_eq!(json.apply(&query).unwrap(), Json::Number(2.));
+    assert_eq!(query.to_string(), r#".users["say \"hi\""]"#);
+    let query =
+        jq::translate_with_variables(".users[$user]", &variables).unwrap();
+    assert_eq!(json.apply(&query).unwrap(), Json::Number(1.));
+
+    for (query, cursor) in [(".users[$nope]", 8), ("[$user]", 2)].iter() {
+        let err =
+            JsonQuery::with_variables(query, &Variables::new()).unwrap_err();
+        assert_eq!(err.error_type, JsonQueryErrorType::UndefinedVariableError);
+        assert_eq!(err.cursor, *cursor, "{}", query);
+    }
+    let err = JsonQuery::with_variables("[$user", &variables).unwrap_err();
+    assert_eq!(err.error_type, JsonQueryErrorType::SyntaxError);
+    assert!(JsonQuery::new("[$user]").is_err());
+}
+
+#[test]
+fn success_query_json_variables() {
+    use crate::json::{error::JsonQueryErrorType, jq, query::Variables};
+
+    let json: Json = r#"[{ "age": 20 }, { "age": 40 }]"#.parse().unwrap();
+    let variables: Variables = [
+        ("min".to_string(), Json::Number(30.)),
+        ("last".to_string(), Json::Number(-1.)),
+        ("key".to_string(), Json::from("age")),
+        ("half".to_string(), Json::Number(0.5)),
+        ("huge".to_string(), Json::Number(1e10)),
+    ]
+    .iter()
+    .cloned()
+    .collect();
+    let apply = |query| {
+        let query = JsonQuery::with_variables(query, &variables).unwrap();
+        json.apply(&query).unwrap()
+    };
+    // integers are indices, strings are keys.
+    assert_eq!(apply("[$last][$key]"), Json::Number(40.));
+    assert_eq!(apply(".any(.age > $min)"), Json::Boolean(true));
+    assert_eq!(apply(".all( .age >= $min )"), Json::Boolean(false));
+    let query =
+        JsonQuery::with_variables(".all(.age > $min)", &variables).unwrap();
+    assert_eq!(query.to_string(), ".all(.age > 30)");
+    let query = jq::translate_with_variables(".[$last]", &variables).unwrap();
+    assert_eq!(query.to_string(), "[-1]");
+
+    for (query, cursor, error_type) in [
+        ("[$half]", 2, JsonQueryErrorType::InvalidVariableError),
+        ("[$huge]", 2, JsonQueryErrorType::InvalidVariableError),
+        (
+            ".any(.age > $max)",
+            13,
+            JsonQueryErrorType::UndefinedVariableError,
+        ),
+    ]
+    .iter()
+    {
+        let err = JsonQuery::with_variables(query, &variables).unwrap_err();
+        assert_eq!(err.error_type, *error_type, "{}", query);
+        assert_eq!(err.cursor, *cursor, "{}", query);
+    }
+    let err = jq::translate_with_variables(".[$half]", &variables).unwrap_err();
+    assert_eq!(err.error_type, JsonQueryErrorType::InvalidVariableError);
+}
+
+#[test]
+fn success_query_by_reference() {
+    use std::borrow::Cow;
+
+    let json =
+        JsonParser::new(r#"{ "meta": { "version": [1, 2] }, "data": [3] }"#)
+            .parse()
+            .unwrap();
+    // path properties borrow from the document.
+    match json.select(&Property::Dot("meta".into(), false)).unwrap() {
+        Cow::Borrowed(meta) => {
+            assert!(std::ptr::eq(meta, json.get("meta").unwrap()))
+        }
+        Cow::Owned(_) => panic!("expected borrowed value"),
+    }
+    assert!(matches!(json.select(&Property::Keys), Ok(Cow::Owned(_))));
+
+    // path properties following a computed value move out of it.
+    let values = json.select(&Property::Values).unwrap().into_owned();
+    let taken = values.take(&Property::Index(0, false)).unwrap();
+    assert!(
+        taken == *json.get("meta").unwrap()
+            || taken == *json.get("data").unwrap()
+    );
+
+    let query = JsonQuery::new(".meta.version[1]").unwrap();
+    assert_eq!(json.apply(&query).unwrap(), Json::Number(2.0));
+    let query = JsonQuery::new(".meta.version.map(.x)").unwrap();
+    assert!(json.apply(&query).is_err());
+}
+
+#[test]
+fn success_query_scan() {
+    use crate::json::scan;
+
+    let input = r#"{
+        "skip": { "nested": ["]", "}", { "a": [1, 2] }], "s": "\"{" },
+        "meta": { "list": [true, { "x\ny": null }, [3]] },
+        "after": this is never scanned
+    }"#;
+    for &(query, expected) in &[
+        (".meta.list[0]", "true"),
+        (r#".meta.list[1]["x\ny"]"#, "null"),
+        (".meta.list[2]", "[3]"),
+        (".skip.s", r#""\"{""#),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        assert_eq!(
+            scan::select(input, &query),
+            Some(JsonParser::new(expected).parse().unwrap()),
+            "{}",
+            query
+        );
+    }
+    let query = JsonQuery::new(".skip.nested").unwrap();
+    assert_eq!(
+        scan::select_raw(input, &query),
+        Some(r#"["]", "}", { "a": [1, 2] }]"#)
+    );
+    let query = JsonQuery::new(".after").unwrap();
+    assert_eq!(scan::select_raw(input, &query), None);
+
+    for &query in &[
+        ".meta.list[3]",
+        ".meta.missing",
+        ".meta.list.length()",
+        ".after",
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        assert_eq!(scan::select(input, &query), None, "{}", query);
+    }
+}
+
+#[test]
+fn success_query_page() {
+    use crate::json::{query::Page, scan};
+
+    let json = |s: &str| s.parse::<Json>().unwrap();
+    let page = |offset, limit| Page { offset, limit };
+    let list = json("[1, 2, 3, 4, 5]");
+    assert_eq!(list.clone().page(page(1, Some(2))), json("[2, 3]"));
+    assert_eq!(list.clone().page(page(3, None)), json("[4, 5]"));
+    assert_eq!(list.clone().page(page(9, Some(1))), json("[]"));
+    assert_eq!(list.clone().page(page(0, Some(0))), json("[]"));
+    assert_eq!(list.clone().page(Page::default()), list);
+    // other values are unchanged.
+    assert_eq!(json("{}").page(page(1, Some(1))), json("{}"));
+
+    // skipped elements are only scanned, the ones after the page never.
+    let input =
+        r#"{ "a": { "list": [{ "x": "]" }, [2], 3, 4, this is never scanned"#;
+    let query = JsonQuery::new(".a.list").unwrap();
+    assert_eq!(
+        scan::select_page(input, &query, page(1, Some(2))),
+        Some(json("[[2], 3]"))
+    );
+    let query = JsonQuery::new(".a").unwrap();
+    assert_eq!(scan::select_page(input, &query, page(0, Some(1))), None);
+    for &(input, offset, limit, expected) in &[
+        ("[]", 1, None, Some("[]")),
+        (" [ 1 , 2 ] ", 1, None, Some("[2]")),
+        ("[1, 2]", 5, None, Some("[]")),
+        ("[1, 2,]", 1, None, None),
+        ("[1, 2", 1, Some(2), None),
+        (r#""x""#, 1, None, Some(r#""x""#)),
+    ] {
+        assert_eq!(
+            scan::select_page(
+                input,
+                &JsonQuery::identity(),
+                page(offset, limit)
+            ),
+            expected.map(json),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn success_query_map_cache() {
+    use crate::json::memo;
+
+    let json: Json = r#"[
+        { "tags": ["a", "b"] }, { "tags": ["a", "b"] }, { "tags": ["c"] },
+        "\u0041", "A", 0, -0
+    ]"#
+    .parse()
+    .unwrap();
+    for query in [".map(.length())", ".map(.tags.length())", ".map(.values())"]
+    {
+        let query: JsonQuery = query.parse().unwrap();
+        let cached = json.apply(&query).map(|json| json.to_string());
+        memo::set_enabled(false);
+        let uncached = json.apply(&query).map(|json| json.to_string());
+        memo::set_enabled(true);
+        // same results (and errors), including for raw string contents.
+        assert_eq!(
+            cached.map_err(|err| err.to_string()),
+            uncached.map_err(|err| err.to_string())
+        );
+    }
+    let query: JsonQuery = ".map(.keys())".parse().unwrap();
+    let json: Json =
+        r#"[{ "\u0041": 1 }, { "A": 1 }, { "\u0041": 1 }]"#.parse().unwrap();
+    assert_eq!(
+        json.apply(&query).unwrap().to_string(),
+        r#"[["\u0041"], ["A"], ["\u0041"]]"#
+    );
+}
+
+#[test]
+fn success_query_pick() {
+    use crate::json::jq;
+
+    let json: Json = r#"{ "users": [
+        { "id": 1, "email": "a@b", "name": "a", "my key": true },
+        { "id": 2, "name": "b" }
+    ] }"#
+        .parse()
+        .unwrap();
+    let query =
+        JsonQuery::new(r#".users.map(.{id, email, "my key"})"#).unwrap();
+    let expected: Json = r#"[
+        { "id": 1, "email": "a@b", "my key": true },
+        { "id": 2, "email": null, "my key": null }
+    ]"#
+    .parse()
+    .unwrap();
+    assert_eq!(json.apply(&query).unwrap(), expected);
+    // moved out of an owned value (repeated keys are kept).
+    let query = JsonQuery::new(".users[0].{id, id}").unwrap();
+    assert_eq!(json.apply(&query).unwrap(), r#"{"id": 1}"#.parse().unwrap());
+    let query = JsonQuery::new(".{}").unwrap();
+    assert_eq!(json.apply(&query).unwrap(), "{}".parse().unwrap());
+
+    for (query, display) in [
+        (r#".{ id,email , "my key" }"#, r#".{id, email, "my key"}"#),
+        (".{}", ".{}"),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        assert_eq!(query.to_string(), display);
+        assert_eq!(JsonQuery::new(display).unwrap(), query);
+    }
+    assert_eq!(
+        jq::translate(r#".users[] | {id, "my key"}"#).unwrap(),
+        JsonQuery::new(r#".users[].{id, "my key"}"#).unwrap()
+    );
+    for query in [".{", ".{a", ".{a,}", ".{a b}", ".{,a}", r#".{"a}"#] {
+        assert!(JsonQuery::new(query).is_err(), "{}", query);
+    }
+
+    let query = JsonQuery::new(".users.{id}").unwrap();
+    assert!(matches!(
+        json.apply(&query).unwrap_err().cause(),
+        JsonEvalError::InvalidType(_, "Array")
+    ));
+}
+
+#[test]
+fn success_query_each() {
+    use crate::json::engine::QueryEngine;
+
+    let json: Json = r#"{
+        "list": [{ "id": 1, "tags": ["a"] }, { "id": 2, "tags": ["b", "c"] }],
+        "object": { "b": { "id": 4 }, "a": { "id": 3 } }
+    }"#
+    .parse()
+    .unwrap();
+    let engine = QueryEngine::new();
+    for (query, expected) in [
+        (".list[].id", "[1, 2]"),
+        (".list[].tags", r#"[["a"], ["b", "c"]]"#),
+        // results of a nested '[]' are spliced in.
+        (".list[].tags[]", r#"["a", "b", "c"]"#),
+        (".list.map(.tags[])", r#"[["a"], ["b", "c"]]"#),
+        // object values (ordered by key).
+        (".object[].id", "[3, 4]"),
+        (".list[0].tags[]", r#"["a"]"#),
+        (".list[].tags.length()", "[1, 2]"),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        let expected: Json = expected.parse().unwrap();
+        assert_eq!(json.apply(&query).unwrap(), expected, "{}", query);
+        assert_eq!(engine.apply(&json, &query).unwrap(), expected, "{}", query);
+        assert_eq!(JsonQuery::new(&query.to_string()).unwrap(), query);
+    }
+    assert_eq!(
+        JsonQuery::new(".a[].b").unwrap(),
+        query![
+            Property::Dot("a".into(), false),
+            Property::Each(query![Property::Dot("b".into(), false)])
+        ]
+    );
+    assert_eq!(
+        json.apply(&JsonQuery::new(".list[].id[]").unwrap())
+            .unwrap_err()
+            .to_string(),
+        " after '.list[0].id' (Number): '[]' can only be applied on 'Array' or \
+         'Object', found 'Number' instead."
+    );
+}
+
+#[test]
+fn success_query_pipe() {
+    let json: Json =
+        r#"{ "a": { "b": [1, 2, 3] }, "l": [{ "x": [1] }, { "x": [2, 3] }] }"#
+            .parse()
+            .unwrap();
+    // stages are kept (written back with `|`), identity stages are dropped.
+    for (query, display, flat) in [
+        (
+            ".a | .b | .length()",
+            ".a | .b | .length()",
+            ".a.b.length()",
+        ),
+        (".a|.b", ".a | .b", ".a.b"),
+        (". | .a", ".a", ".a"),
+        (".a | .", ".a", ".a"),
+        (".a | . | .b[0]", ".a | .b[0]", ".a.b[0]"),
+        (".a\t|\n[\"b\"]", ".a | [\"b\"]", ".a[\"b\"]"),
+        (".a | .[\"b\"]", ".a | [\"b\"]", ".a[\"b\"]"),
+        (".l[] | .x[]", ".l[].x[]", ".l[].x[]"),
+        (
+            ".l.map(.x | .length())",
+            ".l.map(.x | .length())",
+            ".l.map(.x.length())",
+        ),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        assert_eq!(query.to_string(), display);
+        assert_eq!(JsonQuery::new(display).unwrap(), query);
+        assert_eq!(
+            json.apply(&query).unwrap(),
+            json.apply(&JsonQuery::new(flat).unwrap()).unwrap(),
+            "{}",
+            query
+        );
+    }
+    assert_eq!(
+        JsonQuery::new(".a")
+            .unwrap()
+            .then(JsonQuery::new(".b").unwrap()),
+        JsonQuery::new(".a | .b").unwrap()
+    );
+    for &(query, cursor) in &[
+        (".a |", 5),
+        ("| .a", 1),
+        (".a || .b", 5),
+        (".a .b", 3),
+        (".a | ", 6),
+    ] {
+        assert_eq!(
+            JsonQuery::new(query).unwrap_err().cursor,
+            cursor,
+            "{}",
+            query
+        );
+    }
+}
+
+#[test]
+fn success_query_group_by() {
+    use crate::json::{engine::QueryEngine, jq};
+
+    let json: Json = r#"{ "events": [
+        { "user": "b", "n": 1 }, { "user": "a", "n": 2 },
+        { "user": "b", "n": 3 }, { "n": 4 }, { "user": 1, "n": 5 }
+    ] }"#
+        .parse()
+        .unwrap();
+    // groups ordered by key (`null < numbers < strings`), elements in order.
+    let query =
+        JsonQuery::new(".events.group_by(.user?).map(.map(.n))").unwrap();
+    let expected: Json = "[[4], [5], [2], [1, 3]]".parse().unwrap();
+    assert_eq!(json.apply(&query).unwrap(), expected);
+    assert_eq!(QueryEngine::new().apply(&json, &query).unwrap(), expected);
+    assert_eq!(JsonQuery::new(&query.to_string()).unwrap(), query);
+
+    let list: Json = "[1, 3, 2, 1]".parse().unwrap();
+    let query = JsonQuery::new(".group_by(.)").unwrap();
+    assert_eq!(
+        list.apply(&query).unwrap().to_string(),
+        "[[1, 1], [2], [3]]"
+    );
+    assert_eq!(
+        json.apply(&JsonQuery::new(".events.group_by(.user)").unwrap())
+            .unwrap_err()
+            .to_string(),
+        " after '.events[3]' (Object with keys 'n'): key doesn't exist: 'user'"
+    );
+    assert_eq!(
+        jq::translate(".events | group_by(.user)").unwrap(),
+        JsonQuery::new(".events | .group_by(.user)").unwrap()
+    );
+}
+
+#[test]
+fn success_query_any_all() {
+    use crate::json::{engine::QueryEngine, jq};
+
+    let json: Json = r#"{
+        "flags": [false, true], "nums": [1, 2, 3], "empty": [],
+        "users": [{ "age": 20, "name": "a (b)" }, { "age": 17 }]
+    }"#
+    .parse()
+    .unwrap();
+    for (query, expected) in [
+        (".flags.any(. == true)", true),
+        (".flags.all(. == true)", false),
+        (".nums.all(. > 0)", true),
+        (".nums.any(.<=0)", false),
+        (".nums.any(. != 2)", true),
+        (".flags.any()", true),
+        (".flags.all()", false),
+        (".empty.any()", false),
+        (".empty.all(. > 0)", true),
+        (".users.all(.age >= 18)", false),
+        (r#".users.any(.name == "a (b)")"#, true),
+        (".users.any(.email?)", false),
+        (".users.map(.age).all( . < 21 )", true),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        let expected = Json::Boolean(expected);
+        assert_eq!(json.apply(&query).unwrap(), expected, "{}", query);
+        let engine = QueryEngine::new();
+        assert_eq!(engine.apply(&json, &query).unwrap(), expected, "{}", query);
+        assert_eq!(JsonQuery::new(&query.to_string()).unwrap(), query);
+    }
+    for query in [".nums.all(. > x)", ".nums.any(. ~ 1)", ".nums.all(. >)"] {
+        assert!(JsonQuery::new(query).is_err(), "{}", query);
+    }
+    // evaluated up to the first element deciding the result.
+    let query = JsonQuery::new(".users.any(.name)").unwrap();
+    assert_eq!(json.apply(&query).unwrap(), Json::Boolean(true));
+    assert_eq!(
+        json.apply(&JsonQuery::new(".users.all(.name)").unwrap())
+            .unwrap_err()
+            .to_string(),
+        " after '.users[1]' (Object with keys 'age'): key doesn't exist: 'name'"
+    );
+    assert_eq!(
+        jq::translate(".flags | any").unwrap(),
+        JsonQuery::new(".flags | .any()").unwrap()
+    );
+}
+
+#[test]
+fn success_query_flatten() {
+    use crate::json::error::JsonQueryErrorType;
+    use crate::json::jq;
+
+    let json: Json = r#"{ "matrix": [[1, [2, [3]]], 4, []], "flatten": 5 }"#
+        .parse()
+        .unwrap();
+    for (query, expected) in [
+        (".matrix.flatten()", "[1, [2, [3]], 4]"),
+        (".matrix.flatten(1)", "[1, [2, [3]], 4]"),
+        (".matrix.flatten(2)", "[1, 2, [3], 4]"),
+        (".matrix.flatten(9)", "[1, 2, 3, 4]"),
+        (".matrix.flatten(0)", "[[1, [2, [3]]], 4, []]"),
+        // without parentheses, a key.
+        (".flatten", "5"),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        let expected: Json = expected.parse().unwrap();
+        assert_eq!(json.apply(&query).unwrap(), expected, "{}", query);
+        assert_eq!(JsonQuery::new(&query.to_string()).unwrap(), query);
+    }
+    assert_eq!(
+        JsonQuery::new(".flatten(1)").unwrap().to_string(),
+        ".flatten()"
+    );
+    for (jq_query, query) in [
+        (".a | flatten", ".a | .flatten()"),
+        (".a | flatten(3)", ".a | .flatten(3)"),
+    ] {
+        assert_eq!(
+            jq::translate(jq_query).unwrap(),
+            JsonQuery::new(query).unwrap()
+        );
+    }
+    for query in [".flatten(x)", ".flatten(-1)", ".flatten(1"] {
+        assert!(JsonQuery::new(query).is_err(), "{}", query);
+    }
+    assert!(JsonQuery::new(".flatten(4294967295)").is_ok());
+    let err = JsonQuery::new(".a.flatten(99999999999)").unwrap_err();
+    assert_eq!(err.error_type, JsonQueryErrorType::OutOfRangeError);
+    assert_eq!(err.cursor, 12);
+    let err = jq::translate(".a | flatten(99999999999)").unwrap_err();
+    assert_eq!(err.error_type, JsonQueryErrorType::OutOfRangeError);
+    assert_eq!(err.cursor, 14);
+    assert!(json
+        .apply(&JsonQuery::new(".flatten.flatten()").unwrap())
+        .is_err());
+}
+
+#[test]
+fn success_query_aggregate() {
+    let json: Json =
+        r#"{ "prices": [1.5, 2, 4.5], "empty": [], "mixed": [1, "2"] }"#
+            .parse()
+            .unwrap();
+    for (query, expected) in [
+        (".prices.sum()", "8"),
+        (".prices.min()", "1.5"),
+        (".prices.max()", "4.5"),
+        (".prices.avg()", "2.6666667"),
+        (".empty.sum()", "0"),
+        (".empty.min()", "null"),
+        (".empty.max()", "null"),
+        (".empty.avg()", "null"),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        let expected: Json = expected.parse().unwrap();
+        assert_eq!(json.apply(&query).unwrap(), expected, "{}", query);
+        assert_eq!(JsonQuery::new(&query.to_string()).unwrap(), query);
+    }
+    assert_eq!(
+        json.apply(&JsonQuery::new(".mixed.sum()").unwrap())
+            .unwrap_err()
+            .to_string(),
+        " after '.mixed' (Array of len 2): '.sum()' can only be applied on \
+         numbers (element at index 1), found 'String' instead."
+    );
+    assert_eq!(
+        json.apply(&JsonQuery::new(".prices[0].avg()").unwrap())
+            .unwrap_err()
+            .to_string(),
+        " after '.prices[0]' (Number): '.avg()' can only be applied on 'Array' \
+         of numbers, found 'Number' instead."
+    );
+}
+
+#[test]
+fn success_query_optional() {
+    use crate::json::{engine::QueryEngine, jq, scan};
+
+    let json: Json =
+        r#"{ "a": { "b": 1 }, "list": [{ "id": 1 }, {}] }"#.parse().unwrap();
+    for (query, expected) in [
+        (".maybe?.value", "null"),
+        (".a?.b", "1"),
+        (".a.b.c?.d", "null"),
+        (r#".a["x"]?"#, "null"),
+        (".list[5]?.id", "null"),
+        (".list.map(.id?)", "[1, null]"),
+        // up to the end of the stage.
+        (".maybe?.length()", "null"),
+        (".maybe? | .length()", "0"),
+        (".a.x?.y | .type()", r#""null""#),
+        (".list[].x? | .length()", "[0, 0]"),
+        (".list.map(.x?.y | .length())", "[0, 0]"),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        let expected: Json = expected.parse().unwrap();
+        assert_eq!(json.apply(&query).unwrap(), expected, "{}", query);
+        let engine = QueryEngine::new();
+        assert_eq!(engine.apply(&json, &query).unwrap(), expected, "{}", query);
+        assert_eq!(JsonQuery::new(&query.to_string()).unwrap(), query);
+        assert!(!scan::is_path(&query));
+    }
+    // only the marked property is optional.
+    assert!(json.apply(&JsonQuery::new(".a?.x").unwrap()).is_err());
+    for query in [".keys()?", ".a??", ".map(.a)?"] {
+        assert!(JsonQuery::new(query).is_err(), "{}", query);
+    }
+    let mut value = json.clone();
+    value.update(&Property::Dot("maybe".into(), true)).unwrap();
+    assert_eq!(value, Json::Null);
+    assert_eq!(
+        jq::translate(r#".a?.b | .["c"]?"#).unwrap(),
+        JsonQuery::new(r#".a?.b | ["c"]?"#).unwrap()
+    );
+    let query = jq::translate(".maybe? | length").unwrap();
+    assert_eq!(json.apply(&query).unwrap(), Json::Number(0.));
+}
+
+#[test]
+fn success_query_has() {
+    use crate::json::jq;
+
+    let json: Json =
+        r#"{ "a": { "k": null, "my key": 1 }, "list": [1, 2, 3] }"#
+            .parse()
+            .unwrap();
+    for (query, expected) in [
+        (r#".a.has("k")"#, "true"),
+        (r#".a.has("my key")"#, "true"),
+        (r#".a.has( "x" )"#, "false"),
+        (".list.has(2)", "true"),
+        (".list.has(-3)", "true"),
+        (".list.has(3)", "false"),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        let expected: Json = expected.parse().unwrap();
+        assert_eq!(json.apply(&query).unwrap(), expected, "{}", query);
+        assert_eq!(JsonQuery::new(&query.to_string()).unwrap(), query);
+    }
+    assert!(JsonQuery::new(".a.has(k)").is_err());
+    // without parentheses, a plain key.
+    assert_eq!(JsonQuery::new(".has").unwrap().to_string(), ".has");
+    assert_eq!(
+        json.apply(&JsonQuery::new(".list.has(\"k\")").unwrap())
+            .unwrap_err()
+            .to_string(),
+        " after '.list' (Array of len 3): '.has(\"k\")' can only be applied \
+         on 'Object', found 'Array' instead."
+    );
+    assert_eq!(
+        jq::translate(r#".a | has("k")"#).unwrap(),
+        JsonQuery::new(r#".a | .has("k")"#).unwrap()
+    );
+}
+
+#[test]
+fn success_query_slice() {
+    use crate::json::jq;
+
+    let json: Json = r#"{ "list": [1, 2, 3, 4, 5] }"#.parse().unwrap();
+    for (query, expected) in [
+        (".list[1:4]", "[2, 3, 4]"),
+        (".list[:3]", "[1, 2, 3]"),
+        (".list[2:]", "[3, 4, 5]"),
+        (".list[:]", "[1, 2, 3, 4, 5]"),
+        (".list[-2:]", "[4, 5]"),
+        (".list[:-3]", "[1, 2]"),
+        (".list[3:1]", "[]"),
+        (".list[-9:9]", "[1, 2, 3, 4, 5]"),
+        (".list[1:][0]", "2"),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        let expected: Json = expected.parse().unwrap();
+        assert_eq!(json.apply(&query).unwrap(), expected, "{}", query);
+        // moved out of an owned value.
+        assert_eq!(
+            json.clone()
+                .take(&query.0[0])
+                .unwrap()
+                .apply(&JsonQuery(query.0[1..].to_vec()))
+                .unwrap(),
+            expected,
+            "{}",
+            query
+        );
+    }
+    for (query, display) in
+        [("[1:4]", "[1:4]"), ("[:-1]", "[:-1]"), ("[:]", "[:]")]
+    {
+        let query = JsonQuery::new(query).unwrap();
+        assert_eq!(query.to_string(), display);
+        assert_eq!(JsonQuery::new(display).unwrap(), query);
+    }
+    assert_eq!(
+        jq::translate(".list[1:3]").unwrap(),
+        JsonQuery::new(".list[1:3]").unwrap()
+    );
+    assert!(matches!(
+        json.apply(&JsonQuery::new(".list[0][1:]").unwrap()),
+        Err(JsonEvalError::At(..))
+    ));
+    for query in [".list[1:2:3]", ".list[-:]", ".list[1:x]"] {
+        assert!(JsonQuery::new(query).is_err(), "{}", query);
+    }
+}
+
+#[test]
+fn success_query_del() {
+    use crate::json::jq;
+
+    let json: Json =
+        r#"{ "user": "a", "password": "x", "list": [0, 1, 2, 3] }"#
+            .parse()
+            .unwrap();
+    for (query, expected) in [
+        (
+            r#".del("password")"#,
+            r#"{ "user": "a", "list": [0, 1, 2, 3] }"#,
+        ),
+        (r#".del( "x" ).user"#, r#""a""#),
+        (".list.del(1)", "[0, 2, 3]"),
+        (".list.del(-1)", "[0, 1, 2]"),
+        (".list.del(4)", "[0, 1, 2, 3]"),
+        // moved out of an owned value.
+        (".list.map(.).del(0)", "[1, 2, 3]"),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        let expected: Json = expected.parse().unwrap();
+        assert_eq!(json.apply(&query).unwrap(), expected, "{}", query);
+        assert_eq!(JsonQuery::new(&query.to_string()).unwrap(), query);
+    }
+    assert!(JsonQuery::new(".del(password)").is_err());
+    assert!(matches!(
+        json.apply(&JsonQuery::new(".del(0)").unwrap()),
+        Err(JsonEvalError::InvalidType(_, "Object"))
+    ));
+    assert_eq!(
+        jq::translate(".list | del(.[1])").unwrap(),
+        JsonQuery::new(".list | .del(1)").unwrap()
+    );
+}
+
+#[test]
+fn success_query_omit() {
+    use crate::json::jq;
+
+    let json: Json = r#"{ "users": [
+        { "id": 1, "password": "x", "say \"hi\"": 1 },
+        { "id": 2, "token": "y" }
+    ] }"#
+        .parse()
+        .unwrap();
+    let query = JsonQuery::new(
+        r#".users.map(.omit("password", "token", "say \"hi\""))"#,
+    )
+    .unwrap();
+    let expected: Json = r#"[{ "id": 1 }, { "id": 2 }]"#.parse().unwrap();
+    assert_eq!(json.apply(&query).unwrap(), expected);
+    // moved out of an owned value.
+    let query = JsonQuery::new(r#".users[1].omit("token")"#).unwrap();
+    assert_eq!(json.apply(&query).unwrap(), r#"{"id": 2}"#.parse().unwrap());
+    let query = JsonQuery::new(".omit()").unwrap();
+    assert_eq!(json.apply(&query).unwrap(), json);
+
+    for (query, display) in [
+        (r#".omit( "a","b" )"#, r#".omit("a", "b")"#),
+        (".omit()", ".omit()"),
+    ] {
+        let query = JsonQuery::new(query).unwrap();
+        assert_eq!(query.to_string(), display);
+        assert_eq!(JsonQuery::new(display).unwrap(), query);
+    }
+    // '.omit' without parentheses is a key.
+    assert_eq!(
+        JsonQuery::new(".omit").unwrap(),
+        query![Property::Dot("omit".into(), false)]
+    );
+    assert_eq!(
+        jq::translate(r#".users[] | del(.password, .["token"])"#).unwrap(),
+        JsonQuery::new(r#".users[].omit("password", "token")"#).unwrap()
+    );
+    assert!(jq::translate("del(.a.b)").is_err());
+    for query in [".omit(", ".omit(a)", r#".omit("a",)"#, r#".omit("a""#] {
+        assert!(JsonQuery::new(query).is_err(), "{}", query);
+    }
+
+    let query = JsonQuery::new(r#".users.omit("id")"#).unwrap();
+    assert!(matches!(
+        json.apply(&query).unwrap_err().cause(),
+        JsonEvalError::InvalidType(_, "Array")
+    ));
+}
@@ -0,0 +1,98 @@
+use crate::regex::*;
+
+#[test]
+fn success_regex_match() {
+    let cases = [
+        ("abc", "xabcx", true),
+        ("^abc$", "xabc", false),
+        ("^a.c$", "abc", true),
+        ("a.c", "a\nc", false),
+        ("colou?r", "color", true),
+        ("ab*c", "ac", true),
+        ("ab+c", "ac", false),
+        ("a{2,3}", "caab", true),
+        ("^a{2}$", "aaa", false),
+        ("^a{2,}$", "aaaa", true),
+        ("a{x}", "a{x}", true),
+        ("[a-c]+d", "xbcad", true),
+        ("[^0-9]", "123", false),
+        ("[]a]", "]", true),
+        ("[a-]", "-", true),
+        (r"^\d{3}-\w+\s\S$", "123-ab_c d", true),
+        (r"\D\W", "a!", true),
+        (r"[\d.]+", "1.5", true),
+        (r"@example\.com$", "bob@example.com", true),
+        (r"@example\.com$", "bob@exampleXcom", false),
+        ("^(cat|dog)s?$", "dogs", true),
+        ("^(?:a|bc)+$", "abca", true),
+        ("^(a*)*b$", "aaaaaaaaaaaaaaaaaaaac", false),
+        ("", "", true),
+        ("a|", "b", true),
+        ("é+", "café", true),
+    ];
+    for (pattern, s, expected) in cases.iter() {
+        let regex = Regex::new(pattern).unwrap();
+        assert_eq!(regex.is_match(s), *expected, "{} on {:?}", pattern, s);
+    }
+    // time is linear in the input (no backtracking on nested repetitions).
+    let long = "a".repeat(100_000);
+    assert!(Regex::new("^a*$").unwrap().is_match(&long));
+    assert!(!Regex::new("(a*)*b").unwrap().is_match(&long));
+}
+
+#[test]
+fn success_regex_captures() {
+    let captures =
+        |pattern: &str, s: &str| Regex::new(pattern).unwrap().captures(s);
+    assert_eq!(
+        captures(r"(\w+)@(\w+)", "to: bob@example"),
+        Some(vec![Some(4..15), Some(4..7), Some(8..15)])
+    );
+    // offsets are in chars.
+    assert_eq!(
+        captures("é(.)", "café!"),
+        Some(vec![Some(3..5), Some(4..5)])
+    );
+    // greedy and lazy repetition.
+    assert_eq!(captures("<.+>", "<a><b>"), Some(vec![Some(0..6)]));
+    assert_eq!(captures("<.+?>", "<a><b>"), Some(vec![Some(0..3)]));
+    assert_eq!(
+        captures("<(.)*?>", "<ab>"),
+        Some(vec![Some(0..4), Some(2..3)])
+    );
+    // groups that didn't match, and groups of failed branches are reset.
+    assert_eq!(
+        captures("(a)|(b)", "b"),
+        Some(vec![Some(0..1), None, Some(0..1)])
+    );
+    assert_eq!(captures("(a)x|ay", "ay"), Some(vec![Some(0..2), None]));
+    assert_eq!(captures("z", "abc"), None);
+}
+
+#[test]
+fn failure_regex_syntax() {
+    let cases = [
+        ("a(b", 1),
+        ("ab)", 2),
+        ("*a", 0),
+        ("a**", 2),
+        ("a{3,1}", 1),
+        ("[abc", 0),
+        ("[z-a]", 3),
+        (r"a\q", 2),
+        ("a\\", 1),
+        ("a{1001}", 1),
+        // nested repetitions past the program size.
+        ("(?:(?:a?){1000}){1000}", 16),
+        ("((a{1000}){1000}){1000}", 10),
+        ("a{1000}(?:b{1000}|c{1000}){100}", 26),
+    ];
+    for (pattern, offset) in cases.iter() {
+        assert_eq!(Regex::new(pattern).unwrap_err(), *offset, "{}", pattern);
+    }
+    // long chains of optional copies (up to the program size) match.
+    let pattern = "(?:a?){1000}".repeat(20) + "$";
+    assert!(Regex::new(&pattern).unwrap().is_match(&"a".repeat(10)));
+    assert_eq!(Regex::new("a+").unwrap(), Regex::new("a+").unwrap());
+    assert_eq!(Regex::new("a+").unwrap().to_string(), "a+");
+}
@@ -0,0 +1,29 @@
+use crate::json::token::Json;
+use serde_json::{json, Value};
+
+#[test]
+fn success_serialize() {
+    let json: Json =
+        r#"{ "a\"b": [1, 0.1, "x\ny", true, null] }"#.parse().unwrap();
+    let value = serde_json::to_value(&json).unwrap();
+    assert_eq!(value, json!({ "a\"b": [1, 0.1, "x\ny", true, null] }));
+    assert_eq!(Value::from(&json), value);
+}
+
+#[test]
+fn success_deserialize() {
+    let text = r#"{ "a\"b": [1, 0.5, "x\ny", true, null], "c": {} }"#;
+    let json: Json = serde_json::from_str(text).unwrap();
+    assert_eq!(json, text.parse::<Json>().unwrap());
+    assert_eq!(
+        Json::from(serde_json::from_str::<Value>(text).unwrap()),
+        json
+    );
+}
+
+#[test]
+fn success_roundtrip() {
+    let json: Json = r#"[{ "tab\t": "é" }, -2.5e3]"#.parse().unwrap();
+    let text = serde_json::to_string(&json).unwrap();
+    assert_eq!(serde_json::from_str::<Json>(&text).unwrap(), json);
+}
@@ -0,0 +1,18 @@
+use crate::wasm::{format, parse, query};
+
+#[test]
+fn success_wasm() {
+    let input = r#"{ "a": [1, "x"] }"#;
+    assert_eq!(parse(input).unwrap(), r#"{"a": [1, "x"]}"#);
+    assert_eq!(query(input, ".a[1]").unwrap(), r#""x""#);
+    assert_eq!(format("[1]", 2).unwrap(), "[\n  1\n]");
+    assert_eq!(format("[1]", 0).unwrap(), "[1]");
+}
+
+#[test]
+fn error_wasm() {
+    // messages without the leading space.
+    assert!(parse("[1,]").unwrap_err().starts_with(|ch: char| ch != ' '));
+    assert!(query("{}", ".a").unwrap_err().contains("'a'"));
+    assert!(query("{}", ".a[").is_err());
+}
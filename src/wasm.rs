@@ -0,0 +1,46 @@
+//! Javascript bindings (`wasm32-unknown-unknown`, see `make wasm`), for
+//! browser playgrounds and node tooling: json text in, json text out, errors
+//! are thrown as strings.
+use crate::{
+    json::{
+        formatter::{Formatter, PrettyJson, RawJson},
+        token::Json,
+    },
+    prelude::*,
+};
+use wasm_bindgen::prelude::*;
+
+/// `input` as single line json text, throws if `input` is not valid json.
+#[wasm_bindgen]
+pub fn parse(input: &str) -> Result<String, String> {
+    format(input, 0)
+}
+
+/// value selected by `query` from `input`, as single line json text
+/// (example: `query('{"a": [1]}', '.a[0]')` gives `'1'`).
+#[wasm_bindgen]
+pub fn query(input: &str, query: &str) -> Result<String, String> {
+    crate::extract(input, query)
+        .map(|json| RawJson { color: false }.dump(&json))
+        .map_err(message)
+}
+
+/// `input` pretty formatted, indented by `indent` spaces (single line, if
+/// `0`).
+#[wasm_bindgen]
+pub fn format(input: &str, indent: usize) -> Result<String, String> {
+    let json = input.parse::<Json>().map_err(message)?;
+    Ok(match indent {
+        0 => RawJson { color: false }.dump(&json),
+        indent => PrettyJson {
+            indent: &" ".repeat(indent),
+            color: false,
+        }
+        .dump(&json),
+    })
+}
+
+/// error messages start with a space (see [`crate::error`]).
+fn message<E: ToString>(err: E) -> String {
+    err.to_string().trim_start().into()
+}
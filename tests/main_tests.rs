@@ -0,0 +1,82 @@
+#![cfg(feature = "cli")]
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// exit code and stdout of the 'ruson' binary, with `stdin` as input.
+fn ruson(args: &[&str], stdin: &str) -> (i32, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ruson"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn success_main_verbatim_join() {
+    let json = r#"{ "a": [1,2,3] }"#;
+    assert_eq!(
+        ruson(&["-q", ".a", "--verbatim"], json),
+        (0, "[1,2,3]\n".into())
+    );
+    for args in [
+        ["-q", ".a", "--verbatim", "--join", ","],
+        ["--join", ",", "--verbatim", "-q", ".a"],
+    ]
+    .iter()
+    {
+        assert_eq!(ruson(args, json), (0, "1,2,3\n".into()));
+    }
+}
+
+#[test]
+fn failure_main_invalid_input_path_query() {
+    // rejected whatever the flags, unless only scanned ('--lazy').
+    for json in [r#"{"a":1,"a":2}"#, r#"{"a":1,"b":garbage}"#].iter() {
+        for flags in [
+            &[][..],
+            &["--max-depth", "5"],
+            &["--max-memory", "1000000"],
+            &["--timing"],
+            &["--stats"],
+            &["--verbatim"],
+            &["--lazy", "--timing"],
+        ]
+        .iter()
+        {
+            let args: Vec<_> =
+                ["-Q", "-q", ".a"].iter().chain(*flags).copied().collect();
+            assert_eq!(ruson(&args, json), (3, String::new()), "{:?}", flags);
+        }
+        assert_eq!(ruson(&["-q", ".a", "--lazy"], json), (0, "1\n".into()));
+        assert_eq!(
+            ruson(&["-q", ".a", "--lazy", "--verbatim"], json),
+            (0, "1\n".into())
+        );
+    }
+}
+
+#[test]
+fn success_main_flags_after_file() {
+    let path = std::env::temp_dir().join("ruson_main_tests_flags.json");
+    std::fs::write(&path, r#"{ "a": 1 }"#).unwrap();
+    let path = path.to_str().unwrap();
+    assert_eq!(ruson(&[path, "-q", ".a"], ""), (0, "1\n".into()));
+    assert_eq!(ruson(&[path, "-p"], ""), (0, "{\n  \"a\": 1\n}\n".into()));
+    // FILEs that look like flags follow '--'.
+    assert_eq!(ruson(&["-Q", "--", path, "-p"], "").0, 5);
+}